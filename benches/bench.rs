@@ -1,4 +1,5 @@
 #![feature(test, core)]
+#![cfg(feature = "image")]
 
 extern crate image;
 extern crate genmesh;
@@ -10,7 +11,9 @@ extern crate obj;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::path::Path;
-use rusterize::{Frame, Fragment, TileGroup, Tile, Raster};
+use rusterize::{Frame, Fragment, FragmentSimd, TileGroup, Tile, Raster};
+use rusterize::{Barycentric, Interpolate, PerFragment, Batched, Flat};
+use rusterize::f32x8::f32x8;
 use cgmath::*;
 use genmesh::*;
 use test::{Bencher, black_box};
@@ -311,150 +314,83 @@ fn tile_fast_check(bench: &mut Bencher) {
     });
 }
 
-/*
-#[bench]
-fn tile_group_all(bench: &mut Bencher) {
-    use rusterize::Barycentric;
-
-    let tri = Triangle::new(Vector4::new(0.,   0.,   0., 0.),
-                            Vector4::new(256., 0.,   0., 0.),
-                            Vector4::new(0.,   256., 0., 0.));
-
-    let bary = Barycentric::new(tri.map_vertex(|v| Vector2::new(v.x, v.y)));
-    let tri = tri.map_vertex(|t| t.into_fixed());
-
-    let mut group = TileGroup::new(Rgba([0u8, 0, 0, 0]));
-    bench.iter(|| {
-        group.raster(
-            0, 0,
-            &Vector3::new(0., 0., 0.),
-            &bary,
-            &tri,
-            &SetValue(Rgba([255, 255, 255, 255]))
-        );
-    });
-    black_box(group);
+// the tile benches call the rasterizer directly, without the workers
+// or the binning, so they only measure the statically dispatched
+// coverage, depth and shading loop
+fn tile_setup(tri: [[f32; 2]; 3]) -> (Barycentric, Triangle<[f32; 4]>) {
+    let tri = Triangle::new([tri[0][0], tri[0][1], 0., 1.],
+                            [tri[1][0], tri[1][1], 0., 1.],
+                            [tri[2][0], tri[2][1], 0., 1.]);
+    (Barycentric::new(tri.map_vertex(|v| Vector2::new(v[0], v[1]))), tri)
 }
-*/
-
-/*
-#[bench]
-fn tile_group_one(bench: &mut Bencher) {
-    use rusterize::Barycentric;
 
-    let tri = Triangle::new(Vector4::new(0.,  0.,  0., 0.),
-                            Vector4::new(0.5, 0.5, 0., 0.),
-                            Vector4::new(0.,  0.5, 0., 0.));
-
-    let bary = Barycentric::new(tri.map_vertex(|v| Vector2::new(v.x, v.y)));
-    let tri = tri.map_vertex(|t| t.into_fixed());
+const ALL: [[f32; 2]; 3] = [[-1., -1.], [3., -1.], [-1., 3.]];
+const ONE: [[f32; 2]; 3] = [[-1., -1.], [-0.5, -1.], [-1., -0.5]];
+const ZERO: [[f32; 2]; 3] = [[-1., -1.], [-0.99, -1.], [-1., -0.99]];
 
+fn bench_group(bench: &mut Bencher, tri: [[f32; 2]; 3]) {
+    let (bary, tri) = tile_setup(tri);
+    let plane = Interpolate::setup(&tri);
+    let shader = PerFragment(SetValue(Rgba([255, 255, 255, 255])));
     let mut group = TileGroup::new(Rgba([0u8, 0, 0, 0]));
+
     bench.iter(|| {
-        group.raster(
-            0, 0,
-            &Vector3::new(0., 0., 0.),
-            &bary,
-            &tri,
-            &SetValue(Rgba([255, 255, 255, 255]))
-        );
+        group.clear(Rgba([0u8, 0, 0, 0]));
+        black_box(group.raster(Vector2::new(-1., -1.), Vector2::new(2. / 32., 2. / 32.),
+                               &Vector3::new(0., 0., 0.), &bary, &plane, &shader));
     });
-    black_box(group);
 }
 
-#[bench]
-fn tile_group_zero(bench: &mut Bencher) {
-    use rusterize::Barycentric;
-
-    let tri = Triangle::new(Vector4::new(0., 0., 0., 0.),
-                            Vector4::new(0.1, 0.1, 0., 0.),
-                            Vector4::new(0., 0.1, 0., 0.));
-
-    let bary = Barycentric::new(tri.map_vertex(|v| Vector2::new(v.x, v.y)));
-    let tri = tri.map_vertex(|t| t.into_fixed());
+fn bench_tile(bench: &mut Bencher, tri: [[f32; 2]; 3]) {
+    let (bary, tri) = tile_setup(tri);
+    let plane = Interpolate::setup(&tri);
+    let shader = PerFragment(SetValue(Rgba([255, 255, 255, 255])));
+    let mut tile = Tile::new(Rgba([0u8, 0, 0, 0]));
 
-    let mut group = TileGroup::new(Rgba([0u8, 0, 0, 0]));
     bench.iter(|| {
-        group.raster(
-            0, 0,
-            &Vector3::new(0., 0., 0.),
-            &bary,
-            &tri,
-            &SetValue(Rgba([255, 255, 255, 255]))
-        );
+        tile.clear(Rgba([0u8, 0, 0, 0]));
+        black_box(tile.raster(Vector2::new(-1., -1.), Vector2::new(2. / 8., 2. / 8.),
+                              &Vector3::new(0., 0., 0.), &bary, &plane, &shader));
     });
-    black_box(group);
 }
 
-#[bench]
-fn tile_all(bench: &mut Bencher) {
-    use rusterize::Barycentric;
-
-    let tri = Triangle::new(Vector4::new(0.,   0.,   0., 0.),
-                            Vector4::new(256., 0.,   0., 0.),
-                            Vector4::new(0.,   256., 0., 0.));
-
-    let bary = Barycentric::new(tri.map_vertex(|v| Vector2::new(v.x, v.y)));
-    let tri = tri.map_vertex(|t| t.into_fixed());
+#[bench] fn tile_group_all(bench: &mut Bencher) { bench_group(bench, ALL) }
+#[bench] fn tile_group_one(bench: &mut Bencher) { bench_group(bench, ONE) }
+#[bench] fn tile_group_zero(bench: &mut Bencher) { bench_group(bench, ZERO) }
+#[bench] fn tile_all(bench: &mut Bencher) { bench_tile(bench, ALL) }
+#[bench] fn tile_one(bench: &mut Bencher) { bench_tile(bench, ONE) }
+#[bench] fn tile_zero(bench: &mut Bencher) { bench_tile(bench, ZERO) }
 
+#[bench]
+fn tile_all_flat(bench: &mut Bencher) {
+    let (bary, tri) = tile_setup(ALL);
+    let plane = Interpolate::setup(&tri.map_vertex(|v| Flat(v)));
+    let shader = PerFragment(SetValue(Rgba([255, 255, 255, 255])));
     let mut tile = Tile::new(Rgba([0u8, 0, 0, 0]));
+
     bench.iter(|| {
-        tile.raster(
-            0, 0,
-            &Vector3::new(0., 0., 0.),
-            &bary,
-            &tri,
-            &SetValue(Rgba([255, 255, 255, 255]))
-        );
+        tile.clear(Rgba([0u8, 0, 0, 0]));
+        black_box(tile.raster(Vector2::new(-1., -1.), Vector2::new(2. / 8., 2. / 8.),
+                              &Vector3::new(0., 0., 0.), &bary, &plane, &shader));
     });
-    black_box(tile);
 }
 
-#[bench]
-fn tile_one(bench: &mut Bencher) {
-    use rusterize::Barycentric;
-
-    let tri = Triangle::new(Vector4::new(0.,  0.,  0., 0.),
-                            Vector4::new(0.5, 0.5, 0., 0.),
-                            Vector4::new(0.,  0.5, 0., 0.));
-
-    let bary = Barycentric::new(tri.map_vertex(|v| Vector2::new(v.x, v.y)));
-    let tri = tri.map_vertex(|t| t.into_fixed());
+impl FragmentSimd<[f32x8; 4]> for SetValue {
+    type Color = Rgba<u8>;
 
-    let mut tile = Tile::new(Rgba([0u8, 0, 0, 0]));
-    bench.iter(|| {
-        tile.raster(
-            0, 0,
-            &Vector3::new(0., 0., 0.),
-            &bary,
-            &tri,
-            &SetValue(Rgba([255, 255, 255, 255]))
-        );
-    });
-    black_box(tile);
+    fn fragment8(&self, _: [f32x8; 4]) -> [Rgba<u8>; 8] { [self.0; 8] }
 }
 
 #[bench]
-fn tile_zero(bench: &mut Bencher) {
-    use rusterize::Barycentric;
-
-    let tri = Triangle::new(Vector4::new(0., 0., 0., 0.),
-                            Vector4::new(0.1, 0.1, 0., 0.),
-                            Vector4::new(0., 0.1, 0., 0.));
-
-    let bary = Barycentric::new(tri.map_vertex(|v| Vector2::new(v.x, v.y)));
-    let tri = tri.map_vertex(|t| t.into_fixed());
-
+fn tile_all_simd(bench: &mut Bencher) {
+    let (bary, tri) = tile_setup(ALL);
+    let plane = Interpolate::setup(&tri);
+    let shader = Batched(SetValue(Rgba([255, 255, 255, 255])));
     let mut tile = Tile::new(Rgba([0u8, 0, 0, 0]));
+
     bench.iter(|| {
-        tile.raster(
-            0, 0,
-            &Vector3::new(0., 0., 0.),
-            &bary,
-            &tri,
-            &SetValue(Rgba([255, 255, 255, 255]))
-        );
+        tile.clear(Rgba([0u8, 0, 0, 0]));
+        black_box(tile.raster(Vector2::new(-1., -1.), Vector2::new(2. / 8., 2. / 8.),
+                              &Vector3::new(0., 0., 0.), &bary, &plane, &shader));
     });
-    black_box(tile);
 }
-*/
\ No newline at end of file
@@ -1,8 +1,10 @@
 #![feature(core)]
+#![cfg_attr(not(feature = "image"), allow(dead_code, unused_imports))]
 
 
 extern crate rusterize;
 extern crate genmesh;
+#[cfg(feature = "image")]
 extern crate image;
 extern crate obj;
 extern crate cgmath;
@@ -28,12 +30,19 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 
 use genmesh::{Triangulate, MapToVertices};
 use rusterize::{Frame, Fragment, Raster};
+#[cfg(feature = "image")]
 use image::{ImageBuffer, Rgba};
 use cgmath::*;
 use time::precise_time_s;
 
 const SIZE: u32 = 1024;
 
+#[cfg(not(feature = "image"))]
+fn main() {
+    println!("the monkey example needs the image feature");
+}
+
+#[cfg(feature = "image")]
 fn main() {
     let window = Rc::new(RefCell::new(
         Sdl2Window::new(
@@ -0,0 +1,13 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate rusterize;
+
+use rusterize::fuzz::MAX_TRIANGLES;
+
+fuzz_target!(|data: &[u8]| {
+    let buffer = rusterize::fuzz::raster(data);
+    assert_eq!(buffer.data.len(), (buffer.width * buffer.height) as usize);
+    // every pixel is the background or one of the triangles
+    assert!(buffer.data.iter().all(|&p| p as usize <= MAX_TRIANGLES));
+});
@@ -0,0 +1,11 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate rusterize;
+
+fuzz_target!(|data: &[u8]| {
+    let (pattern, all, region) = rusterize::fuzz::readback(data);
+    assert_eq!(all.data, pattern.data);
+    assert_eq!(region.data.len(), (region.width * region.height) as usize);
+    assert!(region.width <= all.width && region.height <= all.height);
+});
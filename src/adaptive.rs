@@ -0,0 +1,122 @@
+use time::precise_time_ns;
+
+use {Frame, Lerp};
+
+/// the steps the scale moves in, so the frame is not resized for every
+/// small change in timing
+const SCALE_STEP: f32 = 1. / 16.;
+
+/// a frame of a fixed size drawn at a fraction of it, the fraction follows
+/// how long the last frames took against a target. A single frame is
+/// resized between the two, so the threads and tiles are kept.
+pub struct AdaptiveFrame<P> {
+    pub width: u32,
+    pub height: u32,
+    clear: P,
+    scale: f32,
+    min_scale: f32,
+    max_scale: f32,
+    /// nanoseconds a frame should take, None keeps the scale where it is
+    target: Option<u64>,
+    frame: Frame<P>,
+    started: u64,
+    last: Option<u64>
+}
+
+impl<P: Copy+Lerp+Sync+Send+'static> AdaptiveFrame<P> {
+    /// a `width` by `height` output cleared to `p` every frame, the scale
+    /// starts at 1 and stays between 0.5 and 1 while aiming for frames of
+    /// `target` nanoseconds
+    pub fn new(width: u32, height: u32, p: P, target: Option<u64>) -> AdaptiveFrame<P> {
+        AdaptiveFrame {
+            width: width,
+            height: height,
+            clear: p,
+            scale: 1.,
+            min_scale: 0.5,
+            max_scale: 1.,
+            target: target,
+            frame: Frame::new(width, height, p),
+            started: 0,
+            last: None
+        }
+    }
+
+    /// the fractions of the size the scale stays between
+    pub fn set_range(&mut self, min: f32, max: f32) {
+        assert!(0. < min && min <= max && max <= 1.);
+        self.min_scale = min;
+        self.max_scale = max;
+        self.scale = self.scale.max(min).min(max);
+    }
+
+    pub fn set_target(&mut self, target: Option<u64>) {
+        self.target = target;
+    }
+
+    /// draw the next frames at `scale`, within the range
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(self.min_scale).min(self.max_scale);
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// the size of the frame drawn into at the current scale
+    pub fn size(&self) -> (u32, u32) {
+        let scaled = |n: u32| ((n as f32 * self.scale).round() as u32).max(1);
+        (scaled(self.width), scaled(self.height))
+    }
+
+    /// how long the last frame took from `begin` to `finish`
+    pub fn frame_time(&self) -> Option<u64> {
+        self.last
+    }
+
+    /// start a frame, the returned frame has the size for the current
+    /// scale and is cleared
+    pub fn begin(&mut self) -> &mut Frame<P> {
+        self.started = precise_time_ns();
+        let (w, h) = self.size();
+        if (w, h) != (self.frame.width, self.frame.height) {
+            self.frame.resize(w, h, self.clear);
+        } else {
+            self.frame.clear(self.clear);
+        }
+        &mut self.frame
+    }
+
+    /// wait for the frame and stretch it over the full size, which is
+    /// returned for reading back. The time the frame took picks the scale
+    /// of the next one.
+    pub fn finish(&mut self) -> &mut Frame<P> {
+        if (self.frame.width, self.frame.height) != (self.width, self.height) {
+            let src = self.frame.to_buffer();
+            self.frame.resize(self.width, self.height, self.clear);
+            self.frame.upsample_from(src);
+        }
+        self.frame.flush();
+
+        let time = precise_time_ns() - self.started;
+        self.last = Some(time);
+        self.update(time);
+        &mut self.frame
+    }
+
+    /// move the scale towards one that takes the target time, given that
+    /// the last frame took `time` nanoseconds. `finish` calls this, it is
+    /// public for timings measured some other way.
+    pub fn update(&mut self, time: u64) {
+        let target = match self.target {
+            Some(t) => t,
+            None => return
+        };
+        // the work grows with the pixels, so with the square of the scale
+        let ideal = self.scale * (target as f32 / time.max(1) as f32).sqrt();
+        // half way there, so a single slow frame does not swing it
+        let next = self.scale + (ideal - self.scale) * 0.5;
+        let next = (next / SCALE_STEP).round() * SCALE_STEP;
+        self.set_scale(next);
+    }
+}
@@ -0,0 +1,236 @@
+//! keyframed translation, rotation and scale tracks for scene nodes, laid
+//! out like glTF animation samplers
+
+use cgmath::Matrix4;
+
+/// how the values between two keyframes are found
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    /// hold the value of the previous keyframe
+    Step,
+    /// lerp, rotations are slerped
+    Linear,
+    /// a hermite spline, every keyframe stores an in tangent, the value and
+    /// an out tangent in this order
+    Cubic
+}
+
+/// a translation, rotation and scale, the rotation is a unit quaternion
+/// stored as x, y, z, w
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trs {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3]
+}
+
+impl Trs {
+    pub fn identity() -> Trs {
+        Trs {
+            translation: [0.; 3],
+            rotation: [0., 0., 0., 1.],
+            scale: [1.; 3]
+        }
+    }
+
+    /// scale first, then rotate and translate
+    pub fn matrix(&self) -> Matrix4<f32> {
+        let (x, y, z, w) = (self.rotation[0], self.rotation[1], self.rotation[2], self.rotation[3]);
+        let s = self.scale;
+        let t = self.translation;
+        Matrix4::new(
+            (1. - 2. * (y * y + z * z)) * s[0], (2. * (x * y + z * w)) * s[0], (2. * (x * z - y * w)) * s[0], 0.,
+            (2. * (x * y - z * w)) * s[1], (1. - 2. * (x * x + z * z)) * s[1], (2. * (y * z + x * w)) * s[1], 0.,
+            (2. * (x * z + y * w)) * s[2], (2. * (y * z - x * w)) * s[2], (1. - 2. * (x * x + y * y)) * s[2], 0.,
+            t[0], t[1], t[2], 1.)
+    }
+}
+
+#[inline]
+fn normalize(q: [f32; 4]) -> [f32; 4] {
+    let l = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    if l == 0. {
+        return [0., 0., 0., 1.];
+    }
+    [q[0] / l, q[1] / l, q[2] / l, q[3] / l]
+}
+
+fn slerp(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    let mut cos = a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3];
+    // take the short way around
+    let b = if cos < 0. {
+        cos = -cos;
+        [-b[0], -b[1], -b[2], -b[3]]
+    } else {
+        b
+    };
+
+    let (wa, wb) = if cos > 0.9995 {
+        (1. - t, t)
+    } else {
+        let angle = cos.acos();
+        let sin = angle.sin();
+        (((1. - t) * angle).sin() / sin, (t * angle).sin() / sin)
+    };
+    normalize([a[0] * wa + b[0] * wb, a[1] * wa + b[1] * wb,
+               a[2] * wa + b[2] * wb, a[3] * wa + b[3] * wb])
+}
+
+/// the keyframes of one property, three component values leave the last
+/// lane at zero
+#[derive(Clone, Debug)]
+pub struct Track {
+    pub times: Vec<f32>,
+    pub values: Vec<[f32; 4]>,
+    pub interpolation: Interpolation
+}
+
+impl Track {
+    pub fn new(times: Vec<f32>, values: Vec<[f32; 4]>, interpolation: Interpolation) -> Track {
+        let per_key = if interpolation == Interpolation::Cubic { 3 } else { 1 };
+        assert!(!times.is_empty());
+        assert_eq!(times.len() * per_key, values.len());
+        Track {
+            times: times,
+            values: values,
+            interpolation: interpolation
+        }
+    }
+
+    /// the time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        *self.times.last().unwrap()
+    }
+
+    #[inline]
+    fn value(&self, key: usize) -> [f32; 4] {
+        match self.interpolation {
+            Interpolation::Cubic => self.values[3 * key + 1],
+            _ => self.values[key]
+        }
+    }
+
+    /// the value at time `t`, clamped to the first and last keyframe.
+    /// `rotation` slerps linear tracks and renormalizes cubic ones.
+    pub fn sample(&self, t: f32, rotation: bool) -> [f32; 4] {
+        let last = self.times.len() - 1;
+        if t <= self.times[0] {
+            return self.value(0);
+        }
+        if t >= self.times[last] {
+            return self.value(last);
+        }
+
+        // the keyframe right before t
+        let k = match self.times.iter().position(|&k| k > t) {
+            Some(i) => i - 1,
+            None => last
+        };
+        let (t0, t1) = (self.times[k], self.times[k + 1]);
+        let dt = t1 - t0;
+        let s = (t - t0) / dt;
+
+        match self.interpolation {
+            Interpolation::Step => self.value(k),
+            Interpolation::Linear => {
+                let (a, b) = (self.value(k), self.value(k + 1));
+                if rotation {
+                    slerp(a, b, s)
+                } else {
+                    [a[0] + (b[0] - a[0]) * s, a[1] + (b[1] - a[1]) * s,
+                     a[2] + (b[2] - a[2]) * s, a[3] + (b[3] - a[3]) * s]
+                }
+            }
+            Interpolation::Cubic => {
+                let (p0, m0) = (self.values[3 * k + 1], self.values[3 * k + 2]);
+                let (m1, p1) = (self.values[3 * (k + 1)], self.values[3 * (k + 1) + 1]);
+                let (s2, s3) = (s * s, s * s * s);
+                let h00 = 2. * s3 - 3. * s2 + 1.;
+                let h10 = (s3 - 2. * s2 + s) * dt;
+                let h01 = -2. * s3 + 3. * s2;
+                let h11 = (s3 - s2) * dt;
+                let mut out = [0.; 4];
+                for i in 0..4 {
+                    out[i] = h00 * p0[i] + h10 * m0[i] + h01 * p1[i] + h11 * m1[i];
+                }
+                if rotation { normalize(out) } else { out }
+            }
+        }
+    }
+}
+
+/// the node property a track drives
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Property {
+    Translation,
+    Rotation,
+    Scale
+}
+
+#[derive(Clone, Debug)]
+pub struct Channel {
+    pub node: usize,
+    pub property: Property,
+    pub track: Track
+}
+
+/// a set of channels played together
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub channels: Vec<Channel>,
+    /// start over after the last keyframe instead of holding it
+    pub looping: bool
+}
+
+impl Animation {
+    pub fn new(looping: bool) -> Animation {
+        Animation {
+            channels: Vec::new(),
+            looping: looping
+        }
+    }
+
+    pub fn channel(mut self, node: usize, property: Property, track: Track) -> Animation {
+        self.channels.push(Channel {
+            node: node,
+            property: property,
+            track: track
+        });
+        self
+    }
+
+    /// the time of the last keyframe of all the channels
+    pub fn duration(&self) -> f32 {
+        self.channels.iter().fold(0f32, |d, c| d.max(c.track.duration()))
+    }
+
+    /// the time inside the animation after `time` seconds of playback
+    pub fn local_time(&self, time: f32) -> f32 {
+        let d = self.duration();
+        if d <= 0. {
+            0.
+        } else if self.looping {
+            time % d
+        } else {
+            time.min(d)
+        }
+    }
+
+    /// write the animated properties at time `t` into the poses
+    pub fn apply(&self, t: f32, poses: &mut [Trs]) {
+        for c in self.channels.iter() {
+            let pose = &mut poses[c.node];
+            match c.property {
+                Property::Translation => {
+                    let v = c.track.sample(t, false);
+                    pose.translation = [v[0], v[1], v[2]];
+                }
+                Property::Rotation => pose.rotation = c.track.sample(t, true),
+                Property::Scale => {
+                    let v = c.track.sample(t, false);
+                    pose.scale = [v[0], v[1], v[2]];
+                }
+            }
+        }
+    }
+}
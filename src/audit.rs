@@ -0,0 +1,54 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use Frame;
+
+/// the number of heap allocations made so far on the calling thread, as
+/// told by the allocator of the application
+pub type AllocCounter = fn() -> usize;
+
+/// run `f` and return its result with the allocations `counter` saw it
+/// make on this thread
+pub fn count_allocations<R, F: FnOnce() -> R>(counter: AllocCounter, f: F) -> (R, usize) {
+    let before = counter();
+    let r = f();
+    (r, counter() - before)
+}
+
+/// the allocations of the tile workers of one draw
+#[derive(Clone)]
+pub struct Audit {
+    counter: AllocCounter,
+    total: Arc<AtomicUsize>
+}
+
+impl Audit {
+    pub fn new(counter: AllocCounter, total: Arc<AtomicUsize>) -> Audit {
+        Audit {
+            counter: counter,
+            total: total
+        }
+    }
+
+    #[inline]
+    pub fn begin(&self) -> usize {
+        (self.counter)()
+    }
+
+    #[inline]
+    pub fn end(&self, before: usize) {
+        self.total.fetch_add((self.counter)() - before, Ordering::Relaxed);
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// count the heap allocations the tile workers make while shading the
+    /// following draws, see `DrawStats::allocations`. The crate cannot see
+    /// the allocator, `counter` is expected to read a per thread count the
+    /// allocator of the application keeps. Binning on the calling thread
+    /// takes a future and a task for every tile group a draw touches, wrap
+    /// the call in `count_allocations` to see those. None turns it off.
+    pub fn set_alloc_counter(&mut self, counter: Option<AllocCounter>) {
+        self.alloc_counter = counter;
+    }
+}
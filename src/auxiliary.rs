@@ -0,0 +1,139 @@
+//! user data stored next to the color of every pixel
+//!
+//! A frame of `Aux<P, A>` keeps the `A` of a pixel in the tiles together
+//! with its color, so it is allocated lazily, cleared, copied and read back
+//! along with it. Shaders are wrapped in `WithAux`, which has an
+//! `AuxStage` work out the value written for every fragment from its
+//! input and the value already stored.
+
+use {Frame, Buffer, Fragment, Mapping};
+use tile::Put;
+
+/// a color with auxiliary data, the pixel type of a frame with auxiliary
+/// storage
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aux<P, A> {
+    pub color: P,
+    pub aux: A
+}
+
+impl<P, A> Aux<P, A> {
+    pub fn new(color: P, aux: A) -> Aux<P, A> {
+        Aux {
+            color: color,
+            aux: aux
+        }
+    }
+}
+
+/// produces the auxiliary value of a fragment
+pub trait AuxStage<T> {
+    type Value: Copy;
+    fn aux(&self, input: &T) -> Self::Value;
+
+    /// merges the value of a new fragment with the one stored in the
+    /// pixel, the new one replaces it by default
+    fn combine(&self, _: Self::Value, new: Self::Value) -> Self::Value { new }
+}
+
+/// runs `fragment` for the color and `stage` for the auxiliary data
+#[derive(Clone, Debug, PartialEq)]
+pub struct WithAux<F, S> {
+    pub fragment: F,
+    pub stage: S
+}
+
+impl<F, S> WithAux<F, S> {
+    pub fn new(fragment: F, stage: S) -> WithAux<F, S> {
+        WithAux {
+            fragment: fragment,
+            stage: stage
+        }
+    }
+}
+
+impl<T, F, S> Fragment<T> for WithAux<F, S>
+    where F: Fragment<T>,
+          S: AuxStage<T> {
+    type Color = Aux<F::Color, S::Value>;
+
+    #[inline]
+    fn fragment(&self, pos: T) -> Aux<F::Color, S::Value> {
+        let aux = self.stage.aux(&pos);
+        Aux::new(self.fragment.fragment(pos), aux)
+    }
+
+    #[inline]
+    fn blend(&self, old: Aux<F::Color, S::Value>, new: Aux<F::Color, S::Value>) -> Aux<F::Color, S::Value> {
+        Aux::new(self.fragment.blend(old.color, new.color),
+                 self.stage.combine(old.aux, new.aux))
+    }
+}
+
+/// writes the same id for every fragment of a draw, for picking
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObjectId(pub u32);
+
+impl<T> AuxStage<T> for ObjectId {
+    type Value = u32;
+
+    #[inline]
+    fn aux(&self, _: &T) -> u32 { self.0 }
+}
+
+/// counts the fragments written to a pixel, for overdraw
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FragmentCount;
+
+impl<T> AuxStage<T> for FragmentCount {
+    type Value = u32;
+
+    #[inline]
+    fn aux(&self, _: &T) -> u32 { 1 }
+
+    #[inline]
+    fn combine(&self, old: u32, new: u32) -> u32 { old.saturating_add(new) }
+}
+
+/// keeps the color of a pixel, for `Frame::map`
+#[derive(Clone, Copy, Debug)]
+pub struct AuxColor;
+
+impl<P, A> Mapping<Aux<P, A>> for AuxColor {
+    type Out = P;
+
+    #[inline]
+    fn mapping(&self, p: Aux<P, A>) -> P { p.color }
+}
+
+/// keeps the auxiliary data of a pixel, for `Frame::map`
+#[derive(Clone, Copy, Debug)]
+pub struct AuxData;
+
+impl<P, A> Mapping<Aux<P, A>> for AuxData {
+    type Out = A;
+
+    #[inline]
+    fn mapping(&self, p: Aux<P, A>) -> A { p.aux }
+}
+
+/// keeps the auxiliary data of the pixels read back
+struct AuxPut<A>(Buffer<A>);
+
+impl<P, A: Copy> Put<Aux<P, A>> for AuxPut<A> {
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, p: Aux<P, A>) {
+        self.0.put(x, y, p.aux);
+    }
+}
+
+impl<P, A> Frame<Aux<P, A>>
+    where P: Copy + Sync + Send + 'static,
+          A: Copy + Default + Sync + Send + 'static {
+    /// the auxiliary data of every pixel, laid out like `to_buffer`. With
+    /// `ObjectId` this is the id buffer that `Frame::outline` reads.
+    pub fn aux_buffer(&mut self) -> Buffer<A> {
+        let aux = AuxPut(Buffer::new(self.width, self.height, A::default()));
+        self.write_into(aux).0
+    }
+}
@@ -0,0 +1,127 @@
+use cgmath::Vector2;
+
+use Barycentric;
+use tile::TileMask;
+
+/// how much of a box a triangle covers, see `RasterBackend::bin_coverage`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coverage {
+    /// not a single pixel
+    Outside,
+    Partial,
+    /// every pixel, they are not tested again
+    Inside
+}
+
+/// decides which pixels a triangle covers. The frame bins every triangle
+/// into the tile groups its bounds touch, inside of a group the backend
+/// skips the quadrants and tiles that `bin` rejects and gives the
+/// coverage of every 8x8 tile that is left. Depth testing, shading and
+/// blending are shared by all backends. `resolve_weights` decides how
+/// `Frame::resolve_backend` reduces the samples of a supersampled frame.
+/// See `Frame::set_backend`.
+///
+/// Positions are in pixels from the center of the frame, `bary` is the
+/// triangle in the same space.
+pub trait RasterBackend: Send + Sync {
+    /// false if the triangle cannot cover any pixel of the `size` box
+    /// whose bottom left corner is `pos`
+    fn bin(&self, bary: &Barycentric, pos: Vector2<f32>, size: Vector2<f32>) -> bool;
+
+    /// the covered pixels of the 8x8 tile whose bottom left pixel is at
+    /// `pos`, neighbouring pixels are `scale` apart
+    fn coverage(&self, bary: &Barycentric, pos: Vector2<f32>, scale: Vector2<f32>) -> TileMask;
+
+    /// like `bin` but telling apart the boxes that are entirely inside of
+    /// the triangle, their tiles get `full_coverage`. Only `bin` is asked
+    /// by default.
+    #[inline]
+    fn bin_coverage(&self, bary: &Barycentric, pos: Vector2<f32>, size: Vector2<f32>) -> Coverage {
+        if self.bin(bary, pos, size) { Coverage::Partial } else { Coverage::Outside }
+    }
+
+    /// the weights of a tile `bin_coverage` found to be inside of the
+    /// triangle, every pixel is covered
+    #[inline]
+    fn full_coverage(&self, bary: &Barycentric, pos: Vector2<f32>, scale: Vector2<f32>) -> TileMask {
+        self.coverage(bary, pos, scale)
+    }
+
+    /// the weights of the `factor`x`factor` samples of a pixel row by row
+    /// from the top left, they all count the same by default
+    fn resolve_weights(&self, factor: u32) -> Vec<f32> {
+        vec![1.; (factor * factor) as usize]
+    }
+
+    /// true only for `SimdBackend`, the frame then calls it directly once
+    /// per tile group instead of through the trait object for every tile
+    #[inline]
+    fn is_simd(&self) -> bool { false }
+}
+
+/// the default backend, a whole tile is tested at once with f32x8 lanes
+#[derive(Clone, Copy, Debug)]
+pub struct SimdBackend;
+
+impl RasterBackend for SimdBackend {
+    #[inline]
+    fn bin(&self, bary: &Barycentric, pos: Vector2<f32>, size: Vector2<f32>) -> bool {
+        !bary.tile_fast_check(pos, size)
+    }
+
+    #[inline]
+    fn coverage(&self, bary: &Barycentric, pos: Vector2<f32>, scale: Vector2<f32>) -> TileMask {
+        TileMask::new(pos, scale, bary)
+    }
+
+    #[inline]
+    fn bin_coverage(&self, bary: &Barycentric, pos: Vector2<f32>, size: Vector2<f32>) -> Coverage {
+        bary.tile_check(pos, size)
+    }
+
+    #[inline]
+    fn full_coverage(&self, bary: &Barycentric, pos: Vector2<f32>, scale: Vector2<f32>) -> TileMask {
+        TileMask::covered(pos, scale, bary)
+    }
+
+    #[inline]
+    fn is_simd(&self) -> bool { true }
+}
+
+/// tests one pixel at a time with `Barycentric::coordinate`, slow but
+/// plain enough to check other backends against
+#[derive(Clone, Copy, Debug)]
+pub struct ScalarBackend;
+
+impl RasterBackend for ScalarBackend {
+    fn bin(&self, bary: &Barycentric, pos: Vector2<f32>, size: Vector2<f32>) -> bool {
+        if bary.is_degenerate() {
+            return false;
+        }
+        let corners = [pos, pos + Vector2::new(size.x, 0.),
+                       pos + Vector2::new(0., size.y), pos + size];
+        let (mut u, mut v, mut w) = (false, false, false);
+        for c in corners.iter() {
+            let b = bary.coordinate(*c);
+            u |= b.u >= 0.;
+            v |= b.v >= 0.;
+            w |= b.u + b.v <= 1.;
+        }
+        // rejected once every corner is outside of the same edge
+        u && v && w
+    }
+
+    fn coverage(&self, bary: &Barycentric, pos: Vector2<f32>, scale: Vector2<f32>) -> TileMask {
+        let (mut u, mut v, mut mask) = ([0.; 64], [0.; 64], 0u64);
+        for i in 0..64 {
+            let p = pos + Vector2::new((i % 8) as f32 * scale.x, (i / 8) as f32 * scale.y);
+            let b = bary.coordinate(p);
+            u[i] = b.u;
+            v[i] = b.v;
+            if !bary.is_degenerate() && b.inside() {
+                mask |= 1u64 << i;
+            }
+        }
+        TileMask::from_weights(u, v, mask)
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt::Debug;
+
+use genmesh::Triangle;
+
+use {Frame, Fragment, Interpolate, FetchPosition, DrawStats};
+
+/// collects many small draws and merges the ones that use an equal fragment
+/// shader, each merged batch goes through a single binning pass. Draws that
+/// are merged keep their relative order.
+pub struct DrawMerger<T, F> {
+    batches: Vec<(F, Vec<Triangle<T>>)>,
+    unordered: bool,
+    draws: usize
+}
+
+impl<T, F: PartialEq> DrawMerger<T, F> {
+    /// only neighbouring draws are merged, so the submission order of the
+    /// draws is kept. Use this when draws blend over each other.
+    pub fn new() -> DrawMerger<T, F> {
+        DrawMerger {
+            batches: Vec::new(),
+            unordered: false,
+            draws: 0
+        }
+    }
+
+    /// a draw joins any earlier batch with an equal shader, which is only
+    /// correct when the order between different shaders does not matter,
+    /// like depth tested opaque geometry
+    pub fn unordered() -> DrawMerger<T, F> {
+        DrawMerger {
+            batches: Vec::new(),
+            unordered: true,
+            draws: 0
+        }
+    }
+
+    pub fn push<S>(&mut self, poly: S, fragment: F) where S: Iterator<Item=Triangle<T>> {
+        self.draws += 1;
+        let found = if self.unordered {
+            self.batches.iter().position(|&(ref f, _)| *f == fragment)
+        } else {
+            match self.batches.last() {
+                Some(&(ref f, _)) if *f == fragment => Some(self.batches.len() - 1),
+                _ => None
+            }
+        };
+
+        match found {
+            Some(i) => self.batches[i].1.extend(poly),
+            None => self.batches.push((fragment, poly.collect()))
+        }
+    }
+
+    /// the number of draws pushed
+    pub fn draws(&self) -> usize {
+        self.draws
+    }
+
+    /// the number of binning passes `submit` is going to make
+    pub fn batches(&self) -> usize {
+        self.batches.len()
+    }
+
+    /// raster every batch into `frame`, one `DrawStats` per batch
+    pub fn submit<P, O>(self, frame: &mut Frame<P>) -> Vec<DrawStats>
+        where P: Copy + Send + Sync + 'static,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: Fragment<O, Color=P> + Send + Sync + 'static {
+
+        self.batches.into_iter().map(|(fragment, poly)| {
+            frame.raster(poly.into_iter(), fragment)
+        }).collect()
+    }
+}
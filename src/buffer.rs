@@ -0,0 +1,204 @@
+use std::mem;
+use std::sync::Arc;
+
+use fibe::{task, IntoTask};
+#[cfg(feature = "image")]
+use image::{ImageBuffer, Rgba};
+use future_pulse::Future;
+
+use {Frame, Rect, Lerp, Pass, group_rect};
+use timing::timed;
+use tile::{Put, Get, Clip};
+
+/// a plain row major pixel buffer, the origin is the top left corner
+/// just like the images produced by `Frame::to_image`
+#[derive(Clone, Debug)]
+pub struct Buffer<P> {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<P>
+}
+
+impl<P: Copy> Buffer<P> {
+    pub fn new(width: u32, height: u32, p: P) -> Buffer<P> {
+        Buffer {
+            width: width,
+            height: height,
+            data: vec![p; (width * height) as usize]
+        }
+    }
+
+    #[inline]
+    pub fn get_pixel(&self, x: u32, y: u32) -> P {
+        self.data[(y * self.width + x) as usize]
+    }
+
+    #[inline]
+    pub fn put_pixel(&mut self, x: u32, y: u32, p: P) {
+        self.data[(y * self.width + x) as usize] = p;
+    }
+
+    /// fetch a pixel with the coordinates clamped to the edge of the buffer
+    #[inline]
+    pub fn get_clamped(&self, x: i32, y: i32) -> P {
+        let x = x.max(0).min(self.width as i32 - 1) as u32;
+        let y = y.max(0).min(self.height as i32 - 1) as u32;
+        self.get_pixel(x, y)
+    }
+}
+
+#[cfg(feature = "image")]
+impl Buffer<Rgba<u8>> {
+    /// copy the pixels of an image, both use the top left origin
+    pub fn from_image(img: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Buffer<Rgba<u8>> {
+        Buffer {
+            width: img.width(),
+            height: img.height(),
+            data: img.pixels().cloned().collect()
+        }
+    }
+
+    /// an image of the pixels, the inverse of `from_image`
+    pub fn to_image(&self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(self.width, self.height, |x, y| self.get_pixel(x, y))
+    }
+}
+
+impl<P: Copy + Lerp> Buffer<P> {
+    /// bilinear fetch at a position measured in pixels, the center of the
+    /// top left pixel is at 0, 0
+    #[inline]
+    pub fn bilinear(&self, sx: f32, sy: f32) -> P {
+        let (x0, y0) = (sx.floor(), sy.floor());
+        let (fx, fy) = (sx - x0, sy - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let top = self.get_clamped(x0, y0).lerp(self.get_clamped(x0 + 1, y0), fx);
+        let bottom = self.get_clamped(x0, y0 + 1).lerp(self.get_clamped(x0 + 1, y0 + 1), fx);
+        top.lerp(bottom, fy)
+    }
+
+    /// bilinear fetch at normalized coordinates, 0, 0 is the top left
+    /// corner and 1, 1 the bottom right, the edges are clamped
+    #[inline]
+    pub fn sample(&self, u: f32, v: f32) -> P {
+        self.bilinear(u * self.width as f32 - 0.5, v * self.height as f32 - 0.5)
+    }
+}
+
+// tiles address pixels from the bottom left corner
+impl<P: Copy> Put<P> for Buffer<P> {
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, p: P) {
+        let h = self.height;
+        self.put_pixel(x, h - 1 - y, p);
+    }
+}
+
+impl<P: Copy> Get<P> for Buffer<P> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        Some(self.get_pixel(x, self.height - 1 - y))
+    }
+}
+
+/// keeps only the pixels inside of `rect`
+struct RegionPut<P> {
+    buffer: Buffer<P>,
+    rect: Rect,
+    height: u32
+}
+
+impl<P: Copy> Put<P> for RegionPut<P> {
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, p: P) {
+        let y = self.height - 1 - y;
+        if self.rect.contains(x, y) {
+            self.buffer.put_pixel(x - self.rect.x, y - self.rect.y, p);
+        }
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// read back every pixel of the frame
+    pub fn to_buffer(&mut self) -> Buffer<P> {
+        let fill = self.fill_value();
+        self.write_into(Buffer::new(self.width, self.height, fill))
+    }
+
+    /// a value for the buffers the tiles are read back into, every pixel
+    /// is overwritten. It waits for the first group.
+    fn fill_value(&mut self) -> P {
+        let (pending, _) = Future::new();
+        let group = mem::replace(&mut self.tile[0][0], pending).get();
+        let p = group.clear_color();
+        self.tile[0][0] = Future::from_value(group);
+        p
+    }
+
+    /// read back the pixels inside of `rect`, this only waits for the
+    /// tiles that cover it
+    pub fn read_region(&mut self, rect: Rect) -> Buffer<P> {
+        let fill = self.fill_value();
+        let out = self.region_put(rect, fill);
+        let rect = out.rect;
+        self.write_tiles(out, rect, |t, x, y, buff| t.write(x, y, buff)).buffer
+    }
+
+    /// like `read_region` for the depth buffer
+    pub fn depth_region(&mut self, rect: Rect) -> Buffer<f32> {
+        let out = self.region_put(rect, 1.);
+        let rect = out.rect;
+        self.write_tiles(out, rect, |t, x, y, buff| t.write_depth(x, y, buff)).buffer
+    }
+
+    /// a buffer filled with `fill` for the part of `rect` inside of the
+    /// frame
+    fn region_put<V: Copy>(&self, rect: Rect, fill: V) -> RegionPut<V> {
+        let rect = rect.intersect(&Rect::new(0, 0, self.width, self.height))
+                       .unwrap_or(Rect::new(0, 0, 0, 0));
+
+        RegionPut {
+            buffer: Buffer::new(rect.width, rect.height, fill),
+            rect: rect,
+            height: self.height
+        }
+    }
+
+    /// read back the depth buffer, laid out like `to_buffer`
+    pub fn depth_buffer(&mut self) -> Buffer<f32> {
+        self.write_depth_into(Buffer::new(self.width, self.height, 1.))
+    }
+
+    /// replace pixels of the frame with the ones provided by `src`, this is
+    /// done tile by tile on the worker pool. The coordinates passed to `src`
+    /// start at the bottom left corner of the frame.
+    pub fn load<G: Get<P> + Send + Sync + 'static>(&mut self, src: Arc<G>) {
+        let all = Rect::new(0, 0, self.width, self.height);
+        self.load_region(src, all);
+    }
+
+    /// like `load` but only the tiles that intersect `region` are visited
+    pub fn load_region<G: Get<P> + Send + Sync + 'static>(&mut self, src: Arc<G>, region: Rect) {
+        let (w, h) = (self.width, self.height);
+        let clock = self.clock(Pass::Load);
+        for (x, row) in self.tile.iter_mut().enumerate() {
+            for (y, tile) in row.iter_mut().enumerate() {
+                if group_rect(x as u32, y as u32, w, h).intersect(&region).is_none() {
+                    continue;
+                }
+
+                let (mut new, set) = Future::new();
+                mem::swap(tile, &mut new);
+                let src = src.clone();
+                let clock = clock.clone();
+                let signal = new.signal();
+                task(move |_| {
+                    let mut t = new.get();
+                    timed(&clock, Some((x as u32, y as u32)), || t.load((x*32_) as u32, (y*32_) as u32, &Clip::new(&*src, w, h)));
+                    set.set(t);
+                }).after(signal).start(&mut self.pool);
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use fibe::Frontend;
+
+use {Frame, ScalarBackend, Error, check_frame_size, error};
+
+/// the options of a new frame gathered one at a time, for the cases that
+/// `Frame::new` does not cover
+pub struct FrameBuilder<P> {
+    width: u32,
+    height: u32,
+    clear: P,
+    depth: bool,
+    samples: u32,
+    split_threshold: usize,
+    threads: Option<usize>,
+    pool: Option<Frontend>,
+    deterministic: bool
+}
+
+impl<P: Copy+Sync+Send+'static> FrameBuilder<P> {
+    /// a `width` by `height` frame filled with `clear`, with the defaults
+    /// of `Frame::new`
+    pub fn new(width: u32, height: u32, clear: P) -> FrameBuilder<P> {
+        FrameBuilder {
+            width: width,
+            height: height,
+            clear: clear,
+            depth: true,
+            samples: 1,
+            split_threshold: ::std::usize::MAX,
+            threads: None,
+            pool: None,
+            deterministic: false
+        }
+    }
+
+    /// without the depth test the frame starts in painter mode, see
+    /// `Frame::set_painter`
+    pub fn depth(mut self, on: bool) -> FrameBuilder<P> {
+        self.depth = on;
+        self
+    }
+
+    /// `factor` by `factor` samples for every pixel, the frame is made
+    /// that many times larger for `Frame::resolve` to bring it back down
+    /// to the size given to `new`
+    pub fn samples(mut self, factor: u32) -> FrameBuilder<P> {
+        assert!(factor > 0);
+        self.samples = factor;
+        self
+    }
+
+    /// the triangles of a draw that split a tile group into quadrants
+    /// with a worker each, see `Frame::set_split_threshold`
+    pub fn split_threshold(mut self, n: usize) -> FrameBuilder<P> {
+        self.split_threshold = n;
+        self
+    }
+
+    /// a pool of `n` workers instead of one per core
+    pub fn threads(mut self, n: usize) -> FrameBuilder<P> {
+        self.threads = Some(n);
+        self.pool = None;
+        self
+    }
+
+    /// run the tasks of the frame on `pool`
+    pub fn pool(mut self, pool: Frontend) -> FrameBuilder<P> {
+        self.pool = Some(pool);
+        self.threads = None;
+        self
+    }
+
+    /// a single worker and the scalar backend, so the frame gives the same
+    /// bits on any machine and its tasks run one at a time in the order
+    /// they were submitted. For tests and replays kept in lockstep.
+    pub fn deterministic(mut self, on: bool) -> FrameBuilder<P> {
+        self.deterministic = on;
+        self
+    }
+
+    /// the frame with the options given so far
+    pub fn build(self) -> Frame<P> {
+        let (width, height) = (self.width * self.samples, self.height * self.samples);
+        let pool = match (self.pool, self.threads, self.deterministic) {
+            (_, _, true) => Frontend::with_size(1),
+            (Some(pool), _, _) => pool,
+            (None, Some(n), _) => Frontend::with_size(n),
+            (None, None, _) => Frontend::new()
+        };
+        let mut frame = Frame::with_pool(width, height, self.clear, pool);
+        frame.set_painter(!self.depth);
+        frame.set_split_threshold(self.split_threshold);
+        if self.deterministic {
+            frame.set_backend(Arc::new(ScalarBackend));
+        }
+        frame
+    }
+
+    /// like `build` but the size of the frame, samples included, has to
+    /// be within the limits of `capabilities` like for `Frame::try_new`
+    pub fn try_build(self) -> error::Result<Frame<P>> {
+        match (self.width.checked_mul(self.samples), self.height.checked_mul(self.samples)) {
+            (Some(w), Some(h)) => try!(check_frame_size(w, h)),
+            _ => return Err(Error::InvalidSize { width: self.width, height: self.height })
+        }
+        Ok(self.build())
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// start building a frame with more options than `new` takes
+    pub fn builder(width: u32, height: u32, clear: P) -> FrameBuilder<P> {
+        FrameBuilder::new(width, height, clear)
+    }
+}
@@ -0,0 +1,59 @@
+use num_cpus;
+
+/// pixel types that work with every part of the crate: rastering, the
+/// post filters that need `Lerp` and reading back into a `Buffer`. Frames
+/// of any other `Copy` type can still be rastered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// `Rgba<u8>`, the only format `to_image` is available for when the
+    /// `image` feature is on
+    Rgba8,
+    /// `f32`
+    R32F,
+    /// `[f32; 2]`
+    Rg32F,
+    /// `[f32; 3]`
+    Rgb32F,
+    /// `[f32; 4]`
+    Rgba32F
+}
+
+/// limits and sizes of this renderer, see `capabilities`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    /// pixels along the side of a tile, the unit one SIMD mask covers
+    pub tile_size: u32,
+    /// pixels along the side of a tile group, the unit of work handed to a
+    /// worker thread
+    pub group_size: u32,
+    /// f32 lanes the fragment rows are shaded with
+    pub simd_width: usize,
+    /// frame sizes have to be a multiple of this, the tile groups along the
+    /// right and top edge of other sizes are partial
+    pub size_granularity: u32,
+    /// the largest width or height a frame can have
+    pub max_dimension: u32,
+    /// the largest number of pixels in a frame, readback into an 8 bit
+    /// RGBA image has to fit 32 bit indices
+    pub max_pixels: u64,
+    pub formats: Vec<Format>,
+    /// worker threads of the task pool every frame starts, unless it is
+    /// made with `Frame::with_threads`
+    pub threads: usize
+}
+
+/// what this build of the renderer supports
+pub fn capabilities() -> Capabilities {
+    let max_pixels = ::std::u32::MAX as u64 / 4;
+    Capabilities {
+        tile_size: 8,
+        group_size: 32,
+        simd_width: 8,
+        size_granularity: 1,
+        // the largest multiple of the group size whose square still fits
+        max_dimension: ((max_pixels as f64).sqrt() as u32) / 32 * 32,
+        max_pixels: max_pixels,
+        formats: vec![Format::Rgba8, Format::R32F, Format::Rg32F, Format::Rgb32F, Format::Rgba32F],
+        threads: num_cpus::get()
+    }
+}
@@ -0,0 +1,166 @@
+use std::io::{self, Write, BufRead};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use genmesh::Triangle;
+
+use {Frame, Fragment, FetchPosition, Issue};
+
+/// one raster call, the positions are in clip space
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptureDraw {
+    /// the type name of the fragment shader
+    pub shader: String,
+    pub two_sided: bool,
+    pub triangles: Vec<[[f32; 4]; 3]>
+}
+
+/// every triangle submitted to a frame while capturing, it can be saved
+/// to a plain text file and rastered again later
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capture {
+    pub width: u32,
+    pub height: u32,
+    pub draws: Vec<CaptureDraw>
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Capture {
+    pub fn new(width: u32, height: u32) -> Capture {
+        Capture {
+            width: width,
+            height: height,
+            draws: Vec::new()
+        }
+    }
+
+    pub fn begin_draw(&mut self, shader: &str, two_sided: bool) {
+        self.draws.push(CaptureDraw {
+            shader: shader.to_string(),
+            two_sided: two_sided,
+            triangles: Vec::new()
+        });
+    }
+
+    /// add a triangle to the last draw
+    pub fn triangle<T: FetchPosition>(&mut self, t: &Triangle<T>) {
+        if let Some(draw) = self.draws.last_mut() {
+            draw.triangles.push([t.x.position(), t.y.position(), t.z.position()]);
+        }
+    }
+
+    pub fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(writeln!(out, "rusterize-capture 1"));
+        try!(writeln!(out, "frame {} {}", self.width, self.height));
+        for draw in self.draws.iter() {
+            try!(writeln!(out, "draw {} {} {}", draw.two_sided as u8, draw.triangles.len(), draw.shader));
+            for t in draw.triangles.iter() {
+                for v in t.iter() {
+                    // the debug formatting of a float round trips exactly
+                    try!(write!(out, "{:?} {:?} {:?} {:?} ", v[0], v[1], v[2], v[3]));
+                }
+                try!(writeln!(out, ""));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load<R: BufRead>(src: R) -> io::Result<Capture> {
+        let mut lines = src.lines();
+        let mut next = || -> io::Result<String> {
+            match lines.next() {
+                Some(line) => line,
+                None => Err(invalid("unexpected end of capture"))
+            }
+        };
+
+        if try!(next()).trim() != "rusterize-capture 1" {
+            return Err(invalid("not a capture"));
+        }
+
+        let frame = try!(next());
+        let frame: Vec<&str> = frame.split_whitespace().collect();
+        if frame.len() != 3 || frame[0] != "frame" {
+            return Err(invalid("missing frame size"));
+        }
+        let mut capture = Capture::new(
+            try!(frame[1].parse().map_err(|_| invalid("bad width"))),
+            try!(frame[2].parse().map_err(|_| invalid("bad height")))
+        );
+
+        loop {
+            let draw = match next() {
+                Ok(line) => line,
+                Err(_) => break
+            };
+            let fields: Vec<&str> = draw.splitn(4, ' ').collect();
+            if fields.len() != 4 || fields[0] != "draw" {
+                return Err(invalid("expected a draw"));
+            }
+            let count: usize = try!(fields[2].parse().map_err(|_| invalid("bad triangle count")));
+            capture.begin_draw(fields[3], fields[1] == "1");
+
+            for _ in 0..count {
+                let line = try!(next());
+                let v: Vec<f32> = try!(line.split_whitespace()
+                                           .map(|f| f.parse().map_err(|_| invalid("bad vertex")))
+                                           .collect());
+                if v.len() != 12 {
+                    return Err(invalid("a triangle needs 12 values"));
+                }
+                capture.draws.last_mut().unwrap().triangles.push([
+                    [v[0], v[1], v[2], v[3]],
+                    [v[4], v[5], v[6], v[7]],
+                    [v[8], v[9], v[10], v[11]]
+                ]);
+            }
+        }
+        Ok(capture)
+    }
+
+    /// raster the captured triangles again, `shader` picks the fragment
+    /// shader of each draw from its recorded name
+    pub fn replay<P, F, M>(&self, frame: &mut Frame<P>, mut shader: M)
+        where P: Copy + Sync + Send + 'static,
+              M: FnMut(&str) -> F,
+              F: Fragment<[f32; 4], Color=P> + Send + Sync + 'static {
+
+        for draw in self.draws.iter() {
+            let tris = draw.triangles.iter().map(|t| Triangle::new(t[0], t[1], t[2]));
+            if draw.two_sided {
+                let (front, back) = (shader(&draw.shader), shader(&draw.shader));
+                frame.raster_two_sided(tris, front, back);
+            } else {
+                frame.raster(tris, shader(&draw.shader));
+            }
+
+            let found = unsafe { ::std::intrinsics::type_name::<F>() };
+            if found != draw.shader {
+                frame.validation_issue(Issue::ShaderMismatch {
+                    expected: draw.shader.clone(),
+                    found: found.to_string()
+                });
+            }
+        }
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// start recording every triangle submitted to the frame
+    pub fn begin_capture(&mut self) {
+        self.capture = Some(Arc::new(Mutex::new(Capture::new(self.width, self.height))));
+    }
+
+    /// stop recording and return what was captured
+    pub fn end_capture(&mut self) -> Option<Capture> {
+        self.capture.take().map(|c| {
+            let mut c = c.lock().unwrap();
+            let empty = Capture::new(c.width, c.height);
+            mem::replace(&mut *c, empty)
+        })
+    }
+
+}
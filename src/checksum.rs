@@ -0,0 +1,71 @@
+use std::mem;
+use std::hash::Hash;
+
+use fibe::{task, IntoTask};
+use future_pulse::Future;
+
+use {Frame, Rect, group_rect};
+
+/// the checksum of every tile group of a frame, see `Frame::checksums`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checksums {
+    pub width: u32,
+    pub height: u32,
+    groups: Vec<Vec<u64>>
+}
+
+impl Checksums {
+    /// the checksum of group `x`, `y`, counted in groups of 32 pixels from
+    /// the bottom left like `Frame::tile`
+    pub fn get(&self, x: u32, y: u32) -> u64 {
+        self.groups[x as usize][y as usize]
+    }
+
+    /// the pixels of the groups whose checksum differs from `since`, in
+    /// image coordinates. Everything counts as changed if the sizes differ.
+    pub fn changed(&self, since: &Checksums) -> Vec<Rect> {
+        let all = (self.width, self.height) != (since.width, since.height);
+        let mut out = Vec::new();
+        for (x, row) in self.groups.iter().enumerate() {
+            for (y, &sum) in row.iter().enumerate() {
+                if all || since.groups[x][y] != sum {
+                    out.push(group_rect(x as u32, y as u32, self.width, self.height));
+                }
+            }
+        }
+        out
+    }
+}
+
+impl<P: Copy+Hash+Sync+Send+'static> Frame<P> {
+    /// wait for the work pending on the frame and take the checksum of
+    /// every tile group, see `TileGroup::checksum`. Only the groups written
+    /// to since the last call are hashed again. Comparing two of them with
+    /// `Checksums::changed` finds the parts of the frame a viewer has to be
+    /// sent again.
+    pub fn checksums(&mut self) -> Checksums {
+        let mut sums = Vec::with_capacity(self.tile.len());
+        for row in self.tile.iter_mut() {
+            let mut column = Vec::with_capacity(row.len());
+            for tile in row.iter_mut() {
+                let (mut new, set) = Future::new();
+                mem::swap(tile, &mut new);
+                let (sum, set_sum) = Future::new();
+                let signal = new.signal();
+                task(move |_| {
+                    let mut t = new.get();
+                    set_sum.set(t.checksum());
+                    set.set(t);
+                }).after(signal).start(&mut self.pool);
+                column.push(sum);
+            }
+            sums.push(column);
+        }
+
+        Checksums {
+            width: self.width,
+            height: self.height,
+            groups: sums.into_iter().map(|row| row.into_iter().map(|s| s.get()).collect()).collect()
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use genmesh::Triangle;
+
+use {Interpolate, Plane, FetchPosition};
+
+/// the most user clip planes a single raster call will accept
+pub const MAX_CLIP_PLANES: usize = 8;
+
+/// a vertex made by clipping a triangle, its attributes are found by
+/// interpolating the original triangle with `weights`
+#[derive(Clone, Debug)]
+pub struct SubVertex<T> {
+    pub source: Arc<Triangle<T>>,
+    pub weights: [f32; 3],
+    pub position: [f32; 4]
+}
+
+impl<T> FetchPosition for SubVertex<T> {
+    fn position(&self) -> [f32; 4] { self.position }
+}
+
+impl<T: Interpolate> Interpolate for SubVertex<T> {
+    type Out = T::Out;
+    type Plane = SubPlane<T::Plane>;
+    #[inline]
+    fn interpolate(src: &Triangle<SubVertex<T>>, w: [f32; 3]) -> T::Out {
+        let (a, b, c) = (src.x.weights, src.y.weights, src.z.weights);
+        let w = [a[0] * w[0] + b[0] * w[1] + c[0] * w[2],
+                 a[1] * w[0] + b[1] * w[1] + c[1] * w[2],
+                 a[2] * w[0] + b[2] * w[1] + c[2] * w[2]];
+        Interpolate::interpolate(&*src.x.source, w)
+    }
+
+    #[inline]
+    fn setup(src: &Triangle<SubVertex<T>>) -> SubPlane<T::Plane> {
+        let (a, b, c) = (src.x.weights, src.y.weights, src.z.weights);
+        SubPlane {
+            plane: Interpolate::setup(&*src.x.source),
+            u: [a[1], b[1] - a[1], c[1] - a[1]],
+            v: [a[2], b[2] - a[2], c[2] - a[2]]
+        }
+    }
+}
+
+/// the plane of the original triangle, with the coordinates of the
+/// clipped triangle mapped back onto it
+#[derive(Clone, Debug)]
+pub struct SubPlane<L> {
+    plane: L,
+    u: [f32; 3],
+    v: [f32; 3]
+}
+
+impl<L: Plane> Plane for SubPlane<L> {
+    type Out = L::Out;
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> L::Out {
+        self.plane.evaluate(self.u.evaluate(u, v), self.v.evaluate(u, v))
+    }
+}
+
+#[inline]
+fn lerp4(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [a[0] + (b[0] - a[0]) * t,
+     a[1] + (b[1] - a[1]) * t,
+     a[2] + (b[2] - a[2]) * t,
+     a[3] + (b[3] - a[3]) * t]
+}
+
+/// cut a triangle by clip space planes, a plane `[a, b, c, d]` keeps the
+/// points where `a*x + b*y + c*z + d*w >= 0`. The result is a fan of
+/// triangles with the same winding as the input.
+pub fn clip_triangle<T>(t: Triangle<T>, planes: &[[f32; 4]]) -> Vec<Triangle<SubVertex<T>>>
+    where T: FetchPosition {
+
+    assert!(planes.len() <= MAX_CLIP_PLANES);
+
+    let positions = [t.x.position(), t.y.position(), t.z.position()];
+    let source = Arc::new(t);
+    let mut poly: Vec<([f32; 4], [f32; 3])> = vec![
+        (positions[0], [1., 0., 0.]),
+        (positions[1], [0., 1., 0.]),
+        (positions[2], [0., 0., 1.])
+    ];
+
+    for plane in planes.iter() {
+        if poly.is_empty() {
+            break;
+        }
+
+        let dist = |p: [f32; 4]| p[0] * plane[0] + p[1] * plane[1] + p[2] * plane[2] + p[3] * plane[3];
+        let input = poly;
+        poly = Vec::with_capacity(input.len() + 1);
+        for (i, &(p, pw)) in input.iter().enumerate() {
+            let (q, qw) = input[(i + 1) % input.len()];
+            let (dp, dq) = (dist(p), dist(q));
+            if dp >= 0. {
+                poly.push((p, pw));
+            }
+            if (dp >= 0.) != (dq >= 0.) {
+                let t = dp / (dp - dq);
+                let w = lerp4([pw[0], pw[1], pw[2], 0.], [qw[0], qw[1], qw[2], 0.], t);
+                poly.push((lerp4(p, q, t), [w[0], w[1], w[2]]));
+            }
+        }
+    }
+
+    if poly.len() < 3 {
+        return Vec::new();
+    }
+
+    let vertex = |&(p, w): &([f32; 4], [f32; 3])| SubVertex {
+        source: source.clone(),
+        weights: w,
+        position: p
+    };
+    (1..poly.len() - 1).map(|i| {
+        Triangle::new(vertex(&poly[0]), vertex(&poly[i]), vertex(&poly[i + 1]))
+    }).collect()
+}
@@ -0,0 +1,75 @@
+#[cfg(feature = "image")]
+pub use image::Rgba;
+
+/// an 8 bit per channel color laid out like `image::Rgba`, it takes its
+/// place when the crate is built without the `image` feature
+#[cfg(not(feature = "image"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rgba<T> {
+    pub data: [T; 4]
+}
+
+/// builds an `Rgba` the same way as `image::Rgba`
+#[cfg(not(feature = "image"))]
+#[allow(non_snake_case)]
+#[inline]
+pub fn Rgba<T>(data: [T; 4]) -> Rgba<T> {
+    Rgba { data: data }
+}
+
+/// linear interpolation between two pixel values, `t` is in the range 0 to 1
+pub trait Lerp {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    #[inline]
+    fn lerp(self, other: f32, t: f32) -> f32 {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for [f32; 2] {
+    #[inline]
+    fn lerp(self, other: [f32; 2], t: f32) -> [f32; 2] {
+        [self[0].lerp(other[0], t),
+         self[1].lerp(other[1], t)]
+    }
+}
+
+impl Lerp for [f32; 3] {
+    #[inline]
+    fn lerp(self, other: [f32; 3], t: f32) -> [f32; 3] {
+        [self[0].lerp(other[0], t),
+         self[1].lerp(other[1], t),
+         self[2].lerp(other[2], t)]
+    }
+}
+
+impl Lerp for [f32; 4] {
+    #[inline]
+    fn lerp(self, other: [f32; 4], t: f32) -> [f32; 4] {
+        [self[0].lerp(other[0], t),
+         self[1].lerp(other[1], t),
+         self[2].lerp(other[2], t),
+         self[3].lerp(other[3], t)]
+    }
+}
+
+impl Lerp for Rgba<u8> {
+    #[inline]
+    fn lerp(self, other: Rgba<u8>, t: f32) -> Rgba<u8> {
+        let (a, b) = (self.data, other.data);
+        let c = |i: usize| (a[i] as f32).lerp(b[i] as f32, t).max(0.).min(255.) + 0.5;
+        Rgba([c(0) as u8, c(1) as u8, c(2) as u8, c(3) as u8])
+    }
+}
+
+/// composite `src` over `dst` using the alpha of `src`
+#[inline]
+pub fn alpha_over(dst: Rgba<u8>, src: Rgba<u8>) -> Rgba<u8> {
+    let a = src.data[3] as f32 / 255.;
+    let mut out = dst.lerp(src, a);
+    out.data[3] = (src.data[3] as f32 + dst.data[3] as f32 * (1. - a) + 0.5).min(255.) as u8;
+    out
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use std::fmt::Debug;
+
+use genmesh::Triangle;
+
+use {Frame, Fragment, Interpolate, FetchPosition, LoadOp};
+
+/// a recorded sequence of frame operations that can be replayed into any
+/// frame of the same pixel type. Geometry is captured in clip space so the
+/// same list can be replayed at different resolutions.
+pub struct CommandList<P> {
+    commands: Vec<Box<Fn(&mut Frame<P>) + Send + Sync>>
+}
+
+impl<P: Copy+Sync+Send+'static> CommandList<P> {
+    pub fn new() -> CommandList<P> {
+        CommandList {
+            commands: Vec::new()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn clear(&mut self, p: P) {
+        self.record(move |frame| frame.clear(p));
+    }
+
+    pub fn load_op(&mut self, load: LoadOp<P>) {
+        self.record(move |frame| frame.load_op(load));
+    }
+
+    /// capture the triangles of `poly`, they are rastered with a clone of
+    /// `fragment` on every replay
+    pub fn raster<S, F, T, O>(&mut self, poly: S, fragment: F)
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: Fragment<O, Color=P> + Clone + Send + Sync + 'static {
+
+        let poly: Arc<Vec<Triangle<T>>> = Arc::new(poly.collect());
+        self.record(move |frame| { frame.raster(poly.iter().cloned(), fragment.clone()); });
+    }
+
+    /// record an arbitrary operation on the frame
+    pub fn record<F>(&mut self, f: F) where F: Fn(&mut Frame<P>) + Send + Sync + 'static {
+        self.commands.push(Box::new(f));
+    }
+
+    /// run every command in the order they were recorded
+    pub fn replay(&self, frame: &mut Frame<P>) {
+        for command in self.commands.iter() {
+            command(frame);
+        }
+    }
+}
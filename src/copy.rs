@@ -0,0 +1,127 @@
+use std::mem;
+
+use fibe::{task, IntoTask};
+use future_pulse::Future;
+use std::sync::Arc;
+
+use {Frame, Buffer, Rect, error};
+use tile::Get;
+
+/// maps the destination of a rectangle copy back into the source pixels
+struct RectCopy<P> {
+    src: Buffer<P>,
+    src_pos: (u32, u32),
+    dst: Rect,
+    dst_height: u32
+}
+
+impl<P: Copy> Get<P> for RectCopy<P> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        let y = self.dst_height - 1 - y;
+        if self.dst.contains(x, y) {
+            Some(self.src.get_pixel(self.src_pos.0 + x - self.dst.x,
+                                    self.src_pos.1 + y - self.dst.y))
+        } else {
+            None
+        }
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// replace the contents of this frame, color and depth, with the ones
+    /// of `src`. Both frames must be the same size.
+    pub fn copy_from(&mut self, src: &mut Frame<P>) {
+        assert!(src.width == self.width);
+        assert!(src.height == self.height);
+
+        for (row, src_row) in self.tile.iter_mut().zip(src.tile.iter_mut()) {
+            for (tile, src_tile) in row.iter_mut().zip(src_row.iter_mut()) {
+                let (mut new, tx_self) = Future::new();
+                mem::swap(tile, &mut new);
+                let (mut src, tx_src) = Future::new();
+                mem::swap(src_tile, &mut src);
+                let (s0, s1) = (new.signal(), src.signal());
+                task(move |_| {
+                    let mut dst = new.get();
+                    let src = src.get();
+                    *dst = (*src).clone();
+                    tx_self.set(dst);
+                    tx_src.set(src);
+                }).after(s0).after(s1).start(&mut self.pool);
+            }
+        }
+    }
+
+    /// like `copy_from` but a source of a different size is an error
+    pub fn try_copy_from(&mut self, src: &mut Frame<P>) -> error::Result<()> {
+        try!(error::check_size((self.width, self.height), (src.width, src.height)));
+        self.copy_from(src);
+        Ok(())
+    }
+
+    /// copy the colors inside of `src_rect` of `src` so that its top left
+    /// corner lands at `dst_pos`. The region is clipped to both frames.
+    pub fn copy_rect(&mut self, src: &mut Frame<P>, src_rect: Rect, dst_pos: (u32, u32)) {
+        let src_rect = match src_rect.intersect(&Rect::new(0, 0, src.width, src.height)) {
+            Some(r) => r,
+            None => return
+        };
+        let dst = Rect::new(dst_pos.0, dst_pos.1, src_rect.width, src_rect.height);
+        let dst = match dst.intersect(&Rect::new(0, 0, self.width, self.height)) {
+            Some(r) => r,
+            None => return
+        };
+
+        let copy = RectCopy {
+            src: src.to_buffer(),
+            src_pos: (src_rect.x, src_rect.y),
+            dst: dst,
+            dst_height: self.height
+        };
+        self.load_region(Arc::new(copy), dst);
+    }
+}
+
+impl<A: Copy+Sync+Send+'static> Frame<A> {
+    /// convert every pixel of this frame with `f` into `dst` of another
+    /// pixel type, like `u8` to `f32` colors, a swizzle or packing before a
+    /// read back. Unlike `map` the depth and stencil come along and the
+    /// groups that were never drawn to stay cheap. The tiles of `dst` are
+    /// written in place. Both frames must be the same size.
+    pub fn convert_into<B, F>(&mut self, dst: &mut Frame<B>, f: F)
+        where B: Copy+Sync+Send+'static,
+              F: Fn(A) -> B + Send + Sync + 'static {
+        assert!(dst.width == self.width);
+        assert!(dst.height == self.height);
+
+        let f = Arc::new(f);
+        for (row, dst_row) in self.tile.iter_mut().zip(dst.tile.iter_mut()) {
+            for (tile, dst_tile) in row.iter_mut().zip(dst_row.iter_mut()) {
+                let (mut src, tx_src) = Future::new();
+                mem::swap(tile, &mut src);
+                let (mut new, tx_dst) = Future::new();
+                mem::swap(dst_tile, &mut new);
+                let f = f.clone();
+                let (s0, s1) = (src.signal(), new.signal());
+                task(move |_| {
+                    let src = src.get();
+                    let mut out = new.get();
+                    out.convert_from(&*src, &*f);
+                    tx_src.set(src);
+                    tx_dst.set(out);
+                }).after(s0).after(s1).start(&mut self.pool);
+            }
+        }
+    }
+
+    /// like `convert_into` but a destination of a different size is an
+    /// error
+    pub fn try_convert_into<B, F>(&mut self, dst: &mut Frame<B>, f: F) -> error::Result<()>
+        where B: Copy+Sync+Send+'static,
+              F: Fn(A) -> B + Send + Sync + 'static {
+        try!(error::check_size((self.width, self.height), (dst.width, dst.height)));
+        self.convert_into(dst, f);
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use fibe::{task, IntoTask};
+use future_pulse::Future;
+use time::precise_time_ns;
+
+use Frame;
+
+/// a point in time after which the draws stop shading, the tile groups
+/// they skipped work on are marked stale
+pub struct Deadline {
+    expires: u64,
+    rows: usize,
+    stale: Vec<AtomicBool>
+}
+
+impl Deadline {
+    fn new(budget: u64, columns: usize, rows: usize) -> Deadline {
+        Deadline {
+            expires: precise_time_ns() + budget,
+            rows: rows,
+            stale: (0..columns * rows).map(|_| AtomicBool::new(false)).collect()
+        }
+    }
+
+    #[inline]
+    pub fn expired(&self) -> bool {
+        precise_time_ns() >= self.expires
+    }
+
+    /// true if the work on group `x`, `y` can go ahead, otherwise the group
+    /// is marked stale. The workers ask once for every batch of triangles
+    /// they take.
+    #[inline]
+    pub fn check(&self, group: (u32, u32)) -> bool {
+        if self.expired() {
+            self.mark(group);
+            false
+        } else {
+            true
+        }
+    }
+
+    /// mark group `x`, `y` stale, work on it was skipped
+    #[inline]
+    pub fn mark(&self, group: (u32, u32)) {
+        self.stale[group.0 as usize * self.rows + group.1 as usize].store(true, Ordering::Relaxed);
+    }
+
+    fn stale(&self) -> Vec<(u32, u32)> {
+        self.stale.iter().enumerate()
+            .filter(|&(_, s)| s.load(Ordering::Relaxed))
+            .map(|(i, _)| ((i / self.rows) as u32, (i % self.rows) as u32))
+            .collect()
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// give the draws from now on `budget` nanoseconds, after that the
+    /// triangles that are still queued for a tile group are dropped
+    /// instead of shaded. Draws made once it has passed are dropped whole,
+    /// the triangles that would have reached a tile group are counted in
+    /// `DrawStats::late`. None turns the deadline off, so does a clear. See
+    /// `finish_late`.
+    pub fn set_deadline(&mut self, budget: Option<u64>) {
+        let (columns, rows) = (self.tile.len(), self.tile.first().map(|r| r.len()).unwrap_or(0));
+        self.deadline = budget.map(|b| ::std::sync::Arc::new(Deadline::new(b, columns, rows)));
+    }
+
+    /// wait for the frame and turn the deadline off, the tile groups that
+    /// missed it are filled from the same groups of `history`, usually the
+    /// last frame. Without one they are left at the color they were last
+    /// cleared to. Returns the stale groups, counted in groups of 32 pixels from the
+    /// bottom left like `tile`.
+    pub fn finish_late(&mut self, history: Option<&mut Frame<P>>) -> Vec<(u32, u32)> {
+        self.flush();
+        let stale = match self.deadline.take() {
+            Some(d) => d.stale(),
+            None => return Vec::new()
+        };
+
+        match history {
+            Some(history) => self.fill_stale(&stale, history),
+            None => self.clear_stale(&stale)
+        }
+        stale
+    }
+
+    fn fill_stale(&mut self, stale: &[(u32, u32)], history: &mut Frame<P>) {
+        assert!(history.width == self.width && history.height == self.height);
+        for &(x, y) in stale.iter() {
+            let (x, y) = (x as usize, y as usize);
+            let (mut new, tx_self) = Future::new();
+            mem::swap(&mut self.tile[x][y], &mut new);
+            let (mut src, tx_src) = Future::new();
+            mem::swap(&mut history.tile[x][y], &mut src);
+            let (s0, s1) = (new.signal(), src.signal());
+            task(move |_| {
+                let mut dst = new.get();
+                let src = src.get();
+                *dst = (*src).clone();
+                tx_self.set(dst);
+                tx_src.set(src);
+            }).after(s0).after(s1).start(&mut self.pool);
+        }
+    }
+
+    fn clear_stale(&mut self, stale: &[(u32, u32)]) {
+        for &(x, y) in stale.iter() {
+            let (mut new, set) = Future::new();
+            mem::swap(&mut self.tile[x as usize][y as usize], &mut new);
+            let signal = new.signal();
+            task(move |_| {
+                let mut group = new.get();
+                let p = group.clear_color();
+                group.clear(p);
+                set.set(group);
+            }).after(signal).start(&mut self.pool);
+        }
+    }
+}
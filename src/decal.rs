@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use cgmath::{Matrix, Matrix4, Vector4};
+
+use {Frame, Buffer, alpha_over, pixel_ndc, Rgba, IntoMatrix};
+use tile::Get;
+
+/// a texture projected onto whatever the frame already shows inside of a
+/// box, see `Frame::decal`
+#[derive(Clone)]
+pub struct Decal {
+    /// from the clip space the frame was drawn with to world space, the
+    /// inverse of the view projection matrix of the camera
+    pub inverse_view_proj: Matrix4<f32>,
+    /// from world space into the box of the decal, the box spans -1 to 1
+    /// on every axis and the texture is laid over x and y with +y up
+    pub world_to_decal: Matrix4<f32>,
+    pub texture: Arc<Buffer<Rgba<u8>>>,
+    /// scales the alpha of the texture
+    pub opacity: f32
+}
+
+impl Decal {
+    pub fn new<A: IntoMatrix, B: IntoMatrix>(inverse_view_proj: A, world_to_decal: B,
+                                             texture: Arc<Buffer<Rgba<u8>>>) -> Decal {
+        Decal {
+            inverse_view_proj: inverse_view_proj.into_matrix(),
+            world_to_decal: world_to_decal.into_matrix(),
+            texture: texture,
+            opacity: 1.
+        }
+    }
+}
+
+/// blends the decal over the pixels whose surface is inside of the box,
+/// the rest are left untouched
+struct DecalPass {
+    decal: Decal,
+    color: Buffer<Rgba<u8>>,
+    depth: Buffer<f32>,
+    /// to the box of the decal straight from clip space
+    clip_to_decal: Matrix4<f32>
+}
+
+impl Get<Rgba<u8>> for DecalPass {
+    fn get(&self, x: u32, y: u32) -> Option<Rgba<u8>> {
+        let (w, h) = (self.depth.width, self.depth.height);
+        let iy = h - 1 - y;
+        let z = self.depth.get_pixel(x, iy);
+        // nothing was drawn here
+        if z >= 1. {
+            return None;
+        }
+
+        let ndc = pixel_ndc(x, iy, w, h);
+        let p = self.clip_to_decal.mul_v(&Vector4::new(ndc[0], ndc[1], z, 1.));
+        let (dx, dy, dz) = (p.x / p.w, p.y / p.w, p.z / p.w);
+        if !(dx.abs() <= 1. && dy.abs() <= 1. && dz.abs() <= 1.) {
+            return None;
+        }
+
+        let mut src = self.decal.texture.sample((dx + 1.) / 2., (1. - dy) / 2.);
+        src.data[3] = (src.data[3] as f32 * self.decal.opacity.max(0.).min(1.) + 0.5) as u8;
+        Some(alpha_over(self.color.get_pixel(x, iy), src))
+    }
+}
+
+impl Frame<Rgba<u8>> {
+    /// project `decal` onto the surfaces in this frame. The position of
+    /// every pixel is rebuilt from the depth buffer, read through the
+    /// current `DepthMode`, the ones that land in the box of the decal get
+    /// its texture blended over their color. The depth buffer is left as
+    /// it is.
+    pub fn decal(&mut self, decal: &Decal) {
+        let pass = DecalPass {
+            clip_to_decal: decal.world_to_decal.mul_m(&decal.inverse_view_proj),
+            decal: decal.clone(),
+            color: self.to_buffer(),
+            depth: self.ndc_depth_buffer()
+        };
+        self.load(Arc::new(pass));
+    }
+}
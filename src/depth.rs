@@ -0,0 +1,204 @@
+use cgmath::{Matrix, Matrix4, Vector4};
+
+use {Frame, Buffer, Rect, IntoMatrix};
+
+/// the kind of projection a frame was drawn with and its clip planes,
+/// the way `cgmath::perspective` and `cgmath::ortho` lay them out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective { near: f32, far: f32 },
+    Orthographic { near: f32, far: f32 }
+}
+
+impl Projection {
+    /// the distance from the eye along the view direction for a stored
+    /// depth, `near` for -1 and `far` for 1
+    pub fn linear_depth(&self, z: f32) -> f32 {
+        match *self {
+            Projection::Perspective { near, far } =>
+                2. * far * near / ((far + near) - z * (far - near)),
+            Projection::Orthographic { near, far } =>
+                (z * (far - near) + (far + near)) / 2.
+        }
+    }
+
+    /// the inverse of `linear_depth`
+    pub fn ndc_depth(&self, d: f32) -> f32 {
+        match *self {
+            Projection::Perspective { near, far } =>
+                ((far + near) - 2. * far * near / d) / (far - near),
+            Projection::Orthographic { near, far } =>
+                (2. * d - (far + near)) / (far - near)
+        }
+    }
+}
+
+/// what the depth of a draw is taken from and how it is stored, see
+/// `Frame::set_depth_mode`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DepthMode {
+    /// the z of the positions after the perspective divide, -1 on the near
+    /// plane and 1 on the far plane
+    Projected,
+    /// for orthographic projections where the far plane matters, like in
+    /// CAD views. The z of the positions is the distance from the eye,
+    /// which an orthographic projection keeps linear, and it is stored
+    /// with the far plane at 0 and the near plane at -1. Floats are dense
+    /// around 0, so the surfaces close to the far plane are told apart as
+    /// finely as the distances handed in.
+    Linear { near: f32, far: f32 }
+}
+
+impl Default for DepthMode {
+    fn default() -> DepthMode { DepthMode::Projected }
+}
+
+impl DepthMode {
+    /// the value stored in the depth buffer for the z of a position
+    #[inline]
+    pub fn store(&self, z: f32) -> f32 {
+        match *self {
+            DepthMode::Projected => z,
+            // the difference is exact close to the far plane
+            DepthMode::Linear { near, far } => (z - far) / (far - near)
+        }
+    }
+
+    /// the stored depth of the far plane, fragments at or beyond it fail
+    /// the depth test
+    #[inline]
+    pub fn far_plane(&self) -> f32 {
+        match *self {
+            DepthMode::Projected => 1.,
+            DepthMode::Linear { .. } => 0.
+        }
+    }
+
+    /// the depth of the projection for a stored value, -1 to 1 like in
+    /// `Projected` mode. Linear depth is taken to come from an orthographic
+    /// projection between the same planes.
+    #[inline]
+    pub fn ndc_depth(&self, stored: f32) -> f32 {
+        match *self {
+            DepthMode::Projected => stored,
+            DepthMode::Linear { .. } => 2. * stored + 1.
+        }
+    }
+
+    /// the distance from the eye for a stored value, see
+    /// `Projection::linear_depth`
+    pub fn linear_depth(&self, projection: Projection, stored: f32) -> f32 {
+        match *self {
+            DepthMode::Projected => projection.linear_depth(stored),
+            DepthMode::Linear { near, far } => stored * (far - near) + far
+        }
+    }
+}
+
+/// the normalized device coordinates the rasterizer samples pixel `x`,
+/// `y` of a `width` by `height` frame at, `y` starts at the top like in
+/// the images read back from a frame
+#[inline]
+pub fn pixel_ndc(x: u32, y: u32, width: u32, height: u32) -> [f32; 2] {
+    // pixels are sampled at their bottom left corner
+    let (wh, hh) = (width as f32 / 2., height as f32 / 2.);
+    [(x as f32 - wh) / wh, ((height - 1 - y) as f32 - hh) / hh]
+}
+
+/// where the clip space position `clip` lands on a `width` by `height`
+/// frame, the inverse of `pixel_ndc`. The first two are image coordinates,
+/// whole numbers fall on the sample points of the pixels, so rounding
+/// them gives the pixel whose sample is closest. The third is the depth
+/// the rasterizer stores for the point. None for points on or behind the
+/// plane of the eye.
+#[inline]
+pub fn clip_to_screen(clip: [f32; 4], width: u32, height: u32) -> Option<[f32; 3]> {
+    if !(clip[3] > 0.) {
+        return None;
+    }
+    let (wh, hh) = (width as f32 / 2., height as f32 / 2.);
+    let ndc = [clip[0] / clip[3], clip[1] / clip[3], clip[2] / clip[3]];
+    let screen = [ndc[0] * wh + wh, (height - 1) as f32 - (ndc[1] * hh + hh), ndc[2]];
+    if screen.iter().all(|v| v.is_finite()) { Some(screen) } else { None }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// `clip_to_screen` for this frame. Frames always draw to all of their
+    /// pixels without a jitter, so this agrees with what is rastered for
+    /// any draw. The depth is stored the way the current `DepthMode` does.
+    pub fn project_to_screen(&self, clip: [f32; 4]) -> Option<[f32; 3]> {
+        clip_to_screen(clip, self.width, self.height).map(|mut p| {
+            p[2] = self.depth_mode().store(p[2]);
+            p
+        })
+    }
+
+    /// the world position of the surface drawn at pixel `x`, `y`, from the
+    /// top left like in the images read back, found with the stored depth
+    /// and `inverse_view_proj`, the inverse of the view projection matrix
+    /// the pixel was drawn with. None where nothing was drawn, the depth is
+    /// still on the far plane there, or outside of the frame. The depth is
+    /// read with the current `DepthMode`.
+    pub fn unproject<M: IntoMatrix>(&mut self, x: u32, y: u32, inverse_view_proj: M) -> Option<[f32; 3]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let z = self.depth_region(Rect::new(x, y, 1, 1)).get_pixel(0, 0);
+        if !(z < 1.) {
+            return None;
+        }
+        let z = self.depth_mode().ndc_depth(z);
+        let ndc = pixel_ndc(x, y, self.width, self.height);
+        let p = inverse_view_proj.into_matrix().mul_v(&Vector4::new(ndc[0], ndc[1], z, 1.));
+        if p.w == 0. {
+            return None;
+        }
+        Some([p.x / p.w, p.y / p.w, p.z / p.w])
+    }
+
+    /// the depth buffer with every value read through the current
+    /// `DepthMode`, -1 on the near plane and 1 on the far plane like in
+    /// `Projected` mode. Passes that rebuild positions from the depth read
+    /// this instead of `depth_buffer`.
+    pub fn ndc_depth_buffer(&mut self) -> Buffer<f32> {
+        let mode = self.depth_mode();
+        let mut depth = self.depth_buffer();
+        if mode != DepthMode::Projected {
+            for z in depth.data.iter_mut() {
+                *z = mode.ndc_depth(*z);
+            }
+        }
+        depth
+    }
+
+    /// the depth buffer turned into distances from the eye, laid out like
+    /// `depth_buffer`. The depth is read with the current `DepthMode`.
+    pub fn linear_depth(&mut self, projection: Projection) -> Buffer<f32> {
+        let mode = self.depth_mode();
+        let mut depth = self.depth_buffer();
+        for z in depth.data.iter_mut() {
+            *z = mode.linear_depth(projection, *z);
+        }
+        depth
+    }
+
+    /// the view space position of the surface behind every pixel, found
+    /// with `inverse_proj`, the inverse of the projection matrix. Pixels
+    /// nothing was drawn to end up on the far plane, beyond it in `Linear`
+    /// depth mode.
+    pub fn view_positions<M: IntoMatrix>(&mut self, inverse_proj: M) -> Buffer<[f32; 3]> {
+        let inverse_proj = inverse_proj.into_matrix();
+        let mode = self.depth_mode();
+        let depth = self.depth_buffer();
+        let (w, h) = (depth.width, depth.height);
+        let mut out = Buffer::new(w, h, [0.; 3]);
+        for y in 0..h {
+            for x in 0..w {
+                let ndc = pixel_ndc(x, y, w, h);
+                let p = inverse_proj.mul_v(&Vector4::new(ndc[0], ndc[1], mode.ndc_depth(depth.get_pixel(x, y)), 1.));
+                out.put_pixel(x, y, [p.x / p.w, p.y / p.w, p.z / p.w]);
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,75 @@
+use image::{ImageBuffer, Rgba};
+
+/// per pixel statistics of how far an image is from a reference, see
+/// `image_diff`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageDiff {
+    pub pixels: usize,
+    /// pixels with a channel further off than the tolerance
+    pub mismatched: usize,
+    /// pixels that are background, an alpha of 0, in one image only. These
+    /// are the coverage differences.
+    pub coverage: usize,
+    /// the largest difference of any channel
+    pub max: u8,
+    /// the mean absolute difference over all channels of all pixels
+    pub mean: f32,
+    /// the mean squared difference, the same way
+    pub mse: f32,
+    /// the image of the differences, white where the images agree, red
+    /// where the coverage differs and grey levels for the rest
+    pub image: ImageBuffer<Rgba<u8>, Vec<u8>>
+}
+
+impl ImageDiff {
+    /// the fraction of the pixels that are off
+    pub fn mismatched_ratio(&self) -> f32 {
+        self.mismatched as f32 / self.pixels.max(1) as f32
+    }
+
+    /// peak signal to noise ratio in decibels, infinite for equal images
+    pub fn psnr(&self) -> f32 {
+        10. * (255. * 255. / self.mse).log10()
+    }
+}
+
+/// compare `image` against `reference`, channels that differ by at most
+/// `tolerance` count as equal. The images have the same size.
+pub fn image_diff(image: &ImageBuffer<Rgba<u8>, Vec<u8>>, reference: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+                  tolerance: u8) -> ImageDiff {
+    assert!(image.width() == reference.width() && image.height() == reference.height());
+
+    let mut out = ImageBuffer::from_pixel(image.width(), image.height(), Rgba([255u8, 255, 255, 255]));
+    let (mut mismatched, mut coverage, mut max, mut sum, mut squares) = (0, 0, 0u8, 0u64, 0u64);
+    for (x, y, a) in image.enumerate_pixels() {
+        let b = reference.get_pixel(x, y);
+        let mut off = 0u8;
+        for k in 0..4 {
+            let d = if a.data[k] > b.data[k] { a.data[k] - b.data[k] } else { b.data[k] - a.data[k] };
+            off = ::std::cmp::max(off, d);
+            sum += d as u64;
+            squares += d as u64 * d as u64;
+        }
+        max = ::std::cmp::max(max, off);
+
+        if (a.data[3] == 0) != (b.data[3] == 0) {
+            coverage += 1;
+            mismatched += 1;
+            out.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else if off > tolerance {
+            mismatched += 1;
+            out.put_pixel(x, y, Rgba([255 - off, 255 - off, 255 - off, 255]));
+        }
+    }
+
+    let pixels = (image.width() * image.height()) as usize;
+    ImageDiff {
+        pixels: pixels,
+        mismatched: mismatched,
+        coverage: coverage,
+        max: max,
+        mean: sum as f32 / (4 * pixels).max(1) as f32,
+        mse: squares as f32 / (4 * pixels).max(1) as f32,
+        image: out
+    }
+}
@@ -0,0 +1,123 @@
+//! the tile raster driven by hand, without a `Frame`
+//!
+//! A frame keeps its tiles behind the task pool. Voxelizing a mesh one
+//! slice at a time or measuring how many pixels a shape covers only needs
+//! the coverage, depth test and shading of the raster, so a `TileRaster`
+//! owns plain `TileGroup`s and rasters triangles into them on the calling
+//! thread. Its groups can be taken apart and spread over threads by the
+//! caller, see `TileGroup::raster` for rastering a single one.
+
+use std::sync::Arc;
+
+use cgmath::{Vector2, Vector3};
+use genmesh::Triangle;
+
+use {Buffer, Barycentric, Interpolate, RasterBackend, SimdBackend, Shade};
+use tile::{TileGroup, Clip};
+
+/// `TileGroup`s covering a `width` by `height` area, positions are in
+/// pixels from its bottom left corner
+pub struct TileRaster<P> {
+    pub width: u32,
+    pub height: u32,
+    columns: u32,
+    groups: Vec<TileGroup<P>>,
+    backend: Arc<RasterBackend>
+}
+
+impl<P: Copy> TileRaster<P> {
+    /// an area filled with `p` and a depth of 1
+    pub fn new(width: u32, height: u32, p: P) -> TileRaster<P> {
+        assert!(width > 0 && height > 0);
+        let (columns, rows) = ((width + 31) / 32, (height + 31) / 32);
+        TileRaster {
+            width: width,
+            height: height,
+            columns: columns,
+            groups: (0..columns * rows).map(|_| TileGroup::new(p)).collect(),
+            backend: Arc::new(SimdBackend)
+        }
+    }
+
+    /// decide the coverage with `backend`, see `Frame::set_backend`
+    pub fn set_backend(&mut self, backend: Arc<RasterBackend>) {
+        self.backend = backend;
+    }
+
+    pub fn clear(&mut self, p: P) {
+        for group in self.groups.iter_mut() {
+            group.clear(p);
+        }
+    }
+
+    /// raster a triangle whose vertices are at `screen`, x and y in pixels
+    /// and the depth in z. Pixel `x`, `y` is covered if its bottom left
+    /// corner is in the triangle. `shader` gets the plane of `attributes`
+    /// and the position of every tile from the bottom left corner. Returns
+    /// the number of pixels shaded.
+    pub fn triangle<T, S>(&mut self, screen: Triangle<[f32; 3]>, attributes: &Triangle<T>, shader: &S) -> usize
+        where T: Interpolate,
+              S: Shade<T::Plane, P> {
+
+        let (a, b, c) = (screen.x, screen.y, screen.z);
+        let bary = Barycentric::new(Triangle::new(Vector2::new(a[0], a[1]),
+                                                  Vector2::new(b[0], b[1]),
+                                                  Vector2::new(c[0], c[1])));
+        let z = Vector3::new(a[2], b[2], c[2]);
+        let plane = Interpolate::setup(attributes);
+
+        // only the groups under the bounds of the triangle
+        let rows = self.groups.len() as u32 / self.columns;
+        let group = |v: f32, n: u32| (v.max(0.) / 32.).min(n as f32 - 1.) as u32;
+        let (x0, x1) = (group(a[0].min(b[0]).min(c[0]), self.columns),
+                        group(a[0].max(b[0]).max(c[0]), self.columns));
+        let (y0, y1) = (group(a[1].min(b[1]).min(c[1]), rows),
+                        group(a[1].max(b[1]).max(c[1]), rows));
+
+        let mut shaded = 0;
+        for gy in y0..y1 + 1 {
+            for gx in x0..x1 + 1 {
+                let pos = Vector2::new((gx * 32) as f32, (gy * 32) as f32);
+                // groups the triangle can't touch are left unallocated
+                if !self.backend.bin(&bary, pos, Vector2::new(32., 32.)) {
+                    continue;
+                }
+                let i = (gy * self.columns + gx) as usize;
+                shaded += self.groups[i].raster(pos, Vector2::new(1., 1.), &z, &bary, &plane,
+                                                shader, &*self.backend);
+            }
+        }
+        shaded
+    }
+
+    /// the group `gx`, `gy` counted from the bottom left, it covers the
+    /// pixels from `32 * gx`, `32 * gy`
+    pub fn group(&self, gx: u32, gy: u32) -> &TileGroup<P> {
+        &self.groups[(gy * self.columns + gx) as usize]
+    }
+
+    pub fn group_mut(&mut self, gx: u32, gy: u32) -> &mut TileGroup<P> {
+        &mut self.groups[(gy * self.columns + gx) as usize]
+    }
+
+    /// the colors, row by row from the top left like every `Buffer`
+    pub fn to_buffer(&self) -> Buffer<P> {
+        let mut out = Clip::new(Buffer::new(self.width, self.height, self.group(0, 0).clear_color()),
+                                self.width, self.height);
+        for (i, group) in self.groups.iter().enumerate() {
+            let (gx, gy) = (i as u32 % self.columns, i as u32 / self.columns);
+            group.write(gx * 32, gy * 32, &mut out);
+        }
+        out.inner
+    }
+
+    /// the depths, laid out like `to_buffer`
+    pub fn depth_buffer(&self) -> Buffer<f32> {
+        let mut out = Clip::new(Buffer::new(self.width, self.height, 1.), self.width, self.height);
+        for (i, group) in self.groups.iter().enumerate() {
+            let (gx, gy) = (i as u32 % self.columns, i as u32 / self.columns);
+            group.write_depth(gx * 32, gy * 32, &mut out);
+        }
+        out.inner
+    }
+}
@@ -0,0 +1,50 @@
+use std::error;
+use std::fmt;
+use std::result;
+
+/// what the `try_` methods of `Frame` report instead of panicking
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// a frame size that is zero, not a multiple of the tile group size
+    /// or larger than `Capabilities` allows
+    InvalidSize { width: u32, height: u32 },
+    /// two frames or buffers that have to be the same size are not
+    SizeMismatch { expected: (u32, u32), found: (u32, u32) },
+    /// a worker task died before handing its tiles back, the contents of
+    /// the frame are lost
+    TaskFailed
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidSize { width, height } =>
+                write!(f, "invalid frame size {}x{}", width, height),
+            Error::SizeMismatch { expected, found } =>
+                write!(f, "expected a size of {}x{}, found {}x{}", expected.0, expected.1, found.0, found.1),
+            Error::TaskFailed => write!(f, "a raster task failed")
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::InvalidSize { .. } => "invalid frame size",
+            Error::SizeMismatch { .. } => "mismatched sizes",
+            Error::TaskFailed => "a raster task failed"
+        }
+    }
+}
+
+/// `SizeMismatch` unless `found` is `expected`
+#[inline]
+pub fn check_size(expected: (u32, u32), found: (u32, u32)) -> Result<()> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(Error::SizeMismatch { expected: expected, found: found })
+    }
+}
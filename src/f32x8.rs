@@ -111,6 +111,18 @@ impl f32x8x8 {
     pub fn to_bit_u32x8x8(self) -> u32x8x8 {
         unsafe { mem::transmute(self) }
     }
+
+    /// the lanes in the same order as the bits of a tile mask
+    #[inline]
+    pub fn to_array(self) -> [f32; 64] {
+        unsafe { mem::transmute(self) }
+    }
+
+    /// the inverse of `to_array`
+    #[inline]
+    pub fn from_array(a: [f32; 64]) -> f32x8x8 {
+        unsafe { mem::transmute(a) }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
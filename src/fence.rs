@@ -0,0 +1,40 @@
+use pulse::Signal;
+
+use {Frame, Error, error};
+
+/// a point in the work submitted to a frame, see `Frame::fence`
+pub struct Fence {
+    /// one for every tile group, each fires when the last task of the
+    /// group submitted before the fence is done
+    signals: Vec<Signal>
+}
+
+impl Fence {
+    /// true once all of the work before the fence is done, without waiting
+    pub fn is_done(&self) -> bool {
+        self.signals.iter().all(|s| !s.is_pending())
+    }
+
+    /// block until the work before the fence is done, a task that died
+    /// on the way is reported like `Frame::try_flush` does
+    pub fn wait(self) -> error::Result<()> {
+        for s in self.signals.into_iter() {
+            if s.wait().is_err() {
+                return Err(Error::TaskFailed);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// a fence that resolves once every draw, clear and pass submitted to
+    /// the frame so far is done. Work submitted after it is not waited
+    /// for, so the frame keeps drawing while the caller waits on the draws
+    /// it needs, or does other work until `Fence::is_done`.
+    pub fn fence(&mut self) -> Fence {
+        Fence {
+            signals: self.tile.iter().flat_map(|row| row.iter().map(|t| t.signal())).collect()
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt::Debug;
+
+use genmesh::{Triangle, MapVertex};
+
+use {Frame, Fragment, Interpolate, FetchPosition, DrawStats, Lerp};
+
+/// how fast the fog thickens with distance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FogMode {
+    /// no fog before `start`, nothing but fog after `end`
+    Linear { start: f32, end: f32 },
+    /// exp(-density * d)
+    Exp { density: f32 },
+    /// exp(-(density * d)^2)
+    Exp2 { density: f32 }
+}
+
+/// what the fog is a function of
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FogCoord {
+    /// the depth after the perspective divide, -1 at the near plane and
+    /// 1 at the far plane
+    Depth,
+    /// w of the clip position, the distance along the view direction for
+    /// a perspective projection
+    ViewDepth
+}
+
+impl FogCoord {
+    #[inline]
+    fn value(self, p: [f32; 4]) -> f32 {
+        match self {
+            FogCoord::Depth => p[2] / p[3],
+            FogCoord::ViewDepth => p[3]
+        }
+    }
+}
+
+/// fog applied to the output of a draw, see `Frame::raster_fog`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fog<P> {
+    pub mode: FogMode,
+    pub coord: FogCoord,
+    pub color: P
+}
+
+impl<P> Fog<P> {
+    pub fn new(mode: FogMode, coord: FogCoord, color: P) -> Fog<P> {
+        Fog {
+            mode: mode,
+            coord: coord,
+            color: color
+        }
+    }
+
+    /// how much of the surface shows through at `d`, 1 without fog
+    pub fn visibility(&self, d: f32) -> f32 {
+        let f = match self.mode {
+            FogMode::Linear { start, end } => {
+                if end > start { (end - d) / (end - start) } else if d < start { 1. } else { 0. }
+            }
+            FogMode::Exp { density } => (-density * d).exp(),
+            FogMode::Exp2 { density } => (-(density * d) * (density * d)).exp()
+        };
+        f.max(0.).min(1.)
+    }
+}
+
+/// runs the fragment shader, then fades its color into the fog by the
+/// distance interpolated next to the vertex attributes
+#[derive(Clone)]
+struct Fogged<F, P> {
+    fragment: F,
+    fog: Fog<P>
+}
+
+impl<T, F, P> Fragment<(T, f32)> for Fogged<F, P>
+    where F: Fragment<T, Color=P>,
+          P: Copy + Lerp {
+    type Color = P;
+
+    #[inline]
+    fn fragment(&self, (pos, d): (T, f32)) -> P {
+        let color = self.fragment.fragment(pos);
+        color.lerp(self.fog.color, 1. - self.fog.visibility(d))
+    }
+
+    #[inline]
+    fn blend(&self, old: P, new: P) -> P { self.fragment.blend(old, new) }
+}
+
+impl<P: Copy+Lerp+Sync+Send+'static> Frame<P> {
+    /// `raster` with `fog` applied to every fragment after `fragment`
+    /// has shaded it, the shader does not need to know about the fog
+    pub fn raster_fog<S, F, T, O>(&mut self, poly: S, fragment: F, fog: Fog<P>) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: Fragment<O, Color=P> + Send + Sync + 'static {
+
+        let coord = fog.coord;
+        let poly = poly.map(move |t| t.map_vertex(|v| {
+            let d = coord.value(v.position());
+            (v, d)
+        }));
+        self.raster(poly, Fogged {
+            fragment: fragment,
+            fog: fog
+        })
+    }
+}
@@ -0,0 +1,122 @@
+//! deterministic entry points for fuzzing, see the harnesses in `fuzz/`
+//!
+//! Each entry point reads everything it needs from a byte slice, so any
+//! input is valid. The frames are small and run on a single worker, the
+//! same bytes always give the same pixels and what is allocated is bounded
+//! by `MAX_SIZE` and `MAX_TRIANGLES` no matter how long the input is.
+
+use std::mem;
+use std::sync::Arc;
+
+use genmesh::Triangle;
+
+use {Frame, Flat, Fragment, Buffer, Rect, ScalarBackend};
+
+/// the largest width or height of the frames
+pub const MAX_SIZE: u32 = 96;
+/// the most triangles read from one input
+pub const MAX_TRIANGLES: usize = 64;
+
+/// reads the input front to back, past its end everything is zero
+struct Input<'a> {
+    data: &'a [u8],
+    at: usize
+}
+
+impl<'a> Input<'a> {
+    fn byte(&mut self) -> u8 {
+        let b = self.data.get(self.at).map(|b| *b).unwrap_or(0);
+        self.at += 1;
+        b
+    }
+
+    /// any bit pattern, so NaNs, infinities and denormals included
+    fn float(&mut self) -> f32 {
+        let mut bits = 0u32;
+        for k in 0..4 {
+            bits |= (self.byte() as u32) << (8 * k);
+        }
+        unsafe { mem::transmute(bits) }
+    }
+
+    fn left(&self) -> usize {
+        self.data.len().saturating_sub(self.at)
+    }
+
+    /// a size between 1 and `MAX_SIZE`
+    fn size(&mut self) -> u32 {
+        1 + self.byte() as u32 % MAX_SIZE
+    }
+}
+
+/// writes the number of the triangle that covers a pixel
+#[derive(Clone)]
+struct Index;
+
+impl Fragment<([f32; 4], u32)> for Index {
+    type Color = u32;
+
+    #[inline]
+    fn fragment(&self, (_, i): ([f32; 4], u32)) -> u32 { i }
+}
+
+/// raster the triangles encoded in `data` and read the frame back. The
+/// first bytes pick the size of the frame and a mode, the bits of the mode
+/// select the scalar backend, split tile groups, the painter's order and a
+/// clip plane read from the input. The rest are triangles of three clip
+/// space positions of four floats each. The pixels hold one more than the
+/// index of the triangle that covers them, 0 is the background.
+pub fn raster(data: &[u8]) -> Buffer<u32> {
+    let mut input = Input { data: data, at: 0 };
+    let (width, height) = (input.size(), input.size());
+    let mode = input.byte();
+
+    let mut frame = Frame::with_threads(width, height, 0u32, 1);
+    if mode & 1 != 0 {
+        frame.set_backend(Arc::new(ScalarBackend));
+    }
+    if mode & 2 != 0 {
+        frame.set_split_threshold(0);
+    }
+    frame.set_painter(mode & 4 != 0);
+    let plane = if mode & 8 != 0 {
+        Some([input.float(), input.float(), input.float(), input.float()])
+    } else {
+        None
+    };
+
+    let count = ::std::cmp::min(input.left() / 48, MAX_TRIANGLES);
+    let mut triangles = Vec::with_capacity(count);
+    for i in 0..count {
+        let mut vertex = || ([input.float(), input.float(), input.float(), input.float()], Flat(i as u32 + 1));
+        let (x, y, z) = (vertex(), vertex(), vertex());
+        triangles.push(Triangle::new(x, y, z));
+    }
+
+    match plane {
+        Some(p) => frame.raster_clipped(triangles.into_iter(), &[p], Index),
+        None => frame.raster(triangles.into_iter(), Index)
+    };
+    frame.to_buffer()
+}
+
+/// load a frame of a size read from `data` with the numbers of its pixels,
+/// then read back all of it and a region read from the input. Returns the
+/// pattern that was loaded, the frame read back and the region, the first
+/// two are the same unless a tile was lost on the way.
+pub fn readback(data: &[u8]) -> (Buffer<u32>, Buffer<u32>, Buffer<u32>) {
+    let mut input = Input { data: data, at: 0 };
+    let (width, height) = (input.size(), input.size());
+    let rect = Rect::new(input.byte() as u32, input.byte() as u32, input.size(), input.size());
+
+    let pattern = Buffer {
+        width: width,
+        height: height,
+        data: (0..width * height).collect()
+    };
+    let mut frame = Frame::with_threads(width, height, 0u32, 1);
+    frame.load(Arc::new(pattern.clone()));
+    let all = frame.to_buffer();
+    let region = frame.read_region(rect);
+    (pattern, all, region)
+}
@@ -0,0 +1,289 @@
+//! loading of glTF 2.0 meshes for drawing with the `Pbr` shader
+//!
+//! Only the .gltf flavour is read, buffers and images may either be files
+//! next to the document or base64 data URIs. Node transforms are not
+//! applied, every primitive of every mesh is returned in model space.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use image;
+use rustc_serialize::base64::FromBase64;
+use rustc_serialize::json::Json;
+
+use Buffer;
+use scene::Mesh;
+use shaders::Pbr;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// one glTF primitive with its material, the vertices are in model space
+#[derive(Clone)]
+pub struct Primitive {
+    pub mesh: Mesh,
+    pub material: Pbr
+}
+
+struct Document<'a> {
+    json: Json,
+    base: &'a Path,
+    buffers: Vec<Vec<u8>>
+}
+
+fn uint(j: &Json, key: &str) -> Option<usize> {
+    j.find(key).and_then(|v| v.as_u64()).map(|v| v as usize)
+}
+
+fn float(j: &Json, key: &str, default: f32) -> f32 {
+    j.find(key).and_then(|v| v.as_f64()).map(|v| v as f32).unwrap_or(default)
+}
+
+fn read_uri(base: &Path, uri: &str) -> io::Result<Vec<u8>> {
+    if uri.starts_with("data:") {
+        let data = match uri.find(";base64,") {
+            Some(i) => &uri[i + 8..],
+            None => return Err(invalid("only base64 data URIs are supported"))
+        };
+        return data.from_base64().map_err(|_| invalid("bad base64 data"));
+    }
+    let mut bytes = Vec::new();
+    try!(try!(File::open(base.join(uri))).read_to_end(&mut bytes));
+    Ok(bytes)
+}
+
+impl<'a> Document<'a> {
+    fn array(&self, key: &str, index: usize) -> io::Result<&Json> {
+        self.json.find(key)
+            .and_then(|a| a.as_array())
+            .and_then(|a| a.get(index))
+            .ok_or_else(|| invalid("reference out of range"))
+    }
+
+    /// the bytes of a buffer view together with its stride
+    fn view(&self, index: usize) -> io::Result<(&[u8], Option<usize>)> {
+        let view = try!(self.array("bufferViews", index));
+        let buffer = try!(uint(view, "buffer").and_then(|b| self.buffers.get(b))
+                                               .ok_or_else(|| invalid("bad buffer view")));
+        let offset = uint(view, "byteOffset").unwrap_or(0);
+        let length = try!(uint(view, "byteLength").ok_or_else(|| invalid("bad buffer view")));
+        if offset + length > buffer.len() {
+            return Err(invalid("buffer view out of range"));
+        }
+        Ok((&buffer[offset..offset + length], uint(view, "byteStride")))
+    }
+
+    /// every element of an accessor converted to floats, normalized
+    /// integers are mapped to 0..1 or -1..1. The elements must have
+    /// `expected` components.
+    fn accessor(&self, index: usize, expected: usize) -> io::Result<Vec<Vec<f32>>> {
+        let acc = try!(self.array("accessors", index));
+        if acc.find("sparse").is_some() {
+            return Err(invalid("sparse accessors are not supported"));
+        }
+        let count = try!(uint(acc, "count").ok_or_else(|| invalid("accessor without count")));
+        let kind = try!(uint(acc, "componentType").ok_or_else(|| invalid("accessor without type")));
+        let normalized = acc.find("normalized").and_then(|n| n.as_boolean()).unwrap_or(false);
+        let components = match acc.find("type").and_then(|t| t.as_string()) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            _ => return Err(invalid("unsupported accessor type"))
+        };
+        if components != expected {
+            return Err(invalid("unexpected accessor type"));
+        }
+        let size = match kind {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => return Err(invalid("unsupported component type"))
+        };
+
+        let view = match uint(acc, "bufferView") {
+            Some(v) => v,
+            // without a view the elements are all zero
+            None => return Ok(vec![vec![0.; components]; count])
+        };
+        let (bytes, stride) = try!(self.view(view));
+        let offset = uint(acc, "byteOffset").unwrap_or(0);
+        let stride = stride.unwrap_or(size * components);
+        if count > 0 && offset + stride * (count - 1) + size * components > bytes.len() {
+            return Err(invalid("accessor out of range"));
+        }
+
+        let read = |at: usize| -> f32 {
+            let b = &bytes[at..at + size];
+            match kind {
+                5120 => if normalized { (b[0] as i8 as f32 / 127.).max(-1.) } else { b[0] as i8 as f32 },
+                5121 => if normalized { b[0] as f32 / 255. } else { b[0] as f32 },
+                5122 => {
+                    let v = (b[0] as u16 | (b[1] as u16) << 8) as i16 as f32;
+                    if normalized { (v / 32767.).max(-1.) } else { v }
+                }
+                5123 => {
+                    let v = (b[0] as u16 | (b[1] as u16) << 8) as f32;
+                    if normalized { v / 65535. } else { v }
+                }
+                5125 => (b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24) as f32,
+                _ => {
+                    let bits = b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24;
+                    unsafe { ::std::mem::transmute::<u32, f32>(bits) }
+                }
+            }
+        };
+
+        Ok((0..count).map(|i| {
+            let at = offset + i * stride;
+            (0..components).map(|c| read(at + c * size)).collect()
+        }).collect())
+    }
+
+    /// the indices are read separately since large u32 values do not
+    /// survive the trip through f32
+    fn indices(&self, index: usize) -> io::Result<Vec<u32>> {
+        let acc = try!(self.array("accessors", index));
+        if acc.find("sparse").is_some() {
+            return Err(invalid("sparse accessors are not supported"));
+        }
+        let count = try!(uint(acc, "count").ok_or_else(|| invalid("accessor without count")));
+        let view = try!(uint(acc, "bufferView").ok_or_else(|| invalid("index accessor without data")));
+        let (bytes, _) = try!(self.view(view));
+        let offset = uint(acc, "byteOffset").unwrap_or(0);
+        let size = match uint(acc, "componentType") {
+            Some(5121) => 1,
+            Some(5123) => 2,
+            Some(5125) => 4,
+            _ => return Err(invalid("unsupported index type"))
+        };
+        if offset + count * size > bytes.len() {
+            return Err(invalid("accessor out of range"));
+        }
+
+        Ok((0..count).map(|i| {
+            let b = &bytes[offset + i * size..offset + (i + 1) * size];
+            b.iter().rev().fold(0u32, |v, &byte| v << 8 | byte as u32)
+        }).collect())
+    }
+
+    fn texture(&self, info: Option<&Json>) -> io::Result<Option<Arc<Buffer<image::Rgba<u8>>>>> {
+        let index = match info.and_then(|t| uint(t, "index")) {
+            Some(i) => i,
+            None => return Ok(None)
+        };
+        let texture = try!(self.array("textures", index));
+        let source = try!(uint(texture, "source").ok_or_else(|| invalid("texture without source")));
+        let img = try!(self.array("images", source));
+
+        let bytes = match (img.find("uri").and_then(|u| u.as_string()), uint(img, "bufferView")) {
+            (Some(uri), _) => try!(read_uri(self.base, uri)),
+            (None, Some(view)) => try!(self.view(view)).0.to_vec(),
+            _ => return Err(invalid("image without data"))
+        };
+        let decoded = try!(image::load_from_memory(&bytes).map_err(|_| invalid("could not decode image")));
+        Ok(Some(Arc::new(Buffer::from_image(&decoded.to_rgba()))))
+    }
+
+    fn material(&self, index: Option<usize>) -> io::Result<Pbr> {
+        let mat = match index {
+            Some(i) => try!(self.array("materials", i)),
+            None => return Ok(Pbr::new([1., 1., 1., 1.], 1., 1.))
+        };
+        let pbr = match mat.find("pbrMetallicRoughness") {
+            Some(p) => p,
+            None => return Ok(Pbr::new([1., 1., 1., 1.], 1., 1.))
+        };
+
+        let mut base = [1f32; 4];
+        if let Some(f) = pbr.find("baseColorFactor").and_then(|f| f.as_array()) {
+            for (b, v) in base.iter_mut().zip(f.iter()) {
+                *b = v.as_f64().unwrap_or(1.) as f32;
+            }
+        }
+        let mut out = Pbr::new(base, float(pbr, "metallicFactor", 1.), float(pbr, "roughnessFactor", 1.));
+        out.base_color_texture = try!(self.texture(pbr.find("baseColorTexture")));
+        out.metallic_roughness_texture = try!(self.texture(pbr.find("metallicRoughnessTexture")));
+        Ok(out)
+    }
+
+    fn primitive(&self, prim: &Json) -> io::Result<Primitive> {
+        // only triangle lists, the default mode
+        if uint(prim, "mode").unwrap_or(4) != 4 {
+            return Err(invalid("only triangle lists are supported"));
+        }
+        let attr = |name: &str| prim.find("attributes").and_then(|a| uint(a, name));
+
+        let position = try!(attr("POSITION").ok_or_else(|| invalid("primitive without positions")));
+        let positions: Vec<[f32; 3]> = try!(self.accessor(position, 3)).iter()
+            .map(|v| [v[0], v[1], v[2]]).collect();
+        let normals = match attr("NORMAL") {
+            Some(n) => try!(self.accessor(n, 3)).iter().map(|v| [v[0], v[1], v[2]]).collect(),
+            None => Vec::new()
+        };
+        let uvs = match attr("TEXCOORD_0") {
+            Some(t) => try!(self.accessor(t, 2)).iter().map(|v| [v[0], v[1]]).collect(),
+            None => Vec::new()
+        };
+        let indices = match uint(prim, "indices") {
+            Some(i) => try!(self.indices(i)),
+            None => (0..positions.len() as u32).collect()
+        };
+        if indices.iter().any(|&i| i as usize >= positions.len()) {
+            return Err(invalid("index out of range"));
+        }
+
+        Ok(Primitive {
+            mesh: Mesh {
+                positions: positions,
+                normals: normals,
+                uvs: uvs,
+                indices: indices
+            },
+            material: try!(self.material(uint(prim, "material")))
+        })
+    }
+}
+
+/// parse a .gltf document, external files are looked up relative to `base`
+pub fn parse(json: &str, base: &Path) -> io::Result<Vec<Primitive>> {
+    let json = try!(Json::from_str(json).map_err(|_| invalid("malformed JSON")));
+
+    let mut buffers = Vec::new();
+    if let Some(list) = json.find("buffers").and_then(|b| b.as_array()) {
+        for b in list.iter() {
+            let uri = try!(b.find("uri").and_then(|u| u.as_string())
+                            .ok_or_else(|| invalid("buffer without uri")));
+            buffers.push(try!(read_uri(base, uri)));
+        }
+    }
+
+    let doc = Document {
+        json: json,
+        base: base,
+        buffers: buffers
+    };
+
+    let mut out = Vec::new();
+    if let Some(meshes) = doc.json.find("meshes").and_then(|m| m.as_array()) {
+        for mesh in meshes.iter() {
+            if let Some(prims) = mesh.find("primitives").and_then(|p| p.as_array()) {
+                for p in prims.iter() {
+                    out.push(try!(doc.primitive(p)));
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// load every primitive of a .gltf file
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Vec<Primitive>> {
+    let path = path.as_ref();
+    let mut text = String::new();
+    try!(try!(File::open(path)).read_to_string(&mut text));
+    parse(&text, path.parent().unwrap_or(Path::new(".")))
+}
@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use genmesh::{Triangle, MapVertex};
+use stb_truetype::{FontInfo, VertexType};
+
+use {Fragment, alpha_over, Rgba};
+use paint::Canvas;
+
+/// a vertex of a text run, the clip space position, the canvas point
+/// and the texel coordinate inside of the glyph atlas
+pub type GlyphVertex = ([f32; 4], [f32; 2], [f32; 2]);
+
+/// a glyph outline made of line segments, quadratic curves are flattened
+/// as they are added. Coordinates are in bitmap pixels with y pointing down.
+#[derive(Clone, Debug)]
+pub struct Outline {
+    lines: Vec<([f32; 2], [f32; 2])>,
+    start: [f32; 2],
+    last: [f32; 2]
+}
+
+impl Outline {
+    pub fn new() -> Outline {
+        Outline {
+            lines: Vec::new(),
+            start: [0., 0.],
+            last: [0., 0.]
+        }
+    }
+
+    pub fn move_to(&mut self, p: [f32; 2]) {
+        self.close();
+        self.start = p;
+        self.last = p;
+    }
+
+    pub fn line_to(&mut self, p: [f32; 2]) {
+        self.lines.push((self.last, p));
+        self.last = p;
+    }
+
+    pub fn quad_to(&mut self, c: [f32; 2], p: [f32; 2]) {
+        let p0 = self.last;
+        let (dx, dy) = (p0[0] - 2. * c[0] + p[0], p0[1] - 2. * c[1] + p[1]);
+        let devsq = dx * dx + dy * dy;
+        if devsq < 0.333 {
+            return self.line_to(p);
+        }
+
+        let n = 1 + (3. * devsq).sqrt().sqrt().floor() as usize;
+        for i in 1..n {
+            let t = i as f32 / n as f32;
+            let mt = 1. - t;
+            self.line_to([mt * mt * p0[0] + 2. * mt * t * c[0] + t * t * p[0],
+                          mt * mt * p0[1] + 2. * mt * t * c[1] + t * t * p[1]]);
+        }
+        self.line_to(p);
+    }
+
+    /// connect the current contour back to its first point
+    pub fn close(&mut self) {
+        if self.last != self.start {
+            let (last, start) = (self.last, self.start);
+            self.lines.push((last, start));
+            self.last = start;
+        }
+    }
+
+    /// compute the exact area coverage of every pixel of a `w`x`h` bitmap
+    pub fn rasterize(&self, w: usize, h: usize) -> Vec<u8> {
+        let mut acc = Accumulator {
+            w: w,
+            h: h,
+            a: vec![0.; w * h + 4]
+        };
+
+        for &(p0, p1) in self.lines.iter() {
+            acc.line(p0, p1);
+        }
+        if self.last != self.start {
+            acc.line(self.last, self.start);
+        }
+
+        let mut sum = 0.;
+        acc.a[..w * h].iter().map(|a| {
+            sum += *a;
+            (sum.abs().min(1.) * 255. + 0.5) as u8
+        }).collect()
+    }
+}
+
+/// signed area accumulation buffer, the prefix sum along each row
+/// gives the coverage of that pixel
+struct Accumulator {
+    w: usize,
+    h: usize,
+    a: Vec<f32>
+}
+
+impl Accumulator {
+    fn add(&mut self, row: usize, x: i32, v: f32) {
+        let x = x.max(0).min(self.w as i32) as usize;
+        self.a[row + x] += v;
+    }
+
+    fn line(&mut self, p0: [f32; 2], p1: [f32; 2]) {
+        if p0[1] == p1[1] {
+            return;
+        }
+
+        let (dir, p0, p1) = if p0[1] < p1[1] { (1., p0, p1) } else { (-1., p1, p0) };
+        let dxdy = (p1[0] - p0[0]) / (p1[1] - p0[1]);
+        let mut x = p0[0];
+        if p0[1] < 0. {
+            x -= p0[1] * dxdy;
+        }
+
+        let y0 = p0[1].max(0.) as usize;
+        let y1 = (p1[1].ceil().max(0.) as usize).min(self.h);
+        for y in y0..y1 {
+            let row = y * self.w;
+            let dy = ((y + 1) as f32).min(p1[1]) - (y as f32).max(p0[1]);
+            let xnext = x + dxdy * dy;
+            let d = dy * dir;
+            let (x0, x1) = if x < xnext { (x, xnext) } else { (xnext, x) };
+            let x0floor = x0.floor();
+            let x0i = x0floor as i32;
+            let x1ceil = x1.ceil();
+            let x1i = x1ceil as i32;
+
+            if x1i <= x0i + 1 {
+                let xmf = 0.5 * (x + xnext) - x0floor;
+                self.add(row, x0i, d - d * xmf);
+                self.add(row, x0i + 1, d * xmf);
+            } else {
+                let s = (x1 - x0).recip();
+                let x0f = x0 - x0floor;
+                let a0 = 0.5 * s * (1. - x0f) * (1. - x0f);
+                let x1f = x1 - x1ceil + 1.;
+                let am = 0.5 * s * x1f * x1f;
+                self.add(row, x0i, d * a0);
+                if x1i == x0i + 2 {
+                    self.add(row, x0i + 1, d * (1. - a0 - am));
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    self.add(row, x0i + 1, d * (a1 - a0));
+                    for xi in (x0i + 2)..(x1i - 1) {
+                        self.add(row, xi, d * s);
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                    self.add(row, x1i - 1, d * (1. - a2 - am));
+                }
+                self.add(row, x1i, d * am);
+            }
+            x = xnext;
+        }
+    }
+}
+
+/// where a glyph lives inside of the atlas and how to place it
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphEntry {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// offset from the pen position to the left edge of the bitmap
+    pub left: f32,
+    /// distance from the baseline up to the top edge of the bitmap
+    pub top: f32,
+    pub advance: f32
+}
+
+/// a single channel coverage texture that glyphs are packed into shelf by shelf
+#[derive(Clone, Debug)]
+pub struct GlyphAtlas {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+    cursor: (u32, u32),
+    shelf: u32
+}
+
+impl GlyphAtlas {
+    pub fn new(width: u32, height: u32) -> GlyphAtlas {
+        GlyphAtlas {
+            width: width,
+            height: height,
+            data: vec![0; (width * height) as usize],
+            cursor: (0, 0),
+            shelf: 0
+        }
+    }
+
+    /// copy a coverage bitmap into the atlas, returns its position or
+    /// None if the atlas is full
+    pub fn insert(&mut self, w: u32, h: u32, coverage: &[u8]) -> Option<(u32, u32)> {
+        // leave a one texel gap so that neighbours never bleed into each other
+        if self.cursor.0 + w + 1 > self.width {
+            self.cursor = (0, self.cursor.1 + self.shelf + 1);
+            self.shelf = 0;
+        }
+        if w + 1 > self.width || self.cursor.1 + h + 1 > self.height {
+            return None;
+        }
+
+        let (x, y) = self.cursor;
+        for row in 0..h {
+            let dst = ((y + row) * self.width + x) as usize;
+            let src = (row * w) as usize;
+            for i in 0..(w as usize) {
+                self.data[dst + i] = coverage[src + i];
+            }
+        }
+
+        self.cursor.0 += w + 1;
+        self.shelf = self.shelf.max(h);
+        Some((x, y))
+    }
+
+    #[inline]
+    pub fn coverage(&self, x: u32, y: u32) -> u8 {
+        if x < self.width && y < self.height {
+            self.data[(y * self.width + x) as usize]
+        } else {
+            0
+        }
+    }
+}
+
+/// a font together with the atlas its glyphs are cached in
+pub struct GlyphCache {
+    font: FontInfo<Vec<u8>>,
+    atlas: GlyphAtlas,
+    glyphs: HashMap<(u32, u32), Option<GlyphEntry>>,
+    snapshot: Option<Arc<GlyphAtlas>>
+}
+
+impl GlyphCache {
+    /// parse a TrueType font, returns None if the data is not a valid font
+    pub fn new(data: Vec<u8>, atlas_size: u32) -> Option<GlyphCache> {
+        FontInfo::new(data, 0).map(|font| {
+            GlyphCache {
+                font: font,
+                atlas: GlyphAtlas::new(atlas_size, atlas_size),
+                glyphs: HashMap::new(),
+                snapshot: None
+            }
+        })
+    }
+
+    /// rasterize the glyph for `c` at `size` pixels if it is not cached yet
+    pub fn glyph(&mut self, c: char, size: f32) -> Option<GlyphEntry> {
+        let index = self.font.find_glyph_index(c as u32);
+        let key = (index, (size * 64.) as u32);
+        if let Some(entry) = self.glyphs.get(&key) {
+            return *entry;
+        }
+
+        let entry = self.rasterize(index, size);
+        self.glyphs.insert(key, entry);
+        entry
+    }
+
+    fn rasterize(&mut self, index: u32, size: f32) -> Option<GlyphEntry> {
+        let scale = self.font.scale_for_pixel_height(size);
+        let advance = self.font.get_glyph_h_metrics(index).advance_width as f32 * scale;
+
+        let (shape, bounds) = match (self.font.get_glyph_shape(index), self.font.get_glyph_box(index)) {
+            (Some(shape), Some(bounds)) => (shape, bounds),
+            // glyphs without an outline, like space, only advance the pen
+            _ => return Some(GlyphEntry {
+                x: 0, y: 0, width: 0, height: 0,
+                left: 0., top: 0., advance: advance
+            })
+        };
+
+        let left = (bounds.x0 as f32 * scale).floor();
+        let top = (bounds.y1 as f32 * scale).ceil();
+        let w = ((bounds.x1 as f32 * scale).ceil() - left) as u32;
+        let h = (top - (bounds.y0 as f32 * scale).floor()) as u32;
+        let point = |x: i16, y: i16| [x as f32 * scale - left, top - y as f32 * scale];
+
+        let mut outline = Outline::new();
+        for v in shape.iter() {
+            match v.vertex_type() {
+                VertexType::MoveTo => outline.move_to(point(v.x, v.y)),
+                VertexType::LineTo => outline.line_to(point(v.x, v.y)),
+                VertexType::CurveTo => outline.quad_to(point(v.cx, v.cy), point(v.x, v.y))
+            }
+        }
+        outline.close();
+
+        let coverage = outline.rasterize(w as usize, h as usize);
+        self.atlas.insert(w, h, &coverage).map(|(x, y)| {
+            self.snapshot = None;
+            GlyphEntry {
+                x: x, y: y, width: w, height: h,
+                left: left, top: top, advance: advance
+            }
+        })
+    }
+
+    /// build the quads of a text run with its baseline starting at `pos`,
+    /// the quads are clipped by the canvas clip stack
+    pub fn text(&mut self, canvas: &Canvas, text: &str, pos: [f32; 2], size: f32) -> Vec<Triangle<GlyphVertex>> {
+        let mut out = Vec::new();
+        let mut pen = pos[0];
+
+        for c in text.chars() {
+            let g = match self.glyph(c, size) {
+                Some(g) => g,
+                None => continue
+            };
+
+            if g.width > 0 && g.height > 0 {
+                let (x, y) = (pen + g.left, pos[1] - g.top);
+                let quad = canvas.rect(x, y, g.width as f32, g.height as f32);
+                out.extend(quad.into_iter().map(|t| {
+                    t.map_vertex(|(v, p)| (v, p, [g.x as f32 + p[0] - x, g.y as f32 + p[1] - y]))
+                }));
+            }
+            pen += g.advance;
+        }
+        out
+    }
+
+    /// a brush that draws text runs from this cache in `color`
+    pub fn brush(&mut self, color: Rgba<u8>) -> GlyphBrush {
+        if self.snapshot.is_none() {
+            self.snapshot = Some(Arc::new(self.atlas.clone()));
+        }
+
+        GlyphBrush {
+            atlas: self.snapshot.clone().unwrap(),
+            color: color
+        }
+    }
+}
+
+/// blends a solid color through the glyph coverage in the atlas
+#[derive(Clone)]
+pub struct GlyphBrush {
+    atlas: Arc<GlyphAtlas>,
+    pub color: Rgba<u8>
+}
+
+impl Fragment<GlyphVertex> for GlyphBrush {
+    type Color = Rgba<u8>;
+
+    #[inline]
+    fn fragment(&self, (_, _, uv): GlyphVertex) -> Rgba<u8> {
+        let coverage = self.atlas.coverage(uv[0].max(0.) as u32, uv[1].max(0.) as u32);
+        let mut color = self.color;
+        color.data[3] = ((color.data[3] as u32 * coverage as u32 + 127) / 255) as u8;
+        color
+    }
+
+    #[inline]
+    fn blend(&self, old: Rgba<u8>, new: Rgba<u8>) -> Rgba<u8> {
+        alpha_over(old, new)
+    }
+}
@@ -0,0 +1,152 @@
+//! per pixel data that fragment shaders read and write in place
+//!
+//! Every pixel of a frame belongs to exactly one tile group, and a group
+//! takes its triangles one at a time in submission order, split draws
+//! included since their quadrants never overlap. So the fragments of a
+//! pixel never run at the same time and always run in the order they were
+//! submitted, which is the guarantee pixel shader interlock gives on GPUs.
+//! `Frame::raster_interlocked` hands that to shaders as a `&mut` to the
+//! data of their pixel in a `PixelStore`, without any locking. Fragment
+//! lists for order independent transparency or per pixel statistics for
+//! adaptive shading are built this way.
+
+use std::cell::UnsafeCell;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cgmath::Vector2;
+use genmesh::Triangle;
+
+use {Frame, DrawStats, Interpolate, FetchPosition, Plane, Shade};
+use tile::TileMask;
+
+/// a fragment shader with exclusive access to the data of its pixel, see
+/// `Frame::raster_interlocked`
+pub trait FragmentInterlock<T, D> {
+    type Color;
+    fn fragment(&self, pos: T, data: &mut D) -> Self::Color;
+
+    #[inline]
+    fn blend(&self, _: Self::Color, new: Self::Color) -> Self::Color { new }
+}
+
+/// a value for every pixel of a frame, written by the fragments of that
+/// pixel one at a time. The store belongs to the first frame that draws
+/// into it, and it is read with `Frame::pixel_store` once the draws are
+/// done.
+pub struct PixelStore<D> {
+    pub width: u32,
+    pub height: u32,
+    data: Vec<UnsafeCell<D>>,
+    owner: AtomicUsize
+}
+
+// the cells of a pixel are only touched by the worker that owns the pixel
+// and by the frame once it waited for the workers
+unsafe impl<D: Send> Sync for PixelStore<D> {}
+
+impl<D: Clone> PixelStore<D> {
+    /// a `width` by `height` store with `value` for every pixel
+    pub fn new(width: u32, height: u32, value: D) -> PixelStore<D> {
+        PixelStore {
+            width: width,
+            height: height,
+            data: (0..width * height).map(|_| UnsafeCell::new(value.clone())).collect(),
+            owner: AtomicUsize::new(0)
+        }
+    }
+}
+
+impl<D> PixelStore<D> {
+    /// the values row by row from the top left pixel, like `Buffer`
+    pub fn into_data(self) -> Vec<D> {
+        self.data.into_iter().map(|c| unsafe { c.into_inner() }).collect()
+    }
+
+    /// tie the store to the frame `id`, a store is never shared between
+    /// frames since their workers would race on it
+    fn claim(&self, id: usize) {
+        let owner = self.owner.compare_and_swap(0, id, Ordering::SeqCst);
+        assert!(owner == 0 || owner == id, "a pixel store is used by another frame");
+    }
+
+    #[inline]
+    unsafe fn cell(&self, x: u32, y: u32) -> &mut D {
+        &mut *self.data[(y * self.width + x) as usize].get()
+    }
+}
+
+/// shades the fragments of `raster_interlocked`, it needs the position of
+/// the tile to find the data of the pixels
+struct Interlocked<F, D> {
+    fragment: F,
+    store: Arc<PixelStore<D>>,
+    center: Vector2<f32>
+}
+
+impl<F, D, L, P> Shade<L, P> for Interlocked<F, D>
+    where L: Plane,
+          F: FragmentInterlock<L::Out, D, Color=P>,
+          P: Copy {
+    fn shade(&self, _: &L, _: &TileMask, _: &mut [P; 64]) {
+        unreachable!("interlocked fragments need the position of their tile")
+    }
+
+    #[inline]
+    fn shade_at(&self, plane: &L, mask: &TileMask, origin: Vector2<f32>, color: &mut [P; 64]) {
+        let x0 = (origin.x + self.center.x).round() as i32;
+        let y0 = (origin.y + self.center.y).round() as i32;
+        let (w, h) = (self.store.width as i32, self.store.height as i32);
+        for (i, b) in mask.iter() {
+            let (x, y) = (x0 + i.x() as i32, y0 + i.y() as i32);
+            // partial tiles along the edges reach out of the frame
+            if x >= w || y >= h {
+                continue;
+            }
+            let data = unsafe { self.store.cell(x as u32, (h - 1 - y) as u32) };
+            let new = self.fragment.fragment(plane.evaluate(b[1], b[2]), data);
+            let dst = unsafe { color.get_unchecked_mut(i.0 as usize) };
+            *dst = self.fragment.blend(*dst, new);
+        }
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// like `raster` but every fragment is also handed the data of its
+    /// pixel in `store` to read and change. The fragments of a pixel run
+    /// one after the other in the order the triangles were submitted, with
+    /// nothing else touching that pixel's data in the meantime. The store
+    /// has to be as large as the frame.
+    pub fn raster_interlocked<S, F, T, O, D>(&mut self, poly: S, fragment: F, store: Arc<PixelStore<D>>) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: FragmentInterlock<O, D, Color=P> + Send + Sync + 'static,
+              D: Send + 'static {
+
+        assert!(store.width == self.width && store.height == self.height,
+                "the pixel store has to match the size of the frame");
+        store.claim(self.id());
+        self.capture_draw::<F>(false);
+        let shader = Interlocked {
+            fragment: fragment,
+            store: store,
+            center: Vector2::new(self.width as f32 / 2., self.height as f32 / 2.)
+        };
+        self.bin(poly, shader, true, |t, back| if back { None } else { Some(t) })
+    }
+
+    /// wait for the draws of the frame and visit the data of every pixel
+    /// in `store` with its position, row by row from the top left pixel
+    pub fn pixel_store<D, F>(&mut self, store: &PixelStore<D>, mut f: F)
+        where F: FnMut(u32, u32, &mut D) {
+
+        store.claim(self.id());
+        self.flush();
+        for y in 0..store.height {
+            for x in 0..store.width {
+                f(x, y, unsafe { store.cell(x, y) });
+            }
+        }
+    }
+}
@@ -1,52 +1,187 @@
 pub use cgmath::*;
 use genmesh::Triangle;
 
+use f32x8::f32x8;
+
 
 #[derive(Clone, Debug, Copy)]
 pub struct Flat<T>(pub T);
 
 impl<T: Clone> Interpolate for Flat<T> {
     type Out = T;
+    type Plane = Flat<T>;
     #[inline]
     fn interpolate(src: &Triangle<Flat<T>>, _: [f32; 3]) -> T { src.x.0.clone() }
+    #[inline]
+    fn setup(src: &Triangle<Flat<T>>) -> Flat<T> { src.x.clone() }
+}
+
+impl<T: Clone> Plane for Flat<T> {
+    type Out = T;
+    #[inline]
+    fn evaluate(&self, _: f32, _: f32) -> T { self.0.clone() }
+    #[inline]
+    fn is_flat() -> bool { true }
+}
+
+/// a vertex tagged with the side of its triangle that faces the viewer,
+/// true for front faces
+#[derive(Clone, Debug, Copy)]
+pub struct Facing<T>(pub T, pub bool);
+
+impl<T: Interpolate + Clone> Interpolate for Facing<T> {
+    type Out = (T::Out, bool);
+    type Plane = Facing<T::Plane>;
+    #[inline]
+    fn interpolate(src: &Triangle<Facing<T>>, w: [f32; 3]) -> (T::Out, bool) {
+        (Interpolate::interpolate(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone()), w),
+         src.x.1)
+    }
+    #[inline]
+    fn setup(src: &Triangle<Facing<T>>) -> Facing<T::Plane> {
+        Facing(Interpolate::setup(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone())),
+               src.x.1)
+    }
+}
+
+impl<L: Plane> Plane for Facing<L> {
+    type Out = (L::Out, bool);
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> (L::Out, bool) { (self.0.evaluate(u, v), self.1) }
+    #[inline]
+    fn is_flat() -> bool { L::is_flat() }
+    #[inline]
+    fn varyings_flat() -> bool { L::varyings_flat() }
 }
 
-pub trait Interpolate {
+pub trait Interpolate: Clone {
     type Out;
+    /// the attribute gradients of a triangle, see `setup`. A vertex that
+    /// names its own plane has to give its own `setup` as well.
+    type Plane: Plane<Out=Self::Out> = Reinterpolate<Self>;
 
     #[inline]
     fn interpolate(src: &Triangle<Self>, w: [f32; 3]) -> Self::Out;
+
+    /// compute the plane equation of the attributes once per triangle,
+    /// it is evaluated for every fragment the triangle covers. By default
+    /// the triangle is kept and interpolated for every fragment.
+    #[inline]
+    fn setup(src: &Triangle<Self>) -> Self::Plane { Reinterpolate(src.clone()) }
+}
+
+/// attributes as a function of the barycentric `u` and `v` of a fragment
+pub trait Plane {
+    type Out;
+    fn evaluate(&self, u: f32, v: f32) -> Self::Out;
+
+    /// true if the attributes are the same over the whole triangle
+    #[inline]
+    fn is_flat() -> bool { false }
+
+    /// like `is_flat` for the vertex of a draw, the position in front is
+    /// left out. It is only taken as flat for a shader that opts in with
+    /// `Fragment::position_unused`, every fragment then gets the position
+    /// of the first vertex.
+    #[inline]
+    fn varyings_flat() -> bool { Self::is_flat() }
+}
+
+/// the plane of a vertex without a setup of its own
+#[derive(Clone, Debug)]
+pub struct Reinterpolate<T>(pub Triangle<T>);
+
+impl<T: Interpolate> Plane for Reinterpolate<T> {
+    type Out = T::Out;
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> T::Out {
+        Interpolate::interpolate(&self.0, [1. - u - v, u, v])
+    }
 }
 
 impl Interpolate for f32 {
     type Out = f32;
+    type Plane = [f32; 3];
     #[inline]
     fn interpolate(src: &Triangle<f32>, w: [f32; 3]) -> f32 {
         src.x * w[0] + src.y * w[1] + src.z * w[2]
     }
+    #[inline]
+    fn setup(src: &Triangle<f32>) -> [f32; 3] {
+        [src.x, src.y - src.x, src.z - src.x]
+    }
+}
+
+/// a plane that can be evaluated for eight fragments at once, every
+/// float attribute becomes an f32x8
+pub trait PlaneSimd: Plane {
+    type Lanes;
+    fn evaluate8(&self, u: f32x8, v: f32x8) -> Self::Lanes;
+}
+
+/// the value at the first vertex followed by the change along `u` and `v`
+impl Plane for [f32; 3] {
+    type Out = f32;
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> f32 {
+        self[0] + self[1] * u + self[2] * v
+    }
 }
 
 impl Interpolate for [f32; 2] {
     type Out = [f32; 2];
+    type Plane = [[f32; 3]; 2];
     #[inline]
     fn interpolate(src: &Triangle<[f32; 2]>, w: [f32; 3]) -> [f32; 2] {
         [Interpolate::interpolate(&Triangle::new(src.x[0], src.y[0], src.z[0]), w),
          Interpolate::interpolate(&Triangle::new(src.x[1], src.y[1], src.z[1]), w)]
     }
+    #[inline]
+    fn setup(src: &Triangle<[f32; 2]>) -> [[f32; 3]; 2] {
+        [Interpolate::setup(&Triangle::new(src.x[0], src.y[0], src.z[0])),
+         Interpolate::setup(&Triangle::new(src.x[1], src.y[1], src.z[1]))]
+    }
+}
+
+impl Plane for [[f32; 3]; 2] {
+    type Out = [f32; 2];
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> [f32; 2] {
+        [self[0].evaluate(u, v),
+         self[1].evaluate(u, v)]
+    }
 }
 
 impl Interpolate for [f32; 3] {
     type Out = [f32; 3];
+    type Plane = [[f32; 3]; 3];
     #[inline]
     fn interpolate(src: &Triangle<[f32; 3]>, w: [f32; 3]) -> [f32; 3] {
         [Interpolate::interpolate(&Triangle::new(src.x[0], src.y[0], src.z[0]), w),
          Interpolate::interpolate(&Triangle::new(src.x[1], src.y[1], src.z[1]), w),
          Interpolate::interpolate(&Triangle::new(src.x[2], src.y[2], src.z[2]), w)]
     }
+    #[inline]
+    fn setup(src: &Triangle<[f32; 3]>) -> [[f32; 3]; 3] {
+        [Interpolate::setup(&Triangle::new(src.x[0], src.y[0], src.z[0])),
+         Interpolate::setup(&Triangle::new(src.x[1], src.y[1], src.z[1])),
+         Interpolate::setup(&Triangle::new(src.x[2], src.y[2], src.z[2]))]
+    }
+}
+
+impl Plane for [[f32; 3]; 3] {
+    type Out = [f32; 3];
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> [f32; 3] {
+        [self[0].evaluate(u, v),
+         self[1].evaluate(u, v),
+         self[2].evaluate(u, v)]
+    }
 }
 
 impl Interpolate for [f32; 4] {
     type Out = [f32; 4];
+    type Plane = [[f32; 3]; 4];
     #[inline]
     fn interpolate(src: &Triangle<[f32; 4]>, w: [f32; 3]) -> [f32; 4] {
         [Interpolate::interpolate(&Triangle::new(src.x[0], src.y[0], src.z[0]), w),
@@ -54,17 +189,59 @@ impl Interpolate for [f32; 4] {
          Interpolate::interpolate(&Triangle::new(src.x[2], src.y[2], src.z[2]), w),
          Interpolate::interpolate(&Triangle::new(src.x[3], src.y[3], src.z[3]), w)]
     }
+    #[inline]
+    fn setup(src: &Triangle<[f32; 4]>) -> [[f32; 3]; 4] {
+        [Interpolate::setup(&Triangle::new(src.x[0], src.y[0], src.z[0])),
+         Interpolate::setup(&Triangle::new(src.x[1], src.y[1], src.z[1])),
+         Interpolate::setup(&Triangle::new(src.x[2], src.y[2], src.z[2])),
+         Interpolate::setup(&Triangle::new(src.x[3], src.y[3], src.z[3]))]
+    }
+}
+
+impl Plane for [[f32; 3]; 4] {
+    type Out = [f32; 4];
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> [f32; 4] {
+        [self[0].evaluate(u, v),
+         self[1].evaluate(u, v),
+         self[2].evaluate(u, v),
+         self[3].evaluate(u, v)]
+    }
 }
 
 impl<A, B, AO, BO> Interpolate for (A, B)
     where A: Interpolate<Out=AO> + Clone,
           B: Interpolate<Out=BO> + Clone {
     type Out = (AO, BO);
+    type Plane = (A::Plane, B::Plane);
     #[inline]
     fn interpolate(src: &Triangle<(A, B)>, w: [f32; 3]) -> (AO, BO) {
         (Interpolate::interpolate(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone()), w),
          Interpolate::interpolate(&Triangle::new(src.x.1.clone(), src.y.1.clone(), src.z.1.clone()), w))
     }
+    #[inline]
+    fn setup(src: &Triangle<(A, B)>) -> (A::Plane, B::Plane) {
+        (Interpolate::setup(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone())),
+         Interpolate::setup(&Triangle::new(src.x.1.clone(), src.y.1.clone(), src.z.1.clone())))
+    }
+}
+
+impl<A: Plane, B: Plane> Plane for (A, B) {
+    type Out = (A::Out, B::Out);
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> (A::Out, B::Out) {
+        (self.0.evaluate(u, v),
+         self.1.evaluate(u, v))
+    }
+    #[inline]
+    fn is_flat() -> bool {
+        A::is_flat() &&
+        B::is_flat()
+    }
+    #[inline]
+    fn varyings_flat() -> bool {
+        B::is_flat()
+    }
 }
 
 impl<A, B, C, AO, BO, CO> Interpolate for (A, B, C)
@@ -72,12 +249,40 @@ impl<A, B, C, AO, BO, CO> Interpolate for (A, B, C)
           B: Interpolate<Out=BO> + Clone,
           C: Interpolate<Out=CO> + Clone {
     type Out = (AO, BO, CO);
+    type Plane = (A::Plane, B::Plane, C::Plane);
     #[inline]
     fn interpolate(src: &Triangle<(A, B, C)>, w: [f32; 3]) -> (AO, BO, CO) {
         (Interpolate::interpolate(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone()), w),
          Interpolate::interpolate(&Triangle::new(src.x.1.clone(), src.y.1.clone(), src.z.1.clone()), w),
          Interpolate::interpolate(&Triangle::new(src.x.2.clone(), src.y.2.clone(), src.z.2.clone()), w))
     }
+    #[inline]
+    fn setup(src: &Triangle<(A, B, C)>) -> (A::Plane, B::Plane, C::Plane) {
+        (Interpolate::setup(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone())),
+         Interpolate::setup(&Triangle::new(src.x.1.clone(), src.y.1.clone(), src.z.1.clone())),
+         Interpolate::setup(&Triangle::new(src.x.2.clone(), src.y.2.clone(), src.z.2.clone())))
+    }
+}
+
+impl<A: Plane, B: Plane, C: Plane> Plane for (A, B, C) {
+    type Out = (A::Out, B::Out, C::Out);
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> (A::Out, B::Out, C::Out) {
+        (self.0.evaluate(u, v),
+         self.1.evaluate(u, v),
+         self.2.evaluate(u, v))
+    }
+    #[inline]
+    fn is_flat() -> bool {
+        A::is_flat() &&
+        B::is_flat() &&
+        C::is_flat()
+    }
+    #[inline]
+    fn varyings_flat() -> bool {
+        B::is_flat() &&
+        C::is_flat()
+    }
 }
 
 impl<A, B, C, D, AO, BO, CO, DO> Interpolate for (A, B, C, D)
@@ -86,6 +291,7 @@ impl<A, B, C, D, AO, BO, CO, DO> Interpolate for (A, B, C, D)
           C: Interpolate<Out=CO> + Clone,
           D: Interpolate<Out=DO> + Clone {
     type Out = (AO, BO, CO, DO);
+    type Plane = (A::Plane, B::Plane, C::Plane, D::Plane);
     #[inline]
     fn interpolate(src: &Triangle<(A, B, C, D)>, w: [f32; 3]) -> (AO, BO, CO, DO) {
         (Interpolate::interpolate(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone()), w),
@@ -93,6 +299,37 @@ impl<A, B, C, D, AO, BO, CO, DO> Interpolate for (A, B, C, D)
          Interpolate::interpolate(&Triangle::new(src.x.2.clone(), src.y.2.clone(), src.z.2.clone()), w),
          Interpolate::interpolate(&Triangle::new(src.x.3.clone(), src.y.3.clone(), src.z.3.clone()), w))
     }
+    #[inline]
+    fn setup(src: &Triangle<(A, B, C, D)>) -> (A::Plane, B::Plane, C::Plane, D::Plane) {
+        (Interpolate::setup(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone())),
+         Interpolate::setup(&Triangle::new(src.x.1.clone(), src.y.1.clone(), src.z.1.clone())),
+         Interpolate::setup(&Triangle::new(src.x.2.clone(), src.y.2.clone(), src.z.2.clone())),
+         Interpolate::setup(&Triangle::new(src.x.3.clone(), src.y.3.clone(), src.z.3.clone())))
+    }
+}
+
+impl<A: Plane, B: Plane, C: Plane, D: Plane> Plane for (A, B, C, D) {
+    type Out = (A::Out, B::Out, C::Out, D::Out);
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> (A::Out, B::Out, C::Out, D::Out) {
+        (self.0.evaluate(u, v),
+         self.1.evaluate(u, v),
+         self.2.evaluate(u, v),
+         self.3.evaluate(u, v))
+    }
+    #[inline]
+    fn is_flat() -> bool {
+        A::is_flat() &&
+        B::is_flat() &&
+        C::is_flat() &&
+        D::is_flat()
+    }
+    #[inline]
+    fn varyings_flat() -> bool {
+        B::is_flat() &&
+        C::is_flat() &&
+        D::is_flat()
+    }
 }
 
 impl<A, B, C, D, E, AO, BO, CO, DO, EO> Interpolate for (A, B, C, D, E)
@@ -102,6 +339,7 @@ impl<A, B, C, D, E, AO, BO, CO, DO, EO> Interpolate for (A, B, C, D, E)
           D: Interpolate<Out=DO> + Clone,
           E: Interpolate<Out=EO> + Clone {
     type Out = (AO, BO, CO, DO, EO);
+    type Plane = (A::Plane, B::Plane, C::Plane, D::Plane, E::Plane);
     #[inline]
     fn interpolate(src: &Triangle<(A, B, C, D, E)>, w: [f32; 3]) -> (AO, BO, CO, DO, EO) {
         (Interpolate::interpolate(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone()), w),
@@ -110,6 +348,41 @@ impl<A, B, C, D, E, AO, BO, CO, DO, EO> Interpolate for (A, B, C, D, E)
          Interpolate::interpolate(&Triangle::new(src.x.3.clone(), src.y.3.clone(), src.z.3.clone()), w),
          Interpolate::interpolate(&Triangle::new(src.x.4.clone(), src.y.4.clone(), src.z.4.clone()), w))
     }
+    #[inline]
+    fn setup(src: &Triangle<(A, B, C, D, E)>) -> (A::Plane, B::Plane, C::Plane, D::Plane, E::Plane) {
+        (Interpolate::setup(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone())),
+         Interpolate::setup(&Triangle::new(src.x.1.clone(), src.y.1.clone(), src.z.1.clone())),
+         Interpolate::setup(&Triangle::new(src.x.2.clone(), src.y.2.clone(), src.z.2.clone())),
+         Interpolate::setup(&Triangle::new(src.x.3.clone(), src.y.3.clone(), src.z.3.clone())),
+         Interpolate::setup(&Triangle::new(src.x.4.clone(), src.y.4.clone(), src.z.4.clone())))
+    }
+}
+
+impl<A: Plane, B: Plane, C: Plane, D: Plane, E: Plane> Plane for (A, B, C, D, E) {
+    type Out = (A::Out, B::Out, C::Out, D::Out, E::Out);
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> (A::Out, B::Out, C::Out, D::Out, E::Out) {
+        (self.0.evaluate(u, v),
+         self.1.evaluate(u, v),
+         self.2.evaluate(u, v),
+         self.3.evaluate(u, v),
+         self.4.evaluate(u, v))
+    }
+    #[inline]
+    fn is_flat() -> bool {
+        A::is_flat() &&
+        B::is_flat() &&
+        C::is_flat() &&
+        D::is_flat() &&
+        E::is_flat()
+    }
+    #[inline]
+    fn varyings_flat() -> bool {
+        B::is_flat() &&
+        C::is_flat() &&
+        D::is_flat() &&
+        E::is_flat()
+    }
 }
 
 impl<A, B, C, D, E, F, AO, BO, CO, DO, EO, FO> Interpolate for (A, B, C, D, E, F)
@@ -120,6 +393,7 @@ impl<A, B, C, D, E, F, AO, BO, CO, DO, EO, FO> Interpolate for (A, B, C, D, E, F
           E: Interpolate<Out=EO> + Clone,
           F: Interpolate<Out=FO> + Clone {
     type Out = (AO, BO, CO, DO, EO, FO);
+    type Plane = (A::Plane, B::Plane, C::Plane, D::Plane, E::Plane, F::Plane);
     #[inline]
     fn interpolate(src: &Triangle<(A, B, C, D, E, F)>, w: [f32; 3]) -> (AO, BO, CO, DO, EO, FO) {
         (Interpolate::interpolate(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone()), w),
@@ -129,6 +403,45 @@ impl<A, B, C, D, E, F, AO, BO, CO, DO, EO, FO> Interpolate for (A, B, C, D, E, F
          Interpolate::interpolate(&Triangle::new(src.x.4.clone(), src.y.4.clone(), src.z.4.clone()), w),
          Interpolate::interpolate(&Triangle::new(src.x.5.clone(), src.y.5.clone(), src.z.5.clone()), w))
     }
+    #[inline]
+    fn setup(src: &Triangle<(A, B, C, D, E, F)>) -> (A::Plane, B::Plane, C::Plane, D::Plane, E::Plane, F::Plane) {
+        (Interpolate::setup(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone())),
+         Interpolate::setup(&Triangle::new(src.x.1.clone(), src.y.1.clone(), src.z.1.clone())),
+         Interpolate::setup(&Triangle::new(src.x.2.clone(), src.y.2.clone(), src.z.2.clone())),
+         Interpolate::setup(&Triangle::new(src.x.3.clone(), src.y.3.clone(), src.z.3.clone())),
+         Interpolate::setup(&Triangle::new(src.x.4.clone(), src.y.4.clone(), src.z.4.clone())),
+         Interpolate::setup(&Triangle::new(src.x.5.clone(), src.y.5.clone(), src.z.5.clone())))
+    }
+}
+
+impl<A: Plane, B: Plane, C: Plane, D: Plane, E: Plane, F: Plane> Plane for (A, B, C, D, E, F) {
+    type Out = (A::Out, B::Out, C::Out, D::Out, E::Out, F::Out);
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> (A::Out, B::Out, C::Out, D::Out, E::Out, F::Out) {
+        (self.0.evaluate(u, v),
+         self.1.evaluate(u, v),
+         self.2.evaluate(u, v),
+         self.3.evaluate(u, v),
+         self.4.evaluate(u, v),
+         self.5.evaluate(u, v))
+    }
+    #[inline]
+    fn is_flat() -> bool {
+        A::is_flat() &&
+        B::is_flat() &&
+        C::is_flat() &&
+        D::is_flat() &&
+        E::is_flat() &&
+        F::is_flat()
+    }
+    #[inline]
+    fn varyings_flat() -> bool {
+        B::is_flat() &&
+        C::is_flat() &&
+        D::is_flat() &&
+        E::is_flat() &&
+        F::is_flat()
+    }
 }
 
 impl<A, B, C, D, E, F, G, AO, BO, CO, DO, EO, FO, GO> Interpolate for (A, B, C, D, E, F, G)
@@ -140,6 +453,7 @@ impl<A, B, C, D, E, F, G, AO, BO, CO, DO, EO, FO, GO> Interpolate for (A, B, C,
           F: Interpolate<Out=FO> + Clone,
           G: Interpolate<Out=GO> + Clone {
     type Out = (AO, BO, CO, DO, EO, FO, GO);
+    type Plane = (A::Plane, B::Plane, C::Plane, D::Plane, E::Plane, F::Plane, G::Plane);
     #[inline]
     fn interpolate(src: &Triangle<(A, B, C, D, E, F, G)>, w: [f32; 3]) -> (AO, BO, CO, DO, EO, FO, GO) {
         (Interpolate::interpolate(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone()), w),
@@ -150,6 +464,49 @@ impl<A, B, C, D, E, F, G, AO, BO, CO, DO, EO, FO, GO> Interpolate for (A, B, C,
          Interpolate::interpolate(&Triangle::new(src.x.5.clone(), src.y.5.clone(), src.z.5.clone()), w),
          Interpolate::interpolate(&Triangle::new(src.x.6.clone(), src.y.6.clone(), src.z.6.clone()), w))
     }
+    #[inline]
+    fn setup(src: &Triangle<(A, B, C, D, E, F, G)>) -> (A::Plane, B::Plane, C::Plane, D::Plane, E::Plane, F::Plane, G::Plane) {
+        (Interpolate::setup(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone())),
+         Interpolate::setup(&Triangle::new(src.x.1.clone(), src.y.1.clone(), src.z.1.clone())),
+         Interpolate::setup(&Triangle::new(src.x.2.clone(), src.y.2.clone(), src.z.2.clone())),
+         Interpolate::setup(&Triangle::new(src.x.3.clone(), src.y.3.clone(), src.z.3.clone())),
+         Interpolate::setup(&Triangle::new(src.x.4.clone(), src.y.4.clone(), src.z.4.clone())),
+         Interpolate::setup(&Triangle::new(src.x.5.clone(), src.y.5.clone(), src.z.5.clone())),
+         Interpolate::setup(&Triangle::new(src.x.6.clone(), src.y.6.clone(), src.z.6.clone())))
+    }
+}
+
+impl<A: Plane, B: Plane, C: Plane, D: Plane, E: Plane, F: Plane, G: Plane> Plane for (A, B, C, D, E, F, G) {
+    type Out = (A::Out, B::Out, C::Out, D::Out, E::Out, F::Out, G::Out);
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> (A::Out, B::Out, C::Out, D::Out, E::Out, F::Out, G::Out) {
+        (self.0.evaluate(u, v),
+         self.1.evaluate(u, v),
+         self.2.evaluate(u, v),
+         self.3.evaluate(u, v),
+         self.4.evaluate(u, v),
+         self.5.evaluate(u, v),
+         self.6.evaluate(u, v))
+    }
+    #[inline]
+    fn is_flat() -> bool {
+        A::is_flat() &&
+        B::is_flat() &&
+        C::is_flat() &&
+        D::is_flat() &&
+        E::is_flat() &&
+        F::is_flat() &&
+        G::is_flat()
+    }
+    #[inline]
+    fn varyings_flat() -> bool {
+        B::is_flat() &&
+        C::is_flat() &&
+        D::is_flat() &&
+        E::is_flat() &&
+        F::is_flat() &&
+        G::is_flat()
+    }
 }
 
 impl<A, B, C, D, E, F, G, H, AO, BO, CO, DO, EO, FO, GO, HO> Interpolate for (A, B, C, D, E, F, G, H)
@@ -162,6 +519,7 @@ impl<A, B, C, D, E, F, G, H, AO, BO, CO, DO, EO, FO, GO, HO> Interpolate for (A,
           G: Interpolate<Out=GO> + Clone,
           H: Interpolate<Out=HO> + Clone {
     type Out = (AO, BO, CO, DO, EO, FO, GO, HO);
+    type Plane = (A::Plane, B::Plane, C::Plane, D::Plane, E::Plane, F::Plane, G::Plane, H::Plane);
     #[inline]
     fn interpolate(src: &Triangle<(A, B, C, D, E, F, G, H)>, w: [f32; 3]) -> (AO, BO, CO, DO, EO, FO, GO, HO) {
         (Interpolate::interpolate(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone()), w),
@@ -173,5 +531,131 @@ impl<A, B, C, D, E, F, G, H, AO, BO, CO, DO, EO, FO, GO, HO> Interpolate for (A,
          Interpolate::interpolate(&Triangle::new(src.x.6.clone(), src.y.6.clone(), src.z.6.clone()), w),
          Interpolate::interpolate(&Triangle::new(src.x.7.clone(), src.y.7.clone(), src.z.7.clone()), w))
     }
+    #[inline]
+    fn setup(src: &Triangle<(A, B, C, D, E, F, G, H)>) -> (A::Plane, B::Plane, C::Plane, D::Plane, E::Plane, F::Plane, G::Plane, H::Plane) {
+        (Interpolate::setup(&Triangle::new(src.x.0.clone(), src.y.0.clone(), src.z.0.clone())),
+         Interpolate::setup(&Triangle::new(src.x.1.clone(), src.y.1.clone(), src.z.1.clone())),
+         Interpolate::setup(&Triangle::new(src.x.2.clone(), src.y.2.clone(), src.z.2.clone())),
+         Interpolate::setup(&Triangle::new(src.x.3.clone(), src.y.3.clone(), src.z.3.clone())),
+         Interpolate::setup(&Triangle::new(src.x.4.clone(), src.y.4.clone(), src.z.4.clone())),
+         Interpolate::setup(&Triangle::new(src.x.5.clone(), src.y.5.clone(), src.z.5.clone())),
+         Interpolate::setup(&Triangle::new(src.x.6.clone(), src.y.6.clone(), src.z.6.clone())),
+         Interpolate::setup(&Triangle::new(src.x.7.clone(), src.y.7.clone(), src.z.7.clone())))
+    }
+}
+
+impl<A: Plane, B: Plane, C: Plane, D: Plane, E: Plane, F: Plane, G: Plane, H: Plane> Plane for (A, B, C, D, E, F, G, H) {
+    type Out = (A::Out, B::Out, C::Out, D::Out, E::Out, F::Out, G::Out, H::Out);
+    #[inline]
+    fn evaluate(&self, u: f32, v: f32) -> (A::Out, B::Out, C::Out, D::Out, E::Out, F::Out, G::Out, H::Out) {
+        (self.0.evaluate(u, v),
+         self.1.evaluate(u, v),
+         self.2.evaluate(u, v),
+         self.3.evaluate(u, v),
+         self.4.evaluate(u, v),
+         self.5.evaluate(u, v),
+         self.6.evaluate(u, v),
+         self.7.evaluate(u, v))
+    }
+    #[inline]
+    fn is_flat() -> bool {
+        A::is_flat() &&
+        B::is_flat() &&
+        C::is_flat() &&
+        D::is_flat() &&
+        E::is_flat() &&
+        F::is_flat() &&
+        G::is_flat() &&
+        H::is_flat()
+    }
+    #[inline]
+    fn varyings_flat() -> bool {
+        B::is_flat() &&
+        C::is_flat() &&
+        D::is_flat() &&
+        E::is_flat() &&
+        F::is_flat() &&
+        G::is_flat() &&
+        H::is_flat()
+    }
+}
+
+impl<T: Clone> PlaneSimd for Flat<T> {
+    type Lanes = T;
+    #[inline]
+    fn evaluate8(&self, _: f32x8, _: f32x8) -> T { self.0.clone() }
+}
+
+impl<L: PlaneSimd> PlaneSimd for Facing<L> {
+    type Lanes = (L::Lanes, bool);
+    #[inline]
+    fn evaluate8(&self, u: f32x8, v: f32x8) -> (L::Lanes, bool) { (self.0.evaluate8(u, v), self.1) }
+}
+
+impl PlaneSimd for [f32; 3] {
+    type Lanes = f32x8;
+    #[inline]
+    fn evaluate8(&self, u: f32x8, v: f32x8) -> f32x8 {
+        f32x8::broadcast(self[0]) + f32x8::broadcast(self[1]) * u + f32x8::broadcast(self[2]) * v
+    }
+}
+
+impl PlaneSimd for [[f32; 3]; 2] {
+    type Lanes = [f32x8; 2];
+    #[inline]
+    fn evaluate8(&self, u: f32x8, v: f32x8) -> [f32x8; 2] {
+        [self[0].evaluate8(u, v),
+         self[1].evaluate8(u, v)]
+    }
+}
+
+impl PlaneSimd for [[f32; 3]; 3] {
+    type Lanes = [f32x8; 3];
+    #[inline]
+    fn evaluate8(&self, u: f32x8, v: f32x8) -> [f32x8; 3] {
+        [self[0].evaluate8(u, v),
+         self[1].evaluate8(u, v),
+         self[2].evaluate8(u, v)]
+    }
 }
 
+impl PlaneSimd for [[f32; 3]; 4] {
+    type Lanes = [f32x8; 4];
+    #[inline]
+    fn evaluate8(&self, u: f32x8, v: f32x8) -> [f32x8; 4] {
+        [self[0].evaluate8(u, v),
+         self[1].evaluate8(u, v),
+         self[2].evaluate8(u, v),
+         self[3].evaluate8(u, v)]
+    }
+}
+
+impl<A: PlaneSimd, B: PlaneSimd> PlaneSimd for (A, B) {
+    type Lanes = (A::Lanes, B::Lanes);
+    #[inline]
+    fn evaluate8(&self, u: f32x8, v: f32x8) -> (A::Lanes, B::Lanes) {
+        (self.0.evaluate8(u, v),
+         self.1.evaluate8(u, v))
+    }
+}
+
+impl<A: PlaneSimd, B: PlaneSimd, C: PlaneSimd> PlaneSimd for (A, B, C) {
+    type Lanes = (A::Lanes, B::Lanes, C::Lanes);
+    #[inline]
+    fn evaluate8(&self, u: f32x8, v: f32x8) -> (A::Lanes, B::Lanes, C::Lanes) {
+        (self.0.evaluate8(u, v),
+         self.1.evaluate8(u, v),
+         self.2.evaluate8(u, v))
+    }
+}
+
+impl<A: PlaneSimd, B: PlaneSimd, C: PlaneSimd, D: PlaneSimd> PlaneSimd for (A, B, C, D) {
+    type Lanes = (A::Lanes, B::Lanes, C::Lanes, D::Lanes);
+    #[inline]
+    fn evaluate8(&self, u: f32x8, v: f32x8) -> (A::Lanes, B::Lanes, C::Lanes, D::Lanes) {
+        (self.0.evaluate8(u, v),
+         self.1.evaluate8(u, v),
+         self.2.evaluate8(u, v),
+         self.3.evaluate8(u, v))
+    }
+}
@@ -1,6 +1,7 @@
-#![feature(simd, unboxed_closures, core, slice_patterns, step_by)]
+#![feature(simd, unboxed_closures, core, slice_patterns, step_by, associated_type_defaults)]
 #![allow(non_camel_case_types)]
 
+#[cfg(feature = "image")]
 extern crate image;
 extern crate genmesh;
 extern crate cgmath;
@@ -9,32 +10,160 @@ extern crate snowstorm;
 extern crate future_pulse;
 extern crate pulse;
 extern crate vec_map;
-
-use std::sync::Arc;
+extern crate num_cpus;
+extern crate time;
+#[cfg(feature = "glyph")]
+extern crate stb_truetype;
+#[cfg(feature = "gltf")]
+extern crate rustc_serialize;
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::fmt::Debug;
-use std::cell::UnsafeCell;
 
 use fibe::{Frontend, task, ResumableTask, WaitState, Schedule, IntoTask};
-use image::{GenericImage, ImageBuffer, Rgba};
+#[cfg(feature = "image")]
+use image::{GenericImage, ImageBuffer};
 use cgmath::*;
 use genmesh::{Triangle, MapVertex};
 use future_pulse::*;
 use pulse::*;
-use snowstorm::channel::*;
 use vec_map::*;
 
-pub use tile::{TileGroup, Tile, Raster};
+pub use tile::{TileGroup, Tile, Pixels, Raster, Quad, Shade, PerFragment, Batched};
+pub use buffer::Buffer;
+pub use rect::Rect;
+use tile::{Put, Clip, TileMask};
+use validate::Validator;
+use pool::{Traffic, Bin, BinSender, BinPool};
+use timing::{Timer, PassClock, timed};
+use deadline::Deadline;
+use audit::Audit;
 use vmath::Dot;
 use f32x8::f32x8x8;
-pub use pipeline::{Fragment, Vertex, Mapping};
-pub use interpolate::{Flat, Interpolate};
+pub use pipeline::{Fragment, FragmentSimd, FragmentWith, WithData, Vertex, Mapping, MappingAt, TwoSided, SolidColor};
+pub use interpolate::{Flat, Facing, Interpolate, Plane, PlaneSimd, Reinterpolate};
+pub use color::{Lerp, Rgba, alpha_over};
+pub use clip::{SubVertex, SubPlane, MAX_CLIP_PLANES, clip_triangle};
+pub use resolve::{Resolve, BoxResolve, TentResolve};
+pub use target::{TiledTarget, Band};
+pub use command::CommandList;
+pub use batch::DrawMerger;
+pub use backend::{RasterBackend, SimdBackend, ScalarBackend, Coverage};
+pub use decal::Decal;
+pub use fog::{Fog, FogMode, FogCoord};
+pub use depth::{Projection, DepthMode, pixel_ndc, clip_to_screen};
+pub use interlock::{FragmentInterlock, PixelStore};
+pub use outline::Outline;
+pub use pool::{TilePool, FramePool, MemoryUsage};
+pub use stereo::Stereo;
+pub use stencil::Stencil;
+pub use adaptive::AdaptiveFrame;
+pub use resolved::Resolved;
+pub use view::FrameView;
+#[cfg(feature = "image")]
+pub use diff::{ImageDiff, image_diff};
+pub use builder::FrameBuilder;
+pub use checksum::Checksums;
+pub use fence::Fence;
+pub use direct::TileRaster;
+pub use remote::{TilePacket, TileSink, WriteSink, StreamPixel};
+pub use offline::FrameQueue;
+pub use timing::{Pass, PassTiming, Trace, TraceEvent, MAX_PASSES};
+pub use audit::{AllocCounter, count_allocations};
+pub use pass::LoadOp;
+pub use caps::{Capabilities, Format, capabilities};
+pub use error::Error;
+pub use validate::{Diagnostic, Issue};
+pub use capture::{Capture, CaptureDraw};
+pub use shared::SharedFrame;
+pub use stream::Drain;
+pub use stats::DrawStats;
+pub use soa::{Gather, IndexedTriangles, IndexedStrip, IndexedFan, PRIMITIVE_RESTART};
+pub use transform::{transform_into, transform_positions, transform_quantized_into};
+pub use transform::{split_view, transform_relative_into};
+pub use quantize::{Quantized, Normalized, Dequantize};
+pub use visualize::{DepthGray, NormalColor, Heatmap};
+pub use post::{Lens, Denoise};
+pub use lut::Lut3d;
+pub use noise::{Noise, BlueNoise};
+pub use math::{IntoMatrix, Mat4, Mat4d, to_columns};
 
 mod interpolate;
 mod pipeline;
 mod f32x4;
 pub mod f32x8;
 mod vmath;
+mod math;
 pub mod tile;
+mod color;
+mod clip;
+mod buffer;
+mod rect;
+mod copy;
+mod sdf;
+mod post;
+mod resolve;
+mod target;
+mod region;
+mod command;
+mod batch;
+mod backend;
+mod decal;
+mod fog;
+mod depth;
+mod interlock;
+mod outline;
+mod pool;
+mod stereo;
+mod stencil;
+mod timing;
+mod deadline;
+mod audit;
+mod adaptive;
+mod resolved;
+mod view;
+mod builder;
+mod checksum;
+mod fence;
+mod direct;
+mod remote;
+mod offline;
+#[cfg(feature = "image")]
+mod diff;
+mod pass;
+mod caps;
+pub mod error;
+mod validate;
+mod capture;
+mod shared;
+mod stream;
+mod stats;
+mod soa;
+mod transform;
+mod quantize;
+mod visualize;
+mod lut;
+pub mod noise;
+pub mod auxiliary;
+pub mod shaders;
+pub mod scene;
+pub mod animation;
+pub mod shadow;
+pub mod reflection;
+pub mod panorama;
+pub mod meshlet;
+pub mod preprocess;
+pub mod occlusion;
+pub mod sparse;
+pub mod fuzz;
+pub mod paint;
+#[cfg(feature = "glyph")]
+pub mod glyph;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+#[cfg(feature = "image")]
+pub mod thumbnail;
 
 
 #[cfg(dump)]
@@ -58,7 +187,11 @@ pub struct Barycentric {
     pub v0: Vector2<f32>,
     pub v1: Vector2<f32>,
     pub base: Vector2<f32>,
-    inv_denom: f32
+    inv_denom: f32,
+    // the dot products of the edges, they are the same for every point
+    d00: f32,
+    d01: f32,
+    d11: f32
 }
 
 #[derive(Debug)]
@@ -89,25 +222,35 @@ impl Barycentric {
         let d01 = v0.dot(v1);
         let d11 = v1.dot(v1);
 
+        // zero for collinear or coincident vertices and NaN for non finite
+        // ones, either way the inverse is not finite, see `is_degenerate`
         let inv_denom = 1. / (d00 * d11 - d01 * d01);
 
         Barycentric {
             v0: v0,
             v1: v1,
             base: t.x,
-            inv_denom: inv_denom
+            inv_denom: inv_denom,
+            d00: d00,
+            d01: d01,
+            d11: d11
         }
     }
 
+    /// a triangle without area or with non finite vertices, the
+    /// coordinates it gives are meaningless and it covers no pixels
+    #[inline]
+    pub fn is_degenerate(&self) -> bool {
+        !self.inv_denom.is_finite()
+    }
+
     #[inline]
     pub fn coordinate(&self, p: Vector2<f32>) -> BarycentricCoordinate {
         let p = Vector2::new(p.x, p.y);
         let v2 = p - self.base;
 
-        let d00 = self.v0.dot(self.v0);
-        let d01 = self.v0.dot(self.v1);
+        let (d00, d01, d11) = (self.d00, self.d01, self.d11);
         let d02 = self.v0.dot(v2);
-        let d11 = self.v1.dot(self.v1);
         let d12 = self.v1.dot(v2);
 
         let u = (d11 * d02 - d01 * d12) * self.inv_denom;
@@ -129,10 +272,10 @@ impl Barycentric {
         let v1 = f32x4_vec2::broadcast(self.v1);
         let v2 = f32x4_vec2::range(v2.x, v2.y, s.x, s.y);
 
-        let d00 = v0.dot(v0);
-        let d01 = v0.dot(v1);
+        let d00 = f32x4::broadcast(self.d00);
+        let d01 = f32x4::broadcast(self.d01);
         let d02 = v0.dot(v2);
-        let d11 = v1.dot(v1);
+        let d11 = f32x4::broadcast(self.d11);
         let d12 = v1.dot(v2);
 
         let inv_denom = f32x4::broadcast(self.inv_denom);
@@ -147,10 +290,8 @@ impl Barycentric {
 
         let v2 = f32x8x8_vec2::range(p, s) - f32x8x8_vec2::broadcast(self.base);
 
-        let d00 = self.v0.dot(self.v0);
-        let d01 = self.v0.dot(self.v1);
+        let (d00, d01, d11) = (self.d00, self.d01, self.d11);
         let d02 = self.v0.dot(v2);
-        let d11 = self.v1.dot(self.v1);
         let d12 = self.v1.dot(v2);
 
         let inv_denom = f32x8x8::broadcast(self.inv_denom);
@@ -183,158 +324,859 @@ impl Barycentric {
 
         mask & 0x8000_0000 != 0
     }
+
+    /// where the box lies against the triangle, `tile_fast_check` and
+    /// `tile_covered` from a single evaluation of its corners
+    #[inline]
+    pub fn tile_check(&self, p: Vector2<f32>, s: Vector2<f32>) -> Coverage {
+        use f32x4::{f32x4};
+        // NaN corners can have either sign
+        if self.is_degenerate() {
+            return Coverage::Outside;
+        }
+        let [u, v] = self.coordinate_f32x4(p, s);
+        let uv = f32x4::broadcast(1.) - (u + v);
+        let (u, v, uv) = (u.to_bit_u32x4(), v.to_bit_u32x4(), uv.to_bit_u32x4());
+
+        if (u.and_self() | v.and_self() | uv.and_self()) & 0x8000_0000 != 0 {
+            Coverage::Outside
+        } else if (u.or_self() | v.or_self() | uv.or_self()) & 0x8000_0000 == 0 {
+            Coverage::Inside
+        } else {
+            Coverage::Partial
+        }
+    }
+}
+
+/// an error unless a frame of this size is within the limits of
+/// `capabilities`
+fn check_frame_size(width: u32, height: u32) -> error::Result<()> {
+    let caps = capabilities();
+    if width == 0 || height == 0 ||
+       width % caps.size_granularity != 0 || height % caps.size_granularity != 0 ||
+       width > caps.max_dimension || height > caps.max_dimension ||
+       width as u64 * height as u64 > caps.max_pixels {
+        return Err(Error::InvalidSize { width: width, height: height });
+    }
+    Ok(())
 }
 
+/// a tiled color and depth target. Every tile group takes the triangles
+/// that touch it one at a time in the order they were submitted, and
+/// `Fragment::blend` sees a pixel's fragments in that same order. So the
+/// image drawn is the same for any number of worker threads and any
+/// scheduling of them, down to the bit.
 pub struct Frame<P> {
     pub width: u32,
     pub height: u32,
     pub tile: Vec<Vec<Future<Box<TileGroup<P>>>>>,
-    pool: Frontend
+    pool: Frontend,
+    capture: Option<Arc<Mutex<Capture>>>,
+    validation: Option<Arc<Mutex<Validator>>>,
+    split_threshold: usize,
+    priority: Option<Rect>,
+    backend: Arc<RasterBackend>,
+    painter: bool,
+    depth_write: bool,
+    depth_mode: DepthMode,
+    traffic: Arc<Traffic>,
+    bins: BinPool,
+    stencil: Stencil,
+    timer: Option<Arc<Timer>>,
+    deadline: Option<Arc<Deadline>>,
+    alloc_counter: Option<AllocCounter>
+}
+
+/// the shader of a draw with the state the frame has for it, the depth
+/// test is turned off in painter mode
+struct DrawState<S> {
+    shade: S,
+    painter: bool,
+    depth_write: bool,
+    far: f32,
+    stencil: Stencil
+}
+
+impl<S, L, P> Shade<L, P> for DrawState<S> where S: Shade<L, P> {
+    #[inline]
+    fn shade(&self, plane: &L, mask: &TileMask, color: &mut [P; 64]) {
+        self.shade.shade(plane, mask, color)
+    }
+
+    #[inline]
+    fn shade_at(&self, plane: &L, mask: &TileMask, origin: Vector2<f32>, color: &mut [P; 64]) {
+        self.shade.shade_at(plane, mask, origin, color)
+    }
+
+    #[inline]
+    fn depth_test(&self) -> bool { !self.painter && self.shade.depth_test() }
+
+    #[inline]
+    fn depth_write(&self) -> bool { self.depth_write && self.shade.depth_write() }
+
+    #[inline]
+    fn far_plane(&self) -> f32 { self.far }
+
+    #[inline]
+    fn stencil(&self) -> Stencil { self.stencil }
 }
 
-struct RasterWorker<P: Send, T: Send+Sync, F> {
+/// a binned triangle in the space of the tiles with its vertices
+type Binned<T> = (Triangle<Vector3<f32>>, Triangle<T>);
+
+/// where a draw sends the triangles of a tile group, the whole group worker
+/// counts them until there are enough to split it
+enum Queued<T> {
+    Whole(BinSender<Binned<T>>, usize),
+    Split(Vec<BinSender<Binned<T>>>)
+}
+
+struct RasterWorker<P: Send, T: Send+Sync+'static, F> {
     tile: Option<Box<TileGroup<P>>>,
-    polygons: Receiver<(Triangle<Vector3<f32>>, Triangle<T>)>,
+    bin: Arc<Bin<Binned<T>>>,
+    batch: Vec<Binned<T>>,
+    bins: BinPool,
     pos: Vector2<f32>,
     scale: Vector2<f32>,
     fragment: Arc<F>,
+    backend: Arc<RasterBackend>,
+    shaded: usize,
+    stats: Arc<AtomicUsize>,
+    clock: Option<Arc<PassClock>>,
+    deadline: Option<Arc<Deadline>>,
+    audit: Option<Audit>,
+    group: (u32, u32),
     result: Option<future_pulse::Set<Box<TileGroup<P>>>>
 }
 
-impl<T: Send+Sync, P: Send+Copy, F, O> ResumableTask for RasterWorker<P, T, F>
-    where F: Fragment<O, Color=P>+Send+Sync,
-          T: Interpolate<Out=O>+Send+Sync+Debug
+impl<T: Send+Sync+'static, P: Send+Copy, F> ResumableTask for RasterWorker<P, T, F>
+    where F: Shade<T::Plane, P>+Send+Sync,
+          T: Interpolate+Send+Sync+Debug
 
 {
     fn resume(&mut self, _: &mut Schedule) -> WaitState {
         let mut tile = self.tile.take().unwrap();
 
-        while let Some(&(ref clip, ref or)) = self.polygons.try_recv() {
-            let z = Vector3::new(clip.x.z, clip.y.z, clip.z.z);
-            let bary = Barycentric::new(clip.map_vertex(|v| v.truncate()));
-            tile.raster(self.pos, self.scale, &z, &bary, or, &*self.fragment);
+        let shader = &*self.fragment;
+        // the default backend is picked once, so its coverage tests are
+        // inlined into the tile loop
+        let simd = self.backend.is_simd();
+        let began = self.clock.as_ref().map(|c| c.begin());
+        let allocated = self.audit.as_ref().map(|a| a.begin());
+        let mut wait = None;
+        loop {
+            let closed = match self.bin.take(&mut self.batch) {
+                Ok(closed) => closed,
+                Err(signal) => {
+                    wait = Some(signal);
+                    break;
+                }
+            };
+            // the clock is read once for every batch
+            let late = !self.batch.is_empty() &&
+                       self.deadline.as_ref().map(|d| !d.check(self.group)).unwrap_or(false);
+            if !late {
+                for &(ref clip, ref or) in self.batch.iter() {
+                    let z = Vector3::new(clip.x.z, clip.y.z, clip.z.z);
+                    let bary = Barycentric::new(clip.map_vertex(|v| v.truncate()));
+                    let plane = Interpolate::setup(or);
+                    self.shaded += if simd {
+                        tile.raster(self.pos, self.scale, &z, &bary, &plane, shader, &SimdBackend)
+                    } else {
+                        tile.raster(self.pos, self.scale, &z, &bary, &plane, shader, &*self.backend)
+                    };
+                }
+            }
+            self.batch.clear();
+            if closed {
+                break;
+            }
+        }
+        if let (Some(ref c), Some(t)) = (self.clock.as_ref(), began) {
+            c.end(t, Some(self.group));
+        }
+        if let (Some(ref a), Some(n)) = (self.audit.as_ref(), allocated) {
+            a.end(n);
         }
 
-        if self.polygons.closed() {
-            self.result.take().unwrap().set(tile);
-            WaitState::Completed
-        } else {
-            self.tile = Some(tile);
-            WaitState::Pending(self.polygons.signal())
+        match wait {
+            Some(signal) => {
+                self.tile = Some(tile);
+                WaitState::Pending(signal)
+            }
+            None => {
+                self.stats.fetch_add(self.shaded, Ordering::Relaxed);
+                let batch = std::mem::replace(&mut self.batch, Vec::new());
+                self.bins.give(self.bin.clone(), batch);
+                self.result.take().unwrap().set(tile);
+                WaitState::Completed
+            }
         }
     }
 }
 
+/// rasters one quadrant of a tile group, the group itself is kept alive
+/// by the task that joins the four quadrants
+struct QuadWorker<P, T: Send+Sync+'static, F> {
+    quad: *mut Quad<Tile<P>>,
+    bin: Arc<Bin<Binned<T>>>,
+    batch: Vec<Binned<T>>,
+    bins: BinPool,
+    pos: Vector2<f32>,
+    scale: Vector2<f32>,
+    fragment: Arc<F>,
+    backend: Arc<RasterBackend>,
+    shaded: usize,
+    stats: Arc<AtomicUsize>,
+    clock: Option<Arc<PassClock>>,
+    deadline: Option<Arc<Deadline>>,
+    audit: Option<Audit>,
+    group: (u32, u32)
+}
+
+// every quadrant is written by exactly one worker
+unsafe impl<P: Send, T: Send+Sync+'static, F: Send+Sync> Send for QuadWorker<P, T, F> {}
+
+impl<T: Send+Sync+'static, P: Send+Copy, F> ResumableTask for QuadWorker<P, T, F>
+    where F: Shade<T::Plane, P>+Send+Sync,
+          T: Interpolate+Send+Sync+Debug
+
+{
+    fn resume(&mut self, _: &mut Schedule) -> WaitState {
+        let quad = unsafe { &mut *self.quad };
+
+        let shader = &*self.fragment;
+        // the default backend is picked once, so its coverage tests are
+        // inlined into the tile loop
+        let simd = self.backend.is_simd();
+        let began = self.clock.as_ref().map(|c| c.begin());
+        let allocated = self.audit.as_ref().map(|a| a.begin());
+        let mut wait = None;
+        loop {
+            let closed = match self.bin.take(&mut self.batch) {
+                Ok(closed) => closed,
+                Err(signal) => {
+                    wait = Some(signal);
+                    break;
+                }
+            };
+            // the clock is read once for every batch
+            let late = !self.batch.is_empty() &&
+                       self.deadline.as_ref().map(|d| !d.check(self.group)).unwrap_or(false);
+            if !late {
+                for &(ref clip, ref or) in self.batch.iter() {
+                    let z = Vector3::new(clip.x.z, clip.y.z, clip.z.z);
+                    let bary = Barycentric::new(clip.map_vertex(|v| v.truncate()));
+                    let plane = Interpolate::setup(or);
+                    self.shaded += if simd {
+                        quad.raster(self.pos, self.scale, &z, &bary, &plane, shader, &SimdBackend)
+                    } else {
+                        quad.raster(self.pos, self.scale, &z, &bary, &plane, shader, &*self.backend)
+                    };
+                }
+            }
+            self.batch.clear();
+            if closed {
+                break;
+            }
+        }
+        if let (Some(ref c), Some(t)) = (self.clock.as_ref(), began) {
+            c.end(t, Some(self.group));
+        }
+        if let (Some(ref a), Some(n)) = (self.audit.as_ref(), allocated) {
+            a.end(n);
+        }
+
+        match wait {
+            Some(signal) => WaitState::Pending(signal),
+            None => {
+                self.stats.fetch_add(self.shaded, Ordering::Relaxed);
+                let batch = std::mem::replace(&mut self.batch, Vec::new());
+                self.bins.give(self.bin.clone(), batch);
+                WaitState::Completed
+            }
+        }
+    }
+}
+
+/// the pixels of the frame tile group `x`, `y` covers, in image
+/// coordinates. The groups along the right and top edge may be partial.
+fn group_rect(x: u32, y: u32, width: u32, height: u32) -> Rect {
+    let top = height - y * 32;
+    let bottom = top.saturating_sub(32);
+    Rect::new(x * 32, bottom, ::std::cmp::min(32, width - x * 32), top - bottom)
+}
+
 impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// a frame filled with `p`, a size that is not a multiple of 32 leaves
+    /// partial tile groups along the right and top edge. See `try_new` for
+    /// a version that checks the size.
     pub fn new(width: u32, height: u32, p: P) -> Frame<P> {
+        Frame::with_pool(width, height, p, Frontend::new())
+    }
+
+    /// like `new` but with a task pool of `threads` workers instead of one
+    /// per core, this does not change what is drawn
+    pub fn with_threads(width: u32, height: u32, p: P, threads: usize) -> Frame<P> {
+        Frame::with_pool(width, height, p, Frontend::with_size(threads))
+    }
+
+    fn with_pool(width: u32, height: u32, p: P, pool: Frontend) -> Frame<P> {
+        let tile = (0..((width + 31) / 32_)).map(
+            |_| (0..((height + 31) / 32_)).map(
+                |_| Future::from_value(Box::new(TileGroup::new(p)))
+            ).collect()
+        ).collect();
+        Frame::with_tiles(width, height, tile, pool)
+    }
+
+    /// a frame with the settings of `new` made of the groups in `tile`
+    fn with_tiles(width: u32, height: u32, tile: Vec<Vec<Future<Box<TileGroup<P>>>>>, pool: Frontend) -> Frame<P> {
         Frame {
             width: width,
             height: height,
-            tile: (0..(height / 32_)).map(
-                |_| (0..(width / 32_)).map(
-                    |_| Future::from_value(Box::new(TileGroup::new(p)))
-                ).collect()
-            ).collect(),
-            pool: Frontend::new()
+            tile: tile,
+            pool: pool,
+            capture: None,
+            validation: None,
+            split_threshold: std::usize::MAX,
+            priority: None,
+            backend: Arc::new(SimdBackend),
+            painter: false,
+            depth_write: true,
+            depth_mode: DepthMode::Projected,
+            traffic: Arc::new(Traffic::new()),
+            bins: BinPool::new(),
+            stencil: Stencil::default(),
+            timer: None,
+            deadline: None,
+            alloc_counter: None
         }
     }
 
+    /// like `new` but the size has to be non zero and within the limits
+    /// of `capabilities`
+    pub fn try_new(width: u32, height: u32, p: P) -> error::Result<Frame<P>> {
+        try!(check_frame_size(width, height));
+        Ok(Frame::new(width, height, p))
+    }
+
+    /// change the size of the frame and clear it to `p`. The worker
+    /// threads, the settings and the tile groups still needed are kept,
+    /// only a deadline is turned off.
+    pub fn resize(&mut self, width: u32, height: u32, p: P) {
+        use std::mem;
+        let old = mem::replace(&mut self.tile, Vec::new());
+        let mut groups: Vec<Box<TileGroup<P>>> = old.into_iter()
+            .flat_map(|row| row.into_iter())
+            .map(|t| t.get())
+            .collect();
+        self.tile = (0..((width + 31) / 32)).map(
+            |_| (0..((height + 31) / 32)).map(|_| {
+                let group = match groups.pop() {
+                    Some(mut group) => {
+                        group.clear(p);
+                        group
+                    }
+                    None => Box::new(TileGroup::new(p))
+                };
+                Future::from_value(group)
+            }).collect()
+        ).collect();
+        self.width = width;
+        self.height = height;
+        self.deadline = None;
+    }
+
     pub fn clear(&mut self, p: P) {
         use std::mem;
-        for row in self.tile.iter_mut() {
-            for tile in row.iter_mut() {
+        // a clear starts the next frame, which is not late yet
+        self.deadline = None;
+        let clock = self.clock(Pass::Clear);
+        for (x, row) in self.tile.iter_mut().enumerate() {
+            for (y, tile) in row.iter_mut().enumerate() {
                 let (mut new, set) = Future::new();
                 mem::swap(tile, &mut new);
                 let signal = new.signal();
+                let clock = clock.clone();
                 task(move |_| {
                     let mut t = new.get();
-                    t.clear(p);
+                    timed(&clock, Some((x as u32, y as u32)), || t.clear(p));
                     set.set(t);
                 }).after(signal).start(&mut self.pool);
             }
         }
     }
 
-    pub fn raster<S, F, T, O>(&mut self, poly: S, fragment: F)
+    pub fn raster<S, F, T, O>(&mut self, poly: S, fragment: F) -> DrawStats
         where S: Iterator<Item=Triangle<T>>,
               T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
               F: Fragment<O, Color=P> + Send + Sync + 'static {
 
+        self.capture_draw::<F>(false);
+        self.bin(poly, PerFragment(fragment), true, |t, back| if back { None } else { Some(t) })
+    }
+
+    /// like `raster` but the shader is handed eight fragments at a time,
+    /// see `FragmentSimd`
+    pub fn raster_simd<S, F, T>(&mut self, poly: S, fragment: F) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate + FetchPosition + Send + Sync + 'static + Debug,
+              T::Plane: PlaneSimd,
+              F: FragmentSimd<<T::Plane as PlaneSimd>::Lanes, Color=P> + Send + Sync + 'static {
+
+        self.capture_draw::<F>(false);
+        self.bin(poly, Batched(fragment), true, |t, back| if back { None } else { Some(t) })
+    }
+
+    /// raster 2D geometry whose positions all have w of 1, like the output
+    /// of `paint::Canvas`. Only the perspective divide is skipped, the depth
+    /// is tested and written like for `raster`, so a layer drawn over
+    /// another at the same depth needs a smaller one.
+    pub fn raster_affine<S, F, T, O>(&mut self, poly: S, fragment: F) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: Fragment<O, Color=P> + Send + Sync + 'static {
+
+        self.capture_draw::<F>(false);
+        self.bin(poly, PerFragment(fragment), false, |t, back| if back { None } else { Some(t) })
+    }
+
+    /// raster the geometry after cutting it by user clip planes, see
+    /// `clip_triangle` for how the planes are specified
+    pub fn raster_clipped<S, F, T, O>(&mut self, poly: S, planes: &[[f32; 4]], fragment: F) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: Fragment<O, Color=P> + Send + Sync + 'static {
+
+        let planes = planes.to_vec();
+        let clipped = poly.flat_map(move |t| clip_triangle(t, &planes).into_iter());
+        self.raster(clipped, fragment)
+    }
+
+    /// raster both sides of the geometry, front faces are shaded by `front`
+    /// and back faces by `back`. Blending always uses `front`.
+    pub fn raster_two_sided<S, F, B, T, O>(&mut self, poly: S, front: F, back: B) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: Fragment<O, Color=P> + Send + Sync + 'static,
+              B: Fragment<O, Color=P> + Send + Sync + 'static {
+
+        self.capture_draw::<TwoSided<F, B>>(true);
+        let fragment = PerFragment(TwoSided {
+            front: front,
+            back: back
+        });
+        self.bin(poly, fragment, true, |t, back| Some(t.map_vertex(|v| Facing(v, !back))))
+    }
+
+    /// like `raster` with `data` handed to every call of the shader, the
+    /// draw shares it with the workers instead of copying it
+    pub fn raster_with<S, F, T, O, U: ?Sized>(&mut self, poly: S, fragment: F, data: Arc<U>) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: FragmentWith<O, U, Color=P> + Send + Sync + 'static,
+              U: Send + Sync + 'static {
+
+        self.raster(poly, WithData {
+            fragment: fragment,
+            data: data
+        })
+    }
+
+    /// a tile group that gets `n` triangles of a draw is split into four
+    /// independently scheduled quadrants for the rest of it, 0 splits every
+    /// group from the start
+    pub fn set_split_threshold(&mut self, n: usize) {
+        self.split_threshold = n;
+    }
+
+    /// change how coverage is worked out for the following draws, see
+    /// `RasterBackend`
+    pub fn set_backend(&mut self, backend: Arc<RasterBackend>) {
+        self.backend = backend;
+    }
+
+    /// in painter mode the depth buffer is neither tested nor written by
+    /// any draw, every pixel ends up with the color of the last fragment
+    /// submitted to it, or what `Fragment::blend` made of all of them in
+    /// submission order. This is what 2D and UI content wants, and how the
+    /// classic painter's algorithm renderers work. The depth buffer keeps
+    /// what it had when the mode was turned on.
+    pub fn set_painter(&mut self, on: bool) {
+        self.painter = on;
+    }
+
+    /// true in painter mode, see `set_painter`
+    pub fn painter(&self) -> bool {
+        self.painter
+    }
+
+    /// with depth writes off the following draws still test the depth
+    /// buffer but leave it as it is, like translucent surfaces that must
+    /// not hide what is drawn behind them after
+    pub fn set_depth_write(&mut self, on: bool) {
+        self.depth_write = on;
+    }
+
+    pub fn depth_write(&self) -> bool {
+        self.depth_write
+    }
+
+    /// how the following draws take and store their depth. Depth stored
+    /// in different modes does not compare, so a frame sticks to one of
+    /// them between clears. Reading the depth back with `linear_depth`,
+    /// `view_positions` or `unproject` goes by the mode set at the time.
+    pub fn set_depth_mode(&mut self, mode: DepthMode) {
+        if let DepthMode::Linear { near, far } = mode {
+            assert!(near < far, "the near plane has to be in front of the far plane");
+        }
+        self.depth_mode = mode;
+    }
+
+    /// the current depth mode, see `set_depth_mode`
+    pub fn depth_mode(&self) -> DepthMode {
+        self.depth_mode
+    }
+
+    /// tiles inside of `rect` are handed their triangles before the rest of
+    /// the frame, so that they finish early and can be read with `read_region`
+    pub fn set_priority(&mut self, rect: Option<Rect>) {
+        self.priority = rect;
+    }
+
+    /// turn the validation layer on or off. While it is on every draw is
+    /// checked for non finite positions and degenerate triangles, and use
+    /// of the frame after a failed `try_flush` is reported. The problems
+    /// are collected until `diagnostics` is called.
+    pub fn set_validation(&mut self, on: bool) {
+        self.validation = if on { Some(Arc::new(Mutex::new(Validator::new()))) } else { None };
+    }
+
+    /// the problems found since the last call, empty without validation
+    pub fn diagnostics(&mut self) -> Vec<Diagnostic> {
+        match self.validation {
+            Some(ref v) => std::mem::replace(&mut v.lock().unwrap().diagnostics, Vec::new()),
+            None => Vec::new()
+        }
+    }
+
+    /// a clock for a new pass if timing is on, see `set_timing`
+    fn clock(&self, pass: Pass) -> Option<Arc<PassClock>> {
+        self.timer.as_ref().map(|t| t.pass(pass))
+    }
+
+    /// tells frames apart for as long as they live
+    fn id(&self) -> usize {
+        &*self.traffic as *const Traffic as usize
+    }
+
+    /// report a problem with the current draw to the validation layer
+    fn validation_issue(&self, issue: Issue) {
+        if let Some(ref v) = self.validation {
+            v.lock().unwrap().report(None, issue);
+        }
+    }
+
+    fn capture_draw<F>(&mut self, two_sided: bool) {
+        if let Some(ref c) = self.capture {
+            let name = unsafe { std::intrinsics::type_name::<F>() };
+            c.lock().unwrap().begin_draw(name, two_sided);
+        }
+    }
+
+    /// sort the triangles into the tiles they touch, `face` is told if a
+    /// triangle is a back face and picks what is sent to the tiles. Without
+    /// `perspective` the positions are taken to already have a w of 1.
+    fn bin<S, F, T, U, M>(&mut self, poly: S, fragment: F, perspective: bool, face: M) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + FetchPosition,
+              U: Clone + Interpolate + Send + Sync + 'static + Debug,
+              F: Shade<U::Plane, P> + Send + Sync + 'static,
+              M: Fn(Triangle<T>, bool) -> Option<Triangle<U>> {
+
         use std::cmp::{min, max};
         let h = self.height;
         let w = self.width;
         let (hf, wf) = (h as f32, w as f32);
         let (hh, wh) = (hf/2., wf/2.);
-        let scale = Vector2::new(hh.recip(), wh.recip());
+        // the tiles work in pixels measured from the center of the frame.
+        // Every pixel position is then an integer that is exact in f32, so
+        // neighbouring tiles and quadrants agree on every coverage decision
+        // no matter how they add up their offsets.
+        let scale = Vector2::new(1., 1.);
+
+        let fragment = Arc::new(DrawState {
+            shade: fragment,
+            painter: self.painter,
+            depth_write: self.depth_write,
+            far: self.depth_mode.far_plane(),
+            stencil: self.stencil
+        });
+        let depth_mode = self.depth_mode;
+        let capture = self.capture.clone();
+        let validation = self.validation.clone();
+        if let Some(ref v) = validation {
+            let (shader, vertex) = unsafe { (std::intrinsics::type_name::<F>(), std::intrinsics::type_name::<T>()) };
+            v.lock().unwrap().begin_draw(shader, vertex);
+        }
+        let mut stats = DrawStats::new();
+        let counter = stats.fragment_counter();
+        let audit = self.alloc_counter.map(|c| Audit::new(c, stats.allocation_counter()));
+        let traffic = self.traffic.clone();
+        traffic.draw();
+        let geometry = self.clock(Pass::Geometry);
+        let geometry_began = geometry.as_ref().map(|c| c.begin());
+        let raster = self.clock(Pass::Raster);
+
+        // a tile group that gets enough triangles of the draw is split into
+        // quadrants with their own worker, so that busy tiles don't end up
+        // on the critical path. The quadrants carry on after the worker of
+        // the whole group is done with the triangles before that.
+        let split_threshold = self.split_threshold;
+
+        // everything outside of the priority region is binned last
+        let priority = self.priority;
+        let in_priority = |x: u32, y: u32| match priority {
+            Some(ref r) => group_rect(x, y, w, h).intersect(r).is_some(),
+            None => true
+        };
+        let mut deferred = Vec::new();
 
-        let fragment = Arc::new(fragment);
+        // a draw made after the deadline is not shaded at all, the clock is
+        // read once for it
+        let late = match self.deadline {
+            Some(ref d) if d.expired() => Some(d.clone()),
+            _ => None
+        };
+
+        // the groups a triangle is sent to go through the backend as well
+        let binner = self.backend.clone();
+        let group_size = Vector2::new(32., 32.);
+        let quadrant_size = Vector2::new(16., 16.);
 
+        // the bins are closed when the queue is dropped at the end
         let mut queue = VecMap::new();
+        let bins = self.bins.clone();
         let width = self.width as usize;
         let index = |x, y| {width * y + x};
 
-        let mut command = |x, y, t| {
-            let i = index(x, y);
+        let mut command = |gx: usize, gy: usize, bary: &Barycentric, t: Binned<T>| {
+            use std::mem;
+            let i = index(gx, gy);
+            // closing the bin of the whole group lets its worker finish
+            let over = match queue.get(&i) {
+                Some(&Queued::Whole(_, count)) => count >= split_threshold,
+                _ => false
+            };
+            if over {
+                queue.remove(&i);
+            }
             if queue.get(&i).is_none() {
-                use std::mem;
-                let (tx, rx) = channel();
                 let (mut future, set) = Future::new();
                 let fragment = fragment.clone();
-                mem::swap(&mut self.tile[x as usize][y as usize], &mut future);
+                let backend = self.backend.clone();
+                let counter = counter.clone();
+                let clock = raster.clone();
+                let deadline = self.deadline.clone();
+                let audit = audit.clone();
+                mem::swap(&mut self.tile[gx][gy], &mut future);
                 let signal = future.signal();
-
-                task(move |sched| {
-                    let wh = wh;
-                    let hh = hh;
-                    let scale = scale;
-                    let signal = rx.signal();
-                    RasterWorker {
-                        tile: Some(future.get()),
-                        polygons: rx,
-                        scale: scale,
-                        pos: Vector2::new(((x*32) as f32 - wh) * scale.x,
-                                          ((y*32) as f32 - hh) * scale.y),
-                        fragment: fragment,
-                        result: Some(set)
-                    }.after(signal).start(sched);
-                }).after(signal).start(&mut self.pool);
-                queue.insert(i, tx);
+                let pos = Vector2::new((gx*32) as f32 - wh, (gy*32) as f32 - hh);
+
+                if over || split_threshold == 0 {
+                    let mut polygons = Vec::new();
+                    let mut senders = Vec::new();
+                    let mut allocated = 0;
+                    for _ in 0..4 {
+                        let (bin, new) = bins.take();
+                        allocated += new as usize;
+                        senders.push(BinSender(bin.clone()));
+                        polygons.push(bin);
+                    }
+                    queue.insert(i, Queued::Split(senders));
+                    traffic.group(allocated);
+                    let bins = bins.clone();
+
+                    task(move |sched| {
+                        let mut tile = future.get();
+                        let quads: *mut Quad<Quad<Tile<P>>> = tile.quads_mut();
+                        let half = scale.mul_s(16.);
+                        let mut done = Vec::new();
+                        for (k, bin) in polygons.into_iter().enumerate() {
+                            let offset = Vector2::new(if k & 1 == 1 { half.x } else { 0. },
+                                                      if k & 2 == 2 { half.y } else { 0. });
+                            done.push(QuadWorker {
+                                quad: unsafe { &mut (*quads).0[k] },
+                                batch: bin.batch(),
+                                bin: bin,
+                                bins: bins.clone(),
+                                pos: pos + offset,
+                                scale: scale,
+                                fragment: fragment.clone(),
+                                backend: backend.clone(),
+                                shaded: 0,
+                                stats: counter.clone(),
+                                clock: clock.clone(),
+                                deadline: deadline.clone(),
+                                audit: audit.clone(),
+                                group: (gx as u32, gy as u32)
+                            }.start(sched));
+                        }
+
+                        let (d3, d2, d1, d0) = (done.pop().unwrap(), done.pop().unwrap(),
+                                                done.pop().unwrap(), done.pop().unwrap());
+                        task(move |_| set.set(tile))
+                            .after(d0).after(d1).after(d2).after(d3)
+                            .start(sched);
+                    }).after(signal).start(&mut self.pool);
+                } else {
+                    let (bin, new) = bins.take();
+                    traffic.group(new as usize);
+                    queue.insert(i, Queued::Whole(BinSender(bin.clone()), 0));
+                    let bins = bins.clone();
+                    task(move |sched| {
+                        RasterWorker {
+                            tile: Some(future.get()),
+                            batch: bin.batch(),
+                            bin: bin,
+                            bins: bins,
+                            scale: scale,
+                            pos: pos,
+                            fragment: fragment,
+                            backend: backend,
+                            shaded: 0,
+                            stats: counter,
+                            clock: clock,
+                            deadline: deadline,
+                            audit: audit,
+                            group: (gx as u32, gy as u32),
+                            result: Some(set)
+                        }.start(sched);
+                    }).after(signal).start(&mut self.pool);
+                }
             }
 
-            queue.get_mut(&i).unwrap().send(t);
+            match *queue.get_mut(&i).unwrap() {
+                Queued::Whole(ref bin, ref mut count) => {
+                    bin.send(t);
+                    *count += 1;
+                }
+                Queued::Split(ref quadrants) => {
+                    for (k, bin) in quadrants.iter().enumerate() {
+                        let (x, y) = ((gx * 32 + 16 * (k & 1)) as u32, (gy * 32 + 8 * (k & 2)) as u32);
+                        if x < w && y < h && binner.bin(bary, Vector2::new(x as f32 - wh, y as f32 - hh), quadrant_size) {
+                            bin.send(t.clone());
+                        }
+                    }
+                }
+            }
         };
 
-        for or in poly {
+        for (n, or) in poly.enumerate() {
+            stats.triangles += 1;
+            if let Some(ref c) = capture {
+                c.lock().unwrap().triangle(&or);
+            }
+            if let Some(ref v) = validation {
+                let p = [or.x.position(), or.y.position(), or.z.position()];
+                // counted like the non finite triangles dropped below
+                if !v.lock().unwrap().positions(n, p) {
+                    stats.degenerate += 1;
+                    continue;
+                }
+            }
+
             let t = or.clone().map_vertex(|v| {
                 let v = v.position();
                 Vector4::new(v[0], v[1], v[2], v[3])
             });
 
-            let clip = t.map_vertex(|v| v.truncate().div_s(v.w) );
+            let clip = if perspective {
+                t.map_vertex(|v| v.truncate().div_s(v.w))
+            } else {
+                t.map_vertex(|v| v.truncate())
+            };
+
+            if let Some(ref v) = validation {
+                let mut v = v.lock().unwrap();
+                let screen = clip.map_vertex(|p| [p.x * wh, p.y * hh]);
+                if !(screen.x[0].is_finite() && screen.y[0].is_finite() && screen.z[0].is_finite() &&
+                     screen.x[1].is_finite() && screen.y[1].is_finite() && screen.z[1].is_finite()) {
+                    // w of zero
+                    let p = [or.x.position(), or.y.position(), or.z.position()];
+                    let i = p.iter().position(|p| p[3] == 0.).unwrap_or(0);
+                    v.report(Some(n), Issue::NonFinite { vertex: i, position: p[i] });
+                    stats.degenerate += 1;
+                    continue;
+                }
+                v.area(n, screen.x, screen.y, screen.z);
+            }
 
-            if is_backface(clip) {
+            // triangles without area or with non finite positions would
+            // poison the coverage math, they are dropped here
+            let area = (clip.y.x - clip.x.x) * (clip.z.y - clip.x.y) -
+                       (clip.z.x - clip.x.x) * (clip.y.y - clip.x.y);
+            if !(area.abs() > 0.) || !area.is_finite() {
+                stats.degenerate += 1;
                 continue;
             }
 
+            let or = match face(or, is_backface(clip)) {
+                Some(t) => t,
+                None => {
+                    stats.culled += 1;
+                    continue
+                }
+            };
+
+            // the shared setup every tile evaluates the triangle from
+            let screen = clip.map_vertex(|v| Vector3::new(v.x * wh, v.y * hh, depth_mode.store(v.z)));
             let clip2 = clip.map_vertex(|v| Vector2::new(v.x * wh + wh, v.y * hh + hh));
             let max_x = clip2.x.x.ceil().partial_max(clip2.y.x.ceil().partial_max(clip2.z.x.ceil()));
             let min_x = clip2.x.x.floor().partial_min(clip2.y.x.floor().partial_min(clip2.z.x.floor()));
             let max_y = clip2.x.y.ceil().partial_max(clip2.y.y.ceil().partial_max(clip2.z.y.ceil()));
             let min_y = clip2.x.y.floor().partial_min(clip2.y.y.floor().partial_min(clip2.z.y.floor()));
 
-            let min_x = (max(min_x as i32, 0) as u32) & (0xFFFFFFFF & !0x1F_);
-            let min_y = (max(min_y as i32, 0) as u32) & (0xFFFFFFFF & !0x1F_);
-            let max_x = min(max_x as u32, w-0x1F_);
-            let max_y = min(max_y as u32, h-0x1F_);
+            let min_x = (max(min_x as i32, 0) as u32) & (0xFFFFFFFF & !31);
+            let min_y = (max(min_y as i32, 0) as u32) & (0xFFFFFFFF & !31);
+            // the last pixel decides the last group, which may be partial
+            let max_x = min(max(max_x as i32, 0) as u32, w-1);
+            let max_y = min(max(max_y as i32, 0) as u32, h-1);
 
+            let bary = Barycentric::new(screen.clone().map_vertex(|v| v.truncate()));
+            let mut skipped = false;
             for y in (min_y..max_y+1).step_by(32) {
                 for x in (min_x..max_x+1).step_by(32) {
-                    let ix = (x / 32_) as usize;
-                    let iy = (y / 32_) as usize;
-                    command(ix, iy, (clip.clone(), or.clone()));
+                    if !binner.bin(&bary, Vector2::new(x as f32 - wh, y as f32 - hh), group_size) {
+                        continue;
+                    }
+                    if let Some(ref d) = late {
+                        // the groups still need their history
+                        d.mark((x / 32, y / 32));
+                        skipped = true;
+                        continue;
+                    }
+                    let (ix, iy) = (x / 32, y / 32);
+                    if in_priority(ix, iy) {
+                        command(ix as usize, iy as usize, &bary, (screen.clone(), or.clone()));
+                    } else {
+                        deferred.push((ix as usize, iy as usize, bary, screen.clone(), or.clone()));
+                    }
                 }
             }
+            if skipped {
+                stats.late += 1;
+            }
         }
+
+        for (ix, iy, bary, screen, or) in deferred.into_iter() {
+            command(ix, iy, &bary, (screen, or));
+        }
+        if let (Some(ref c), Some(t)) = (geometry.as_ref(), geometry_began) {
+            c.end(t, None);
+        }
+        stats
     }
 
     pub fn map<S, F>(&mut self, src: &mut Frame<S>, pixel: F)
@@ -346,19 +1188,64 @@ impl<P: Copy+Sync+Send+'static> Frame<P> {
         assert!(src.height == self.height);
 
         let pixel = Arc::new(pixel);
+        let clock = self.clock(Pass::Map);
+
+        for (x, (row, src_row)) in self.tile.iter_mut().zip(src.tile.iter_mut()).enumerate() {
+            for (y, (tile, src_tile)) in row.iter_mut().zip(src_row.iter_mut()).enumerate() {
+                let (mut new, tx_self) = Future::new();
+                mem::swap(tile, &mut new);
+                let (mut src, tx_src) = Future::new();
+                mem::swap(src_tile, &mut src);
+                let pixel = pixel.clone();
+                let clock = clock.clone();
+                let (s0, s1) = (new.signal(), src.signal());
+                task(move |_| {
+                    let mut dst = new.get();
+                    let src = src.get();
+                    timed(&clock, Some((x as u32, y as u32)), || dst.map(&src, &*pixel));
+                    tx_self.set(dst);
+                    tx_src.set(src);
+                }).after(s0).after(s1).start(&mut self.pool);
+            }
+        }
+    }
+
+    /// like `map` but a source of a different size is an error
+    pub fn try_map<S, F>(&mut self, src: &mut Frame<S>, pixel: F) -> error::Result<()>
+        where F: Mapping<S, Out=P> + Sized + Send + Sync + 'static,
+              S: Send + Sync + 'static + Copy {
+        try!(error::check_size((self.width, self.height), (src.width, src.height)));
+        self.map(src, pixel);
+        Ok(())
+    }
+
+    /// like `map` but `pixel` is also told the position of every pixel and
+    /// the size of the frame, see `MappingAt`
+    pub fn map_at<S, F>(&mut self, src: &mut Frame<S>, pixel: F)
+        where F: MappingAt<S, Out=P> + Sized + Send + Sync + 'static,
+              S: Send + Sync + 'static + Copy {
+        use std::mem;
+
+        assert!(src.width == self.width);
+        assert!(src.height == self.height);
+
+        let pixel = Arc::new(pixel);
+        let (w, h) = (self.width, self.height);
+        let clock = self.clock(Pass::Map);
 
-        for (row, src_row) in self.tile.iter_mut().zip(src.tile.iter_mut()) {
-            for (tile, src_tile) in row.iter_mut().zip(src_row.iter_mut()) {
+        for (x, (row, src_row)) in self.tile.iter_mut().zip(src.tile.iter_mut()).enumerate() {
+            for (y, (tile, src_tile)) in row.iter_mut().zip(src_row.iter_mut()).enumerate() {
                 let (mut new, tx_self) = Future::new();
                 mem::swap(tile, &mut new);
                 let (mut src, tx_src) = Future::new();
                 mem::swap(src_tile, &mut src);
                 let pixel = pixel.clone();
+                let clock = clock.clone();
                 let (s0, s1) = (new.signal(), src.signal());
                 task(move |_| {
                     let mut dst = new.get();
                     let src = src.get();
-                    dst.map(&src, &*pixel);
+                    timed(&clock, Some((x as u32, y as u32)), || dst.map_at(&src, (x*32) as u32, (y*32) as u32, w, h, &*pixel));
                     tx_self.set(dst);
                     tx_src.set(src);
                 }).after(s0).after(s1).start(&mut self.pool);
@@ -366,43 +1253,122 @@ impl<P: Copy+Sync+Send+'static> Frame<P> {
         }
     }
 
+    /// like `map_at` but a source of a different size is an error
+    pub fn try_map_at<S, F>(&mut self, src: &mut Frame<S>, pixel: F) -> error::Result<()>
+        where F: MappingAt<S, Out=P> + Sized + Send + Sync + 'static,
+              S: Send + Sync + 'static + Copy {
+        try!(error::check_size((self.width, self.height), (src.width, src.height)));
+        self.map_at(src, pixel);
+        Ok(())
+    }
+
     pub fn flush(&mut self) {
+        self.try_flush().unwrap();
+    }
+
+    /// wait for all the work on the frame, a task that died on the way is
+    /// reported instead of panicking
+    pub fn try_flush(&mut self) -> error::Result<()> {
         for row in self.tile.iter_mut() {
             for tile in row.iter_mut() {
-                tile.signal().wait().unwrap();
+                if tile.signal().wait().is_err() {
+                    if let Some(ref v) = self.validation {
+                        v.lock().unwrap().flush_failed();
+                    }
+                    return Err(Error::TaskFailed);
+                }
             }
         }
+        Ok(())
     }
-}
 
-impl Frame<Rgba<u8>> {
-    pub fn into_image(&mut self, img: ImageBuffer<Rgba<u8>, Vec<u8>>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    /// write every pixel of the frame into `out`, this waits for
+    /// all pending work on the frame to complete
+    pub fn write_into<W: Put<P> + Send + 'static>(&mut self, out: W) -> W {
+        let all = Rect::new(0, 0, self.width, self.height);
+        self.write_tiles(out, all, |t, x, y, buff| t.write(x, y, buff))
+    }
+
+    /// like `write_into` but for the contents of the depth buffer
+    pub fn write_depth_into<W: Put<f32> + Send + 'static>(&mut self, out: W) -> W {
+        let all = Rect::new(0, 0, self.width, self.height);
+        self.write_tiles(out, all, |t, x, y, buff| t.write_depth(x, y, buff))
+    }
+
+    /// hand every tile group that intersects `region` to `f`, this only
+    /// waits for the work pending on those groups. The groups are read on
+    /// the worker pool into buffers of their own, which are written into
+    /// `out` on the calling thread.
+    fn write_tiles<V, W, F>(&mut self, out: W, region: Rect, f: F) -> W
+        where V: Send + 'static,
+              W: Put<V>,
+              F: Fn(&TileGroup<P>, u32, u32, &mut Gathered<V>) + Send + Sync + 'static {
         use std::mem;
-        let buffer = UnsafeCell::new(img);
-        let mut signals = Vec::new();
+        let (w, h) = (self.width, self.height);
+        let f = Arc::new(f);
+        let mut groups = Vec::new();
+        let clock = self.clock(Pass::Readback);
 
         for (x, row) in self.tile.iter_mut().enumerate() {
             for (y, tile) in row.iter_mut().enumerate() {
+                if group_rect(x as u32, y as u32, w, h).intersect(&region).is_none() {
+                    continue;
+                }
+
                 let (mut new, tx_self) = Future::new();
                 mem::swap(tile, &mut new);
-                let buff: &mut ImageBuffer<_, Vec<_>> = unsafe { mem::transmute(buffer.get()) };
+                let (pixels, tx_pixels) = Future::new();
+                let f = f.clone();
+                let clock = clock.clone();
                 let signal = new.signal();
-                signals.push(task(move |_| {
+                task(move |_| {
                     let t = new.get();
-                    t.write((x*32_) as u32, (y*32_) as u32, buff);
+                    let mut gathered = Gathered(Vec::with_capacity(32 * 32));
+                    timed(&clock, Some((x as u32, y as u32)), || f(&t, (x*32_) as u32, (y*32_) as u32, &mut gathered));
                     tx_self.set(t);
-                }).after(signal).start(&mut self.pool));
+                    tx_pixels.set(gathered);
+                }).after(signal).start(&mut self.pool);
+                groups.push((x as u32, y as u32, pixels));
             }
         }
 
-        Barrier::new(&signals).wait().unwrap();
-        unsafe { buffer.into_inner() }
+        let mut out = Clip::new(out, w, h);
+        for (x, y, pixels) in groups.into_iter() {
+            let pixels = pixels.get();
+            timed(&clock, Some((x, y)), || for (x, y, v) in pixels.0.into_iter() {
+                out.put(x, y, v);
+            });
+        }
+        out.inner
+    }
+}
+
+/// the pixels of one tile group, in the order the group wrote them
+struct Gathered<V>(Vec<(u32, u32, V)>);
+
+impl<V> Put<V> for Gathered<V> {
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, v: V) {
+        self.0.push((x, y, v));
+    }
+}
+
+#[cfg(feature = "image")]
+impl Frame<Rgba<u8>> {
+    pub fn into_image(&mut self, img: ImageBuffer<Rgba<u8>, Vec<u8>>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        self.write_into(img)
     }
 
     pub fn to_image(&mut self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
         let img = ImageBuffer::new(self.width, self.height);
         self.into_image(img)
     }
+
+    /// like `to_image` but reports a failed raster task as an error
+    pub fn try_to_image(&mut self) -> error::Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+        try!(self.try_flush());
+        Ok(self.to_image())
+    }
 }
 
 
@@ -414,30 +1380,38 @@ impl FetchPosition for [f32; 4] {
     fn position(&self) -> [f32; 4] { *self }
 }
 
-impl<A> FetchPosition for ([f32; 4], A) {
-    fn position(&self) -> [f32; 4] { self.0 }
+impl<T: FetchPosition> FetchPosition for Facing<T> {
+    fn position(&self) -> [f32; 4] { self.0.position() }
+}
+
+impl<T: FetchPosition> FetchPosition for Flat<T> {
+    fn position(&self) -> [f32; 4] { self.0.position() }
+}
+
+impl<V: FetchPosition, A> FetchPosition for (V, A) {
+    fn position(&self) -> [f32; 4] { self.0.position() }
 }
 
-impl<A, B> FetchPosition for ([f32; 4], A, B) {
-    fn position(&self) -> [f32; 4] { self.0 }
+impl<V: FetchPosition, A, B> FetchPosition for (V, A, B) {
+    fn position(&self) -> [f32; 4] { self.0.position() }
 }
 
-impl<A, B, C> FetchPosition for ([f32; 4], A, B, C) {
-    fn position(&self) -> [f32; 4] { self.0 }
+impl<V: FetchPosition, A, B, C> FetchPosition for (V, A, B, C) {
+    fn position(&self) -> [f32; 4] { self.0.position() }
 }
 
-impl<A, B, C, D> FetchPosition for ([f32; 4], A, B, C, D) {
-    fn position(&self) -> [f32; 4] { self.0 }
+impl<V: FetchPosition, A, B, C, D> FetchPosition for (V, A, B, C, D) {
+    fn position(&self) -> [f32; 4] { self.0.position() }
 }
 
-impl<A, B, C, D, E> FetchPosition for ([f32; 4], A, B, C, D, E) {
-    fn position(&self) -> [f32; 4] { self.0 }
+impl<V: FetchPosition, A, B, C, D, E> FetchPosition for (V, A, B, C, D, E) {
+    fn position(&self) -> [f32; 4] { self.0.position() }
 }
 
-impl<A, B, C, D, E, F> FetchPosition for ([f32; 4], A, B, C, D, E, F) {
-    fn position(&self) -> [f32; 4] { self.0 }
+impl<V: FetchPosition, A, B, C, D, E, F> FetchPosition for (V, A, B, C, D, E, F) {
+    fn position(&self) -> [f32; 4] { self.0.position() }
 }
 
-impl<A, B, C, D, E, F, G> FetchPosition for ([f32; 4], A, B, C, D, E, F, G) {
-    fn position(&self) -> [f32; 4] { self.0 }
+impl<V: FetchPosition, A, B, C, D, E, F, G> FetchPosition for (V, A, B, C, D, E, F, G) {
+    fn position(&self) -> [f32; 4] { self.0.position() }
 }
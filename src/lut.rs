@@ -0,0 +1,189 @@
+use std::io::{self, BufRead};
+use std::sync::Arc;
+
+#[cfg(feature = "image")]
+use image::ImageBuffer;
+
+use {Frame, Mapping, Rgba};
+use tile::Put;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// a 3D color lookup table, the entries are stored with red changing the
+/// fastest followed by green and then blue, like in a .cube file
+#[derive(Clone, Debug)]
+pub struct Lut3d {
+    pub size: usize,
+    pub data: Vec<[f32; 3]>,
+    /// the colors looked up at the first and the last entries of every
+    /// channel, 0 and 1 unless the table says otherwise
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3]
+}
+
+impl Lut3d {
+    /// a table that maps every color to itself
+    pub fn identity(size: usize) -> Lut3d {
+        assert!(size >= 2);
+        let s = (size - 1) as f32;
+        let mut data = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push([r as f32 / s, g as f32 / s, b as f32 / s]);
+                }
+            }
+        }
+        Lut3d {
+            size: size,
+            data: data,
+            domain_min: [0.; 3],
+            domain_max: [1.; 3]
+        }
+    }
+
+    /// read a table in the .cube format, the entries are kept as they are
+    /// and the domain applies to the colors looked up
+    pub fn load_cube<R: BufRead>(src: R) -> io::Result<Lut3d> {
+        let mut size = 0;
+        let mut min = [0f32; 3];
+        let mut max = [1f32; 3];
+        let mut data = Vec::new();
+
+        for line in src.lines() {
+            let line = try!(line);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("#") {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let floats = |f: &[&str]| -> io::Result<[f32; 3]> {
+                if f.len() != 3 {
+                    return Err(invalid("expected three values"));
+                }
+                let p = |s: &str| s.parse::<f32>().map_err(|_| invalid("bad value"));
+                Ok([try!(p(f[0])), try!(p(f[1])), try!(p(f[2]))])
+            };
+
+            match fields[0] {
+                "TITLE" => (),
+                "LUT_1D_SIZE" => return Err(invalid("1D tables are not supported")),
+                "LUT_3D_SIZE" => {
+                    if fields.len() != 2 {
+                        return Err(invalid("bad size"));
+                    }
+                    size = try!(fields[1].parse().map_err(|_| invalid("bad size")));
+                }
+                "LUT_3D_INPUT_RANGE" => {
+                    if fields.len() != 3 {
+                        return Err(invalid("bad input range"));
+                    }
+                    let p = |s: &str| s.parse::<f32>().map_err(|_| invalid("bad input range"));
+                    min = [try!(p(fields[1])); 3];
+                    max = [try!(p(fields[2])); 3];
+                }
+                "DOMAIN_MIN" => min = try!(floats(&fields[1..])),
+                "DOMAIN_MAX" => max = try!(floats(&fields[1..])),
+                _ => data.push(try!(floats(&fields)))
+            }
+        }
+
+        if size < 2 || data.len() != size * size * size {
+            return Err(invalid("the table does not match its size"));
+        }
+        if (0..3).any(|i| !(max[i] > min[i])) {
+            return Err(invalid("empty domain"));
+        }
+
+        Ok(Lut3d {
+            size: size,
+            data: data,
+            domain_min: min,
+            domain_max: max
+        })
+    }
+
+    #[inline]
+    fn entry(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.data[(b * self.size + g) * self.size + r]
+    }
+
+    /// look up a color with trilinear filtering, the channels are clamped
+    /// to the domain of the table
+    pub fn sample(&self, c: [f32; 3]) -> [f32; 3] {
+        let s = (self.size - 1) as f32;
+        let mut i = [0; 3];
+        let mut f = [0.; 3];
+        for k in 0..3 {
+            let (lo, hi) = (self.domain_min[k], self.domain_max[k]);
+            let x = ((c[k] - lo) / (hi - lo)).max(0.).min(1.) * s;
+            i[k] = (x as usize).min(self.size - 2);
+            f[k] = x - i[k] as f32;
+        }
+
+        let mut out = [0.; 3];
+        for corner in 0..8 {
+            let (dr, dg, db) = (corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let w = (if dr == 1 { f[0] } else { 1. - f[0] }) *
+                    (if dg == 1 { f[1] } else { 1. - f[1] }) *
+                    (if db == 1 { f[2] } else { 1. - f[2] });
+            let e = self.entry(i[0] + dr, i[1] + dg, i[2] + db);
+            for k in 0..3 {
+                out[k] += e[k] * w;
+            }
+        }
+        out
+    }
+}
+
+impl Mapping<[f32; 3]> for Lut3d {
+    type Out = [f32; 3];
+
+    #[inline]
+    fn mapping(&self, c: [f32; 3]) -> [f32; 3] {
+        self.sample(c)
+    }
+}
+
+impl Mapping<Rgba<u8>> for Lut3d {
+    type Out = Rgba<u8>;
+
+    #[inline]
+    fn mapping(&self, p: Rgba<u8>) -> Rgba<u8> {
+        let c = self.sample([p.data[0] as f32 / 255., p.data[1] as f32 / 255., p.data[2] as f32 / 255.]);
+        let q = |v: f32| (v.max(0.).min(1.) * 255. + 0.5) as u8;
+        Rgba([q(c[0]), q(c[1]), q(c[2]), p.data[3]])
+    }
+}
+
+/// grades the pixels on their way into an image
+#[cfg(feature = "image")]
+struct Graded {
+    img: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    lut: Arc<Lut3d>
+}
+
+#[cfg(feature = "image")]
+impl Put<Rgba<u8>> for Graded {
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, p: Rgba<u8>) {
+        let p = self.lut.mapping(p);
+        self.img.put(x, y, p);
+    }
+}
+
+#[cfg(feature = "image")]
+impl Frame<Rgba<u8>> {
+    /// read the frame back like `to_image` with every pixel passed
+    /// through `lut` on the way
+    pub fn to_image_graded(&mut self, lut: Arc<Lut3d>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let graded = Graded {
+            img: ImageBuffer::new(self.width, self.height),
+            lut: lut
+        };
+        self.write_into(graded).img
+    }
+}
@@ -0,0 +1,71 @@
+//! the matrices the public API accepts
+//!
+//! The crate does its math with cgmath, but everything that takes a matrix
+//! is generic over `IntoMatrix`. Column major arrays work the same as
+//! cgmath matrices, so nalgebra, glam and engine math types only need to
+//! hand out their columns.
+
+use cgmath::{Matrix4, FixedArray};
+
+/// a 4x4 matrix as four columns, the layout of `Matrix4::into_fixed` and
+/// of the column arrays most math libraries can produce
+pub type Mat4 = [[f32; 4]; 4];
+
+/// the same in f64, for the positions of large worlds, see
+/// `transform_relative_into`
+pub type Mat4d = [[f64; 4]; 4];
+
+/// anything that can be taken as a 4x4 matrix
+pub trait IntoMatrix {
+    fn into_matrix(self) -> Matrix4<f32>;
+}
+
+impl IntoMatrix for Matrix4<f32> {
+    #[inline]
+    fn into_matrix(self) -> Matrix4<f32> { self }
+}
+
+impl<'a> IntoMatrix for &'a Matrix4<f32> {
+    #[inline]
+    fn into_matrix(self) -> Matrix4<f32> { *self }
+}
+
+impl IntoMatrix for Mat4 {
+    #[inline]
+    fn into_matrix(self) -> Matrix4<f32> {
+        let (a, b, c, d) = (self[0], self[1], self[2], self[3]);
+        Matrix4::new(a[0], a[1], a[2], a[3],
+                     b[0], b[1], b[2], b[3],
+                     c[0], c[1], c[2], c[3],
+                     d[0], d[1], d[2], d[3])
+    }
+}
+
+impl<'a> IntoMatrix for &'a Mat4 {
+    #[inline]
+    fn into_matrix(self) -> Matrix4<f32> { (*self).into_matrix() }
+}
+
+/// sixteen floats, column after column
+impl IntoMatrix for [f32; 16] {
+    #[inline]
+    fn into_matrix(self) -> Matrix4<f32> {
+        let m = self;
+        Matrix4::new(m[0], m[1], m[2], m[3],
+                     m[4], m[5], m[6], m[7],
+                     m[8], m[9], m[10], m[11],
+                     m[12], m[13], m[14], m[15])
+    }
+}
+
+impl<'a> IntoMatrix for &'a [f32; 16] {
+    #[inline]
+    fn into_matrix(self) -> Matrix4<f32> { (*self).into_matrix() }
+}
+
+/// the columns of a matrix handed out by the crate, for passing it on to
+/// another math library
+#[inline]
+pub fn to_columns(m: &Matrix4<f32>) -> Mat4 {
+    m.into_fixed()
+}
@@ -0,0 +1,131 @@
+//! small clusters of triangles with bounds of their own, so that whole
+//! clusters can be skipped before any of their vertices are transformed
+
+#[inline]
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+#[inline]
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[inline]
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// a cluster of triangles of a mesh
+#[derive(Clone, Debug, PartialEq)]
+pub struct Meshlet {
+    /// three indices into the vertices of the mesh for every triangle
+    pub indices: Vec<u32>,
+    /// a sphere around the vertices
+    pub center: [f32; 3],
+    pub radius: f32,
+    /// the average direction the triangles face in
+    pub cone_axis: [f32; 3],
+    /// the cosine of the widest angle between the axis and the face
+    /// normal of a triangle, -1 if they spread too far for a cone
+    pub cone_cutoff: f32
+}
+
+impl Meshlet {
+    fn new(positions: &[[f32; 3]], indices: Vec<u32>) -> Meshlet {
+        let (mut lo, mut hi) = ([::std::f32::INFINITY; 3], [::std::f32::NEG_INFINITY; 3]);
+        for &i in indices.iter() {
+            let p = positions[i as usize];
+            for k in 0..3 {
+                lo[k] = lo[k].min(p[k]);
+                hi[k] = hi[k].max(p[k]);
+            }
+        }
+        let center = [(lo[0] + hi[0]) * 0.5, (lo[1] + hi[1]) * 0.5, (lo[2] + hi[2]) * 0.5];
+        let radius = indices.iter().fold(0f32, |r, &i| {
+            let d = sub(positions[i as usize], center);
+            r.max(dot(d, d))
+        }).sqrt();
+
+        let normals: Vec<[f32; 3]> = indices.chunks(3).filter_map(|t| {
+            let (a, b, c) = (positions[t[0] as usize], positions[t[1] as usize], positions[t[2] as usize]);
+            let n = cross(sub(b, a), sub(c, a));
+            let l = dot(n, n).sqrt();
+            if l > 0. { Some([n[0] / l, n[1] / l, n[2] / l]) } else { None }
+        }).collect();
+        let sum = normals.iter().fold([0.; 3], |s, n| [s[0] + n[0], s[1] + n[1], s[2] + n[2]]);
+        let l = dot(sum, sum).sqrt();
+        let (axis, cutoff) = if l > 0. {
+            let axis = [sum[0] / l, sum[1] / l, sum[2] / l];
+            let cutoff = normals.iter().fold(1f32, |c, n| c.min(dot(axis, *n)));
+            (axis, if cutoff > 0. { cutoff } else { -1. })
+        } else {
+            ([0., 0., 1.], -1.)
+        };
+
+        Meshlet {
+            indices: indices,
+            center: center,
+            radius: radius,
+            cone_axis: axis,
+            cone_cutoff: cutoff
+        }
+    }
+
+    pub fn triangles(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// true if every triangle faces away from `eye`, given in the model
+    /// space of the mesh
+    pub fn backfacing(&self, eye: [f32; 3]) -> bool {
+        if self.cone_cutoff <= 0. {
+            return false;
+        }
+        // the normals are at most `a` away from the axis and the direction
+        // to the sphere at most `b`, with `a + b` under 90 degrees the eye
+        // is behind every face
+        let v = sub(self.center, eye);
+        let d = dot(v, v).sqrt();
+        if d <= self.radius {
+            return false;
+        }
+        let cos_b = dot(v, self.cone_axis) / d;
+        let sin_b = (1. - cos_b * cos_b).max(0.).sqrt();
+        let sin_a = (1. - self.cone_cutoff * self.cone_cutoff).max(0.).sqrt();
+        cos_b > 0. && d * (cos_b * self.cone_cutoff - sin_b * sin_a) > self.radius
+    }
+}
+
+/// split the triangles of `indices` into meshlets of at most
+/// `max_vertices` distinct vertices and `max_triangles` triangles. The
+/// triangles are taken in order, so a list optimized for the vertex cache
+/// gives tighter meshlets.
+pub fn build_meshlets(positions: &[[f32; 3]], indices: &[u32], max_vertices: usize,
+                      max_triangles: usize) -> Vec<Meshlet> {
+    assert!(max_vertices >= 3 && max_triangles >= 1);
+    let mut meshlets = Vec::new();
+    let mut vertices: Vec<u32> = Vec::with_capacity(max_vertices);
+    let mut current = Vec::with_capacity(max_triangles * 3);
+
+    for t in indices.chunks(3).filter(|t| t.len() == 3) {
+        let new = t.iter().enumerate().filter(|&(k, i)| {
+            !vertices.contains(i) && !t[..k].contains(i)
+        }).count();
+        if vertices.len() + new > max_vertices || current.len() == max_triangles * 3 {
+            meshlets.push(Meshlet::new(positions, current));
+            vertices.clear();
+            current = Vec::with_capacity(max_triangles * 3);
+        }
+        for &i in t.iter() {
+            if !vertices.contains(&i) {
+                vertices.push(i);
+            }
+            current.push(i);
+        }
+    }
+    if !current.is_empty() {
+        meshlets.push(Meshlet::new(positions, current));
+    }
+    meshlets
+}
@@ -0,0 +1,136 @@
+/// a well mixed 32 bit hash of a pixel position and a seed
+#[inline]
+pub fn hash(x: u32, y: u32, seed: u32) -> u32 {
+    let mut h = seed ^ x.wrapping_mul(0x27d4_eb2d) ^ y.wrapping_mul(0x1656_67b1);
+    h = (h ^ (h >> 15)).wrapping_mul(0x85eb_ca6b);
+    h = (h ^ (h >> 13)).wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+/// `hash` mapped to the range 0 to 1, 1 excluded
+#[inline]
+pub fn hash_unit(x: u32, y: u32, seed: u32) -> f32 {
+    (hash(x, y, seed) >> 8) as f32 / (1 << 24) as f32
+}
+
+#[inline]
+fn smooth(t: f32) -> f32 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+#[inline]
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// deterministic procedural noise, shaders keep a copy of it. The same
+/// seed always gives the same pattern, use `Noise::frame` to get a new
+/// pattern every frame.
+#[derive(Clone, Copy, Debug)]
+pub struct Noise {
+    pub seed: u32
+}
+
+impl Noise {
+    pub fn new(seed: u32) -> Noise {
+        Noise { seed: seed }
+    }
+
+    /// the noise for frame number `frame` of a sequence seeded by `seed`
+    pub fn frame(seed: u32, frame: u32) -> Noise {
+        Noise { seed: hash(frame, 0, seed) }
+    }
+
+    /// white noise for a pixel, in the range 0 to 1
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> f32 {
+        hash_unit(x, y, self.seed)
+    }
+
+    /// value noise with one random value per lattice point, in the range 0 to 1
+    pub fn value(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+        let (ix, iy) = (x0 as i32 as u32, y0 as i32 as u32);
+        let v = |dx: u32, dy: u32| hash_unit(ix.wrapping_add(dx), iy.wrapping_add(dy), self.seed);
+        lerp(lerp(v(0, 0), v(1, 0), tx), lerp(v(0, 1), v(1, 1), tx), ty)
+    }
+
+    /// gradient noise with a random direction per lattice point, roughly
+    /// in the range -1 to 1 and 0 on the lattice points
+    pub fn perlin(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (ix, iy) = (x0 as i32 as u32, y0 as i32 as u32);
+        let g = |dx: u32, dy: u32| {
+            let h = hash(ix.wrapping_add(dx), iy.wrapping_add(dy), self.seed);
+            let a = (h >> 8) as f32 / (1 << 24) as f32 * 2. * ::std::f32::consts::PI;
+            let (px, py) = (fx - dx as f32, fy - dy as f32);
+            a.cos() * px + a.sin() * py
+        };
+        let (tx, ty) = (smooth(fx), smooth(fy));
+        lerp(lerp(g(0, 0), g(1, 0), tx), lerp(g(0, 1), g(1, 1), tx), ty) * ::std::f32::consts::SQRT_2
+    }
+}
+
+/// a tileable blue noise texture made with void and cluster, every value
+/// in the range 0 to 1 shows up once so thresholding it gives evenly
+/// spread points
+#[derive(Clone, Debug)]
+pub struct BlueNoise {
+    pub size: u32,
+    pub data: Vec<f32>
+}
+
+impl BlueNoise {
+    /// build a `size`x`size` texture, this is quadratic in the number of
+    /// pixels so it is meant to be done once and shared
+    pub fn new(size: u32, seed: u32) -> BlueNoise {
+        let n = (size * size) as usize;
+        let s = size as i32;
+        let mut energy = vec![0f32; n];
+        let mut rank = vec![None; n];
+
+        // a gaussian splat on the torus, sigma of 1.5 pixels
+        let falloff: Vec<f32> = (0..s).map(|d| {
+            let d = d.min(s - d) as f32;
+            (-d * d / (2. * 1.5 * 1.5)).exp()
+        }).collect();
+
+        for r in 0..n {
+            // the emptiest pixel left, ties broken by a hash of the position
+            let mut best = None;
+            for i in 0..n {
+                if rank[i].is_some() {
+                    continue;
+                }
+                let e = energy[i] + hash_unit(i as u32, 0, seed) * 1e-6;
+                match best {
+                    Some((_, b)) if b <= e => (),
+                    _ => best = Some((i, e))
+                }
+            }
+
+            let (i, _) = best.unwrap();
+            rank[i] = Some(r);
+            let (px, py) = ((i as u32 % size) as i32, (i as u32 / size) as i32);
+            for y in 0..s {
+                let fy = falloff[((y - py + s) % s) as usize];
+                for x in 0..s {
+                    energy[(y * s + x) as usize] += fy * falloff[((x - px + s) % s) as usize];
+                }
+            }
+        }
+
+        BlueNoise {
+            size: size,
+            data: rank.into_iter().map(|r| (r.unwrap() as f32 + 0.5) / n as f32).collect()
+        }
+    }
+
+    /// the value at a pixel, the texture repeats in both directions
+    #[inline]
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        self.data[((y % self.size) * self.size + x % self.size) as usize]
+    }
+}
@@ -0,0 +1,138 @@
+//! occlusion culling against the depth of a few large occluders
+//!
+//! The occluders are rastered into a small frame, its depth is reduced into
+//! a pyramid of the farthest depth of every 2x2 block, and bounding boxes
+//! are tested against the level where they cover at most 2x2 texels. A box
+//! that is reported hidden is behind the occluders everywhere, one that is
+//! reported visible may still be hidden.
+
+use std::cmp::min;
+
+use cgmath::{Matrix, Matrix4, Vector4};
+use genmesh::{Triangle, MapVertex};
+
+use {Frame, Buffer, SolidColor, IntoMatrix};
+
+/// a low resolution depth buffer answering visibility queries
+pub struct OcclusionBuffer {
+    frame: Frame<u8>,
+    view_proj: Matrix4<f32>,
+    /// level 0 is the depth of the frame, every next level is half the
+    /// size, all laid out like `Frame::ndc_depth_buffer`
+    levels: Vec<Buffer<f32>>
+}
+
+impl OcclusionBuffer {
+    /// a `width` by `height` buffer, a few hundred pixels across is usually
+    /// enough
+    pub fn new(width: u32, height: u32) -> OcclusionBuffer {
+        OcclusionBuffer {
+            frame: Frame::new(width, height, 0u8),
+            view_proj: Matrix4::identity(),
+            levels: vec![Buffer::new(width, height, 1.)]
+        }
+    }
+
+    /// start a new frame seen through `view_proj`, this drops the
+    /// occluders of the last one
+    pub fn begin<M: IntoMatrix>(&mut self, view_proj: M) {
+        self.frame.clear(0u8);
+        self.view_proj = view_proj.into_matrix();
+        let (w, h) = (self.frame.width, self.frame.height);
+        self.levels = vec![Buffer::new(w, h, 1.)];
+    }
+
+    /// raster the world space triangles of `occluders`, both of their sides
+    /// occlude
+    pub fn add_occluders<S>(&mut self, occluders: S)
+        where S: Iterator<Item=Triangle<[f32; 3]>> {
+
+        let view_proj = self.view_proj;
+        let poly = occluders.map(|t| t.map_vertex(|v| {
+            let p = view_proj.mul_v(&Vector4::new(v[0], v[1], v[2], 1.));
+            [p.x, p.y, p.z, p.w]
+        }));
+        self.frame.raster_two_sided(poly, SolidColor(0u8), SolidColor(0u8));
+    }
+
+    /// wait for the occluders and build the depth pyramid, queries only see
+    /// the occluders added before the last call
+    pub fn finish(&mut self) {
+        let mut levels = vec![self.frame.ndc_depth_buffer()];
+        loop {
+            let next = {
+                let last = levels.last().unwrap();
+                if last.width == 1 && last.height == 1 {
+                    break;
+                }
+                let (w, h) = ((last.width + 1) / 2, (last.height + 1) / 2);
+                let mut next = Buffer::new(w, h, 0.);
+                for y in 0..h {
+                    for x in 0..w {
+                        let (x1, y1) = (min(2 * x + 1, last.width - 1), min(2 * y + 1, last.height - 1));
+                        let far = last.get_pixel(2 * x, 2 * y).max(last.get_pixel(x1, 2 * y))
+                                      .max(last.get_pixel(2 * x, y1)).max(last.get_pixel(x1, y1));
+                        next.put_pixel(x, y, far);
+                    }
+                }
+                next
+            };
+            levels.push(next);
+        }
+        self.levels = levels;
+    }
+
+    /// the depth pyramid, the largest level first
+    pub fn levels(&self) -> &[Buffer<f32>] {
+        &self.levels
+    }
+
+    /// false if the world space box from `min` to `max` is hidden behind the
+    /// occluders or outside of the view. Boxes that reach from the front
+    /// to behind the eye are always visible.
+    pub fn aabb_visible(&self, min: [f32; 3], max: [f32; 3]) -> bool {
+        let (mut lo, mut hi) = ([::std::f32::INFINITY; 3], [::std::f32::NEG_INFINITY; 3]);
+        let mut behind = 0;
+        for i in 0..8 {
+            let c = [if i & 1 == 0 { min[0] } else { max[0] },
+                     if i & 2 == 0 { min[1] } else { max[1] },
+                     if i & 4 == 0 { min[2] } else { max[2] }];
+            let p = self.view_proj.mul_v(&Vector4::new(c[0], c[1], c[2], 1.));
+            if p.w <= 0. {
+                behind += 1;
+                continue;
+            }
+            let ndc = [p.x / p.w, p.y / p.w, p.z / p.w];
+            for k in 0..3 {
+                lo[k] = lo[k].min(ndc[k]);
+                hi[k] = hi[k].max(ndc[k]);
+            }
+        }
+        if behind > 0 {
+            return behind < 8;
+        }
+        if hi[0] < -1. || lo[0] > 1. || hi[1] < -1. || lo[1] > 1. || lo[2] > 1. || hi[2] < -1. {
+            return false;
+        }
+
+        // the pixels whose samples the box can reach, see `pixel_ndc`. They
+        // are counted from the top in the levels
+        let (w, h) = (self.levels[0].width, self.levels[0].height);
+        let px = |x: f32, size: u32| ((x + 1.) * size as f32 / 2.).max(0.).min(size as f32 - 1.);
+        let (x0, x1) = (px(lo[0], w).floor() as u32, px(hi[0], w).ceil() as u32);
+        let (y0, y1) = (h - 1 - px(hi[1], h).ceil() as u32, h - 1 - px(lo[1], h).floor() as u32);
+
+        let mut level = 0;
+        while level + 1 < self.levels.len() && ((x1 >> level) - (x0 >> level) > 1 || (y1 >> level) - (y0 >> level) > 1) {
+            level += 1;
+        }
+        let depth = &self.levels[level];
+        let mut far = ::std::f32::NEG_INFINITY;
+        for y in (y0 >> level)..(y1 >> level) + 1 {
+            for x in (x0 >> level)..(x1 >> level) + 1 {
+                far = far.max(depth.get_pixel(x, y));
+            }
+        }
+        lo[2] < far
+    }
+}
@@ -0,0 +1,126 @@
+//! many whole frames drawn at once, for rendering animations offline
+//!
+//! A frame only has as much parallel work as it has tile groups, so small
+//! frames leave most cores idle. A `FrameQueue` keeps several frames in
+//! flight instead, each on its own thread with a share of the cores.
+
+use std::cmp::{min, max};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+use vec_map::VecMap;
+
+use {Frame, Buffer, capabilities};
+
+/// frames waiting to be drawn, each with the state that changes from one
+/// frame to the next, like the camera or the time of the animation
+pub struct FrameQueue<P, C> {
+    pub width: u32,
+    pub height: u32,
+    clear: P,
+    frames: Vec<C>,
+    in_flight: Option<usize>
+}
+
+impl<P: Copy+Sync+Send+'static, C: Send+'static> FrameQueue<P, C> {
+    /// an empty queue of `width` by `height` frames cleared to `p`
+    pub fn new(width: u32, height: u32, p: P) -> FrameQueue<P, C> {
+        FrameQueue {
+            width: width,
+            height: height,
+            clear: p,
+            frames: Vec::new(),
+            in_flight: None
+        }
+    }
+
+    /// add a frame drawn with `state`
+    pub fn push(&mut self, state: C) {
+        self.frames.push(state);
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// draw `n` frames at the same time, None picks a number from the
+    /// size of the frames, see `in_flight`
+    pub fn set_in_flight(&mut self, n: Option<usize>) {
+        assert!(n != Some(0));
+        self.in_flight = n;
+    }
+
+    /// how many frames are drawn at the same time. Unless it was set, a
+    /// frame with a tile group for every core is drawn on its own and
+    /// smaller frames share the cores between them.
+    pub fn in_flight(&self) -> usize {
+        let cores = capabilities().threads;
+        let n = self.in_flight.unwrap_or_else(|| {
+            let groups = ((self.width + 31) / 32 * ((self.height + 31) / 32)) as usize;
+            cores / max(groups, 1)
+        });
+        max(1, min(n, self.frames.len()))
+    }
+
+    /// draw every frame with `draw` and hand the results to `done` in the
+    /// order the frames were pushed. Every frame starts out cleared.
+    pub fn run<F, D>(self, draw: F, mut done: D)
+        where F: Fn(&mut Frame<P>, &C) + Send + Sync + 'static,
+              D: FnMut(usize, Buffer<P>) {
+
+        let n = self.in_flight();
+        let threads = max(1, capabilities().threads / n);
+        let (w, h, clear) = (self.width, self.height, self.clear);
+        let jobs: VecDeque<(usize, C)> = self.frames.into_iter().enumerate().collect();
+        let jobs = Arc::new(Mutex::new(jobs));
+        let draw = Arc::new(draw);
+        let (tx, rx) = mpsc::channel();
+
+        let workers: Vec<_> = (0..n).map(|_| {
+            let (jobs, draw, tx) = (jobs.clone(), draw.clone(), tx.clone());
+            thread::spawn(move || {
+                let mut frame = Frame::with_threads(w, h, clear, threads);
+                loop {
+                    let job = jobs.lock().unwrap().pop_front();
+                    let (i, state) = match job {
+                        Some(job) => job,
+                        None => break
+                    };
+                    frame.clear(clear);
+                    draw(&mut frame, &state);
+                    if tx.send((i, frame.to_buffer())).is_err() {
+                        break;
+                    }
+                }
+            })
+        }).collect();
+        drop(tx);
+
+        // the frames finish in any order, they are held back until the
+        // ones before them are done
+        let mut pending = VecMap::new();
+        let mut next = 0;
+        for (i, buffer) in rx.iter() {
+            pending.insert(i, buffer);
+            while let Some(buffer) = pending.remove(&next) {
+                done(next, buffer);
+                next += 1;
+            }
+        }
+        for worker in workers.into_iter() {
+            if worker.join().is_err() {
+                panic!("a frame of the queue failed to draw");
+            }
+        }
+    }
+
+    /// `run` with the frames gathered in order
+    pub fn render<F>(self, draw: F) -> Vec<Buffer<P>>
+        where F: Fn(&mut Frame<P>, &C) + Send + Sync + 'static {
+
+        let mut out = Vec::with_capacity(self.frames.len());
+        self.run(draw, |_, buffer| out.push(buffer));
+        out
+    }
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use {Frame, Buffer, Rgba, error};
+use tile::Get;
+
+/// a selection highlight, see `Frame::outline`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outline {
+    /// the ids of the selected objects
+    pub selected: Vec<u32>,
+    pub color: Rgba<u8>,
+    /// how many pixels the outline reaches out from the objects
+    pub width: u32
+}
+
+impl Outline {
+    pub fn new(selected: Vec<u32>, color: Rgba<u8>) -> Outline {
+        Outline {
+            selected: selected,
+            color: color,
+            width: 1
+        }
+    }
+}
+
+/// dilates the selected ids by a disc of `width` pixels, the pixels that
+/// are reached but not selected themselves get the outline color
+struct OutlinePass {
+    ids: Buffer<u32>,
+    /// sorted for the lookups
+    selected: Vec<u32>,
+    color: Rgba<u8>,
+    width: i32
+}
+
+impl OutlinePass {
+    #[inline]
+    fn is_selected(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.ids.width as i32 || y >= self.ids.height as i32 {
+            return false;
+        }
+        self.selected.binary_search(&self.ids.get_pixel(x as u32, y as u32)).is_ok()
+    }
+}
+
+impl Get<Rgba<u8>> for OutlinePass {
+    fn get(&self, x: u32, y: u32) -> Option<Rgba<u8>> {
+        let (x, y) = (x as i32, (self.ids.height - 1 - y) as i32);
+        if self.is_selected(x, y) {
+            return None;
+        }
+
+        let r = self.width;
+        for dy in -r..r + 1 {
+            for dx in -r..r + 1 {
+                if dx * dx + dy * dy <= r * r && self.is_selected(x + dx, y + dy) {
+                    return Some(self.color);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Frame<Rgba<u8>> {
+    /// draw `outline` around the objects it selects. `ids` holds the object
+    /// of every pixel, like `aux_buffer` of a frame drawn with `ObjectId`,
+    /// and has the size of this frame. Only the pixels around the selected
+    /// objects change, the objects themselves and the depth buffer are
+    /// left as they are. `ids` of another size is an error.
+    pub fn outline(&mut self, ids: &Buffer<u32>, outline: &Outline) -> error::Result<()> {
+        try!(error::check_size((self.width, self.height), (ids.width, ids.height)));
+        let mut selected = outline.selected.clone();
+        selected.sort();
+        self.load(Arc::new(OutlinePass {
+            ids: ids.clone(),
+            selected: selected,
+            color: outline.color,
+            width: outline.width as i32
+        }));
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use genmesh::Triangle;
+
+use {Fragment, Lerp};
+
+/// a vertex of a 2D primitive, the clip space position followed
+/// by the canvas point it was generated from
+pub type CanvasVertex = ([f32; 4], [f32; 2]);
+
+/// maps canvas coordinates (origin top left, y pointing down, one unit
+/// per pixel) to clip space and builds fillable primitives
+#[derive(Clone, Debug)]
+pub struct Canvas {
+    pub width: f32,
+    pub height: f32,
+    clip: Vec<Vec<[f32; 2]>>
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Canvas {
+        Canvas {
+            width: width as f32,
+            height: height as f32,
+            clip: Vec::new()
+        }
+    }
+
+    /// restrict all following fills to the rectangle, nested clips intersect
+    pub fn push_clip_rect(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        self.clip.push(vec![[x, y], [x + w, y], [x + w, y + h], [x, y + h]]);
+    }
+
+    /// restrict all following fills to a convex path
+    pub fn push_clip_path(&mut self, points: &[[f32; 2]]) {
+        self.clip.push(points.to_vec());
+    }
+
+    /// remove the most recently pushed clip region
+    pub fn pop_clip(&mut self) {
+        self.clip.pop();
+    }
+
+    #[inline]
+    pub fn vertex(&self, p: [f32; 2]) -> CanvasVertex {
+        ([p[0] / self.width * 2. - 1., 1. - p[1] / self.height * 2., 0., 1.], p)
+    }
+
+    pub fn rect(&self, x: f32, y: f32, w: f32, h: f32) -> Vec<Triangle<CanvasVertex>> {
+        self.polygon(&[[x, y], [x + w, y], [x + w, y + h], [x, y + h]])
+    }
+
+    /// fill a convex polygon, the points can be in either winding
+    pub fn polygon(&self, points: &[[f32; 2]]) -> Vec<Triangle<CanvasVertex>> {
+        let mut points = points.to_vec();
+        for clip in self.clip.iter() {
+            points = clip_polygon(points, clip);
+        }
+
+        if points.len() < 3 {
+            return Vec::new();
+        }
+
+        // the rasterizer culls clockwise triangles, with y pointing down
+        // a positive area means the polygon is clockwise on screen
+        let v: Vec<CanvasVertex> = if signed_area(&points) > 0. {
+            points.iter().rev().map(|p| self.vertex(*p)).collect()
+        } else {
+            points.iter().map(|p| self.vertex(*p)).collect()
+        };
+
+        (1..v.len() - 1).map(|i| Triangle::new(v[0], v[i], v[i + 1])).collect()
+    }
+}
+
+fn signed_area(points: &[[f32; 2]]) -> f32 {
+    let mut area = 0.;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        area += a[0] * b[1] - b[0] * a[1];
+    }
+    area
+}
+
+/// Sutherland-Hodgman clip of `subject` against the convex polygon `clip`
+fn clip_polygon(subject: Vec<[f32; 2]>, clip: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    let orient = if signed_area(clip) > 0. { 1. } else { -1. };
+    let mut out = subject;
+
+    for (i, a) in clip.iter().enumerate() {
+        if out.is_empty() {
+            break;
+        }
+
+        let b = clip[(i + 1) % clip.len()];
+        let side = |p: [f32; 2]| orient * ((b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0]));
+
+        let input = out;
+        out = Vec::with_capacity(input.len() + 1);
+        for (j, &p) in input.iter().enumerate() {
+            let q = input[(j + 1) % input.len()];
+            let (sp, sq) = (side(p), side(q));
+            if sp >= 0. {
+                out.push(p);
+            }
+            if (sp >= 0.) != (sq >= 0.) {
+                let t = sp / (sp - sq);
+                out.push([p[0] + (q[0] - p[0]) * t, p[1] + (q[1] - p[1]) * t]);
+            }
+        }
+    }
+    out
+}
+
+/// a list of color stops sorted by offset
+#[derive(Clone, Debug)]
+pub struct Gradient<P> {
+    stops: Vec<(f32, P)>
+}
+
+impl<P: Lerp + Copy> Gradient<P> {
+    pub fn new(start: P, end: P) -> Gradient<P> {
+        Gradient {
+            stops: vec![(0., start), (1., end)]
+        }
+    }
+
+    /// add a color stop at `offset`
+    pub fn stop(mut self, offset: f32, color: P) -> Gradient<P> {
+        let i = self.stops.iter().position(|s| s.0 > offset).unwrap_or(self.stops.len());
+        self.stops.insert(i, (offset, color));
+        self
+    }
+
+    /// the color at `t`, clamped to the first and last stop
+    pub fn color(&self, t: f32) -> P {
+        let (first, last) = (self.stops[0], self.stops[self.stops.len() - 1]);
+        if t <= first.0 {
+            return first.1;
+        }
+
+        for s in self.stops.windows(2) {
+            let (a, b) = (s[0], s[1]);
+            if t <= b.0 {
+                let span = b.0 - a.0;
+                return if span > 0. { a.1.lerp(b.1, (t - a.0) / span) } else { b.1 };
+            }
+        }
+        last.1
+    }
+}
+
+/// a gradient along the line from `start` to `end`
+#[derive(Clone, Debug)]
+pub struct LinearGradient<P> {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+    pub gradient: Gradient<P>
+}
+
+impl<P: Lerp + Copy> Fragment<CanvasVertex> for LinearGradient<P> {
+    type Color = P;
+
+    #[inline]
+    fn fragment(&self, (_, p): CanvasVertex) -> P {
+        let d = [self.end[0] - self.start[0], self.end[1] - self.start[1]];
+        let len = d[0] * d[0] + d[1] * d[1];
+        let t = if len > 0. {
+            ((p[0] - self.start[0]) * d[0] + (p[1] - self.start[1]) * d[1]) / len
+        } else {
+            0.
+        };
+        self.gradient.color(t)
+    }
+}
+
+/// a gradient from `center` outwards to `radius`
+#[derive(Clone, Debug)]
+pub struct RadialGradient<P> {
+    pub center: [f32; 2],
+    pub radius: f32,
+    pub gradient: Gradient<P>
+}
+
+impl<P: Lerp + Copy> Fragment<CanvasVertex> for RadialGradient<P> {
+    type Color = P;
+
+    #[inline]
+    fn fragment(&self, (_, p): CanvasVertex) -> P {
+        let (dx, dy) = (p[0] - self.center[0], p[1] - self.center[1]);
+        let t = if self.radius > 0. { (dx * dx + dy * dy).sqrt() / self.radius } else { 1. };
+        self.gradient.color(t)
+    }
+}
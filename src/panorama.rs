@@ -0,0 +1,144 @@
+//! 360 degree panoramas: the scene is drawn into the six faces of a cube
+//! around the eye, which are then resampled into an equirectangular image
+//! for viewers and VR previews
+
+use std::sync::Arc;
+
+use cgmath::{Matrix4, perspective, deg};
+
+use {Frame, Buffer, Lerp, Rgba};
+use scene::{Scene, Camera};
+use tile::Get;
+
+/// the forward, up and right directions of the faces in the order +x, -x,
+/// +y, -y, +z, -z, right is forward cross up so the faces keep the winding
+/// of the triangles
+const FACES: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+    ([1., 0., 0.], [0., 1., 0.], [0., 0., 1.]),
+    ([-1., 0., 0.], [0., 1., 0.], [0., 0., -1.]),
+    ([0., 1., 0.], [0., 0., 1.], [1., 0., 0.]),
+    ([0., -1., 0.], [0., 0., -1.], [1., 0., 0.]),
+    ([0., 0., 1.], [0., 1., 0.], [-1., 0., 0.]),
+    ([0., 0., -1.], [0., 1., 0.], [1., 0., 0.])
+];
+
+#[inline]
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// the face a direction points into
+#[inline]
+fn face_of(dir: [f32; 3]) -> usize {
+    let (x, y, z) = (dir[0].abs(), dir[1].abs(), dir[2].abs());
+    if x >= y && x >= z {
+        if dir[0] > 0. { 0 } else { 1 }
+    } else if y >= z {
+        if dir[1] > 0. { 2 } else { 3 }
+    } else {
+        if dir[2] > 0. { 4 } else { 5 }
+    }
+}
+
+/// the camera at `eye` that sees `face` of a cube map, in the order +x, -x,
+/// +y, -y, +z, -z, with a 90 degree view
+pub fn face_camera(face: usize, eye: [f32; 3], near: f32, far: f32) -> Camera {
+    let (f, u, r) = FACES[face];
+    let view = Matrix4::new(r[0], u[0], -f[0], 0.,
+                            r[1], u[1], -f[1], 0.,
+                            r[2], u[2], -f[2], 0.,
+                            -dot(r, eye), -dot(u, eye), dot(f, eye), 1.);
+    Camera::new(view, perspective(deg(90.), 1., near, far), eye)
+}
+
+/// six square images of what is around a point, see `face_camera` for
+/// the order of the faces
+pub struct CubeMap<P> {
+    pub faces: Vec<Buffer<P>>
+}
+
+impl<P: Copy + Lerp> CubeMap<P> {
+    /// the width and height of the faces
+    pub fn size(&self) -> u32 {
+        self.faces[0].width
+    }
+
+    /// the bilinear color seen along `dir`, which needs not be normalized.
+    /// The samples don't cross the edges of the faces.
+    pub fn sample(&self, dir: [f32; 3]) -> P {
+        let face = face_of(dir);
+        let (f, u, r) = FACES[face];
+        let d = dot(dir, f);
+        let (x, y) = (dot(dir, r) / d, dot(dir, u) / d);
+        let size = self.size() as f32;
+        // half a pixel over to the centers that `sample` expects
+        self.faces[face].sample((x + 1.) / 2. + 0.5 / size, (1. - y) / 2. - 0.5 / size)
+    }
+}
+
+impl<P: Copy + Sync + Send + 'static> CubeMap<P> {
+    /// draw the faces around `eye` into `size` frames cleared to `clear`,
+    /// `draw` is called once per face with the camera that sees it
+    pub fn render<F>(size: u32, clear: P, eye: [f32; 3], near: f32, far: f32, mut draw: F) -> CubeMap<P>
+        where F: FnMut(&mut Frame<P>, &Camera) {
+
+        let mut frame = Frame::new(size, size, clear);
+        let faces = (0..6).map(|face| {
+            frame.clear(clear);
+            draw(&mut frame, &face_camera(face, eye, near, far));
+            frame.to_buffer()
+        }).collect();
+        CubeMap { faces: faces }
+    }
+}
+
+/// the direction at the center of the pixel `x`, `y` of a `width` by
+/// `height` equirectangular image counted from the top left. The middle of
+/// the image looks down -z with +x to the right of it and +y up.
+pub fn equirect_dir(x: u32, y: u32, width: u32, height: u32) -> [f32; 3] {
+    use std::f32::consts::PI;
+    let lon = ((x as f32 + 0.5) / width as f32 - 0.5) * 2. * PI;
+    let lat = (0.5 - (y as f32 + 0.5) / height as f32) * PI;
+    [lat.cos() * lon.sin(), lat.sin(), -lat.cos() * lon.cos()]
+}
+
+struct Equirect<P> {
+    cube: Arc<CubeMap<P>>,
+    width: u32,
+    height: u32
+}
+
+impl<P: Copy + Lerp> Get<P> for Equirect<P> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        let y = self.height - 1 - y;
+        Some(self.cube.sample(equirect_dir(x, y, self.width, self.height)))
+    }
+}
+
+impl<P: Copy + Lerp + Sync + Send + 'static> Frame<P> {
+    /// resample `cube` into this frame as an equirectangular panorama, the
+    /// longitude goes across and the latitude down, see `equirect_dir`
+    pub fn equirect(&mut self, cube: Arc<CubeMap<P>>) {
+        let (w, h) = (self.width, self.height);
+        self.load(Arc::new(Equirect {
+            cube: cube,
+            width: w,
+            height: h
+        }));
+    }
+}
+
+/// a `width` by `height` equirectangular panorama of `scene` seen from
+/// `eye`, usually twice as wide as it is high. The faces get a quarter of
+/// the width so the detail matches around the equator.
+pub fn render_panorama(scene: &Scene, eye: [f32; 3], width: u32, height: u32,
+                       near: f32, far: f32, clear: Rgba<u8>) -> Buffer<Rgba<u8>> {
+    let size = ::std::cmp::max(width / 4, 1);
+    let cube = CubeMap::render(size, clear, eye, near, far, |frame, camera| {
+        scene.render(frame, camera);
+    });
+    let mut frame = Frame::new(width, height, clear);
+    frame.equirect(Arc::new(cube));
+    frame.to_buffer()
+}
@@ -0,0 +1,36 @@
+use std::fmt::Debug;
+
+use genmesh::Triangle;
+
+use {Frame, Fragment, Interpolate, FetchPosition, DrawStats};
+
+/// what a draw does with the contents the frame had before it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoadOp<P> {
+    /// draw over the pixels that are already there
+    Load,
+    /// start from color `P` and the far plane, like `Frame::clear`
+    Clear(P)
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// apply `load` to the frame ahead of the next draw. A clear only
+    /// drops the tiles of every group in a task of its own, they are
+    /// cleared lazily when a triangle first touches them and the storage
+    /// is kept for that.
+    pub fn load_op(&mut self, load: LoadOp<P>) {
+        if let LoadOp::Clear(p) = load {
+            self.clear(p);
+        }
+    }
+
+    /// `raster` with a load op, see `load_op`
+    pub fn raster_with<S, F, T, O>(&mut self, load: LoadOp<P>, poly: S, fragment: F) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: Fragment<O, Color=P> + Send + Sync + 'static {
+
+        self.load_op(load);
+        self.raster(poly, fragment)
+    }
+}
@@ -1,10 +1,98 @@
 
 
+use std::sync::Arc;
+
 pub trait Fragment<T> {
     type Color;
     fn fragment(&self, pos: T) -> Self::Color;
 
     fn blend(&self, _: Self::Color, new: Self::Color) -> Self::Color { new }
+
+    /// the color of every fragment if it does not depend on the input, the
+    /// tiles then write it without calling `fragment`
+    #[inline]
+    fn constant(&self) -> Option<Self::Color> { None }
+
+    /// true if the shader never reads the position in front of its input,
+    /// the tiles then shade a triangle whose other attributes are all flat
+    /// once per tile. See `Plane::varyings_flat`.
+    #[inline]
+    fn position_unused(&self) -> bool { false }
+}
+
+/// fills every fragment with the same color
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SolidColor<P>(pub P);
+
+impl<T, P: Copy> Fragment<T> for SolidColor<P> {
+    type Color = P;
+
+    #[inline]
+    fn fragment(&self, _: T) -> P { self.0 }
+
+    #[inline]
+    fn constant(&self) -> Option<P> { Some(self.0) }
+}
+
+/// a fragment shader that is handed data owned by the draw, so one shader
+/// can be used with different tables and the tables are not copied into
+/// it. See `Frame::raster_with`.
+pub trait FragmentWith<T, U: ?Sized> {
+    type Color;
+    fn fragment(&self, data: &U, pos: T) -> Self::Color;
+
+    fn blend(&self, _: &U, _: Self::Color, new: Self::Color) -> Self::Color { new }
+}
+
+/// a `FragmentWith` together with the data of its draw
+pub struct WithData<F, U: ?Sized> {
+    pub fragment: F,
+    pub data: Arc<U>
+}
+
+impl<T, F, U: ?Sized> Fragment<T> for WithData<F, U> where F: FragmentWith<T, U> {
+    type Color = F::Color;
+
+    #[inline]
+    fn fragment(&self, pos: T) -> F::Color { self.fragment.fragment(&*self.data, pos) }
+
+    #[inline]
+    fn blend(&self, old: F::Color, new: F::Color) -> F::Color { self.fragment.blend(&*self.data, old, new) }
+}
+
+/// a fragment shader that shades a row of eight fragments at once, `T`
+/// holds their attributes in f32x8 lanes, see `PlaneSimd`. Shaders that
+/// only implement `Fragment` are rastered one fragment at a time.
+pub trait FragmentSimd<T> {
+    type Color;
+    fn fragment8(&self, lanes: T) -> [Self::Color; 8];
+
+    fn blend(&self, _: Self::Color, new: Self::Color) -> Self::Color { new }
+}
+
+/// picks a fragment shader by the side of the triangle being shaded
+#[derive(Clone, Debug)]
+pub struct TwoSided<F, B> {
+    pub front: F,
+    pub back: B
+}
+
+impl<T, P, F, B> Fragment<(T, bool)> for TwoSided<F, B>
+    where F: Fragment<T, Color=P>,
+          B: Fragment<T, Color=P> {
+    type Color = P;
+
+    #[inline]
+    fn fragment(&self, (pos, front): (T, bool)) -> P {
+        if front {
+            self.front.fragment(pos)
+        } else {
+            self.back.fragment(pos)
+        }
+    }
+
+    #[inline]
+    fn blend(&self, old: P, new: P) -> P { self.front.blend(old, new) }
 }
 
 pub trait Vertex<T> {
@@ -17,3 +105,10 @@ pub trait Mapping<T> {
     fn mapping(&self, pixel: T) -> Self::Out;
 }
 
+/// like `Mapping` but also told where the pixel is, `x` and `y` start at
+/// the top left corner of a `width` by `height` frame
+pub trait MappingAt<T> {
+    type Out;
+    fn mapping_at(&self, pixel: T, x: u32, y: u32, width: u32, height: u32) -> Self::Out;
+}
+
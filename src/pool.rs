@@ -0,0 +1,418 @@
+use std::any::Any;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use fibe::{Frontend, task};
+use future_pulse::Future;
+use pulse::{Signal, Pulse};
+
+use {Frame, TileGroup, DepthMode, SimdBackend, Stencil};
+
+/// what a frame holds on to and what its draws have allocated, see
+/// `Frame::memory_usage`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MemoryUsage {
+    pub groups: usize,
+    /// bytes of tiles that were written to since the last clear
+    pub tile_bytes: usize,
+    /// bytes of tiles that clears kept for the next writes
+    pub spare_bytes: usize,
+    pub draws: usize,
+    /// allocated by the draws, one per tile group a draw touches or four
+    /// if the group was split into quadrants, unless a bin of an earlier
+    /// draw was free to take, see `Bin`
+    pub bins: usize,
+    /// created by the draws, one per tile group a draw touches. They hand
+    /// the group from one task to the next and can only be set once.
+    pub futures: usize
+}
+
+/// the counters of the allocations made by the draws of a frame
+pub struct Traffic {
+    draws: AtomicUsize,
+    bins: AtomicUsize,
+    futures: AtomicUsize
+}
+
+impl Traffic {
+    pub fn new() -> Traffic {
+        Traffic {
+            draws: AtomicUsize::new(0),
+            bins: AtomicUsize::new(0),
+            futures: AtomicUsize::new(0)
+        }
+    }
+
+    #[inline]
+    pub fn draw(&self) {
+        self.draws.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// a draw touched a tile group, `bins` of the bins it took for it were
+    /// allocated
+    #[inline]
+    pub fn group(&self, bins: usize) {
+        self.bins.fetch_add(bins, Ordering::Relaxed);
+        self.futures.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// the triangles of a draw headed for one tile group or quadrant, sent by
+/// the binning loop to the worker that rasters them. The worker hands the
+/// bin back to the `BinPool` of the frame once it is done, so the next
+/// draws take it again along with the storage of its queue.
+pub struct Bin<I> {
+    state: Mutex<BinState<I>>
+}
+
+struct BinState<I> {
+    queue: Vec<I>,
+    /// the storage the worker takes the queue into
+    spare: Vec<I>,
+    closed: bool,
+    wake: Option<Pulse>
+}
+
+impl<I> Bin<I> {
+    fn new() -> Bin<I> {
+        Bin {
+            state: Mutex::new(BinState {
+                queue: Vec::new(),
+                spare: Vec::new(),
+                closed: false,
+                wake: None
+            })
+        }
+    }
+
+    /// wake the worker if it waits, outside of the lock since it may run
+    /// right away
+    #[inline]
+    fn wake(&self, wake: Option<Pulse>) {
+        if let Some(p) = wake {
+            p.pulse();
+        }
+    }
+
+    #[inline]
+    pub fn send(&self, item: I) {
+        let wake = {
+            let mut state = self.state.lock().unwrap();
+            state.queue.push(item);
+            state.wake.take()
+        };
+        self.wake(wake);
+    }
+
+    /// no more items follow for this draw
+    pub fn close(&self) {
+        let wake = {
+            let mut state = self.state.lock().unwrap();
+            state.closed = true;
+            state.wake.take()
+        };
+        self.wake(wake);
+    }
+
+    /// the storage to `take` the items into, kept from the last draw
+    pub fn batch(&self) -> Vec<I> {
+        mem::replace(&mut self.state.lock().unwrap().spare, Vec::new())
+    }
+
+    /// swap the items sent so far into `batch`, which has to be empty.
+    /// Returns whether the bin was closed, no more items follow then, or
+    /// the signal to wait for if there is nothing to take yet.
+    pub fn take(&self, batch: &mut Vec<I>) -> Result<bool, Signal> {
+        let mut state = self.state.lock().unwrap();
+        if state.queue.is_empty() && !state.closed {
+            let (signal, pulse) = Signal::new();
+            state.wake = Some(pulse);
+            return Err(signal);
+        }
+        mem::swap(&mut state.queue, batch);
+        Ok(state.closed)
+    }
+
+    fn reset(&self, mut batch: Vec<I>) {
+        let mut state = self.state.lock().unwrap();
+        batch.clear();
+        state.queue.clear();
+        state.spare = batch;
+        state.closed = false;
+        state.wake = None;
+    }
+}
+
+/// the binning side of a `Bin`, it closes the bin when dropped
+pub struct BinSender<I>(pub Arc<Bin<I>>);
+
+impl<I> BinSender<I> {
+    #[inline]
+    pub fn send(&self, item: I) {
+        self.0.send(item)
+    }
+}
+
+impl<I> Drop for BinSender<I> {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+/// the bins of the finished draws of a frame, for items of any type
+#[derive(Clone)]
+pub struct BinPool {
+    bins: Arc<Mutex<Vec<Box<Any + Send>>>>
+}
+
+impl BinPool {
+    pub fn new() -> BinPool {
+        BinPool {
+            bins: Arc::new(Mutex::new(Vec::new()))
+        }
+    }
+
+    /// a bin for items of type `I`, one handed back before if there is
+    /// any. The second value tells if it was allocated.
+    pub fn take<I: Send + 'static>(&self) -> (Arc<Bin<I>>, bool) {
+        let mut bins = self.bins.lock().unwrap();
+        match bins.iter().position(|b| b.is::<Arc<Bin<I>>>()) {
+            Some(i) => (*bins.swap_remove(i).downcast::<Arc<Bin<I>>>().ok().unwrap(), false),
+            None => (Arc::new(Bin::new()), true)
+        }
+    }
+
+    /// hand a bin back once its worker is done with it, together with the
+    /// storage the worker took its items into
+    pub fn give<I: Send + 'static>(&self, bin: Arc<Bin<I>>, batch: Vec<I>) {
+        bin.reset(batch);
+        self.bins.lock().unwrap().push(Box::new(bin));
+    }
+
+    /// free the bins and their storage
+    pub fn clear(&self) {
+        self.bins.lock().unwrap().clear();
+    }
+}
+
+/// tile groups kept from frames that are done with, so that frames of a
+/// similar size do not have to allocate them again. Clones share the same
+/// groups. See `Frame::from_pool` and `Frame::recycle`.
+pub struct TilePool<P> {
+    state: Arc<Mutex<PoolState<P>>>,
+    budget: usize
+}
+
+struct PoolState<P> {
+    groups: Vec<Box<TileGroup<P>>>,
+    /// the `group_memory` of all the groups
+    bytes: usize
+}
+
+impl<P> Clone for TilePool<P> {
+    fn clone(&self) -> TilePool<P> {
+        TilePool {
+            state: self.state.clone(),
+            budget: self.budget
+        }
+    }
+}
+
+impl<P: Copy> TilePool<P> {
+    /// a pool that holds on to at most `budget` bytes, the groups past it
+    /// are freed when they are handed back
+    pub fn new(budget: usize) -> TilePool<P> {
+        TilePool {
+            state: Arc::new(Mutex::new(PoolState {
+                groups: Vec::new(),
+                bytes: 0
+            })),
+            budget: budget
+        }
+    }
+
+    /// the number of groups in the pool
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().groups.len()
+    }
+
+    /// the bytes held by the groups in the pool and their tiles
+    pub fn memory(&self) -> usize {
+        self.state.lock().unwrap().bytes
+    }
+
+    fn take(&self) -> Option<Box<TileGroup<P>>> {
+        let mut state = self.state.lock().unwrap();
+        let group = state.groups.pop();
+        if let Some(ref g) = group {
+            state.bytes -= group_memory(g);
+        }
+        group
+    }
+
+    fn give(&self, mut group: Box<TileGroup<P>>) {
+        let mut state = self.state.lock().unwrap();
+        if state.bytes + group_memory(&group) > self.budget {
+            // the storage of the tiles is what matters, try without it
+            group.trim();
+        }
+        let bytes = group_memory(&group);
+        if state.bytes + bytes <= self.budget {
+            state.bytes += bytes;
+            state.groups.push(group);
+        }
+    }
+}
+
+/// whole frames of one size kept for reuse. Making a frame starts its
+/// worker threads and its first draws allocate the tiles, a frame handed
+/// back with `put` keeps both for the next `get`. For thumbnails, video and
+/// other work that goes through many frames of the same size.
+pub struct FramePool<P> {
+    pub width: u32,
+    pub height: u32,
+    clear: P,
+    frames: Vec<Frame<P>>,
+    max: usize
+}
+
+impl<P: Copy+Sync+Send+'static> FramePool<P> {
+    /// a pool of `width` by `height` frames cleared to `clear`, it keeps at
+    /// most `max` of the frames handed back
+    pub fn new(width: u32, height: u32, clear: P, max: usize) -> FramePool<P> {
+        FramePool {
+            width: width,
+            height: height,
+            clear: clear,
+            frames: Vec::new(),
+            max: max
+        }
+    }
+
+    /// the number of frames waiting to be handed out again
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// a cleared frame with the settings of `Frame::new`, one handed back
+    /// before if there is any
+    pub fn get(&mut self) -> Frame<P> {
+        match self.frames.pop() {
+            Some(mut frame) => {
+                frame.reset();
+                frame.clear(self.clear);
+                frame
+            }
+            None => Frame::new(self.width, self.height, self.clear)
+        }
+    }
+
+    /// hand a frame back once done with it, the pool does not wait for the
+    /// work still pending on it. Frames past the limit of the pool are
+    /// dropped.
+    pub fn put(&mut self, frame: Frame<P>) {
+        assert!(frame.width == self.width && frame.height == self.height,
+                "the frame does not match the size of the pool");
+        if self.frames.len() < self.max {
+            self.frames.push(frame);
+        }
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// go back to the settings of a new frame, the contents stay
+    fn reset(&mut self) {
+        self.capture = None;
+        self.validation = None;
+        self.split_threshold = ::std::usize::MAX;
+        self.priority = None;
+        self.backend = Arc::new(SimdBackend);
+        self.painter = false;
+        self.depth_write = true;
+        self.depth_mode = DepthMode::Projected;
+        self.traffic = Arc::new(Traffic::new());
+        self.stencil = Stencil::default();
+        self.timer = None;
+        self.deadline = None;
+        self.alloc_counter = None;
+    }
+}
+
+#[inline]
+fn group_memory<P: Copy>(group: &TileGroup<P>) -> usize {
+    mem::size_of::<TileGroup<P>>() + group.memory() + group.spare_memory()
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// like `new` but the tile groups are taken from `pool` as long as it
+    /// has any, together with the storage of their tiles
+    pub fn from_pool(width: u32, height: u32, p: P, pool: &TilePool<P>) -> Frame<P> {
+        let tile = (0..((width + 31) / 32)).map(
+            |_| (0..((height + 31) / 32)).map(|_| {
+                let group = match pool.take() {
+                    Some(mut group) => {
+                        group.clear(p);
+                        group
+                    }
+                    None => Box::new(TileGroup::new(p))
+                };
+                Future::from_value(group)
+            }).collect()
+        ).collect();
+        Frame::with_tiles(width, height, tile, Frontend::new())
+    }
+
+    /// wait for the frame and hand its tile groups over to `pool`
+    pub fn recycle(self, pool: &TilePool<P>) {
+        for row in self.tile.into_iter() {
+            for tile in row.into_iter() {
+                pool.give(tile.get());
+            }
+        }
+    }
+
+    /// free the storage that clears keep for the next writes to a tile
+    /// group and the bins of the finished draws, the frame reads back the
+    /// same
+    pub fn trim(&mut self) {
+        self.bins.clear();
+        for row in self.tile.iter_mut() {
+            for tile in row.iter_mut() {
+                let (mut new, set) = Future::new();
+                mem::swap(tile, &mut new);
+                let signal = new.signal();
+                task(move |_| {
+                    let mut t = new.get();
+                    t.trim();
+                    set.set(t);
+                }).after(signal).start(&mut self.pool);
+            }
+        }
+    }
+
+    /// wait for the frame and report the memory it holds, along with the
+    /// allocations made by the draws since it was created
+    pub fn memory_usage(&mut self) -> MemoryUsage {
+        let mut usage = MemoryUsage {
+            groups: 0,
+            tile_bytes: 0,
+            spare_bytes: 0,
+            draws: self.traffic.draws.load(Ordering::Relaxed),
+            bins: self.traffic.bins.load(Ordering::Relaxed),
+            futures: self.traffic.futures.load(Ordering::Relaxed)
+        };
+        for row in self.tile.iter_mut() {
+            for tile in row.iter_mut() {
+                let (mut new, set) = Future::new();
+                mem::swap(tile, &mut new);
+                let group = new.get();
+                usage.groups += 1;
+                usage.tile_bytes += group.memory();
+                usage.spare_bytes += group.spare_memory();
+                set.set(group);
+            }
+        }
+        usage
+    }
+}
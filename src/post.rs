@@ -0,0 +1,358 @@
+use std::sync::Arc;
+
+use {Frame, Buffer, Lerp, Rgba};
+use tile::Get;
+
+/// a 2x2 box filter over the source, one destination pixel per block
+struct Downsample<P> {
+    src: Buffer<P>,
+    height: u32
+}
+
+impl<P: Copy + Lerp> Get<P> for Downsample<P> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        let (x, y) = ((2 * x) as i32, (2 * (self.height - 1 - y)) as i32);
+        let top = self.src.get_clamped(x, y).lerp(self.src.get_clamped(x + 1, y), 0.5);
+        let bottom = self.src.get_clamped(x, y + 1).lerp(self.src.get_clamped(x + 1, y + 1), 0.5);
+        Some(top.lerp(bottom, 0.5))
+    }
+}
+
+/// bilinear filtering of the source, pixel centers of both buffers line up
+struct Upsample<P> {
+    src: Buffer<P>,
+    width: u32,
+    height: u32
+}
+
+impl<P: Copy + Lerp> Get<P> for Upsample<P> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        let y = self.height - 1 - y;
+        let sx = (x as f32 + 0.5) * self.src.width as f32 / self.width as f32 - 0.5;
+        let sy = (y as f32 + 0.5) * self.src.height as f32 / self.height as f32 - 0.5;
+        Some(self.src.bilinear(sx, sy))
+    }
+}
+
+/// radial lens distortion, a pixel at distance `r` from the center fetches
+/// the source at `r * (1 + k1 r^2 + k2 r^4)`. The radius is 1 at the
+/// corners of the frame.
+#[derive(Clone, Copy, Debug)]
+pub struct Lens {
+    pub k1: f32,
+    pub k2: f32
+}
+
+impl Lens {
+    /// the source position for the destination pixel `x`, `y` of a `w`x`h`
+    /// frame, scaled around the center by an extra `scale`
+    #[inline]
+    fn source(&self, x: u32, y: u32, w: u32, h: u32, scale: f32) -> (f32, f32) {
+        let (cx, cy) = (w as f32 * 0.5, h as f32 * 0.5);
+        let norm = (cx * cx + cy * cy).sqrt().recip();
+        let (dx, dy) = ((x as f32 + 0.5 - cx) * norm, (y as f32 + 0.5 - cy) * norm);
+        let r2 = dx * dx + dy * dy;
+        let f = scale * (1. + self.k1 * r2 + self.k2 * r2 * r2) / norm;
+        (cx + dx * f - 0.5, cy + dy * f - 0.5)
+    }
+}
+
+struct Distort<P> {
+    src: Buffer<P>,
+    lens: Lens
+}
+
+impl<P: Copy + Lerp> Get<P> for Distort<P> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        let (w, h) = (self.src.width, self.src.height);
+        let (sx, sy) = self.lens.source(x, h - 1 - y, w, h, 1.);
+        Some(self.src.bilinear(sx, sy))
+    }
+}
+
+/// the red and blue channels are fetched through lenses scaled apart by
+/// `spread`, green is the reference
+struct Aberration {
+    src: Buffer<Rgba<u8>>,
+    lens: Lens,
+    spread: f32
+}
+
+impl Get<Rgba<u8>> for Aberration {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<Rgba<u8>> {
+        let (w, h) = (self.src.width, self.src.height);
+        let y = h - 1 - y;
+        let fetch = |scale: f32| {
+            let (sx, sy) = self.lens.source(x, y, w, h, scale);
+            self.src.bilinear(sx, sy)
+        };
+
+        let (r, g, b) = (fetch(1. + self.spread), fetch(1.), fetch(1. - self.spread));
+        Some(Rgba([r.data[0], g.data[1], b.data[2], g.data[3]]))
+    }
+}
+
+/// how far apart two depths can be and still be treated as the same surface
+const DEPTH_EPSILON: f32 = 1e-3;
+
+/// bilinear filtering where every tap is weighted down by how far its
+/// depth is from the depth of the destination pixel
+struct DepthUpsample<P> {
+    src: Buffer<P>,
+    src_depth: Buffer<f32>,
+    depth: Buffer<f32>
+}
+
+impl<P: Copy + Lerp> Get<P> for DepthUpsample<P> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        let y = self.depth.height - 1 - y;
+        let z = self.depth.get_pixel(x, y);
+        let sx = (x as f32 + 0.5) * self.src.width as f32 / self.depth.width as f32 - 0.5;
+        let sy = (y as f32 + 0.5) * self.src.height as f32 / self.depth.height as f32 - 0.5;
+        let (x0, y0) = (sx.floor(), sy.floor());
+        let (fx, fy) = (sx - x0, sy - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let taps = [(x0, y0, (1. - fx) * (1. - fy)),
+                    (x0 + 1, y0, fx * (1. - fy)),
+                    (x0, y0 + 1, (1. - fx) * fy),
+                    (x0 + 1, y0 + 1, fx * fy)];
+
+        // a running weighted average, each tap is blended in by its
+        // share of the total weight seen so far
+        let mut out = self.src.get_clamped(x0, y0);
+        let mut total = 0.;
+        for &(tx, ty, w) in taps.iter() {
+            let dz = (self.src_depth.get_clamped(tx, ty) - z).abs();
+            let w = w / (DEPTH_EPSILON + dz);
+            total += w;
+            if total > 0. {
+                out = out.lerp(self.src.get_clamped(tx, ty), w / total);
+            }
+        }
+        Some(out)
+    }
+}
+
+/// the settings of `Frame::denoise`
+#[derive(Clone, Copy, Debug)]
+pub struct Denoise {
+    /// the number of passes, pass `i` takes taps `2^i` pixels apart so `n`
+    /// passes reach `2^(n+1) - 2` pixels in all
+    pub iterations: u32,
+    /// the change in depth per pixel of distance that weights a tap down
+    /// by a factor of e
+    pub depth_sigma: f32,
+    /// the cosine of the angle between the normals is raised to this power
+    /// to weight a tap
+    pub normal_power: f32
+}
+
+impl Default for Denoise {
+    fn default() -> Denoise {
+        Denoise {
+            iterations: 3,
+            depth_sigma: 0.01,
+            normal_power: 32.
+        }
+    }
+}
+
+/// the B3 spline the a trous filter spreads out with every pass
+const ATROUS_KERNEL: [f32; 5] = [1. / 16., 1. / 4., 3. / 8., 1. / 4., 1. / 16.];
+
+/// one pass of the a trous wavelet filter, the taps that are on another
+/// surface by their depth or normal are weighted down
+struct ATrous<P> {
+    src: Buffer<P>,
+    depth: Arc<Buffer<f32>>,
+    normals: Arc<Buffer<[f32; 3]>>,
+    step: i32,
+    params: Denoise
+}
+
+impl<P: Copy + Lerp> Get<P> for ATrous<P> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        let y = self.src.height - 1 - y;
+        let (w, h) = (self.src.width as i32, self.src.height as i32);
+        let z = self.depth.get_pixel(x, y);
+        let n = self.normals.get_pixel(x, y);
+
+        let mut out = self.src.get_pixel(x, y);
+        let mut total = ATROUS_KERNEL[2] * ATROUS_KERNEL[2];
+        for j in 0..5 {
+            for i in 0..5 {
+                let (dx, dy) = ((i as i32 - 2) * self.step, (j as i32 - 2) * self.step);
+                let (tx, ty) = (x as i32 + dx, y as i32 + dy);
+                if (dx == 0 && dy == 0) || tx < 0 || ty < 0 || tx >= w || ty >= h {
+                    continue;
+                }
+                let (tx, ty) = (tx as u32, ty as u32);
+                let dist = ((dx * dx + dy * dy) as f32).sqrt();
+                let dz = (self.depth.get_pixel(tx, ty) - z).abs();
+                let tn = self.normals.get_pixel(tx, ty);
+                let cos = n[0] * tn[0] + n[1] * tn[1] + n[2] * tn[2];
+                let w = ATROUS_KERNEL[i] * ATROUS_KERNEL[j] *
+                        (-dz / (self.params.depth_sigma * dist)).exp() *
+                        cos.max(0.).powf(self.params.normal_power);
+                // a running weighted average like `DepthUpsample`
+                total += w;
+                out = out.lerp(self.src.get_pixel(tx, ty), w / total);
+            }
+        }
+        Some(out)
+    }
+}
+
+/// averages the source along the motion of every pixel, centered on it
+struct MotionBlur<P> {
+    src: Buffer<P>,
+    motion: Buffer<[f32; 2]>,
+    shutter: f32,
+    samples: u32
+}
+
+impl<P: Copy + Lerp> Get<P> for MotionBlur<P> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        let y = self.src.height - 1 - y;
+        let m = self.motion.get_pixel(x, y);
+        let (dx, dy) = (m[0] * self.shutter, m[1] * self.shutter);
+        let mut color = self.src.get_pixel(x, y);
+        if self.samples < 2 || dx * dx + dy * dy < 0.25 {
+            return Some(color);
+        }
+        for i in 0..self.samples {
+            let t = (i as f32 + 0.5) / self.samples as f32 - 0.5;
+            let tap = self.src.bilinear(x as f32 + dx * t, y as f32 + dy * t);
+            // a running average, the first tap replaces the center
+            color = color.lerp(tap, 1. / (i + 1) as f32);
+        }
+        Some(color)
+    }
+}
+
+impl<P: Copy+Lerp+Sync+Send+'static> Frame<P> {
+    /// write a half resolution copy of this frame into `dst`, every
+    /// destination pixel is the average of a 2x2 block
+    pub fn downsample(&mut self, dst: &mut Frame<P>) {
+        assert!(dst.width == (self.width + 1) / 2);
+        assert!(dst.height == (self.height + 1) / 2);
+
+        let filter = Downsample {
+            src: self.to_buffer(),
+            height: dst.height
+        };
+        dst.load(Arc::new(filter));
+    }
+
+    /// stretch this frame over all of `dst` using bilinear filtering,
+    /// `dst` is normally larger than the source
+    pub fn upsample(&mut self, dst: &mut Frame<P>) {
+        dst.upsample_from(self.to_buffer());
+    }
+
+    /// stretch `src` over all of this frame, see `upsample`
+    pub fn upsample_from(&mut self, src: Buffer<P>) {
+        let filter = Upsample {
+            src: src,
+            width: self.width,
+            height: self.height
+        };
+        self.load(Arc::new(filter));
+    }
+
+    /// upsample a low resolution effect into `dst` without bleeding across
+    /// depth discontinuities. The depth buffer of this frame is compared
+    /// against `depth`, the full resolution depth read back from the scene
+    /// with `ndc_depth_buffer`, so the two frames may store it differently.
+    pub fn upsample_depth_aware(&mut self, dst: &mut Frame<P>, depth: Buffer<f32>) {
+        assert!(depth.width == dst.width);
+        assert!(depth.height == dst.height);
+
+        let filter = DepthUpsample {
+            src: self.to_buffer(),
+            src_depth: self.ndc_depth_buffer(),
+            depth: depth
+        };
+        dst.load(Arc::new(filter));
+    }
+
+    /// resample this frame into `dst` through a barrel (positive `k1`) or
+    /// pincushion (negative `k1`) distortion, see `Lens`
+    pub fn lens_distort(&mut self, dst: &mut Frame<P>, lens: Lens) {
+        assert!(dst.width == self.width);
+        assert!(dst.height == self.height);
+
+        let filter = Distort {
+            src: self.to_buffer(),
+            lens: lens
+        };
+        dst.load(Arc::new(filter));
+    }
+
+    /// blur this frame into `dst` along `motion`, how far every pixel moved
+    /// in pixels since the previous frame with y pointing down, like the
+    /// aux buffer of a frame of `Aux<P, [f32; 2]>`. `shutter` is the part
+    /// of the frame time the shutter stays open and `samples` the number
+    /// of taps along the motion, pixels that moved less than half a pixel
+    /// are copied.
+    pub fn motion_blur(&mut self, dst: &mut Frame<P>, motion: Buffer<[f32; 2]>, shutter: f32, samples: u32) {
+        assert!(dst.width == self.width && dst.height == self.height);
+        assert!(motion.width == self.width && motion.height == self.height);
+
+        let filter = MotionBlur {
+            src: self.to_buffer(),
+            motion: motion,
+            shutter: shutter,
+            samples: samples
+        };
+        dst.load(Arc::new(filter));
+    }
+
+    /// smooth out the noise of a stochastic effect like ambient occlusion
+    /// or soft shadows into `dst` without blurring across the edges of the
+    /// surfaces, which are found in `depth` and the unit `normals` of the
+    /// scene. Each pass of `params.iterations` reads the previous one from
+    /// `dst`.
+    pub fn denoise(&mut self, dst: &mut Frame<P>, depth: Buffer<f32>, normals: Buffer<[f32; 3]>, params: Denoise) {
+        assert!(dst.width == self.width && dst.height == self.height);
+        assert!(depth.width == self.width && depth.height == self.height);
+        assert!(normals.width == self.width && normals.height == self.height);
+
+        let (depth, normals) = (Arc::new(depth), Arc::new(normals));
+        for i in 0..::std::cmp::max(params.iterations, 1) {
+            let src = if i == 0 { self.to_buffer() } else { dst.to_buffer() };
+            dst.load(Arc::new(ATrous {
+                src: src,
+                depth: depth.clone(),
+                normals: normals.clone(),
+                step: 1 << i,
+                params: params
+            }));
+        }
+    }
+}
+
+impl Frame<Rgba<u8>> {
+    /// resample this frame into `dst` through `lens` with the red channel
+    /// pushed outwards and the blue channel pulled inwards by `spread`, a
+    /// fraction of the distance to the center
+    pub fn chromatic_aberration(&mut self, dst: &mut Frame<Rgba<u8>>, lens: Lens, spread: f32) {
+        assert!(dst.width == self.width);
+        assert!(dst.height == self.height);
+
+        let filter = Aberration {
+            src: self.to_buffer(),
+            lens: lens,
+            spread: spread
+        };
+        dst.load(Arc::new(filter));
+    }
+}
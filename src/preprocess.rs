@@ -0,0 +1,259 @@
+//! turns raw indexed meshes into draw ready ones once, instead of redoing
+//! the work every frame
+//!
+//! Every mesh goes through its own task on the workers of a frame, so a
+//! model made of many meshes is prepared on all the cores.
+
+use fibe::{task, IntoTask};
+use future_pulse::Future;
+
+use Frame;
+use scene::Mesh;
+use meshlet::{Meshlet, build_meshlets};
+
+/// the vertices a post transform cache is simulated with
+const CACHE_SIZE: usize = 32;
+
+/// what `preprocess` does besides the bounds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+    /// reorder the triangles for the vertex cache
+    pub optimize: bool,
+    /// store the positions as 16 bit and the normals as 8 bit integers
+    pub quantize: bool,
+    /// split into meshlets of at most this many vertices and triangles
+    pub meshlets: Option<(usize, usize)>
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options {
+            optimize: true,
+            quantize: true,
+            meshlets: None
+        }
+    }
+}
+
+/// the attributes of a mesh in less space, see `Dequantize` and
+/// `Normalized` for reading them back
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuantizedMesh {
+    /// decoded as `offset + scale * p`
+    pub positions: Vec<[u16; 3]>,
+    pub scale: [f32; 3],
+    pub offset: [f32; 3],
+    /// empty if the mesh has no normals
+    pub normals: Vec<[i8; 3]>
+}
+
+impl QuantizedMesh {
+    fn new(mesh: &Mesh, lo: [f32; 3], hi: [f32; 3]) -> QuantizedMesh {
+        let mut scale = [0.; 3];
+        for k in 0..3 {
+            scale[k] = if hi[k] > lo[k] { (hi[k] - lo[k]) / 65535. } else { 1. };
+        }
+        let q = |v: f32, k: usize| ((v - lo[k]) / scale[k] + 0.5).max(0.).min(65535.) as u16;
+        let n = |v: f32| (v.max(-1.).min(1.) * 127.).round() as i8;
+
+        QuantizedMesh {
+            positions: mesh.positions.iter().map(|p| [q(p[0], 0), q(p[1], 1), q(p[2], 2)]).collect(),
+            scale: scale,
+            offset: lo,
+            normals: mesh.normals.iter().map(|v| [n(v[0]), n(v[1]), n(v[2])]).collect()
+        }
+    }
+}
+
+/// a mesh ready to be drawn many times
+#[derive(Clone, Debug)]
+pub struct PreparedMesh {
+    /// the source mesh, with its triangles reordered if that was asked for
+    pub mesh: Mesh,
+    pub quantized: Option<QuantizedMesh>,
+    /// the box around the positions
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+    /// the sphere `Mesh::bounds` gives
+    pub center: [f32; 3],
+    pub radius: f32,
+    /// empty unless `Options::meshlets` is set
+    pub meshlets: Vec<Meshlet>
+}
+
+/// prepare a single mesh on the calling thread
+pub fn prepare(mut mesh: Mesh, options: Options) -> PreparedMesh {
+    if options.optimize {
+        mesh.indices = optimize_vertex_cache(&mesh.indices, mesh.positions.len());
+    }
+
+    let (mut lo, mut hi) = ([0.; 3], [0.; 3]);
+    if let Some(p) = mesh.positions.first() {
+        lo = *p;
+        hi = *p;
+    }
+    for p in mesh.positions.iter() {
+        for k in 0..3 {
+            lo[k] = lo[k].min(p[k]);
+            hi[k] = hi[k].max(p[k]);
+        }
+    }
+    let (center, radius) = mesh.bounds();
+
+    let quantized = if options.quantize { Some(QuantizedMesh::new(&mesh, lo, hi)) } else { None };
+    let meshlets = match options.meshlets {
+        Some((vertices, triangles)) => build_meshlets(&mesh.positions, &mesh.indices, vertices, triangles),
+        None => Vec::new()
+    };
+
+    PreparedMesh {
+        mesh: mesh,
+        quantized: quantized,
+        min: lo,
+        max: hi,
+        center: center,
+        radius: radius,
+        meshlets: meshlets
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// prepare every mesh in a task of its own on the workers of the
+    /// frame, the results are in the same order as `meshes`
+    pub fn preprocess(&mut self, meshes: Vec<Mesh>, options: Options) -> Vec<PreparedMesh> {
+        let futures: Vec<Future<PreparedMesh>> = meshes.into_iter().map(|mesh| {
+            let (future, set) = Future::new();
+            task(move |_| set.set(prepare(mesh, options))).start(&mut self.pool);
+            future
+        }).collect();
+        futures.into_iter().map(|f| f.get()).collect()
+    }
+}
+
+#[inline]
+fn vertex_score(cache_position: Option<usize>, remaining: usize) -> f32 {
+    if remaining == 0 {
+        return -1.;
+    }
+    // the last triangle's vertices get a fixed score so that its
+    // neighbours do not just continue the same strip
+    let cache = match cache_position {
+        Some(p) if p < 3 => 0.75,
+        Some(p) => (1. - (p - 3) as f32 / (CACHE_SIZE - 3) as f32).powf(1.5),
+        None => 0.
+    };
+    // vertices with few triangles left are finished off first
+    cache + 2. * (remaining as f32).powf(-0.5)
+}
+
+/// reorder the triangles of `indices` so the vertices they share are
+/// still in a post transform cache when they are used again, using Tom
+/// Forsyth's linear speed algorithm
+pub fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangles = indices.len() / 3;
+
+    // the triangles around every vertex, the first `remaining` of them
+    // are the ones not emitted yet
+    let mut remaining = vec![0usize; vertex_count];
+    for &i in indices[..3 * triangles].iter() {
+        remaining[i as usize] += 1;
+    }
+    let mut offsets = vec![0usize; vertex_count + 1];
+    for v in 0..vertex_count {
+        offsets[v + 1] = offsets[v] + remaining[v];
+    }
+    let mut adjacency = vec![0usize; 3 * triangles];
+    let mut fill = offsets.clone();
+    for t in 0..triangles {
+        for k in 0..3 {
+            let v = indices[3 * t + k] as usize;
+            adjacency[fill[v]] = t;
+            fill[v] += 1;
+        }
+    }
+
+    let mut position: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut score: Vec<f32> = (0..vertex_count).map(|v| vertex_score(None, remaining[v])).collect();
+    let triangle_score = |score: &[f32], t: usize| {
+        score[indices[3 * t] as usize] + score[indices[3 * t + 1] as usize] + score[indices[3 * t + 2] as usize]
+    };
+
+    let mut emitted = vec![false; triangles];
+    let mut cache: Vec<usize> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut out = Vec::with_capacity(3 * triangles);
+    let mut scan = 0;
+    let mut best = (0..triangles).fold(None, |best: Option<(usize, f32)>, t| {
+        let s = triangle_score(&score, t);
+        match best {
+            Some((_, b)) if b >= s => best,
+            _ => Some((t, s))
+        }
+    }).map(|(t, _)| t);
+
+    while let Some(t) = best {
+        emitted[t] = true;
+        let tv = [indices[3 * t] as usize, indices[3 * t + 1] as usize, indices[3 * t + 2] as usize];
+        out.extend(tv.iter().map(|&v| v as u32));
+
+        for &v in tv.iter() {
+            let start = offsets[v];
+            let live = &mut adjacency[start..start + remaining[v]];
+            let k = live.iter().position(|&a| a == t).unwrap();
+            let last = live.len() - 1;
+            live.swap(k, last);
+            remaining[v] -= 1;
+        }
+
+        // the vertices of the triangle move to the front of the cache
+        let mut next: Vec<usize> = tv.to_vec();
+        next.extend(cache.iter().cloned().filter(|v| !tv.contains(v)));
+        for (p, &v) in next.iter().enumerate() {
+            position[v] = if p < CACHE_SIZE { Some(p) } else { None };
+            score[v] = vertex_score(position[v], remaining[v]);
+        }
+
+        // the next triangle is the best one around the cache
+        best = None;
+        let mut best_score = ::std::f32::NEG_INFINITY;
+        for &v in next.iter() {
+            let start = offsets[v];
+            for &a in adjacency[start..start + remaining[v]].iter() {
+                let s = triangle_score(&score, a);
+                if s > best_score {
+                    best = Some(a);
+                    best_score = s;
+                }
+            }
+        }
+        next.truncate(CACHE_SIZE);
+        cache = next;
+
+        if best.is_none() {
+            while scan < triangles && emitted[scan] {
+                scan += 1;
+            }
+            if scan < triangles {
+                best = Some(scan);
+            }
+        }
+    }
+    out
+}
+
+/// the vertices transformed per triangle with a FIFO post transform cache
+/// of `cache_size` vertices, 3 is the worst and 0.5 about the best a
+/// regular grid can do
+pub fn average_cache_miss_ratio(indices: &[u32], cache_size: usize) -> f32 {
+    let mut cache: Vec<u32> = Vec::with_capacity(cache_size);
+    let mut misses = 0;
+    for &i in indices.iter() {
+        if !cache.contains(&i) {
+            misses += 1;
+            if cache.len() == cache_size {
+                cache.remove(0);
+            }
+            cache.push(i);
+        }
+    }
+    misses as f32 / (indices.len() / 3).max(1) as f32
+}
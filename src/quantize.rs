@@ -0,0 +1,116 @@
+use soa::Gather;
+
+/// an integer component of a compressed vertex attribute
+pub trait Quantized: Copy {
+    /// the integer as it is
+    fn value(self) -> f32;
+    /// -1 to 1 for the signed types and 0 to 1 for the unsigned ones, the
+    /// lowest signed value is clamped to -1 like graphics APIs do
+    fn normalized(self) -> f32;
+}
+
+impl Quantized for i8 {
+    #[inline]
+    fn value(self) -> f32 { self as f32 }
+    #[inline]
+    fn normalized(self) -> f32 { (self as f32 / 127.).max(-1.) }
+}
+
+impl Quantized for u8 {
+    #[inline]
+    fn value(self) -> f32 { self as f32 }
+    #[inline]
+    fn normalized(self) -> f32 { self as f32 / 255. }
+}
+
+impl Quantized for i16 {
+    #[inline]
+    fn value(self) -> f32 { self as f32 }
+    #[inline]
+    fn normalized(self) -> f32 { (self as f32 / 32767.).max(-1.) }
+}
+
+impl Quantized for u16 {
+    #[inline]
+    fn value(self) -> f32 { self as f32 }
+    #[inline]
+    fn normalized(self) -> f32 { self as f32 / 65535. }
+}
+
+/// normalized integer vectors, like packed normals, tangents, colors and
+/// texture coordinates
+#[derive(Clone, Copy, Debug)]
+pub struct Normalized<S>(pub S);
+
+impl<'a, T: Quantized> Gather for Normalized<&'a [[T; 2]]> {
+    type Out = [f32; 2];
+    #[inline]
+    fn gather(&self, i: usize) -> [f32; 2] {
+        let v = self.0[i];
+        [v[0].normalized(), v[1].normalized()]
+    }
+}
+
+impl<'a, T: Quantized> Gather for Normalized<&'a [[T; 3]]> {
+    type Out = [f32; 3];
+    #[inline]
+    fn gather(&self, i: usize) -> [f32; 3] {
+        let v = self.0[i];
+        [v[0].normalized(), v[1].normalized(), v[2].normalized()]
+    }
+}
+
+impl<'a, T: Quantized> Gather for Normalized<&'a [[T; 4]]> {
+    type Out = [f32; 4];
+    #[inline]
+    fn gather(&self, i: usize) -> [f32; 4] {
+        let v = self.0[i];
+        [v[0].normalized(), v[1].normalized(), v[2].normalized(), v[3].normalized()]
+    }
+}
+
+/// integer positions on a grid, decoded as `offset + scale * q`. Meshes
+/// are usually quantized over their bounds, with `offset` the lower corner
+/// and `scale` the size of a grid step.
+#[derive(Clone, Copy, Debug)]
+pub struct Dequantize<S> {
+    pub source: S,
+    pub scale: [f32; 3],
+    pub offset: [f32; 3]
+}
+
+impl<S> Dequantize<S> {
+    pub fn new(source: S, scale: [f32; 3], offset: [f32; 3]) -> Dequantize<S> {
+        Dequantize {
+            source: source,
+            scale: scale,
+            offset: offset
+        }
+    }
+
+    #[inline]
+    fn decode<T: Quantized>(&self, v: [T; 3]) -> [f32; 3] {
+        [self.offset[0] + self.scale[0] * v[0].value(),
+         self.offset[1] + self.scale[1] * v[1].value(),
+         self.offset[2] + self.scale[2] * v[2].value()]
+    }
+}
+
+impl<'a, T: Quantized> Gather for Dequantize<&'a [[T; 3]]> {
+    type Out = [f32; 3];
+    #[inline]
+    fn gather(&self, i: usize) -> [f32; 3] {
+        self.decode(self.source[i])
+    }
+}
+
+/// the fourth component is padding and a w of 1 is gathered in its place
+impl<'a, T: Quantized> Gather for Dequantize<&'a [[T; 4]]> {
+    type Out = [f32; 4];
+    #[inline]
+    fn gather(&self, i: usize) -> [f32; 4] {
+        let v = self.source[i];
+        let p = self.decode([v[0], v[1], v[2]]);
+        [p[0], p[1], p[2], 1.]
+    }
+}
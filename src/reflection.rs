@@ -0,0 +1,153 @@
+//! planar mirrors: the scene is drawn reflected about a plane into a
+//! texture of its own, which the surfaces of the mirror then sample by
+//! their screen position in the main pass
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use cgmath::{Matrix, Matrix4, Vector4};
+use genmesh::{Triangle, MapVertex};
+
+use {Frame, Buffer, Fragment, Interpolate, FetchPosition, DrawStats, Lerp, Rgba};
+use scene::{Scene, Camera};
+
+/// a flat mirror in world space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mirror {
+    /// `[a, b, c, d]` with a unit normal, the points where
+    /// `a*x + b*y + c*z + d` is positive are in front of the mirror and
+    /// the only ones it reflects
+    pub plane: [f32; 4]
+}
+
+impl Mirror {
+    /// the mirror through `point` facing along `normal`
+    pub fn new(point: [f32; 3], normal: [f32; 3]) -> Mirror {
+        let l = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+        let n = [normal[0] / l, normal[1] / l, normal[2] / l];
+        Mirror {
+            plane: [n[0], n[1], n[2], -(n[0] * point[0] + n[1] * point[1] + n[2] * point[2])]
+        }
+    }
+
+    /// reflects world space points about the plane
+    pub fn matrix(&self) -> Matrix4<f32> {
+        let p = self.plane;
+        let (a, b, c, d) = (p[0], p[1], p[2], p[3]);
+        Matrix4::new(1. - 2. * a * a, -2. * a * b, -2. * a * c, 0.,
+                     -2. * a * b, 1. - 2. * b * b, -2. * b * c, 0.,
+                     -2. * a * c, -2. * b * c, 1. - 2. * c * c, 0.,
+                     -2. * a * d, -2. * b * d, -2. * c * d, 1.)
+    }
+
+    /// `camera` looking at the reflected world. The image comes out flipped
+    /// left to right so the reflection keeps the winding of the triangles,
+    /// `Reflection::sample` flips it back.
+    pub fn camera(&self, camera: &Camera) -> Camera {
+        let flip = Matrix4::new(-1., 0., 0., 0.,
+                                0., 1., 0., 0.,
+                                0., 0., 1., 0.,
+                                0., 0., 0., 1.);
+        let p = camera.position;
+        let p = self.matrix().mul_v(&Vector4::new(p[0], p[1], p[2], 1.));
+        Camera {
+            view: camera.view.mul_m(&self.matrix()),
+            proj: flip.mul_m(&camera.proj),
+            position: [p.x, p.y, p.z]
+        }
+    }
+
+    /// the plane in the clip space of `self.camera(camera)`, for
+    /// `Frame::raster_clipped`. It takes the place of the near plane so
+    /// nothing behind the mirror ends up in the reflection.
+    pub fn clip_plane(&self, camera: &Camera) -> [f32; 4] {
+        let inverse = match self.camera(camera).view_proj().invert() {
+            Some(m) => m,
+            None => return [0., 0., 0., 1.]
+        };
+        // planes go through the transpose of the inverse
+        let p = self.plane;
+        let dot = |c: Vector4<f32>| p[0] * c.x + p[1] * c.y + p[2] * c.z + p[3] * c.w;
+        [dot(inverse.x), dot(inverse.y), dot(inverse.z), dot(inverse.w)]
+    }
+
+    /// draw `scene` as the mirror shows it to `camera` into a `width` by
+    /// `height` texture cleared to `clear`, it should match the size of
+    /// the frame the mirror is composited into
+    pub fn render(&self, scene: &Scene, camera: &Camera, width: u32, height: u32,
+                  clear: Rgba<u8>) -> Reflection {
+        let mut frame = Frame::new(width, height, clear);
+        scene.render_clipped(&mut frame, &self.camera(camera), &[self.clip_plane(camera)]);
+        Reflection::new(Arc::new(frame.to_buffer()))
+    }
+}
+
+/// the texture a `Mirror` rendered, see `Frame::raster_reflective`
+#[derive(Clone)]
+pub struct Reflection {
+    pub texture: Arc<Buffer<Rgba<u8>>>,
+    /// how much of the reflection is mixed into the surface color, 1 for
+    /// a perfect mirror
+    pub strength: f32
+}
+
+impl Reflection {
+    pub fn new(texture: Arc<Buffer<Rgba<u8>>>) -> Reflection {
+        Reflection {
+            texture: texture,
+            strength: 1.
+        }
+    }
+
+    /// the reflected color at `clip`, a position on the mirror in the clip
+    /// space of the camera the reflection was rendered for
+    pub fn sample(&self, clip: [f32; 4]) -> Rgba<u8> {
+        let (x, y) = (clip[0] / clip[3], clip[1] / clip[3]);
+        let (w, h) = (self.texture.width as f32, self.texture.height as f32);
+        // `sample` takes the centers of the pixels rather than the points
+        // of `pixel_ndc`, x is mirrored back
+        self.texture.sample((1. - x) / 2. + 0.5 / w, (1. - y) / 2. - 0.5 / h)
+    }
+}
+
+/// runs the fragment shader, then mixes the reflection in by the clip
+/// position interpolated next to the vertex attributes
+#[derive(Clone)]
+struct Reflective<F> {
+    fragment: F,
+    reflection: Reflection
+}
+
+impl<T, F> Fragment<(T, [f32; 4])> for Reflective<F>
+    where F: Fragment<T, Color=Rgba<u8>> {
+    type Color = Rgba<u8>;
+
+    #[inline]
+    fn fragment(&self, (pos, clip): (T, [f32; 4])) -> Rgba<u8> {
+        let color = self.fragment.fragment(pos);
+        color.lerp(self.reflection.sample(clip), self.reflection.strength.max(0.).min(1.))
+    }
+
+    #[inline]
+    fn blend(&self, old: Rgba<u8>, new: Rgba<u8>) -> Rgba<u8> { self.fragment.blend(old, new) }
+}
+
+impl Frame<Rgba<u8>> {
+    /// `raster` the surface of a mirror with `reflection` mixed into the
+    /// output of `fragment`, the positions must be in the clip space of the
+    /// camera the reflection was rendered for
+    pub fn raster_reflective<S, F, T, O>(&mut self, poly: S, fragment: F, reflection: &Reflection) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: Fragment<O, Color=Rgba<u8>> + Send + Sync + 'static {
+
+        let poly = poly.map(|t| t.map_vertex(|v| {
+            let clip = v.position();
+            (v, clip)
+        }));
+        self.raster(poly, Reflective {
+            fragment: fragment,
+            reflection: reflection.clone()
+        })
+    }
+}
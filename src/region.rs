@@ -0,0 +1,61 @@
+use std::mem;
+use std::sync::Arc;
+use std::fmt::Debug;
+
+use fibe::{task, IntoTask};
+use future_pulse::Future;
+use genmesh::Triangle;
+
+use {Frame, Rect, Pass, group_rect, Fragment, Interpolate, FetchPosition, DrawStats};
+use timing::timed;
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// reset the color to `p` and the depth to the far plane inside `rect`
+    pub fn clear_region(&mut self, rect: Rect, p: P) {
+        let (w, h) = (self.width, self.height);
+        let inside = Arc::new(move |x: u32, y: u32| y < h && rect.contains(x, h - 1 - y));
+        let clock = self.clock(Pass::Clear);
+
+        for (x, row) in self.tile.iter_mut().enumerate() {
+            for (y, tile) in row.iter_mut().enumerate() {
+                if group_rect(x as u32, y as u32, w, h).intersect(&rect).is_none() {
+                    continue;
+                }
+
+                let (mut new, set) = Future::new();
+                mem::swap(tile, &mut new);
+                let inside = inside.clone();
+                let clock = clock.clone();
+                let signal = new.signal();
+                task(move |_| {
+                    let mut t = new.get();
+                    timed(&clock, Some((x as u32, y as u32)), || t.clear_where((x*32_) as u32, (y*32_) as u32, &*inside, p));
+                    set.set(t);
+                }).after(signal).start(&mut self.pool);
+            }
+        }
+    }
+
+    /// raster the geometry only into the pixels inside of `rect`, the
+    /// triangles are clipped to the region so the rest of the frame is
+    /// left untouched. Call `clear_region` first to redraw a damaged area.
+    pub fn reraster_region<S, F, T, O>(&mut self, rect: Rect, poly: S, fragment: F) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: Fragment<O, Color=P> + Send + Sync + 'static {
+
+        // keep half a pixel of slack so that the samples on the border of
+        // the region are kept, see `pixel_ndc`
+        let (w, h) = (self.width as f32, self.height as f32);
+        let x0 = (rect.x as f32 - 0.5) / w * 2. - 1.;
+        let x1 = ((rect.x + rect.width) as f32 - 0.5) / w * 2. - 1.;
+        let y0 = 1. - ((rect.y + rect.height) as f32 + 0.5) / h * 2.;
+        let y1 = 1. - (rect.y as f32 + 0.5) / h * 2.;
+
+        let planes = [[ 1., 0., 0., -x0],
+                      [-1., 0., 0.,  x1],
+                      [ 0., 1., 0., -y0],
+                      [ 0.,-1., 0.,  y1]];
+        self.raster_clipped(poly, &planes, fragment)
+    }
+}
@@ -0,0 +1,358 @@
+//! finished tile groups sent to a viewer somewhere else
+//!
+//! `Frame::stream_tiles` hands every tile group to a `TileSink` as soon as
+//! the work on it is done, packed into a `TilePacket`. A packet carries
+//! the format of its pixels and where they go, so a client only needs
+//! `TilePacket::read_from` and `TilePacket::write_into` to keep a copy of
+//! the frame up to date.
+
+use std::io::{self, Read, Write};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use fibe::{task, IntoTask};
+use future_pulse::Future;
+
+use {Frame, Buffer, Format, Rect, Rgba, TileGroup, group_rect};
+
+/// starts every serialized packet
+const MAGIC: &'static [u8; 4] = b"RTIL";
+/// the flag of packets whose data is run length encoded
+const COMPRESSED: u8 = 1;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn tag(format: Format) -> u8 {
+    match format {
+        Format::Rgba8 => 0,
+        Format::R32F => 1,
+        Format::Rg32F => 2,
+        Format::Rgb32F => 3,
+        Format::Rgba32F => 4
+    }
+}
+
+fn from_tag(tag: u8) -> io::Result<Format> {
+    match tag {
+        0 => Ok(Format::Rgba8),
+        1 => Ok(Format::R32F),
+        2 => Ok(Format::Rg32F),
+        3 => Ok(Format::Rgb32F),
+        4 => Ok(Format::Rgba32F),
+        _ => Err(invalid("unknown pixel format"))
+    }
+}
+
+/// the bytes of a pixel format
+fn pixel_size(format: Format) -> usize {
+    match format {
+        Format::Rgba8 | Format::R32F => 4,
+        Format::Rg32F => 8,
+        Format::Rgb32F => 12,
+        Format::Rgba32F => 16
+    }
+}
+
+fn put_u32(out: &mut Vec<u8>, v: u32) {
+    for k in 0..4 {
+        out.push((v >> (8 * k)) as u8);
+    }
+}
+
+fn get_u32(b: &[u8]) -> u32 {
+    b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+}
+
+fn put_f32(out: &mut Vec<u8>, v: f32) {
+    put_u32(out, unsafe { mem::transmute(v) });
+}
+
+fn get_f32(b: &[u8]) -> f32 {
+    unsafe { mem::transmute(get_u32(b)) }
+}
+
+/// a pixel type that can be sent in a `TilePacket`, the formats of
+/// `capabilities` all are. The bytes are little endian.
+pub trait StreamPixel: Copy {
+    fn format() -> Format;
+    fn encode(&self, out: &mut Vec<u8>);
+    /// `bytes` has the size of the format
+    fn decode(bytes: &[u8]) -> Self;
+}
+
+impl StreamPixel for Rgba<u8> {
+    fn format() -> Format { Format::Rgba8 }
+    fn encode(&self, out: &mut Vec<u8>) { out.extend(self.data.iter().cloned()); }
+    fn decode(b: &[u8]) -> Rgba<u8> { Rgba([b[0], b[1], b[2], b[3]]) }
+}
+
+impl StreamPixel for f32 {
+    fn format() -> Format { Format::R32F }
+    fn encode(&self, out: &mut Vec<u8>) { put_f32(out, *self); }
+    fn decode(b: &[u8]) -> f32 { get_f32(b) }
+}
+
+impl StreamPixel for [f32; 2] {
+    fn format() -> Format { Format::Rg32F }
+    fn encode(&self, out: &mut Vec<u8>) {
+        for v in self.iter() { put_f32(out, *v); }
+    }
+    fn decode(b: &[u8]) -> [f32; 2] { [get_f32(&b[0..]), get_f32(&b[4..])] }
+}
+
+impl StreamPixel for [f32; 3] {
+    fn format() -> Format { Format::Rgb32F }
+    fn encode(&self, out: &mut Vec<u8>) {
+        for v in self.iter() { put_f32(out, *v); }
+    }
+    fn decode(b: &[u8]) -> [f32; 3] { [get_f32(&b[0..]), get_f32(&b[4..]), get_f32(&b[8..])] }
+}
+
+impl StreamPixel for [f32; 4] {
+    fn format() -> Format { Format::Rgba32F }
+    fn encode(&self, out: &mut Vec<u8>) {
+        for v in self.iter() { put_f32(out, *v); }
+    }
+    fn decode(b: &[u8]) -> [f32; 4] {
+        [get_f32(&b[0..]), get_f32(&b[4..]), get_f32(&b[8..]), get_f32(&b[12..])]
+    }
+}
+
+/// the pixels of one tile group, the rows go from the top of `rect` to
+/// its bottom like in a `Buffer`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TilePacket {
+    pub format: Format,
+    /// where the pixels go in image coordinates, the groups along the
+    /// right and top edge of the frame are smaller than 32x32
+    pub rect: Rect,
+    /// `data` holds runs of a count byte followed by one pixel instead of
+    /// the pixels one after the other
+    pub compressed: bool,
+    pub data: Vec<u8>
+}
+
+impl TilePacket {
+    /// pack `pixels`, laid out row by row from the top left of `rect`
+    pub fn new<P: StreamPixel>(rect: Rect, pixels: &[P], compress: bool) -> TilePacket {
+        assert!(pixels.len() == (rect.width * rect.height) as usize);
+        let size = pixel_size(P::format());
+        let mut raw = Vec::with_capacity(pixels.len() * size);
+        for p in pixels.iter() {
+            p.encode(&mut raw);
+        }
+        let data = if compress {
+            let mut out = Vec::new();
+            let mut i = 0;
+            while i < raw.len() {
+                let pixel = &raw[i..i + size];
+                let mut run = 1;
+                while run < 255 && i + (run + 1) * size <= raw.len() &&
+                      &raw[i + run * size..i + (run + 1) * size] == pixel {
+                    run += 1;
+                }
+                out.push(run as u8);
+                out.extend(pixel.iter().cloned());
+                i += run * size;
+            }
+            out
+        } else {
+            raw
+        };
+
+        TilePacket {
+            format: P::format(),
+            rect: rect,
+            compressed: compress,
+            data: data
+        }
+    }
+
+    /// the pixels of the packet in the order they were packed, an error if
+    /// the packet holds another format or its data is cut short
+    pub fn pixels<P: StreamPixel>(&self) -> io::Result<Vec<P>> {
+        if self.format != P::format() {
+            return Err(invalid("the packet holds another pixel format"));
+        }
+        let size = pixel_size(self.format);
+        let count = (self.rect.width * self.rect.height) as usize;
+        let mut out = Vec::with_capacity(count);
+        if self.compressed {
+            for run in self.data.chunks(size + 1) {
+                if run.len() != size + 1 || out.len() + run[0] as usize > count {
+                    return Err(invalid("broken run"));
+                }
+                let p = P::decode(&run[1..]);
+                for _ in 0..run[0] {
+                    out.push(p);
+                }
+            }
+        } else {
+            out.extend(self.data.chunks(size).filter(|c| c.len() == size).map(|c| P::decode(c)));
+        }
+        if out.len() != count {
+            return Err(invalid("the packet is missing pixels"));
+        }
+        Ok(out)
+    }
+
+    /// copy the pixels to where they go in `dst`, which has the size of
+    /// the frame they were sent from
+    pub fn write_into<P: StreamPixel>(&self, dst: &mut Buffer<P>) -> io::Result<()> {
+        let r = self.rect;
+        let inside = |start: u32, len: u32, size: u32| start.checked_add(len).map(|end| end <= size).unwrap_or(false);
+        if !inside(r.x, r.width, dst.width) || !inside(r.y, r.height, dst.height) {
+            return Err(invalid("the packet is outside of the buffer"));
+        }
+        let pixels = try!(self.pixels::<P>());
+        for (i, p) in pixels.into_iter().enumerate() {
+            let i = i as u32;
+            dst.put_pixel(r.x + i % r.width, r.y + i / r.width, p);
+        }
+        Ok(())
+    }
+
+    /// the magic, the format tag and flags, the rect and the length of the
+    /// data as little endian u32 and then the data
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut head = Vec::with_capacity(26);
+        head.extend(MAGIC.iter().cloned());
+        head.push(tag(self.format));
+        head.push(if self.compressed { COMPRESSED } else { 0 });
+        for v in [self.rect.x, self.rect.y, self.rect.width, self.rect.height, self.data.len() as u32].iter() {
+            put_u32(&mut head, *v);
+        }
+        try!(out.write_all(&head));
+        out.write_all(&self.data)
+    }
+
+    /// read a packet written by `write_to`
+    pub fn read_from<R: Read>(src: &mut R) -> io::Result<TilePacket> {
+        let mut head = [0u8; 26];
+        try!(read_exact(src, &mut head));
+        if &head[0..4] != &MAGIC[..] {
+            return Err(invalid("not a tile packet"));
+        }
+        let format = try!(from_tag(head[4]));
+        let u = |i: usize| get_u32(&head[6 + 4 * i..]);
+        let rect = Rect::new(u(0), u(1), u(2), u(3));
+        let len = u(4) as usize;
+        // a group never holds more than 32x32 pixels, twice that leaves
+        // room for the run counts
+        if rect.width > 32 || rect.height > 32 || len > 2 * 32 * 32 * pixel_size(format) {
+            return Err(invalid("the packet is too large"));
+        }
+        // groups start every 32 pixels across, only the ones along the top
+        // of the frame are cut short, see `group_rect`
+        if rect.width == 0 || rect.height == 0 || rect.x % 32 != 0 || (rect.height != 32 && rect.y != 0) {
+            return Err(invalid("the packet is not a tile group"));
+        }
+        let mut data = vec![0; len];
+        try!(read_exact(src, &mut data));
+        Ok(TilePacket {
+            format: format,
+            rect: rect,
+            compressed: head[5] & COMPRESSED != 0,
+            data: data
+        })
+    }
+}
+
+fn read_exact<R: Read>(src: &mut R, mut buf: &mut [u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        match try!(src.read(buf)) {
+            0 => return Err(io::Error::new(io::ErrorKind::Other, "the stream ended inside of a packet")),
+            n => { let rest = buf; buf = &mut rest[n..]; }
+        }
+    }
+    Ok(())
+}
+
+/// takes the tile groups of `Frame::stream_tiles` as they are finished,
+/// it is called from the worker threads in whatever order the groups are
+/// done in
+pub trait TileSink: Send + Sync {
+    fn tile(&self, packet: TilePacket);
+}
+
+/// the packets gathered in memory
+impl TileSink for Mutex<Vec<TilePacket>> {
+    fn tile(&self, packet: TilePacket) {
+        self.lock().unwrap().push(packet);
+    }
+}
+
+/// writes the packets to a socket, a file or anything else. After the
+/// first error the packets that follow are dropped.
+pub struct WriteSink<W> {
+    out: Mutex<(W, Option<io::Error>)>
+}
+
+impl<W: Write + Send> WriteSink<W> {
+    pub fn new(out: W) -> WriteSink<W> {
+        WriteSink {
+            out: Mutex::new((out, None))
+        }
+    }
+
+    /// the writer back, or the error that stopped the packets
+    pub fn into_inner(self) -> io::Result<W> {
+        let (out, error) = self.out.into_inner().unwrap();
+        match error {
+            Some(e) => Err(e),
+            None => Ok(out)
+        }
+    }
+}
+
+impl<W: Write + Send> TileSink for WriteSink<W> {
+    fn tile(&self, packet: TilePacket) {
+        let mut guard = self.out.lock().unwrap();
+        let &mut (ref mut out, ref mut error) = &mut *guard;
+        if error.is_none() {
+            if let Err(e) = packet.write_to(out) {
+                *error = Some(e);
+            }
+        }
+    }
+}
+
+impl<P: StreamPixel+Sync+Send+'static> Frame<P> {
+    /// send every tile group to `sink` once the work pending on it is done,
+    /// the groups that finish first are sent first. With `compress` the
+    /// pixels are run length encoded. Every packet is delivered by the time
+    /// the next `flush` returns.
+    pub fn stream_tiles(&mut self, sink: Arc<TileSink>, compress: bool) {
+        let (w, h) = (self.width, self.height);
+        for (x, row) in self.tile.iter_mut().enumerate() {
+            for (y, tile) in row.iter_mut().enumerate() {
+                let (mut new, set) = Future::new();
+                mem::swap(tile, &mut new);
+                let signal = new.signal();
+                let sink = sink.clone();
+                task(move |_| {
+                    let t = new.get();
+                    sink.tile(encode(&t, x as u32, y as u32, w, h, compress));
+                    set.set(t);
+                }).after(signal).start(&mut self.pool);
+            }
+        }
+    }
+}
+
+/// the visible pixels of group `x`, `y` of a `width` by `height` frame
+fn encode<P: StreamPixel>(group: &TileGroup<P>, x: u32, y: u32, width: u32, height: u32,
+                          compress: bool) -> TilePacket {
+    let rect = group_rect(x, y, width, height);
+    let mut pixels = Vec::with_capacity((rect.width * rect.height) as usize);
+    for row in 0..rect.height {
+        // rows in the group count from its bottom
+        let gy = height - 1 - (rect.y + row) - y * 32;
+        for gx in 0..rect.width {
+            pixels.push(group.get(gx, gy));
+        }
+    }
+    TilePacket::new(rect, &pixels, compress)
+}
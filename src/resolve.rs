@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use {Frame, Buffer, Lerp, Error, error};
+use tile::Get;
+
+/// reduce the `factor`x`factor` block of samples that covers a pixel into
+/// a single value, the samples are stored row by row from the top left
+pub trait Resolve<P> {
+    fn resolve(&self, samples: &[P], factor: u32) -> P;
+}
+
+impl<P, F> Resolve<P> for F where F: Fn(&[P], u32) -> P {
+    #[inline]
+    fn resolve(&self, samples: &[P], factor: u32) -> P {
+        self(samples, factor)
+    }
+}
+
+/// every sample has the same weight
+#[derive(Clone, Copy, Debug)]
+pub struct BoxResolve;
+
+impl<P: Copy + Lerp> Resolve<P> for BoxResolve {
+    #[inline]
+    fn resolve(&self, samples: &[P], _: u32) -> P {
+        let mut out = samples[0];
+        for (i, s) in samples.iter().enumerate().skip(1) {
+            out = out.lerp(*s, 1. / (i + 1) as f32);
+        }
+        out
+    }
+}
+
+/// samples are weighted by their distance from the center of the pixel
+#[derive(Clone, Copy, Debug)]
+pub struct TentResolve;
+
+impl<P: Copy + Lerp> Resolve<P> for TentResolve {
+    #[inline]
+    fn resolve(&self, samples: &[P], factor: u32) -> P {
+        let center = factor as f32 / 2.;
+        let tent = |i: u32| 1. - ((i as f32 + 0.5 - center).abs() / center) * 0.5;
+
+        let mut out = samples[0];
+        let mut total = 0.;
+        for (i, s) in samples.iter().enumerate() {
+            let i = i as u32;
+            let w = tent(i % factor) * tent(i / factor);
+            total += w;
+            out = out.lerp(*s, w / total);
+        }
+        out
+    }
+}
+
+/// the samples weighted by `RasterBackend::resolve_weights`
+struct Weighted(Vec<f32>);
+
+impl<P: Copy + Lerp> Resolve<P> for Weighted {
+    #[inline]
+    fn resolve(&self, samples: &[P], _: u32) -> P {
+        let mut out = samples[0];
+        let mut total = 0.;
+        for (s, &w) in samples.iter().zip(self.0.iter()) {
+            total += w;
+            if total > 0. {
+                out = out.lerp(*s, w / total);
+            }
+        }
+        out
+    }
+}
+
+struct Resolver<P, R> {
+    src: Buffer<P>,
+    factor: u32,
+    height: u32,
+    resolve: R
+}
+
+impl<P: Copy, R: Resolve<P>> Get<P> for Resolver<P, R> {
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        let f = self.factor;
+        let (x, y) = (x * f, (self.height - 1 - y) * f);
+        let mut samples = Vec::with_capacity((f * f) as usize);
+        for sy in 0..f {
+            for sx in 0..f {
+                samples.push(self.src.get_pixel(x + sx, y + sy));
+            }
+        }
+        Some(self.resolve.resolve(&samples, f))
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// resolve a frame rendered at `factor` times the resolution of `dst`,
+    /// the individual samples can be read from this frame before resolving
+    pub fn resolve<R>(&mut self, dst: &mut Frame<P>, factor: u32, resolve: R)
+        where R: Resolve<P> + Send + Sync + 'static {
+
+        assert!(factor > 0);
+        assert!(self.width == dst.width * factor);
+        assert!(self.height == dst.height * factor);
+
+        let resolver = Resolver {
+            src: self.to_buffer(),
+            factor: factor,
+            height: dst.height,
+            resolve: resolve
+        };
+        dst.load(Arc::new(resolver));
+    }
+
+    /// like `resolve` but this frame has to be exactly `factor` times the
+    /// size of `dst`
+    pub fn try_resolve<R>(&mut self, dst: &mut Frame<P>, factor: u32, resolve: R) -> error::Result<()>
+        where R: Resolve<P> + Send + Sync + 'static {
+
+        if factor == 0 {
+            return Err(Error::InvalidSize { width: dst.width, height: dst.height });
+        }
+        try!(error::check_size((dst.width * factor, dst.height * factor), (self.width, self.height)));
+        self.resolve(dst, factor, resolve);
+        Ok(())
+    }
+}
+
+impl<P: Copy+Lerp+Sync+Send+'static> Frame<P> {
+    /// `resolve` with the sample weights of the backend of this frame, see
+    /// `RasterBackend::resolve_weights`
+    pub fn resolve_backend(&mut self, dst: &mut Frame<P>, factor: u32) {
+        let weights = self.backend.resolve_weights(factor);
+        assert!(weights.len() == (factor * factor) as usize);
+        self.resolve(dst, factor, Weighted(weights));
+    }
+}
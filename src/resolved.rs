@@ -0,0 +1,165 @@
+//! finished tile groups shared by any number of readers
+//!
+//! `map` takes its source tile by tile and hands each back when done, so
+//! two passes reading the same frame run one after the other. A frame
+//! turned into a `Resolved` gives its groups away instead, and every pass
+//! reading them starts on a group as soon as it is finished.
+
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use fibe::{Frontend, task, IntoTask};
+use pulse::Signal;
+
+use {Frame, Buffer, Mapping, MappingAt, TileGroup};
+use tile::Clip;
+
+/// one tile group, set once by the task that waits for the frame
+struct Slot<P> {
+    ready: Signal,
+    group: Mutex<Option<Arc<Box<TileGroup<P>>>>>
+}
+
+impl<P> Slot<P> {
+    /// only valid once `ready` fired
+    fn get(&self) -> Arc<Box<TileGroup<P>>> {
+        self.group.lock().unwrap().clone().expect("the tile group is not resolved")
+    }
+}
+
+/// the read only tile groups of a frame, see `Frame::into_resolved`.
+/// Cloning it is cheap and shares the groups.
+pub struct Resolved<P> {
+    pub width: u32,
+    pub height: u32,
+    slots: Vec<Vec<Arc<Slot<P>>>>,
+    /// the workers that finish the last writes to the groups
+    _pool: Option<Arc<Mutex<Frontend>>>
+}
+
+impl<P> Clone for Resolved<P> {
+    fn clone(&self) -> Resolved<P> {
+        Resolved {
+            width: self.width,
+            height: self.height,
+            slots: self.slots.clone(),
+            _pool: self._pool.clone()
+        }
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Resolved<P> {
+    /// groups that are already finished
+    pub fn from_groups(width: u32, height: u32, groups: &[Vec<Arc<Box<TileGroup<P>>>>]) -> Resolved<P> {
+        Resolved {
+            width: width,
+            height: height,
+            slots: groups.iter().map(|row| row.iter().map(|g| Arc::new(Slot {
+                ready: Signal::pulsed(),
+                group: Mutex::new(Some(g.clone()))
+            })).collect()).collect(),
+            _pool: None
+        }
+    }
+
+    /// wait for the groups and read them back, laid out like
+    /// `Frame::to_buffer`
+    pub fn to_buffer(&self) -> Buffer<P> {
+        // the buffer starts out with the clear color of the first group,
+        // every pixel is overwritten
+        let first = &self.slots[0][0];
+        first.ready.clone().wait().unwrap();
+        let fill = first.get().clear_color();
+        let mut out = Clip::new(Buffer::new(self.width, self.height, fill), self.width, self.height);
+
+        for (x, row) in self.slots.iter().enumerate() {
+            for (y, slot) in row.iter().enumerate() {
+                slot.ready.clone().wait().unwrap();
+                slot.get().write((x*32) as u32, (y*32) as u32, &mut out);
+            }
+        }
+        out.inner
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// give the tile groups away to be read by any number of passes, each
+    /// group is shared without a copy as soon as the work pending on it is
+    /// done
+    pub fn into_resolved(self) -> Resolved<P> {
+        let Frame { width, height, tile, pool, .. } = self;
+        let mut pool = pool;
+
+        let slots = tile.into_iter().map(|row| row.into_iter().map(|future| {
+            let (ready, pulse) = Signal::new();
+            let slot = Arc::new(Slot {
+                ready: ready,
+                group: Mutex::new(None)
+            });
+            let set = slot.clone();
+            let signal = future.signal();
+            task(move |_| {
+                *set.group.lock().unwrap() = Some(Arc::new(future.get()));
+                pulse.pulse();
+            }).after(signal).start(&mut pool);
+            slot
+        }).collect()).collect();
+
+        Resolved {
+            width: width,
+            height: height,
+            slots: slots,
+            _pool: Some(Arc::new(Mutex::new(pool)))
+        }
+    }
+
+    /// like `map` but reading shared groups, any number of passes can read
+    /// `src` at the same time
+    pub fn map_resolved<S, F>(&mut self, src: &Resolved<S>, pixel: F)
+        where F: Mapping<S, Out=P> + Sized + Send + Sync + 'static,
+              S: Send + Sync + 'static + Copy {
+
+        assert!(src.width == self.width);
+        assert!(src.height == self.height);
+        let pixel = Arc::new(pixel);
+
+        for (row, src_row) in self.tile.iter_mut().zip(src.slots.iter()) {
+            for (tile, slot) in row.iter_mut().zip(src_row.iter()) {
+                let (mut new, set) = ::future_pulse::Future::new();
+                mem::swap(tile, &mut new);
+                let (slot, pixel) = (slot.clone(), pixel.clone());
+                let (s0, s1) = (new.signal(), slot.ready.clone());
+                task(move |_| {
+                    let mut dst = new.get();
+                    dst.map(&**slot.get(), &*pixel);
+                    set.set(dst);
+                }).after(s0).after(s1).start(&mut self.pool);
+            }
+        }
+    }
+
+    /// like `map_at` but reading shared groups, see `map_resolved`
+    pub fn map_at_resolved<S, F>(&mut self, src: &Resolved<S>, pixel: F)
+        where F: MappingAt<S, Out=P> + Sized + Send + Sync + 'static,
+              S: Send + Sync + 'static + Copy {
+
+        assert!(src.width == self.width);
+        assert!(src.height == self.height);
+        let pixel = Arc::new(pixel);
+        let (w, h) = (self.width, self.height);
+
+        for (x, (row, src_row)) in self.tile.iter_mut().zip(src.slots.iter()).enumerate() {
+            for (y, (tile, slot)) in row.iter_mut().zip(src_row.iter()).enumerate() {
+                let (mut new, set) = ::future_pulse::Future::new();
+                mem::swap(tile, &mut new);
+                let (slot, pixel) = (slot.clone(), pixel.clone());
+                let (s0, s1) = (new.signal(), slot.ready.clone());
+                task(move |_| {
+                    let mut dst = new.get();
+                    dst.map_at(&**slot.get(), (x*32) as u32, (y*32) as u32, w, h, &*pixel);
+                    set.set(dst);
+                }).after(s0).after(s1).start(&mut self.pool);
+            }
+        }
+    }
+}
@@ -0,0 +1,514 @@
+//! a small retained scene for previewing meshes without assembling the
+//! draw iterators by hand
+
+use std::cmp::Ordering;
+
+use cgmath::{Matrix, Matrix4, Vector4};
+use genmesh::Triangle;
+
+use {Frame, DrawStats, Rgba, IntoMatrix};
+use shaders::{Pbr, PbrVertex};
+use animation::{Animation, Trs};
+use meshlet::Meshlet;
+
+/// the inverse transpose of `model`, which keeps normals perpendicular to
+/// the surfaces under non uniform scales
+fn normal_matrix(model: &Matrix4<f32>) -> Matrix4<f32> {
+    model.invert().map(|m| m.transpose()).unwrap_or(*model)
+}
+
+/// indexed triangle geometry in model space
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    pub positions: Vec<[f32; 3]>,
+    /// optional, faces point along +z without them
+    pub normals: Vec<[f32; 3]>,
+    /// optional, all zero without them
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u32>
+}
+
+impl Mesh {
+    /// the triangles of the mesh ready for `Frame::raster` with a `Pbr`
+    /// fragment. `model` places the mesh in the world and `view_proj` takes
+    /// the world to clip space.
+    pub fn triangles<A: IntoMatrix, B: IntoMatrix>(&self, model: A, view_proj: B) -> Vec<Triangle<PbrVertex>> {
+        let (model, view_proj) = (&model.into_matrix(), &view_proj.into_matrix());
+        let normal = &normal_matrix(model);
+        self.indices.chunks(3)
+            .filter(|t| t.len() == 3)
+            .map(|t| Triangle::new(self.vertex(t[0], model, normal, view_proj),
+                                   self.vertex(t[1], model, normal, view_proj),
+                                   self.vertex(t[2], model, normal, view_proj)))
+            .collect()
+    }
+
+    /// `triangles` for a mesh split into `meshlets`, the meshlets outside
+    /// of the camera or facing away from it are skipped before any of their
+    /// vertices are transformed. The number of skipped meshlets comes
+    /// second.
+    pub fn meshlet_triangles<M: IntoMatrix>(&self, meshlets: &[Meshlet], model: M,
+                                            camera: &Camera) -> (Vec<Triangle<PbrVertex>>, usize) {
+        let model = &model.into_matrix();
+        let normal = &normal_matrix(model);
+        let view_proj = camera.view_proj();
+        let frustum = Frustum::new(&view_proj);
+        let scale = max_scale(model);
+        // the cones are in model space, so the eye is taken there
+        let eye = model.invert().map(|inv| {
+            let e = inv.mul_v(&Vector4::new(camera.position[0], camera.position[1], camera.position[2], 1.));
+            [e.x / e.w, e.y / e.w, e.z / e.w]
+        });
+
+        let mut triangles = Vec::new();
+        let mut culled = 0;
+        for m in meshlets.iter() {
+            let c = model.mul_v(&Vector4::new(m.center[0], m.center[1], m.center[2], 1.));
+            let outside = !frustum.sphere_visible([c.x, c.y, c.z], m.radius * scale);
+            if outside || eye.map(|e| m.backfacing(e)).unwrap_or(false) {
+                culled += 1;
+                continue;
+            }
+            triangles.extend(m.indices.chunks(3).map(|t| {
+                Triangle::new(self.vertex(t[0], model, normal, &view_proj),
+                              self.vertex(t[1], model, normal, &view_proj),
+                              self.vertex(t[2], model, normal, &view_proj))
+            }));
+        }
+        (triangles, culled)
+    }
+
+    #[inline]
+    fn vertex(&self, i: u32, model: &Matrix4<f32>, normal: &Matrix4<f32>, view_proj: &Matrix4<f32>) -> PbrVertex {
+        let i = i as usize;
+        let p = self.positions[i];
+        let n = self.normals.get(i).cloned().unwrap_or([0., 0., 1.]);
+        let uv = self.uvs.get(i).cloned().unwrap_or([0., 0.]);
+
+        let world = model.mul_v(&Vector4::new(p[0], p[1], p[2], 1.));
+        let normal = normal.mul_v(&Vector4::new(n[0], n[1], n[2], 0.));
+        let clip = view_proj.mul_v(&world);
+        (clip.into_fixed(), [world.x, world.y, world.z], [normal.x, normal.y, normal.z], uv)
+    }
+
+    /// a sphere around all the positions, the center of the box is used so
+    /// it is not the tightest one
+    pub fn bounds(&self) -> ([f32; 3], f32) {
+        if self.positions.is_empty() {
+            return ([0.; 3], 0.);
+        }
+        let (mut min, mut max) = (self.positions[0], self.positions[0]);
+        for p in self.positions.iter() {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        let c = [(min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5, (min[2] + max[2]) * 0.5];
+        let r = self.positions.iter().fold(0f32, |r, p| {
+            let d = [p[0] - c[0], p[1] - c[1], p[2] - c[2]];
+            r.max(d[0] * d[0] + d[1] * d[1] + d[2] * d[2])
+        });
+        (c, r.sqrt())
+    }
+}
+
+/// where the scene is seen from
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub view: Matrix4<f32>,
+    pub proj: Matrix4<f32>,
+    /// world space position, used for the specular highlights
+    pub position: [f32; 3]
+}
+
+impl Camera {
+    /// a camera from any matrix type, see `IntoMatrix`
+    pub fn new<V: IntoMatrix, P: IntoMatrix>(view: V, proj: P, position: [f32; 3]) -> Camera {
+        Camera {
+            view: view.into_matrix(),
+            proj: proj.into_matrix(),
+            position: position
+        }
+    }
+
+    pub fn view_proj(&self) -> Matrix4<f32> {
+        self.proj.mul_m(&self.view)
+    }
+}
+
+/// the largest factor `m` scales a length by
+fn max_scale(m: &Matrix4<f32>) -> f32 {
+    let f = m.into_fixed();
+    (0..3).map(|i| (f[i][0] * f[i][0] + f[i][1] * f[i][1] + f[i][2] * f[i][2]).sqrt())
+          .fold(0f32, |a, b| a.max(b))
+}
+
+/// the six planes of the clip volume in world space, inside is positive
+struct Frustum {
+    planes: [[f32; 4]; 6]
+}
+
+impl Frustum {
+    fn new(m: &Matrix4<f32>) -> Frustum {
+        let c = m.into_fixed();
+        let row = |i: usize| [c[0][i], c[1][i], c[2][i], c[3][i]];
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+        let add = |a: [f32; 4], b: [f32; 4], s: f32| [a[0] + s * b[0], a[1] + s * b[1], a[2] + s * b[2], a[3] + s * b[3]];
+        Frustum {
+            planes: [add(r3, r0, 1.), add(r3, r0, -1.),
+                     add(r3, r1, 1.), add(r3, r1, -1.),
+                     add(r3, r2, 1.), add(r3, r2, -1.)]
+        }
+    }
+
+    fn sphere_visible(&self, c: [f32; 3], r: f32) -> bool {
+        self.planes.iter().all(|p| {
+            let len = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+            p[0] * c[0] + p[1] * c[1] + p[2] * c[2] + p[3] >= -r * len
+        })
+    }
+}
+
+/// a node of the hierarchy, the transform is relative to the parent
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub parent: Option<usize>,
+    pub transform: Matrix4<f32>,
+    /// nodes with a pose have their transform rebuilt from it when the
+    /// scene is animated
+    pub pose: Option<Trs>,
+    /// indices into `Scene::meshes` and `Scene::materials`
+    pub mesh: Option<(usize, usize)>
+}
+
+/// the counters of one `Scene::render`, one draw per batch
+#[derive(Clone, Debug)]
+pub struct SceneStats {
+    pub draws: Vec<DrawStats>,
+    /// nodes with a mesh that were outside of the camera
+    pub culled_nodes: usize,
+    /// meshlets of the visible nodes that were outside of the camera or
+    /// facing away from it
+    pub culled_meshlets: usize
+}
+
+impl SceneStats {
+    pub fn triangles(&self) -> usize {
+        self.draws.iter().map(|d| d.triangles).fold(0, |a, b| a + b)
+    }
+
+    /// complete after the frame is flushed
+    pub fn fragments(&self) -> usize {
+        self.draws.iter().map(|d| d.fragments()).fold(0, |a, b| a + b)
+    }
+}
+
+/// nodes, meshes and materials. A node can only be parented to a node that
+/// was added before it, which keeps flattening a single pass.
+#[derive(Clone)]
+pub struct Scene {
+    pub nodes: Vec<Node>,
+    pub meshes: Vec<Mesh>,
+    /// the meshlets of `meshes` at the same index, meshes without any are
+    /// drawn whole
+    pub meshlets: Vec<Vec<Meshlet>>,
+    /// the `Mesh::bounds` of `meshes` at the same index, worked out once
+    /// by `add_mesh`
+    pub bounds: Vec<([f32; 3], f32)>,
+    pub materials: Vec<Pbr>,
+    pub animations: Vec<Animation>,
+    /// seconds of animation played so far
+    pub time: f32
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene {
+            nodes: Vec::new(),
+            meshes: Vec::new(),
+            meshlets: Vec::new(),
+            bounds: Vec::new(),
+            materials: Vec::new(),
+            animations: Vec::new(),
+            time: 0.
+        }
+    }
+
+    pub fn add_mesh(&mut self, mesh: Mesh) -> usize {
+        self.bounds.push(mesh.bounds());
+        self.meshes.push(mesh);
+        self.meshlets.push(Vec::new());
+        self.meshes.len() - 1
+    }
+
+    /// draw `mesh` through its meshlets, which are culled one by one, see
+    /// `meshlet::build_meshlets` or `Frame::preprocess`
+    pub fn set_meshlets(&mut self, mesh: usize, meshlets: Vec<Meshlet>) {
+        assert!(mesh < self.meshes.len());
+        while self.meshlets.len() < self.meshes.len() {
+            self.meshlets.push(Vec::new());
+        }
+        self.meshlets[mesh] = meshlets;
+    }
+
+    pub fn add_material(&mut self, material: Pbr) -> usize {
+        self.materials.push(material);
+        self.materials.len() - 1
+    }
+
+    /// add a node and return its index
+    pub fn add_node<M: IntoMatrix>(&mut self, parent: Option<usize>, transform: M, mesh: Option<(usize, usize)>) -> usize {
+        if let Some(p) = parent {
+            assert!(p < self.nodes.len(), "the parent has to be added first");
+        }
+        self.nodes.push(Node {
+            parent: parent,
+            transform: transform.into_matrix(),
+            pose: None,
+            mesh: mesh
+        });
+        self.nodes.len() - 1
+    }
+
+    /// add a node placed by a translation, rotation and scale, only these
+    /// nodes can be animated
+    pub fn add_node_trs(&mut self, parent: Option<usize>, pose: Trs, mesh: Option<(usize, usize)>) -> usize {
+        let node = self.add_node(parent, pose.matrix(), mesh);
+        self.nodes[node].pose = Some(pose);
+        node
+    }
+
+    pub fn add_animation(&mut self, animation: Animation) -> usize {
+        for c in animation.channels.iter() {
+            assert!(self.nodes[c.node].pose.is_some(), "animated nodes need a pose");
+        }
+        self.animations.push(animation);
+        self.animations.len() - 1
+    }
+
+    /// move the animations `dt` seconds forward and update the transforms
+    /// of the nodes they drive
+    pub fn advance(&mut self, dt: f32) {
+        self.time += dt;
+        if self.animations.is_empty() {
+            return;
+        }
+
+        let mut poses: Vec<Trs> = self.nodes.iter().map(|n| n.pose.unwrap_or(Trs::identity())).collect();
+        for a in self.animations.iter() {
+            a.apply(a.local_time(self.time), &mut poses);
+        }
+        for (node, pose) in self.nodes.iter_mut().zip(poses.into_iter()) {
+            if node.pose.is_some() {
+                node.pose = Some(pose);
+                node.transform = pose.matrix();
+            }
+        }
+    }
+
+    /// the transform of every node from its model space to the world
+    pub fn world_transforms(&self) -> Vec<Matrix4<f32>> {
+        let mut world: Vec<Matrix4<f32>> = Vec::with_capacity(self.nodes.len());
+        for node in self.nodes.iter() {
+            let m = match node.parent {
+                Some(p) => world[p].mul_m(&node.transform),
+                None => node.transform
+            };
+            world.push(m);
+        }
+        world
+    }
+
+    /// the bounds of mesh `index`, meshes pushed without `add_mesh` have
+    /// theirs worked out every time
+    fn mesh_bounds(&self, index: usize) -> ([f32; 3], f32) {
+        match self.bounds.get(index) {
+            Some(&bounds) => bounds,
+            None => self.meshes[index].bounds()
+        }
+    }
+
+    /// a sphere in world space around the meshes of all the nodes, the
+    /// center of the box around their spheres is used
+    pub fn world_bounds(&self) -> ([f32; 3], f32) {
+        let spheres: Vec<([f32; 3], f32)> = self.nodes.iter().zip(self.world_transforms().iter())
+            .filter_map(|(node, m)| node.mesh.map(|(mesh, _)| {
+                let (c, r) = self.mesh_bounds(mesh);
+                let center = m.mul_v(&Vector4::new(c[0], c[1], c[2], 1.));
+                ([center.x, center.y, center.z], r * max_scale(m))
+            })).collect();
+        if spheres.is_empty() {
+            return ([0.; 3], 0.);
+        }
+
+        let (mut min, mut max) = ([::std::f32::INFINITY; 3], [::std::f32::NEG_INFINITY; 3]);
+        for &(c, r) in spheres.iter() {
+            for i in 0..3 {
+                min[i] = min[i].min(c[i] - r);
+                max[i] = max[i].max(c[i] + r);
+            }
+        }
+        let c = [(min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5, (min[2] + max[2]) * 0.5];
+        let r = spheres.iter().fold(0f32, |r, &(s, sr)| {
+            let d = [s[0] - c[0], s[1] - c[1], s[2] - c[2]];
+            r.max((d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt() + sr)
+        });
+        (c, r)
+    }
+
+    /// draw every visible mesh into `frame`. Nodes whose bounds are outside
+    /// of the camera are skipped and the rest goes through a `RenderQueue`.
+    pub fn render(&self, frame: &mut Frame<Rgba<u8>>, camera: &Camera) -> SceneStats {
+        self.render_clipped(frame, camera, &[])
+    }
+
+    /// `render` with the triangles cut by the clip space `planes`, see
+    /// `Frame::raster_clipped`
+    pub fn render_clipped(&self, frame: &mut Frame<Rgba<u8>>, camera: &Camera,
+                          planes: &[[f32; 4]]) -> SceneStats {
+        let view_proj = camera.view_proj();
+        let frustum = Frustum::new(&view_proj);
+        let world = self.world_transforms();
+        let eye = camera.position;
+
+        let mut queue = RenderQueue::new();
+        let mut culled = 0;
+        let mut culled_meshlets = 0;
+        for (node, m) in self.nodes.iter().zip(world.iter()) {
+            let (index, mesh, material) = match node.mesh {
+                Some((mesh, material)) => (mesh, &self.meshes[mesh], material),
+                None => continue
+            };
+
+            let (c, r) = self.mesh_bounds(index);
+            let center = m.mul_v(&Vector4::new(c[0], c[1], c[2], 1.));
+            if !frustum.sphere_visible([center.x, center.y, center.z], r * max_scale(m)) {
+                culled += 1;
+                continue;
+            }
+
+            let d = [center.x - eye[0], center.y - eye[1], center.z - eye[2]];
+            let distance = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+            let triangles = match self.meshlets.get(index) {
+                Some(meshlets) if !meshlets.is_empty() => {
+                    let (triangles, n) = mesh.meshlet_triangles(meshlets, m, camera);
+                    culled_meshlets += n;
+                    triangles
+                }
+                _ => mesh.triangles(m, &view_proj)
+            };
+            queue.push(material, self.materials[material].translucent(), distance, triangles);
+        }
+
+        // translucent surfaces are tested against the depth buffer but do
+        // not write it, or the ones behind them would be dropped
+        let depth_write = frame.depth_write();
+        let draws = queue.into_batches().into_iter().map(|(material, tris)| {
+            let mut material = self.materials[material].clone();
+            material.eye = eye;
+            frame.set_depth_write(depth_write && !material.translucent());
+            if planes.is_empty() {
+                frame.raster(tris.into_iter(), material)
+            } else {
+                frame.raster_clipped(tris.into_iter(), planes, material)
+            }
+        }).collect();
+        frame.set_depth_write(depth_write);
+
+        SceneStats {
+            draws: draws,
+            culled_nodes: culled,
+            culled_meshlets: culled_meshlets
+        }
+    }
+}
+
+/// nearest first, a NaN distance from a broken transform counts as the
+/// farthest
+fn by_distance(a: f32, b: f32) -> Ordering {
+    match a.partial_cmp(&b) {
+        Some(order) => order,
+        None => a.is_nan().cmp(&b.is_nan())
+    }
+}
+
+struct QueueItem {
+    material: usize,
+    distance: f32,
+    triangles: Vec<Triangle<PbrVertex>>
+}
+
+/// orders the draws of a frame. Opaque draws are grouped by material, the
+/// groups go nearest first and so do the draws inside them, which lets the
+/// depth test reject as much as possible. Translucent draws come after all
+/// of them from back to front so they blend over the right things.
+pub struct RenderQueue {
+    opaque: Vec<QueueItem>,
+    translucent: Vec<QueueItem>
+}
+
+impl RenderQueue {
+    pub fn new() -> RenderQueue {
+        RenderQueue {
+            opaque: Vec::new(),
+            translucent: Vec::new()
+        }
+    }
+
+    /// queue the triangles of one draw, `distance` is from the camera
+    pub fn push(&mut self, material: usize, translucent: bool, distance: f32, triangles: Vec<Triangle<PbrVertex>>) {
+        let item = QueueItem {
+            material: material,
+            distance: distance,
+            triangles: triangles
+        };
+        if translucent {
+            self.translucent.push(item);
+        } else {
+            self.opaque.push(item);
+        }
+    }
+
+    /// the sorted draws, neighbouring draws with the same material are
+    /// merged into one batch
+    pub fn into_batches(self) -> Vec<(usize, Vec<Triangle<PbrVertex>>)> {
+        let RenderQueue { mut opaque, mut translucent } = self;
+
+        // the nearest draw of each material decides where its group goes
+        let mut nearest: Vec<(usize, f32)> = Vec::new();
+        for item in opaque.iter() {
+            match nearest.iter().position(|&(m, _)| m == item.material) {
+                Some(i) => nearest[i].1 = nearest[i].1.min(item.distance),
+                None => nearest.push((item.material, item.distance))
+            }
+        }
+        let group = |m: usize| nearest.iter().find(|&&(n, _)| n == m).unwrap().1;
+        opaque.sort_by(|a, b| {
+            let (ga, gb) = (group(a.material), group(b.material));
+            match by_distance(ga, gb) {
+                Ordering::Equal => {}
+                order => return order
+            }
+            if a.material != b.material {
+                return a.material.cmp(&b.material);
+            }
+            by_distance(a.distance, b.distance)
+        });
+        translucent.sort_by(|a, b| by_distance(b.distance, a.distance));
+
+        let mut out: Vec<(usize, Vec<Triangle<PbrVertex>>)> = Vec::new();
+        for item in opaque.into_iter().chain(translucent.into_iter()) {
+            let merge = match out.last() {
+                Some(&(m, _)) => m == item.material,
+                None => false
+            };
+            if merge {
+                out.last_mut().unwrap().1.extend(item.triangles.into_iter());
+            } else {
+                out.push((item.material, item.triangles));
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use {Frame, Buffer};
+
+const FAR: i32 = 9999;
+
+#[derive(Clone, Copy)]
+struct Offset(i32, i32);
+
+impl Offset {
+    #[inline]
+    fn dist_sq(self) -> i32 {
+        self.0 * self.0 + self.1 * self.1
+    }
+}
+
+/// a grid of offsets to the closest seed pixel, filled using 8SSEDT
+struct Grid {
+    w: i32,
+    h: i32,
+    cells: Vec<Offset>
+}
+
+impl Grid {
+    #[inline]
+    fn get(&self, x: i32, y: i32) -> Offset {
+        if x < 0 || y < 0 || x >= self.w || y >= self.h {
+            Offset(FAR, FAR)
+        } else {
+            self.cells[(y * self.w + x) as usize]
+        }
+    }
+
+    #[inline]
+    fn compare(&mut self, x: i32, y: i32, ox: i32, oy: i32) {
+        let other = self.get(x + ox, y + oy);
+        let other = Offset(other.0 + ox, other.1 + oy);
+        let i = (y * self.w + x) as usize;
+        if other.dist_sq() < self.cells[i].dist_sq() {
+            self.cells[i] = other;
+        }
+    }
+
+    fn propagate(&mut self) {
+        for y in 0..self.h {
+            for x in 0..self.w {
+                self.compare(x, y, -1,  0);
+                self.compare(x, y,  0, -1);
+                self.compare(x, y, -1, -1);
+                self.compare(x, y,  1, -1);
+            }
+            for x in (0..self.w).rev() {
+                self.compare(x, y, 1, 0);
+            }
+        }
+
+        for y in (0..self.h).rev() {
+            for x in (0..self.w).rev() {
+                self.compare(x, y,  1, 0);
+                self.compare(x, y,  0, 1);
+                self.compare(x, y, -1, 1);
+                self.compare(x, y,  1, 1);
+            }
+            for x in 0..self.w {
+                self.compare(x, y, -1, 0);
+            }
+        }
+    }
+
+    #[inline]
+    fn distance(&self, i: usize) -> f32 {
+        (self.cells[i].dist_sq() as f32).sqrt()
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// write the signed distance field of the shape made of the pixels
+    /// that `inside` accepts into `dst`. Distances are in pixels, positive
+    /// inside of the shape, and clamped to `spread`.
+    pub fn distance_field<F>(&mut self, dst: &mut Frame<f32>, inside: F, spread: f32)
+        where F: Fn(P) -> bool {
+
+        assert!(dst.width == self.width);
+        assert!(dst.height == self.height);
+
+        let src = self.to_buffer();
+        let (w, h) = (src.width as i32, src.height as i32);
+        let mask: Vec<bool> = src.data.iter().map(|p| inside(*p)).collect();
+
+        // distances to the closest pixel inside and outside of the shape
+        let seed = |target: bool| Grid {
+            w: w,
+            h: h,
+            cells: mask.iter().map(|&m| if m == target { Offset(0, 0) } else { Offset(FAR, FAR) }).collect()
+        };
+        let mut to_inside = seed(true);
+        let mut to_outside = seed(false);
+        to_inside.propagate();
+        to_outside.propagate();
+
+        let data = (0..mask.len()).map(|i| {
+            (to_outside.distance(i) - to_inside.distance(i)).max(-spread).min(spread)
+        }).collect();
+
+        dst.load(Arc::new(Buffer {
+            width: src.width,
+            height: src.height,
+            data: data
+        }));
+    }
+}
@@ -0,0 +1,299 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use cgmath::{Matrix, Matrix4, Vector2, Vector4};
+use genmesh::Triangle;
+
+use {Frame, Fragment, DrawStats, Buffer, alpha_over, Rgba, IntoMatrix, Shade, PerFragment, Stencil};
+use tile::TileMask;
+
+/// a vertex of the full screen pass, the clip space position followed by
+/// the same point in normalized device coordinates
+pub type ScreenVertex = ([f32; 4], [f32; 2]);
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// run `fragment` once for every pixel of the frame. The depth buffer is
+    /// left alone, so drawing a background first keeps the scene on top.
+    pub fn fullscreen<F>(&mut self, fragment: F) -> DrawStats
+        where F: Fragment<ScreenVertex, Color=P> + Send + Sync + 'static {
+
+        // one triangle that covers all of clip space
+        let v = |x: f32, y: f32| ([x, y, 0., 1.], [x, y]);
+        let tri = Triangle::new(v(-1., -1.), v(3., -1.), v(-1., 3.));
+        self.capture_draw::<F>(false);
+        self.bin(vec![tri].into_iter(), Background(PerFragment(fragment)), false, |t, back| if back { None } else { Some(t) })
+    }
+}
+
+/// the shader of a full screen pass, the depth buffer is left alone
+struct Background<S>(S);
+
+impl<S, L, P> Shade<L, P> for Background<S> where S: Shade<L, P> {
+    #[inline]
+    fn shade(&self, plane: &L, mask: &TileMask, color: &mut [P; 64]) {
+        self.0.shade(plane, mask, color)
+    }
+
+    #[inline]
+    fn shade_at(&self, plane: &L, mask: &TileMask, origin: Vector2<f32>, color: &mut [P; 64]) {
+        self.0.shade_at(plane, mask, origin, color)
+    }
+
+    #[inline]
+    fn depth_test(&self) -> bool { false }
+
+    #[inline]
+    fn stencil(&self) -> Stencil { self.0.stencil() }
+}
+
+#[inline]
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[inline]
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let l = dot(a, a).sqrt().recip();
+    [a[0] * l, a[1] * l, a[2] * l]
+}
+
+#[inline]
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+#[inline]
+fn to_rgba(c: [f32; 3]) -> Rgba<u8> {
+    let q = |v: f32| (v.max(0.).min(1.) * 255. + 0.5) as u8;
+    Rgba([q(c[0]), q(c[1]), q(c[2]), 255])
+}
+
+/// the world space direction seen through a point in normalized device
+/// coordinates, `inverse` undoes the view projection
+#[inline]
+fn view_dir(inverse: &Matrix4<f32>, ndc: [f32; 2]) -> [f32; 3] {
+    let near = inverse.mul_v(&Vector4::new(ndc[0], ndc[1], -1., 1.));
+    let far = inverse.mul_v(&Vector4::new(ndc[0], ndc[1], 1., 1.));
+    normalize([far.x / far.w - near.x / near.w,
+               far.y / far.w - near.y / near.w,
+               far.z / far.w - near.z / near.w])
+}
+
+/// an analytic sky for the full screen pass. The sky darkens towards the
+/// zenith, the horizon turns warm as the sun sets, and the sun gets a disc
+/// with a forward scattering glow. Y points up.
+#[derive(Clone, Copy, Debug)]
+pub struct Sky {
+    /// unit direction towards the sun
+    pub sun: [f32; 3],
+    /// the inverse of the view projection matrix of the camera
+    pub inverse: Matrix4<f32>,
+    pub zenith: [f32; 3],
+    pub horizon: [f32; 3],
+    pub ground: [f32; 3]
+}
+
+impl Sky {
+    pub fn new<M: IntoMatrix>(sun: [f32; 3], inverse: M) -> Sky {
+        Sky {
+            sun: normalize(sun),
+            inverse: inverse.into_matrix(),
+            zenith: [0.16, 0.35, 0.75],
+            horizon: [0.7, 0.82, 0.95],
+            ground: [0.25, 0.23, 0.21]
+        }
+    }
+
+    /// the linear color in direction `dir`
+    pub fn color(&self, dir: [f32; 3]) -> [f32; 3] {
+        let elevation = self.sun[1].max(-0.2);
+        // the whole sky fades out once the sun is below the horizon
+        let light = (elevation * 4. + 0.8).max(0.05).min(1.);
+        let sunset = (1. - elevation.max(0.) * 3.).max(0.);
+        let horizon = mix(self.horizon, [1., 0.55, 0.3], sunset * 0.8);
+
+        let base = if dir[1] >= 0. {
+            // rayleigh like falloff, most of the change is close to the horizon
+            let t = 1. - (1. - dir[1]).powf(4.);
+            mix(horizon, self.zenith, t)
+        } else {
+            mix(horizon, self.ground, (-dir[1] * 8.).min(1.))
+        };
+
+        let cos = dot(dir, self.sun).max(0.);
+        let glow = cos.powf(8.) * 0.35 + cos.powf(64.) * 0.5;
+        let disc = if cos > 0.9995 && dir[1] >= 0. { 4. } else { 0. };
+        let sun_color = mix([1., 1., 0.95], [1., 0.6, 0.3], sunset);
+
+        let mut c = [0.; 3];
+        for i in 0..3 {
+            c[i] = base[i] * light + sun_color[i] * (glow + disc) * light;
+        }
+        c
+    }
+}
+
+impl Fragment<ScreenVertex> for Sky {
+    type Color = Rgba<u8>;
+
+    #[inline]
+    fn fragment(&self, (_, ndc): ScreenVertex) -> Rgba<u8> {
+        to_rgba(self.color(view_dir(&self.inverse, ndc)))
+    }
+}
+
+/// matcap shading, the color is looked up from a picture of a lit sphere
+/// by the view space normal. The input is the position followed by the
+/// interpolated view space normal.
+#[derive(Clone)]
+pub struct Matcap {
+    pub texture: Arc<Buffer<Rgba<u8>>>
+}
+
+impl Matcap {
+    pub fn new(texture: Arc<Buffer<Rgba<u8>>>) -> Matcap {
+        Matcap { texture: texture }
+    }
+
+    /// the color for a unit view space normal
+    #[inline]
+    pub fn color(&self, n: [f32; 3]) -> Rgba<u8> {
+        // the sphere is seen from the front, so only x and y matter and
+        // the texture is stored with y down
+        let n = normalize(n);
+        self.texture.sample(n[0] * 0.5 + 0.5, 0.5 - n[1] * 0.5)
+    }
+}
+
+impl Fragment<([f32; 4], [f32; 3])> for Matcap {
+    type Color = Rgba<u8>;
+
+    #[inline]
+    fn fragment(&self, (_, n): ([f32; 4], [f32; 3])) -> Rgba<u8> {
+        self.color(n)
+    }
+}
+
+/// the vertex of the `Pbr` shader: position, world space position, world
+/// space normal and texture coordinates
+pub type PbrVertex = ([f32; 4], [f32; 3], [f32; 3], [f32; 2]);
+
+#[inline]
+fn srgb_to_linear(c: u8) -> f32 {
+    (c as f32 / 255.).powf(2.2)
+}
+
+#[inline]
+fn linear_to_srgb(c: f32) -> f32 {
+    c.max(0.).powf(1. / 2.2)
+}
+
+/// a metallic roughness material lit by a single directional light, the
+/// same model glTF uses. Specular is GGX with Smith visibility and Schlick
+/// fresnel, diffuse is Lambert. Colors are linear, the output is sRGB.
+#[derive(Clone)]
+pub struct Pbr {
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    /// sRGB base color, multiplied with `base_color`
+    pub base_color_texture: Option<Arc<Buffer<Rgba<u8>>>>,
+    /// roughness in green and metallic in blue, multiplied with the factors
+    pub metallic_roughness_texture: Option<Arc<Buffer<Rgba<u8>>>>,
+    /// unit direction towards the light
+    pub light_dir: [f32; 3],
+    pub light_color: [f32; 3],
+    pub ambient: [f32; 3],
+    /// world space position of the camera
+    pub eye: [f32; 3]
+}
+
+impl Pbr {
+    pub fn new(base_color: [f32; 4], metallic: f32, roughness: f32) -> Pbr {
+        Pbr {
+            base_color: base_color,
+            metallic: metallic,
+            roughness: roughness,
+            base_color_texture: None,
+            metallic_roughness_texture: None,
+            light_dir: normalize([0.3, 1., 0.5]),
+            light_color: [3., 3., 3.],
+            ambient: [0.03, 0.03, 0.03],
+            eye: [0., 0., 0.]
+        }
+    }
+
+    /// materials with a base alpha below one are blended over what is
+    /// behind them and have to be drawn back to front
+    #[inline]
+    pub fn translucent(&self) -> bool {
+        self.base_color[3] < 1.
+    }
+
+    /// the linear color and alpha of a surface point
+    pub fn shade(&self, pos: [f32; 3], normal: [f32; 3], uv: [f32; 2]) -> [f32; 4] {
+        let mut base = self.base_color;
+        if let Some(ref t) = self.base_color_texture {
+            let c = t.sample(uv[0], uv[1]).data;
+            for i in 0..3 {
+                base[i] *= srgb_to_linear(c[i]);
+            }
+            base[3] *= c[3] as f32 / 255.;
+        }
+        let (mut metallic, mut roughness) = (self.metallic, self.roughness);
+        if let Some(ref t) = self.metallic_roughness_texture {
+            let c = t.sample(uv[0], uv[1]).data;
+            roughness *= c[1] as f32 / 255.;
+            metallic *= c[2] as f32 / 255.;
+        }
+        // perfectly smooth surfaces turn the highlight into a single point
+        let roughness = roughness.max(0.04).min(1.);
+        let metallic = metallic.max(0.).min(1.);
+
+        let n = normalize(normal);
+        let l = normalize(self.light_dir);
+        let v = normalize([self.eye[0] - pos[0], self.eye[1] - pos[1], self.eye[2] - pos[2]]);
+        let h = normalize([l[0] + v[0], l[1] + v[1], l[2] + v[2]]);
+
+        let n_l = dot(n, l).max(0.);
+        let n_v = dot(n, v).abs().max(1e-4);
+        let n_h = dot(n, h).max(0.);
+        let v_h = dot(v, h).max(0.);
+
+        let a = roughness * roughness;
+        let a2 = a * a;
+        let d = n_h * n_h * (a2 - 1.) + 1.;
+        let distribution = a2 / (PI * d * d);
+
+        let k = (roughness + 1.) * (roughness + 1.) / 8.;
+        let visibility = (n_l / (n_l * (1. - k) + k)) * (n_v / (n_v * (1. - k) + k));
+
+        let f0 = mix([0.04; 3], [base[0], base[1], base[2]], metallic);
+        let schlick = (1. - v_h).powf(5.);
+
+        let mut c = [0., 0., 0., base[3]];
+        for i in 0..3 {
+            let fresnel = f0[i] + (1. - f0[i]) * schlick;
+            let specular = distribution * visibility * fresnel / (4. * n_l.max(1e-4) * n_v);
+            let diffuse = (1. - fresnel) * (1. - metallic) * base[i] / PI;
+            c[i] = (diffuse + specular) * self.light_color[i] * n_l + self.ambient[i] * base[i];
+        }
+        c
+    }
+}
+
+impl Fragment<PbrVertex> for Pbr {
+    type Color = Rgba<u8>;
+
+    #[inline]
+    fn fragment(&self, (_, pos, normal, uv): PbrVertex) -> Rgba<u8> {
+        let c = self.shade(pos, normal, uv);
+        let q = |v: f32| (v.max(0.).min(1.) * 255. + 0.5) as u8;
+        Rgba([q(linear_to_srgb(c[0])), q(linear_to_srgb(c[1])), q(linear_to_srgb(c[2])), q(c[3])])
+    }
+
+    #[inline]
+    fn blend(&self, old: Rgba<u8>, new: Rgba<u8>) -> Rgba<u8> {
+        if self.translucent() { alpha_over(old, new) } else { new }
+    }
+}
@@ -0,0 +1,171 @@
+//! shadow maps rendered from a directional light, and cascades of them
+//! that cover slices of the camera frustum at growing distances
+
+use cgmath::{Matrix, Matrix4, Vector4, ortho};
+use genmesh::{Triangle, MapVertex};
+
+use {Frame, Buffer, SolidColor, Projection, IntoMatrix};
+
+#[inline]
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[inline]
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+#[inline]
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let l = dot(a, a).sqrt();
+    [a[0] / l, a[1] / l, a[2] / l]
+}
+
+/// a view matrix looking along `dir` from the origin
+fn light_view(dir: [f32; 3]) -> Matrix4<f32> {
+    let f = normalize(dir);
+    let up = if f[1].abs() > 0.99 { [0., 0., 1.] } else { [0., 1., 0.] };
+    let s = normalize(cross(f, up));
+    let u = cross(s, f);
+    Matrix4::new(s[0], u[0], -f[0], 0.,
+                 s[1], u[1], -f[1], 0.,
+                 s[2], u[2], -f[2], 0.,
+                 0., 0., 0., 1.)
+}
+
+/// the depth of a scene as seen from a light
+#[derive(Clone)]
+pub struct ShadowMap {
+    /// from world space to the clip space of the light
+    pub view_proj: Matrix4<f32>,
+    /// laid out like `Frame::ndc_depth_buffer`
+    pub depth: Buffer<f32>
+}
+
+impl ShadowMap {
+    /// raster the world space triangles of `casters` into a `size` square
+    /// depth map through `view_proj`, both sides of them cast shadows
+    pub fn render<M, S>(size: u32, view_proj: M, casters: S) -> ShadowMap
+        where M: IntoMatrix, S: Iterator<Item=Triangle<[f32; 3]>> {
+
+        let view_proj = view_proj.into_matrix();
+        let mut frame = Frame::new(size, size, 0u8);
+        let poly = casters.map(|t| t.map_vertex(|v| {
+            let p = view_proj.mul_v(&Vector4::new(v[0], v[1], v[2], 1.));
+            [p.x, p.y, p.z, p.w]
+        }));
+        frame.raster_two_sided(poly, SolidColor(0u8), SolidColor(0u8));
+        ShadowMap {
+            view_proj: view_proj,
+            depth: frame.ndc_depth_buffer()
+        }
+    }
+
+    /// 1 if `world` is lit and 0 if something is closer to the light,
+    /// `bias` is added to the depth of `world` to keep surfaces from
+    /// shadowing themselves. Points outside of the map are lit.
+    pub fn visibility(&self, world: [f32; 3], bias: f32) -> f32 {
+        let p = self.view_proj.mul_v(&Vector4::new(world[0], world[1], world[2], 1.));
+        let (x, y, z) = (p.x / p.w, p.y / p.w, p.z / p.w);
+        if !(x.abs() <= 1. && y.abs() <= 1.) {
+            return 1.;
+        }
+
+        // the pixel with the nearest sample, see `pixel_ndc`
+        let (w, h) = (self.depth.width, self.depth.height);
+        let px = ((x + 1.) * w as f32 / 2. + 0.5).floor().max(0.).min(w as f32 - 1.) as u32;
+        let py = ((y + 1.) * h as f32 / 2. + 0.5).floor().max(0.).min(h as f32 - 1.) as u32;
+        if z - bias <= self.depth.get_pixel(px, h - 1 - py) { 1. } else { 0. }
+    }
+}
+
+/// the distances from the eye where the slices of `count` cascades end,
+/// `lambda` blends between an even split at 0 and a logarithmic one at 1
+pub fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..count + 1).map(|i| {
+        let t = i as f32 / count as f32;
+        let log = near * (far / near).powf(t);
+        let even = near + (far - near) * t;
+        lambda * log + (1. - lambda) * even
+    }).collect()
+}
+
+/// shadow maps for consecutive slices of the camera frustum, the closer
+/// slices get the same resolution over a smaller area
+#[derive(Clone)]
+pub struct CascadedShadows {
+    /// the distance from the eye where each cascade ends and its map
+    pub cascades: Vec<(f32, ShadowMap)>
+}
+
+impl CascadedShadows {
+    /// render a `size` square map for every slice that ends at one of
+    /// `splits`. `inverse_view_proj` and `projection` describe the camera,
+    /// `light_dir` points from the light into the scene and `casters` are
+    /// the world space triangles that cast shadows.
+    pub fn render<M: IntoMatrix>(size: u32, inverse_view_proj: M, projection: Projection,
+                                 splits: &[f32], light_dir: [f32; 3], casters: &[Triangle<[f32; 3]>]) -> CascadedShadows {
+
+        let inverse_view_proj = inverse_view_proj.into_matrix();
+        let view = light_view(light_dir);
+        let to_light = |p: [f32; 3]| {
+            let v = view.mul_v(&Vector4::new(p[0], p[1], p[2], 1.));
+            [v.x, v.y, -v.z]
+        };
+
+        // the casters can sit in front of any slice, so the near plane of
+        // every cascade reaches back to the closest of them
+        let mut caster_near = ::std::f32::INFINITY;
+        for t in casters.iter() {
+            for v in [t.x, t.y, t.z].iter() {
+                caster_near = caster_near.min(to_light(*v)[2]);
+            }
+        }
+
+        let near = match projection {
+            Projection::Perspective { near, .. } => near,
+            Projection::Orthographic { near, .. } => near
+        };
+        let mut start = near;
+        let mut cascades = Vec::new();
+        for &end in splits.iter() {
+            let (z0, z1) = (projection.ndc_depth(start), projection.ndc_depth(end));
+            let (mut lo, mut hi) = ([::std::f32::INFINITY; 3], [::std::f32::NEG_INFINITY; 3]);
+            for &z in [z0, z1].iter() {
+                for &(x, y) in [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)].iter() {
+                    let c = inverse_view_proj.mul_v(&Vector4::new(x, y, z, 1.));
+                    let l = to_light([c.x / c.w, c.y / c.w, c.z / c.w]);
+                    for k in 0..3 {
+                        lo[k] = lo[k].min(l[k]);
+                        hi[k] = hi[k].max(l[k]);
+                    }
+                }
+            }
+
+            let proj = ortho(lo[0], hi[0], lo[1], hi[1], lo[2].min(caster_near), hi[2]);
+            let view_proj = proj.mul_m(&view);
+            cascades.push((end, ShadowMap::render(size, view_proj, casters.iter().cloned())));
+            start = end;
+        }
+
+        CascadedShadows {
+            cascades: cascades
+        }
+    }
+
+    /// the cascade for a point `view_depth` away from the eye, None past
+    /// the last one
+    pub fn select(&self, view_depth: f32) -> Option<usize> {
+        self.cascades.iter().position(|&(end, _)| view_depth <= end)
+    }
+
+    /// like `ShadowMap::visibility` with the map picked by `view_depth`,
+    /// points past the last cascade are lit
+    pub fn visibility(&self, world: [f32; 3], view_depth: f32, bias: f32) -> f32 {
+        match self.select(view_depth) {
+            Some(i) => self.cascades[i].1.visibility(world, bias),
+            None => 1.
+        }
+    }
+}
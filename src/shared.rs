@@ -0,0 +1,45 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::fmt::Debug;
+
+use genmesh::Triangle;
+
+use {Frame, Fragment, Interpolate, FetchPosition, DrawStats};
+
+/// a handle to a frame that many threads can submit draws to at once.
+/// The geometry of a draw is gathered on the calling thread and only
+/// binning is serialized, the rasterization itself runs on the worker
+/// pool as usual. Draws from different threads are applied in the order
+/// they reach the frame.
+pub struct SharedFrame<P> {
+    frame: Arc<Mutex<Frame<P>>>
+}
+
+impl<P> Clone for SharedFrame<P> {
+    fn clone(&self) -> SharedFrame<P> {
+        SharedFrame {
+            frame: self.frame.clone()
+        }
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> SharedFrame<P> {
+    pub fn new(frame: Frame<P>) -> SharedFrame<P> {
+        SharedFrame {
+            frame: Arc::new(Mutex::new(frame))
+        }
+    }
+
+    pub fn raster<S, F, T, O>(&self, poly: S, fragment: F) -> DrawStats
+        where S: Iterator<Item=Triangle<T>>,
+              T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: Fragment<O, Color=P> + Send + Sync + 'static {
+
+        let poly: Vec<Triangle<T>> = poly.collect();
+        self.frame.lock().unwrap().raster(poly.into_iter(), fragment)
+    }
+
+    /// exclusive access to the frame, for clears and readback
+    pub fn lock(&self) -> MutexGuard<Frame<P>> {
+        self.frame.lock().unwrap()
+    }
+}
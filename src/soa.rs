@@ -0,0 +1,198 @@
+use genmesh::Triangle;
+
+/// a source of per vertex attributes addressed by index, implemented by
+/// slices and by tuples of other sources so that each attribute can live
+/// in its own array
+pub trait Gather {
+    type Out;
+    fn gather(&self, index: usize) -> Self::Out;
+}
+
+impl<'a, T: Clone> Gather for &'a [T] {
+    type Out = T;
+    #[inline]
+    fn gather(&self, index: usize) -> T { self[index].clone() }
+}
+
+impl<A: Gather, B: Gather> Gather for (A, B) {
+    type Out = (A::Out, B::Out);
+    #[inline]
+    fn gather(&self, i: usize) -> (A::Out, B::Out) {
+        (self.0.gather(i), self.1.gather(i))
+    }
+}
+
+impl<A: Gather, B: Gather, C: Gather> Gather for (A, B, C) {
+    type Out = (A::Out, B::Out, C::Out);
+    #[inline]
+    fn gather(&self, i: usize) -> (A::Out, B::Out, C::Out) {
+        (self.0.gather(i), self.1.gather(i), self.2.gather(i))
+    }
+}
+
+impl<A: Gather, B: Gather, C: Gather, D: Gather> Gather for (A, B, C, D) {
+    type Out = (A::Out, B::Out, C::Out, D::Out);
+    #[inline]
+    fn gather(&self, i: usize) -> (A::Out, B::Out, C::Out, D::Out) {
+        (self.0.gather(i), self.1.gather(i), self.2.gather(i), self.3.gather(i))
+    }
+}
+
+/// assembles triangles from an index list, every three indices make a triangle
+pub struct IndexedTriangles<'a, G> {
+    indices: &'a [u32],
+    attributes: G
+}
+
+impl<'a, G: Gather> IndexedTriangles<'a, G> {
+    pub fn new(indices: &'a [u32], attributes: G) -> IndexedTriangles<'a, G> {
+        IndexedTriangles {
+            indices: indices,
+            attributes: attributes
+        }
+    }
+}
+
+impl<'a, G: Gather> Iterator for IndexedTriangles<'a, G> {
+    type Item = Triangle<G::Out>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Triangle<G::Out>> {
+        if self.indices.len() < 3 {
+            return None;
+        }
+
+        let i = self.indices;
+        self.indices = &i[3..];
+        Some(Triangle::new(self.attributes.gather(i[0] as usize),
+                           self.attributes.gather(i[1] as usize),
+                           self.attributes.gather(i[2] as usize)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.indices.len() / 3;
+        (n, Some(n))
+    }
+}
+
+/// an index that ends a strip or a fan, the next index starts a new one
+pub const PRIMITIVE_RESTART: u32 = !0;
+
+/// assembles a triangle strip from an index list, every index after the
+/// first two makes a triangle with the two before it. Every other triangle
+/// has its first two vertices swapped so they all keep the winding of the
+/// first one. Triangles with a repeated index, which join strips into
+/// one, are skipped and `PRIMITIVE_RESTART` starts a new strip.
+pub struct IndexedStrip<'a, G> {
+    indices: &'a [u32],
+    attributes: G,
+    odd: bool
+}
+
+impl<'a, G: Gather> IndexedStrip<'a, G> {
+    pub fn new(indices: &'a [u32], attributes: G) -> IndexedStrip<'a, G> {
+        IndexedStrip {
+            indices: indices,
+            attributes: attributes,
+            odd: false
+        }
+    }
+}
+
+impl<'a, G: Gather> Iterator for IndexedStrip<'a, G> {
+    type Item = Triangle<G::Out>;
+
+    fn next(&mut self) -> Option<Triangle<G::Out>> {
+        loop {
+            let i = self.indices;
+            if i.len() < 3 {
+                return None;
+            }
+
+            if let Some(r) = i[..3].iter().rposition(|&x| x == PRIMITIVE_RESTART) {
+                self.indices = &i[r + 1..];
+                self.odd = false;
+                continue;
+            }
+
+            self.indices = &i[1..];
+            let odd = self.odd;
+            self.odd = !odd;
+            if i[0] == i[1] || i[1] == i[2] || i[0] == i[2] {
+                continue;
+            }
+
+            let (a, b) = if odd { (i[1], i[0]) } else { (i[0], i[1]) };
+            return Some(Triangle::new(self.attributes.gather(a as usize),
+                                      self.attributes.gather(b as usize),
+                                      self.attributes.gather(i[2] as usize)));
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.indices.len().saturating_sub(2)))
+    }
+}
+
+/// assembles a triangle fan from an index list, the first index is shared
+/// by all the triangles and every index after the second makes a triangle
+/// with it and the index before. `PRIMITIVE_RESTART` starts a new fan.
+pub struct IndexedFan<'a, G> {
+    indices: &'a [u32],
+    attributes: G,
+    center: Option<u32>
+}
+
+impl<'a, G: Gather> IndexedFan<'a, G> {
+    pub fn new(indices: &'a [u32], attributes: G) -> IndexedFan<'a, G> {
+        IndexedFan {
+            indices: indices,
+            attributes: attributes,
+            center: None
+        }
+    }
+}
+
+impl<'a, G: Gather> Iterator for IndexedFan<'a, G> {
+    type Item = Triangle<G::Out>;
+
+    fn next(&mut self) -> Option<Triangle<G::Out>> {
+        loop {
+            let i = self.indices;
+            let center = match self.center {
+                Some(c) => c,
+                None => {
+                    if i.is_empty() {
+                        return None;
+                    }
+                    self.indices = &i[1..];
+                    if i[0] != PRIMITIVE_RESTART {
+                        self.center = Some(i[0]);
+                    }
+                    continue;
+                }
+            };
+
+            if i.len() < 2 {
+                return None;
+            }
+            if let Some(r) = i[..2].iter().rposition(|&x| x == PRIMITIVE_RESTART) {
+                self.indices = &i[r + 1..];
+                self.center = None;
+                continue;
+            }
+
+            self.indices = &i[1..];
+            return Some(Triangle::new(self.attributes.gather(center as usize),
+                                      self.attributes.gather(i[0] as usize),
+                                      self.attributes.gather(i[1] as usize)));
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.indices.len()))
+    }
+}
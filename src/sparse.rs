@@ -0,0 +1,328 @@
+//! textures too large to keep in memory, paged in as they are sampled
+//!
+//! A `SparseTexture` cuts a mip chain into square pages and only holds the
+//! ones that were asked for. Shaders sample a `PageTable`, a snapshot of the
+//! pages resident when it was taken, from any number of tile workers at
+//! once. A missing page is filled in from the closest coarser level that is
+//! there and noted in the feedback of the table. Between frames `update`
+//! loads what was asked for from a `PageSource` and evicts the pages that
+//! went unused the longest.
+
+use std::cmp::{min, max};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use {Buffer, Lerp};
+
+/// a page of a sparse texture, `x` and `y` count pages from the top left
+/// corner of mip `level`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Page {
+    pub level: u32,
+    pub x: u32,
+    pub y: u32
+}
+
+/// where the pages of a sparse texture come from, like a tiled image file
+/// or a scan cut up ahead of time
+pub trait PageSource<P>: Send + Sync {
+    /// the `width` by `height` texels of `page`, the pages on the right and
+    /// bottom edges of a level can be smaller than the page size
+    fn load(&self, page: Page, width: u32, height: u32) -> Buffer<P>;
+}
+
+impl<P, F> PageSource<P> for F where F: Fn(Page, u32, u32) -> Buffer<P> + Send + Sync {
+    fn load(&self, page: Page, width: u32, height: u32) -> Buffer<P> {
+        self(page, width, height)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Level {
+    width: u32,
+    height: u32,
+    columns: u32,
+    rows: u32,
+    /// the index of the first page of the level
+    first: usize
+}
+
+const BITS: usize = 32;
+
+/// the pages of a sparse texture resident at one point, shared by the
+/// shaders of the draws that sample it
+pub struct PageTable<P> {
+    page_size: u32,
+    levels: Vec<Level>,
+    pages: Vec<Option<Arc<Buffer<P>>>>,
+    feedback: Vec<AtomicUsize>
+}
+
+impl<P: Copy> PageTable<P> {
+    /// the number of mip levels, the last one is a single page that is
+    /// always resident
+    pub fn levels(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    /// the level of detail for a texture that covers `texels_per_pixel`
+    /// texels of the base level with every pixel drawn
+    pub fn lod(&self, texels_per_pixel: f32) -> f32 {
+        texels_per_pixel.max(1.).log2()
+    }
+
+    #[inline]
+    fn request(&self, i: usize) {
+        let bit = 1 << (i % BITS);
+        let word = &self.feedback[i / BITS];
+        // most samples hit pages that were asked for already
+        if word.load(Ordering::Relaxed) & bit == 0 {
+            word.fetch_or(bit, Ordering::Relaxed);
+        }
+    }
+
+    /// the texel of `level` at `x`, `y` if its page is resident, the
+    /// position is clamped to the edges of the level
+    #[inline]
+    pub fn texel(&self, level: u32, x: i32, y: i32) -> Option<P> {
+        self.fetch(level as usize, x, y, false)
+    }
+
+    #[inline]
+    fn fetch(&self, level: usize, x: i32, y: i32, record: bool) -> Option<P> {
+        let l = &self.levels[level];
+        let x = x.max(0).min(l.width as i32 - 1) as u32;
+        let y = y.max(0).min(l.height as i32 - 1) as u32;
+        let (px, py) = (x / self.page_size, y / self.page_size);
+        let i = l.first + (py * l.columns + px) as usize;
+        if record {
+            self.request(i);
+        }
+        self.pages[i].as_ref().map(|page| page.get_pixel(x % self.page_size, y % self.page_size))
+    }
+
+    /// the page that holds the texel of `level` at `x`, `y`
+    pub fn page_of(&self, level: u32, x: u32, y: u32) -> Page {
+        Page {
+            level: level,
+            x: x / self.page_size,
+            y: y / self.page_size
+        }
+    }
+
+    /// true if `page` is in memory
+    pub fn is_resident(&self, page: Page) -> bool {
+        let l = &self.levels[page.level as usize];
+        self.pages[l.first + (page.y * l.columns + page.x) as usize].is_some()
+    }
+
+    /// the pages that were sampled from this table, resident or not
+    pub fn feedback(&self) -> Vec<Page> {
+        let mut out = Vec::new();
+        for (level, l) in self.levels.iter().enumerate() {
+            for i in 0..(l.columns * l.rows) as usize {
+                let k = l.first + i;
+                if self.feedback[k / BITS].load(Ordering::Relaxed) & (1 << (k % BITS)) != 0 {
+                    out.push(Page {
+                        level: level as u32,
+                        x: i as u32 % l.columns,
+                        y: i as u32 / l.columns
+                    });
+                }
+            }
+        }
+        out
+    }
+}
+
+impl<P: Copy + Lerp> PageTable<P> {
+    fn bilinear(&self, level: usize, u: f32, v: f32, record: bool) -> Option<P> {
+        let l = &self.levels[level];
+        let (sx, sy) = (u * l.width as f32 - 0.5, v * l.height as f32 - 0.5);
+        let (x0, y0) = (sx.floor(), sy.floor());
+        let (fx, fy) = (sx - x0, sy - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        // every texel is looked at so that all of the pages under the
+        // footprint end up in the feedback
+        let a = self.fetch(level, x0, y0, record);
+        let b = self.fetch(level, x0 + 1, y0, record);
+        let c = self.fetch(level, x0, y0 + 1, record);
+        let d = self.fetch(level, x0 + 1, y0 + 1, record);
+        match (a, b, c, d) {
+            (Some(a), Some(b), Some(c), Some(d)) => Some(a.lerp(b, fx).lerp(c.lerp(d, fx), fy)),
+            _ => None
+        }
+    }
+
+    /// bilinear fetch at normalized coordinates from the level `lod`
+    /// rounds down to, 0, 0 is the top left corner like for
+    /// `Buffer::sample`. The pages it wants are put in the feedback, while
+    /// they are missing the closest coarser level stands in.
+    pub fn sample(&self, u: f32, v: f32, lod: f32) -> P {
+        let last = self.levels.len() - 1;
+        let wanted = min(lod.max(0.) as usize, last);
+        for level in wanted..last {
+            if let Some(p) = self.bilinear(level, u, v, level == wanted) {
+                return p;
+            }
+        }
+        self.bilinear(last, u, v, wanted == last).expect("the last level is always resident")
+    }
+}
+
+/// a texture of which only the pages in use are kept in memory
+pub struct SparseTexture<P> {
+    pub width: u32,
+    pub height: u32,
+    page_size: u32,
+    capacity: usize,
+    source: Arc<PageSource<P>>,
+    table: Arc<PageTable<P>>,
+    last_used: Vec<u64>,
+    resident: usize,
+    frame: u64
+}
+
+impl<P: Copy + Send + Sync + 'static> SparseTexture<P> {
+    /// a `width` by `height` texture made of `page_size` pages loaded from
+    /// `source`, with room for at most `capacity` of them. The single page
+    /// of the coarsest level is loaded right away and stays.
+    pub fn new(width: u32, height: u32, page_size: u32, capacity: usize,
+               source: Arc<PageSource<P>>) -> SparseTexture<P> {
+        assert!(width > 0 && height > 0 && page_size > 0);
+        assert!(capacity > 0, "the coarsest page needs room");
+
+        let mut levels = Vec::new();
+        let mut count = 0;
+        let (mut w, mut h) = (width, height);
+        loop {
+            let (columns, rows) = ((w + page_size - 1) / page_size, (h + page_size - 1) / page_size);
+            levels.push(Level {
+                width: w,
+                height: h,
+                columns: columns,
+                rows: rows,
+                first: count
+            });
+            count += (columns * rows) as usize;
+            if columns * rows == 1 {
+                break;
+            }
+            w = max(w / 2, 1);
+            h = max(h / 2, 1);
+        }
+
+        let mut texture = SparseTexture {
+            width: width,
+            height: height,
+            page_size: page_size,
+            capacity: capacity,
+            source: source,
+            table: Arc::new(PageTable {
+                page_size: page_size,
+                levels: levels,
+                pages: vec![None; count],
+                feedback: Vec::new()
+            }),
+            last_used: vec![0; count],
+            resident: 0,
+            frame: 0
+        };
+        let last = texture.table.levels() - 1;
+        let mut pages = texture.table.pages.clone();
+        texture.load(&mut pages, Page { level: last, x: 0, y: 0 });
+        texture.publish(pages);
+        texture
+    }
+
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    /// the number of pages in memory
+    pub fn resident(&self) -> usize {
+        self.resident
+    }
+
+    /// the pages resident right now, for the shaders of the next draws.
+    /// Every table collects its own feedback, `update` reads it from the
+    /// latest one.
+    pub fn table(&self) -> Arc<PageTable<P>> {
+        self.table.clone()
+    }
+
+    fn index(&self, page: Page) -> usize {
+        let l = &self.table.levels[page.level as usize];
+        l.first + (page.y * l.columns + page.x) as usize
+    }
+
+    fn load(&mut self, pages: &mut Vec<Option<Arc<Buffer<P>>>>, page: Page) {
+        let l = self.table.levels[page.level as usize];
+        let (x, y) = (page.x * self.page_size, page.y * self.page_size);
+        let (w, h) = (min(self.page_size, l.width - x), min(self.page_size, l.height - y));
+        let texels = self.source.load(page, w, h);
+        assert!(texels.width == w && texels.height == h, "page {:?} loaded with the wrong size", page);
+        let i = self.index(page);
+        pages[i] = Some(Arc::new(texels));
+        self.last_used[i] = self.frame;
+        self.resident += 1;
+    }
+
+    fn publish(&mut self, pages: Vec<Option<Arc<Buffer<P>>>>) {
+        let words = (pages.len() + BITS - 1) / BITS;
+        self.table = Arc::new(PageTable {
+            page_size: self.page_size,
+            levels: self.table.levels.clone(),
+            pages: pages,
+            feedback: (0..words).map(|_| AtomicUsize::new(0)).collect()
+        });
+    }
+
+    /// load up to `max_loads` of the missing pages the latest table was
+    /// asked for, coarse levels first, and start a new table. Once the
+    /// texture is at capacity the pages that went unused the longest make
+    /// room, the ones asked for this time are kept. Returns the number of
+    /// pages loaded.
+    pub fn update(&mut self, max_loads: usize) -> usize {
+        self.frame += 1;
+        let last = self.table.levels() - 1;
+        let mut missing = Vec::new();
+        for page in self.table.feedback().into_iter() {
+            let i = self.index(page);
+            if self.table.pages[i].is_some() {
+                self.last_used[i] = self.frame;
+            } else {
+                missing.push(page);
+            }
+        }
+        missing.sort_by(|a, b| b.level.cmp(&a.level));
+
+        let mut pages = self.table.pages.clone();
+        let mut loaded = 0;
+        for page in missing.into_iter().take(max_loads) {
+            if self.resident >= self.capacity {
+                // the coarsest page and the ones in use now stay
+                let coarsest = self.table.levels[last as usize].first;
+                let mut victim = None;
+                for i in 0..coarsest {
+                    if pages[i].is_some() && self.last_used[i] < self.frame &&
+                       victim.map_or(true, |v: usize| self.last_used[i] < self.last_used[v]) {
+                        victim = Some(i);
+                    }
+                }
+                match victim {
+                    Some(i) => {
+                        pages[i] = None;
+                        self.resident -= 1;
+                    }
+                    None => break
+                }
+            }
+            self.load(&mut pages, page);
+            loaded += 1;
+        }
+        self.publish(pages);
+        loaded
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// counters for a single raster call. The triangle counts are known as
+/// soon as the call returns, the fragment count is complete after `flush`.
+#[derive(Clone, Debug)]
+pub struct DrawStats {
+    /// triangles that were submitted
+    pub triangles: usize,
+    /// triangles that were dropped for facing away from the viewer
+    pub culled: usize,
+    /// triangles that were dropped for having no area on screen or a
+    /// position that is not finite
+    pub degenerate: usize,
+    /// triangles that were dropped for coming after the deadline of the
+    /// frame, see `Frame::set_deadline`
+    pub late: usize,
+    fragments: Arc<AtomicUsize>,
+    allocations: Arc<AtomicUsize>
+}
+
+impl DrawStats {
+    pub fn new() -> DrawStats {
+        DrawStats {
+            triangles: 0,
+            culled: 0,
+            degenerate: 0,
+            late: 0,
+            fragments: Arc::new(AtomicUsize::new(0)),
+            allocations: Arc::new(AtomicUsize::new(0))
+        }
+    }
+
+    /// fragments that passed the depth test and were shaded
+    pub fn fragments(&self) -> usize {
+        self.fragments.load(Ordering::Relaxed)
+    }
+
+    /// the counter the tile workers add their fragments to
+    pub fn fragment_counter(&self) -> Arc<AtomicUsize> {
+        self.fragments.clone()
+    }
+
+    /// heap allocations the tile workers made while shading, 0 unless the
+    /// frame has an allocation counter. Complete after `flush`.
+    pub fn allocations(&self) -> usize {
+        self.allocations.load(Ordering::Relaxed)
+    }
+
+    /// the counter the tile workers add their allocations to
+    pub fn allocation_counter(&self) -> Arc<AtomicUsize> {
+        self.allocations.clone()
+    }
+}
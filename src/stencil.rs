@@ -0,0 +1,54 @@
+use {Frame, Buffer, Rect};
+use tile::Put;
+
+/// how draws test and write the stencil buffer, see `Frame::set_stencil`.
+/// The stencil of every pixel is 0 after a clear.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stencil {
+    /// only the pixels whose stencil equals this are drawn
+    pub test: Option<u8>,
+    /// written to the stencil of the pixels that pass the stencil and
+    /// depth tests
+    pub write: Option<u8>
+}
+
+impl Stencil {
+    /// draw where the stencil is `reference`
+    pub fn test(reference: u8) -> Stencil {
+        Stencil {
+            test: Some(reference),
+            write: None
+        }
+    }
+
+    /// set the stencil of every pixel drawn to `value`
+    pub fn write(value: u8) -> Stencil {
+        Stencil {
+            test: None,
+            write: Some(value)
+        }
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// the stencil state of the following draws. With `Stencil::write` a
+    /// draw marks the pixels it covers, like the opening of a portal or a
+    /// mirror, and with `Stencil::test` the draws after it are kept inside
+    /// or outside of them. Combine with `set_painter` to mark pixels
+    /// without touching the depth buffer.
+    pub fn set_stencil(&mut self, stencil: Stencil) {
+        self.stencil = stencil;
+    }
+
+    /// read back the stencil buffer, laid out like `to_buffer`
+    pub fn stencil_buffer(&mut self) -> Buffer<u8> {
+        let (w, h) = (self.width, self.height);
+        self.write_tiles(Buffer::new(w, h, 0u8), Rect::new(0, 0, w, h), |t, x, y, buff| {
+            for j in 0..32 {
+                for i in 0..32 {
+                    buff.put(x + i, y + j, t.stencil(i, j));
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use {Frame, Buffer, Rgba};
+use tile::Get;
+
+/// how the two eyes of a stereo pair are put into one image, see
+/// `Frame::stereo`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stereo {
+    /// red from the left eye, green and blue from the right one, for
+    /// red/cyan glasses
+    Anaglyph,
+    /// the left eye on the left half and the right eye on the right half
+    SideBySide,
+    /// the rows alternate between the eyes starting with the left one at
+    /// the top, for line interleaved displays
+    RowInterleaved,
+    /// the columns alternate between the eyes starting with the left one
+    ColumnInterleaved
+}
+
+impl Stereo {
+    /// the size of the image made from two eyes of `width` by `height`
+    pub fn size(&self, width: u32, height: u32) -> (u32, u32) {
+        match *self {
+            Stereo::SideBySide => (2 * width, height),
+            _ => (width, height)
+        }
+    }
+}
+
+struct StereoPass {
+    mode: Stereo,
+    left: Buffer<Rgba<u8>>,
+    right: Buffer<Rgba<u8>>
+}
+
+impl Get<Rgba<u8>> for StereoPass {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<Rgba<u8>> {
+        let (w, h) = (self.left.width, self.left.height);
+        let y = h - 1 - y;
+        Some(match self.mode {
+            Stereo::Anaglyph => {
+                let (l, r) = (self.left.get_pixel(x, y).data, self.right.get_pixel(x, y).data);
+                Rgba([l[0], r[1], r[2], ::std::cmp::max(l[3], r[3])])
+            }
+            Stereo::SideBySide if x < w => self.left.get_pixel(x, y),
+            Stereo::SideBySide => self.right.get_pixel(x - w, y),
+            Stereo::RowInterleaved if y % 2 == 0 => self.left.get_pixel(x, y),
+            Stereo::RowInterleaved => self.right.get_pixel(x, y),
+            Stereo::ColumnInterleaved if x % 2 == 0 => self.left.get_pixel(x, y),
+            Stereo::ColumnInterleaved => self.right.get_pixel(x, y)
+        })
+    }
+}
+
+impl Frame<Rgba<u8>> {
+    /// combine the frames of the `left` and `right` eye into this one, the
+    /// work is spread over the task pool like any other pass. The eyes have
+    /// the same size and this frame the size `mode.size` gives for it.
+    pub fn stereo(&mut self, left: &mut Frame<Rgba<u8>>, right: &mut Frame<Rgba<u8>>, mode: Stereo) {
+        assert!(left.width == right.width && left.height == right.height);
+        assert!(mode.size(left.width, left.height) == (self.width, self.height));
+        self.load(Arc::new(StereoPass {
+            mode: mode,
+            left: left.to_buffer(),
+            right: right.to_buffer()
+        }));
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt::Debug;
+
+use genmesh::Triangle;
+use snowstorm::channel::Receiver;
+
+use {Frame, Fragment, Interpolate, FetchPosition, DrawStats};
+
+/// a blocking iterator over everything sent to a channel, it ends once
+/// the sending side is dropped and the channel is empty
+pub struct Drain<T> {
+    rx: Receiver<T>
+}
+
+impl<T> Drain<T> {
+    pub fn new(rx: Receiver<T>) -> Drain<T> {
+        Drain {
+            rx: rx
+        }
+    }
+}
+
+impl<T: Clone> Iterator for Drain<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if let Some(t) = self.rx.try_recv() {
+                return Some(t.clone());
+            }
+            if self.rx.closed() {
+                // the sender may have sent more right before closing
+                return self.rx.try_recv().map(|t| t.clone());
+            }
+            let _ = self.rx.signal().wait();
+        }
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// raster triangles as they arrive on `rx`, binning runs while the
+    /// producer is still generating geometry. Returns once the sender hangs up.
+    pub fn raster_channel<F, T, O>(&mut self, rx: Receiver<Triangle<T>>, fragment: F) -> DrawStats
+        where T: Clone + Interpolate<Out=O> + FetchPosition + Send + Sync + 'static + Debug,
+              F: Fragment<O, Color=P> + Send + Sync + 'static {
+
+        self.raster(Drain::new(rx), fragment)
+    }
+}
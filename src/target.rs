@@ -0,0 +1,86 @@
+use std::io::{self, Write};
+
+use cgmath::Matrix4;
+
+use {Frame, Buffer, Rgba};
+
+/// a horizontal strip of a `TiledTarget`, the origin is the top left corner
+#[derive(Clone, Copy, Debug)]
+pub struct Band {
+    pub y: u32,
+    pub height: u32,
+    /// maps clip space of the whole image to clip space of this band,
+    /// apply it to the positions of the geometry drawn into the band
+    pub transform: Matrix4<f32>
+}
+
+/// renders images that are too large to keep in memory one band at a time,
+/// the geometry is submitted again for every band
+pub struct TiledTarget<P> {
+    pub width: u32,
+    pub height: u32,
+    band: u32,
+    clear: P
+}
+
+impl<P: Copy+Sync+Send+'static> TiledTarget<P> {
+    /// `band` is the number of rows rendered at once, it must be a multiple of 32
+    pub fn new(width: u32, height: u32, band: u32, clear: P) -> TiledTarget<P> {
+        assert!(band > 0 && band % 32 == 0);
+        TiledTarget {
+            width: width,
+            height: height,
+            band: band,
+            clear: clear
+        }
+    }
+
+    fn band(&self, y: u32) -> Band {
+        let sy = self.height as f32 / self.band as f32;
+        let mid = 1. - (2 * y + self.band) as f32 / self.height as f32;
+        Band {
+            y: y,
+            height: self.band.min(self.height - y),
+            transform: Matrix4::new(1., 0., 0., 0.,
+                                    0., sy, 0., 0.,
+                                    0., 0., 1., 0.,
+                                    0., -sy * mid, 0., 1.)
+        }
+    }
+
+    /// render the bands from top to bottom, `draw` submits the geometry
+    /// for a band and `write` receives its rows as soon as they are done
+    pub fn render<D, W>(&self, mut draw: D, mut write: W)
+        where D: FnMut(&mut Frame<P>, &Band),
+              W: FnMut(&Band, Buffer<P>) {
+
+        let mut frame = Frame::new(self.width, self.band, self.clear);
+        for y in (0..self.height).step_by(self.band) {
+            let band = self.band(y);
+            frame.clear(self.clear);
+            draw(&mut frame, &band);
+
+            let mut rows = frame.to_buffer();
+            rows.data.truncate((self.width * band.height) as usize);
+            rows.height = band.height;
+            write(&band, rows);
+        }
+    }
+}
+
+impl TiledTarget<Rgba<u8>> {
+    /// render the image as raw RGBA rows into `out`
+    pub fn render_to<D, O>(&self, draw: D, out: &mut O) -> io::Result<()>
+        where D: FnMut(&mut Frame<Rgba<u8>>, &Band),
+              O: Write {
+
+        let mut result = Ok(());
+        self.render(draw, |_, rows| {
+            if result.is_ok() {
+                let bytes: Vec<u8> = rows.data.iter().flat_map(|p| p.data.iter().cloned()).collect();
+                result = out.write_all(&bytes);
+            }
+        });
+        result
+    }
+}
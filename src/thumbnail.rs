@@ -0,0 +1,96 @@
+//! still images of many meshes at once, for asset pipelines
+//!
+//! `render_batch` frames every item with its own camera and draws them on
+//! a `FrameQueue`, so the frames are reused and several of them are in
+//! flight when they are too small to keep every core busy on their own.
+
+use std::sync::Arc;
+
+use cgmath::{Matrix4, Vector3, perspective, deg};
+use image::ImageBuffer;
+
+use {Frame, Fragment, FrameQueue, Rgba};
+use scene::{Mesh, Scene, Camera};
+use shaders::PbrVertex;
+
+/// what `render_batch` can draw
+pub trait Thumbnail {
+    /// a sphere around everything that is drawn, the camera is placed so
+    /// that it fits
+    fn bounds(&self) -> ([f32; 3], f32);
+
+    /// draw into `frame` with the surfaces shaded by `shader`
+    fn draw<F>(&self, frame: &mut Frame<Rgba<u8>>, camera: &Camera, shader: &F)
+        where F: Fragment<PbrVertex, Color=Rgba<u8>> + Clone + Send + Sync + 'static;
+}
+
+impl Thumbnail for Mesh {
+    fn bounds(&self) -> ([f32; 3], f32) {
+        Mesh::bounds(self)
+    }
+
+    fn draw<F>(&self, frame: &mut Frame<Rgba<u8>>, camera: &Camera, shader: &F)
+        where F: Fragment<PbrVertex, Color=Rgba<u8>> + Clone + Send + Sync + 'static {
+        frame.raster(self.triangles(Matrix4::identity(), camera.view_proj()).into_iter(), shader.clone());
+    }
+}
+
+/// every node of the scene is drawn with the shader, its materials are
+/// left out
+impl Thumbnail for Scene {
+    fn bounds(&self) -> ([f32; 3], f32) {
+        self.world_bounds()
+    }
+
+    fn draw<F>(&self, frame: &mut Frame<Rgba<u8>>, camera: &Camera, shader: &F)
+        where F: Fragment<PbrVertex, Color=Rgba<u8>> + Clone + Send + Sync + 'static {
+        let view_proj = camera.view_proj();
+        for (node, m) in self.nodes.iter().zip(self.world_transforms().iter()) {
+            if let Some((mesh, _)) = node.mesh {
+                frame.raster(self.meshes[mesh].triangles(*m, view_proj).into_iter(), shader.clone());
+            }
+        }
+    }
+}
+
+impl<T: Thumbnail> Thumbnail for Arc<T> {
+    fn bounds(&self) -> ([f32; 3], f32) {
+        (**self).bounds()
+    }
+
+    fn draw<F>(&self, frame: &mut Frame<Rgba<u8>>, camera: &Camera, shader: &F)
+        where F: Fragment<PbrVertex, Color=Rgba<u8>> + Clone + Send + Sync + 'static {
+        (**self).draw(frame, camera, shader)
+    }
+}
+
+/// a camera on the +z side of the sphere at `center` looking down -z, close
+/// enough for the sphere to fill a square view
+pub fn thumbnail_camera(center: [f32; 3], radius: f32) -> Camera {
+    let r = if radius > 0. { radius } else { 1. };
+    // the sine of the half angle of the view, 20 degrees
+    let distance = r / 0.342;
+    let eye = [center[0], center[1], center[2] + distance];
+    let view = Matrix4::from_translation(&Vector3::new(-eye[0], -eye[1], -eye[2]));
+    let proj = perspective(deg(40.), 1., (distance - r) * 0.5, distance + 2. * r);
+    Camera::new(view, proj, eye)
+}
+
+/// draw every item into a `size` by `size` image with a transparent
+/// background, seen through `thumbnail_camera` and shaded by `shader`.
+/// The images come back in the order of `items`.
+pub fn render_batch<T, F>(items: Vec<T>, size: u32, shader: F) -> Vec<ImageBuffer<Rgba<u8>, Vec<u8>>>
+    where T: Thumbnail + Send + 'static,
+          F: Fragment<PbrVertex, Color=Rgba<u8>> + Clone + Send + Sync + 'static {
+
+    let mut queue = FrameQueue::new(size, size, Rgba([0, 0, 0, 0]));
+    for item in items.into_iter() {
+        queue.push(item);
+    }
+    let mut images = Vec::with_capacity(queue.len());
+    queue.run(move |frame, item| {
+        let (center, radius) = item.bounds();
+        item.draw(frame, &thumbnail_camera(center, radius), &shader);
+    }, |_, buffer| images.push(buffer.to_image()));
+    images
+}
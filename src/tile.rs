@@ -1,18 +1,23 @@
 
 use std::mem;
+use std::cmp::min;
+use std::sync::Arc;
+use std::hash::{Hash, Hasher};
 
 use cgmath::*;
+#[cfg(feature = "image")]
 use image::{Rgba, ImageBuffer};
-use genmesh::Triangle;
 
-use {Barycentric, Interpolate, Fragment, Mapping};
-use f32x8::{f32x8x8, f32x8x8_vec3};
+use {Barycentric, RasterBackend, Coverage, Plane, PlaneSimd, Fragment, FragmentSimd, Mapping, MappingAt, Stencil};
+use f32x8::{f32x8, f32x8x8, f32x8x8_vec3};
 
 
 #[derive(Clone, Copy, Debug)]
 pub struct TileMask {
     u: f32x8x8,
     v: f32x8x8,
+    /// the weight of the first vertex, 1 - u - v
+    w: f32x8x8,
     mask: u64
 }
 
@@ -23,25 +28,76 @@ impl TileMask {
         let [u, v] =  bary.coordinate_f32x8x8(pos, scale);
         let uv = f32x8x8::broadcast(1.) - (u + v);
 
-        let mask = !(uv.to_bit_u32x8x8().bitmask() |
-                      u.to_bit_u32x8x8().bitmask() |
-                      v.to_bit_u32x8x8().bitmask());
+        // NaN lanes can have either sign, so a degenerate triangle is not
+        // left to the sign bits
+        let mask = if bary.is_degenerate() {
+            0
+        } else {
+            !(uv.to_bit_u32x8x8().bitmask() |
+              u.to_bit_u32x8x8().bitmask() |
+              v.to_bit_u32x8x8().bitmask())
+        };
 
         TileMask {
             u: u,
             v: v,
+            w: uv,
             mask: mask
         }
     }
 
+    /// the weights of a tile that is known to be inside of the triangle,
+    /// the signs are not looked at
+    #[inline(always)]
+    pub fn covered(pos: Vector2<f32>, scale: Vector2<f32>, bary: &Barycentric) -> TileMask {
+        let [u, v] = bary.coordinate_f32x8x8(pos, scale);
+        TileMask {
+            u: u,
+            v: v,
+            w: f32x8x8::broadcast(1.) - (u + v),
+            mask: !0
+        }
+    }
+
+    /// a mask from barycentric weights worked out elsewhere, they are laid
+    /// out row by row from the bottom left pixel of the tile
+    pub fn from_weights(u: [f32; 64], v: [f32; 64], mask: u64) -> TileMask {
+        let (u, v) = (f32x8x8::from_array(u), f32x8x8::from_array(v));
+        TileMask {
+            u: u,
+            v: v,
+            w: f32x8x8::broadcast(1.) - (u + v),
+            mask: mask
+        }
+    }
+
+    /// a bit per covered pixel, row by row from the bottom left
+    #[inline]
+    pub fn coverage(&self) -> u64 {
+        self.mask
+    }
+
     #[inline(always)]
     pub fn mask_with_depth(&mut self, z: &Vector3<f32>, d: &mut f32x8x8) {
         let z = f32x8x8_vec3::broadcast(Vector3::new(z.x, z.y, z.z));
-        let uv = f32x8x8::broadcast(1.) - (self.u + self.v);
-        let weights = f32x8x8_vec3([uv, self.u, self.v]);
+        let weights = f32x8x8_vec3([self.w, self.u, self.v]);
+        let depth = weights.dot(z);
+
+        self.mask &= (depth - *d).to_bit_u32x8x8().bitmask();
+        self.mask &= !(f32x8x8::broadcast(1.) + depth).to_bit_u32x8x8().bitmask();
+        d.replace(depth, self.mask);
+    }
+
+    /// `mask_with_depth` that also drops the fragments at or beyond a far
+    /// plane closer than the one the depth buffer is cleared to
+    #[inline(always)]
+    pub fn mask_with_depth_range(&mut self, z: &Vector3<f32>, far: f32, d: &mut f32x8x8) {
+        let z = f32x8x8_vec3::broadcast(Vector3::new(z.x, z.y, z.z));
+        let weights = f32x8x8_vec3([self.w, self.u, self.v]);
         let depth = weights.dot(z);
 
         self.mask &= (depth - *d).to_bit_u32x8x8().bitmask();
+        self.mask &= (depth - f32x8x8::broadcast(far)).to_bit_u32x8x8().bitmask();
         self.mask &= !(f32x8x8::broadcast(1.) + depth).to_bit_u32x8x8().bitmask();
         d.replace(depth, self.mask);
     }
@@ -49,8 +105,9 @@ impl TileMask {
     #[inline]
     pub fn iter(self) -> TileMaskIter {
         TileMaskIter {
-            u: unsafe { mem::transmute(self.u) },
-            v: unsafe { mem::transmute(self.v) },
+            u: self.u.to_array(),
+            v: self.v.to_array(),
+            w: self.w.to_array(),
             mask: self.mask
         }
     }
@@ -73,6 +130,7 @@ impl TileIndex {
 pub struct TileMaskIter {
     u: [f32; 64],
     v: [f32; 64],
+    w: [f32; 64],
     mask: u64
 }
 
@@ -91,11 +149,108 @@ impl Iterator for TileMaskIter {
         unsafe {
             let u = self.u.get_unchecked(next as usize);
             let v = self.v.get_unchecked(next as usize);
-            Some((
-                TileIndex(next as u32),
-                [1. - (u + v), *u, *v]
+            let w = self.w.get_unchecked(next as usize);
+            Some((TileIndex(next as u32), [*w, *u, *v]))
+        }
+    }
+}
+
+/// shades the pixels of a tile that survived the coverage and depth tests,
+/// the raster is generic over it so the shader and the plane evaluation are
+/// inlined into the per tile loop
+pub trait Shade<L, P> {
+    fn shade(&self, plane: &L, mask: &TileMask, color: &mut [P; 64]);
 
-            ))
+    /// `shade` for the tile whose bottom left pixel is `origin` pixels away
+    /// from the center of the frame, this is what the raster calls
+    #[inline]
+    fn shade_at(&self, plane: &L, mask: &TileMask, _origin: Vector2<f32>, color: &mut [P; 64]) {
+        self.shade(plane, mask, color)
+    }
+
+    /// false if the draw neither tests nor writes the depth buffer
+    #[inline]
+    fn depth_test(&self) -> bool { true }
+
+    /// false if the draw tests the depth buffer without writing it
+    #[inline]
+    fn depth_write(&self) -> bool { true }
+
+    /// the depth of the far plane, fragments at it or beyond are dropped.
+    /// The depth buffer is cleared to 1, so only closer planes need a test.
+    #[inline]
+    fn far_plane(&self) -> f32 { 1. }
+
+    /// how the draw tests and writes the stencil buffer, it is left alone
+    /// by default
+    #[inline]
+    fn stencil(&self) -> Stencil { Stencil::default() }
+}
+
+/// runs a `Fragment` shader once for every covered pixel, once per tile
+/// if the attributes are flat, or not at all if its output is constant
+pub struct PerFragment<F>(pub F);
+
+impl<F, L, P> Shade<L, P> for PerFragment<F>
+    where L: Plane,
+          F: Fragment<L::Out, Color=P>,
+          P: Copy {
+    #[inline]
+    fn shade(&self, plane: &L, mask: &TileMask, color: &mut [P; 64]) {
+        // a constant shader is never called, and when every fragment gets
+        // the same input it is called once and the weights are skipped
+        let flat = L::is_flat() || (L::varyings_flat() && self.0.position_unused());
+        let constant = match self.0.constant() {
+            Some(p) => Some(p),
+            None if flat => Some(self.0.fragment(plane.evaluate(0., 0.))),
+            None => None
+        };
+
+        if let Some(new) = constant {
+            let mut bits = mask.mask;
+            while bits != 0 {
+                let i = bits.trailing_zeros() as usize;
+                bits &= !(1 << i);
+                let dst = unsafe { color.get_unchecked_mut(i) };
+                *dst = self.0.blend(*dst, new);
+            }
+            return;
+        }
+
+        for (i, w) in mask.iter() {
+            let new = self.0.fragment(plane.evaluate(w[1], w[2]));
+            let dst = unsafe { color.get_unchecked_mut(i.0 as usize) };
+            *dst = self.0.blend(*dst, new);
+        }
+    }
+}
+
+/// runs a `FragmentSimd` shader once for every row of the tile that has
+/// a covered pixel, only the covered pixels are written
+pub struct Batched<F>(pub F);
+
+impl<F, L, P> Shade<L, P> for Batched<F>
+    where L: PlaneSimd,
+          F: FragmentSimd<L::Lanes, Color=P>,
+          P: Copy {
+    #[inline]
+    fn shade(&self, plane: &L, mask: &TileMask, color: &mut [P; 64]) {
+        let u: [f32x8; 8] = unsafe { mem::transmute(mask.u) };
+        let v: [f32x8; 8] = unsafe { mem::transmute(mask.v) };
+
+        for row in 0..8 {
+            let bits = (mask.mask >> (8 * row)) as u8;
+            if bits == 0 {
+                continue;
+            }
+
+            let new = self.0.fragment8(plane.evaluate8(u[row], v[row]));
+            for (lane, p) in new.iter().enumerate() {
+                if bits & (1 << lane) != 0 {
+                    let dst = &mut color[8 * row + lane];
+                    *dst = self.0.blend(*dst, *p);
+                }
+            }
         }
     }
 }
@@ -104,13 +259,15 @@ impl Iterator for TileMaskIter {
 pub struct Tile<P> {
     depth: f32x8x8,
     color: [P; 64],
+    stencil: [u8; 64]
 }
 
 impl<P: Copy> Clone for Tile<P> {
     fn clone(&self) -> Tile<P> {
         Tile {
             depth: self.depth,
-            color: self.color
+            color: self.color,
+            stencil: self.stencil
         }
     }
 }
@@ -119,13 +276,117 @@ impl<P: Copy> Tile<P> {
     pub fn new(p: P) -> Tile<P> {
          Tile {
             depth: f32x8x8::broadcast(1.),
-            color: [p; 64]
-        }       
+            color: [p; 64],
+            stencil: [0; 64]
+        }
+    }
+
+    /// the colors of `src` passed through `f`, the depth and stencil are
+    /// copied
+    fn convert_from<S: Copy, F: Fn(S) -> P>(&mut self, src: &Tile<S>, f: &F) {
+        for (dst, src) in self.color.iter_mut().zip(src.color.iter()) {
+            *dst = f(*src);
+        }
+        self.depth = src.depth;
+        self.stencil = src.stencil;
+    }
+
+    /// the color at `x`, `y` from the bottom left corner of the tile
+    #[inline]
+    pub fn get(&self, x: u32, y: u32) -> P {
+        self.color[(y * 8 + x) as usize]
+    }
+
+    #[inline]
+    pub fn set(&mut self, x: u32, y: u32, p: P) {
+        self.color[(y * 8 + x) as usize] = p;
+    }
+
+    /// the depth at `x`, `y` from the bottom left corner of the tile
+    #[inline]
+    pub fn depth(&self, x: u32, y: u32) -> f32 {
+        self.depth.to_array()[(y * 8 + x) as usize]
+    }
+
+    /// the stencil at `x`, `y` from the bottom left corner of the tile
+    #[inline]
+    pub fn stencil(&self, x: u32, y: u32) -> u8 {
+        self.stencil[(y * 8 + x) as usize]
+    }
+
+    /// the eight colors of row `y`, counted from the bottom
+    #[inline]
+    pub fn row(&self, y: u32) -> &[P] {
+        &self.color[(y * 8) as usize..(y * 8 + 8) as usize]
+    }
+
+    #[inline]
+    pub fn row_mut(&mut self, y: u32) -> &mut [P] {
+        &mut self.color[(y * 8) as usize..(y * 8 + 8) as usize]
+    }
+
+    /// the stencil and depth tests, then the shading of the covered
+    /// pixels of `mask`
+    #[inline]
+    fn shade_mask<S, L>(&mut self, mut mask: TileMask, pos: Vector2<f32>, z: &Vector3<f32>,
+                        plane: &L, shader: &S) -> usize where
+              S: Shade<L, P> {
+
+        if mask.mask == 0 {
+            return 0;
+        }
+
+        let stencil = shader.stencil();
+        if let Some(reference) = stencil.test {
+            let mut pass = 0u64;
+            for (i, &s) in self.stencil.iter().enumerate() {
+                if s == reference {
+                    pass |= 1 << i;
+                }
+            }
+            mask.mask &= pass;
+            if mask.mask == 0 {
+                return 0;
+            }
+        }
+
+        if shader.depth_test() {
+            let far = shader.far_plane();
+            // without writes the test runs against a copy
+            let mut scratch;
+            let depth = if shader.depth_write() {
+                &mut self.depth
+            } else {
+                scratch = self.depth;
+                &mut scratch
+            };
+            if far < 1. {
+                mask.mask_with_depth_range(z, far, depth);
+            } else {
+                mask.mask_with_depth(z, depth);
+            }
+            if mask.mask == 0 {
+                return 0;
+            }
+        }
+
+        if let Some(value) = stencil.write {
+            let mut bits = mask.mask;
+            while bits != 0 {
+                let i = bits.trailing_zeros() as usize;
+                bits &= !(1 << i);
+                self.stencil[i] = value;
+            }
+        }
+
+        shader.shade_at(plane, &mask, pos, &mut self.color);
+        mask.mask.count_ones() as usize
     }
 }
 
+/// four children laid out as bottom left, bottom right, top left, top right
 #[derive(Copy)]
-struct Quad<T>(pub [T; 4]);
+pub struct Quad<T>(pub [T; 4]);
 
 impl<T: Copy> Quad<T> {
     pub fn new(t: T) -> Quad<T> {
@@ -139,15 +400,74 @@ impl<T: Copy> Clone for Quad<T> {
     }
 }
 
-#[derive(Copy)]
+type Tiles<P> = Quad<Quad<Tile<P>>>;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x100_0000_01b3;
+
+/// a 64 bit FNV-1a hash of what the pixels write through `Hash`
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes.iter() {
+            self.0 = (self.0 ^ *b as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// a 32x32 block of pixels, the tiles are only allocated once something
+/// writes to them. Until then the group reads back as its clear color.
+/// Clones share the tiles until one of them is written to.
 pub struct TileGroup<P> {
-    tiles: Quad<Quad<Tile<P>>>
+    clear: P,
+    tiles: Option<Arc<Tiles<P>>>,
+    /// the storage of the tiles dropped by the last clear, the next write
+    /// reuses it instead of allocating
+    spare: Option<Arc<Tiles<P>>>,
+    /// the hash of the colors, dropped by every write and worked out again
+    /// when it is asked for
+    checksum: Option<u64>
+}
+
+/// the iterator of `TileGroup::pixels`
+pub struct Pixels<'a, P: 'a> {
+    group: &'a TileGroup<P>,
+    index: u32
+}
+
+impl<'a, P: Copy> Iterator for Pixels<'a, P> {
+    type Item = (u32, u32, P);
+
+    #[inline]
+    fn next(&mut self) -> Option<(u32, u32, P)> {
+        if self.index == 32 * 32 {
+            return None;
+        }
+        let (x, y) = (self.index % 32, self.index / 32);
+        self.index += 1;
+        Some((x, y, self.group.get(x, y)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = (32 * 32 - self.index) as usize;
+        (n, Some(n))
+    }
 }
 
 impl<P: Copy> Clone for TileGroup<P> {
     fn clone(&self) -> TileGroup<P> {
         TileGroup {
-            tiles: self.tiles
+            clear: self.clear,
+            tiles: self.tiles.clone(),
+            spare: None,
+            checksum: self.checksum
         }
     }
 }
@@ -155,51 +475,340 @@ impl<P: Copy> Clone for TileGroup<P> {
 impl<P: Copy> TileGroup<P> {
     pub fn new(p: P) -> TileGroup<P> {
         TileGroup {
-            tiles: Quad::new(Quad::new(Tile::new(p)))
+            clear: p,
+            tiles: None,
+            spare: None,
+            checksum: None
+        }
+    }
+
+    /// the contents of `src` with every color passed through `f`, depth
+    /// and stencil included. The storage of the group is kept, and cleared
+    /// like `clear` if `src` was never written to.
+    pub fn convert_from<S: Copy, F: Fn(S) -> P>(&mut self, src: &TileGroup<S>, f: &F) {
+        let clear = f(src.clear);
+        match src.tiles {
+            Some(ref tiles) => {
+                self.clear = clear;
+                for (dst, src) in self.tiles_mut().0.iter_mut().zip(tiles.0.iter()) {
+                    for (dst, src) in dst.0.iter_mut().zip(src.0.iter()) {
+                        dst.convert_from(src, f);
+                    }
+                }
+            }
+            None => self.clear(clear)
+        }
+    }
+
+    /// true once the tiles have been written to since the last clear
+    pub fn is_allocated(&self) -> bool {
+        self.tiles.is_some()
+    }
+
+    /// the color the group reads back as until it is allocated
+    pub fn clear_color(&self) -> P {
+        self.clear
+    }
+
+    /// the 8x8 tile `tx`, `ty` from the bottom left of the group, None
+    /// until the group is allocated
+    pub fn tile(&self, tx: u32, ty: u32) -> Option<&Tile<P>> {
+        assert!(tx < 4 && ty < 4);
+        self.tiles.as_ref().map(|t| &t.0[(tx / 2 + 2 * (ty / 2)) as usize].0[(tx % 2 + 2 * (ty % 2)) as usize])
+    }
+
+    /// like `tile`, allocating the group if needed
+    pub fn tile_mut(&mut self, tx: u32, ty: u32) -> &mut Tile<P> {
+        assert!(tx < 4 && ty < 4);
+        &mut self.tiles_mut().0[(tx / 2 + 2 * (ty / 2)) as usize].0[(tx % 2 + 2 * (ty % 2)) as usize]
+    }
+
+    /// the color at `x`, `y` from the bottom left corner of the group
+    pub fn get(&self, x: u32, y: u32) -> P {
+        match self.tile(x / 8, y / 8) {
+            Some(t) => t.get(x % 8, y % 8),
+            None => self.clear
+        }
+    }
+
+    /// the depth at `x`, `y`, the far plane until the group is allocated
+    pub fn depth(&self, x: u32, y: u32) -> f32 {
+        match self.tile(x / 8, y / 8) {
+            Some(t) => t.depth(x % 8, y % 8),
+            None => 1.
+        }
+    }
+
+    /// the stencil at `x`, `y`, 0 until the group is allocated
+    pub fn stencil(&self, x: u32, y: u32) -> u8 {
+        match self.tile(x / 8, y / 8) {
+            Some(t) => t.stencil(x % 8, y % 8),
+            None => 0
+        }
+    }
+
+    /// write a single color, leaving the depth alone
+    pub fn set(&mut self, x: u32, y: u32, p: P) {
+        self.tile_mut(x / 8, y / 8).set(x % 8, y % 8, p);
+    }
+
+    /// copy the 32 colors of row `y`, counted from the bottom, to the
+    /// start of `out`
+    pub fn row(&self, y: u32, out: &mut [P]) {
+        assert!(y < 32 && out.len() >= 32);
+        for (tx, dst) in out[..32].chunks_mut(8).enumerate() {
+            match self.tile(tx as u32, y / 8) {
+                Some(tile) => for (d, &p) in dst.iter_mut().zip(tile.row(y % 8)) {
+                    *d = p;
+                },
+                None => for d in dst.iter_mut() {
+                    *d = self.clear;
+                }
+            }
+        }
+    }
+
+    /// every pixel of the group with its position, row by row from the
+    /// bottom left corner
+    pub fn pixels(&self) -> Pixels<P> {
+        Pixels {
+            group: self,
+            index: 0
+        }
+    }
+
+    /// the quadrants of the group, allocating them if needed
+    pub fn quads_mut(&mut self) -> &mut Quad<Quad<Tile<P>>> {
+        self.tiles_mut()
+    }
+
+    fn tiles_mut(&mut self) -> &mut Tiles<P> {
+        self.checksum = None;
+        if self.tiles.is_none() {
+            let fresh = Quad::new(Quad::new(Tile::new(self.clear)));
+            self.tiles = Some(match self.spare.take() {
+                Some(mut tiles) => {
+                    *Arc::get_mut(&mut tiles).expect("spare tiles are never shared") = fresh;
+                    tiles
+                }
+                None => Arc::new(fresh)
+            });
+        }
+        let tiles = self.tiles.as_mut().unwrap();
+        // the tiles are shared with a snapshot, it keeps the old ones
+        if Arc::get_mut(tiles).is_none() {
+            *tiles = Arc::new((**tiles).clone());
         }
+        Arc::get_mut(tiles).unwrap()
     }
 
     pub fn write<W: Put<P>>(&self, x: u32, y: u32, v: &mut W) {
-        self.tiles.write(x, y, v);
+        match self.tiles {
+            Some(ref tiles) => tiles.write(x, y, v),
+            None => {
+                for j in 0..32 {
+                    for i in 0..32 {
+                        v.put(x+i, y+j, self.clear);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn load<G: Get<P>>(&mut self, x: u32, y: u32, src: &G) {
+        self.tiles_mut().load(x, y, src);
+    }
+
+    pub fn write_depth<W: Put<f32>>(&self, x: u32, y: u32, v: &mut W) {
+        match self.tiles {
+            Some(ref tiles) => tiles.write_depth(x, y, v),
+            None => {
+                for j in 0..32 {
+                    for i in 0..32 {
+                        v.put(x+i, y+j, 1.);
+                    }
+                }
+            }
+        }
     }
 
-    pub fn raster<F, T, O>(&mut self,
-                           pos: Vector2<f32>,
-                           scale: Vector2<f32>,
-                           z: &Vector3<f32>,
-                           bary: &Barycentric,
-                           t: &Triangle<T>,
-                           fragment: &F) where
-              T: Interpolate<Out=O>,
-              F: Fragment<O, Color=P> {
+    /// raster a triangle into the group, see `Raster::raster`. `pos` is the
+    /// bottom left pixel of the group in the space of `bary`.
+    pub fn raster<S, L, B: RasterBackend + ?Sized>(&mut self,
+                                                   pos: Vector2<f32>,
+                                                   scale: Vector2<f32>,
+                                                   z: &Vector3<f32>,
+                                                   bary: &Barycentric,
+                                                   plane: &L,
+                                                   shader: &S,
+                                                   backend: &B) -> usize where
+              S: Shade<L, P> {
+
+        self.tiles_mut().raster(pos, scale, z, bary, plane, shader, backend)
+    }
 
-        self.tiles.raster(pos, scale, z, bary, t, fragment);
+    /// reset the color to `p` and the depth to the far plane for the pixels
+    /// that `inside` accepts
+    pub fn clear_where<F: Fn(u32, u32) -> bool>(&mut self, x: u32, y: u32, inside: &F, p: P) {
+        self.tiles_mut().clear_where(x, y, inside, p);
     }
 
+    /// drops the tiles, the group reads back as `p` until the next write.
+    /// Their storage is kept for that write, see `trim`.
     pub fn clear(&mut self, p: P) {
-        Raster::clear(&mut self.tiles, p);
+        self.clear = p;
+        self.checksum = None;
+        if let Some(mut tiles) = self.tiles.take() {
+            // shared tiles stay with the other groups
+            if Arc::get_mut(&mut tiles).is_some() {
+                self.spare = Some(tiles);
+            }
+        }
+    }
+
+    /// frees the storage a clear kept for reuse
+    pub fn trim(&mut self) {
+        self.spare = None;
+    }
+
+    /// a 64 bit FNV-1a hash of the colors row by row from the bottom left,
+    /// fed through their `Hash`. Two groups with the same colors have the
+    /// same checksum whether they are allocated or not. It is kept until
+    /// the group is written to, so asking again for an unchanged group
+    /// costs nothing.
+    pub fn checksum(&mut self) -> u64 where P: Hash {
+        if let Some(c) = self.checksum {
+            return c;
+        }
+        let mut hash = Fnv(FNV_OFFSET);
+        match self.tiles {
+            Some(_) => {
+                for y in 0..32 {
+                    for x in 0..32 {
+                        self.get(x, y).hash(&mut hash);
+                    }
+                }
+            }
+            None => {
+                for _ in 0..32 * 32 {
+                    self.clear.hash(&mut hash);
+                }
+            }
+        }
+        let hash = hash.finish();
+        self.checksum = Some(hash);
+        hash
+    }
+
+    /// the bytes of the tiles written to since the last clear
+    pub fn memory(&self) -> usize {
+        if self.tiles.is_some() { mem::size_of::<Tiles<P>>() } else { 0 }
+    }
+
+    /// the bytes of the tiles a clear kept for reuse
+    pub fn spare_memory(&self) -> usize {
+        if self.spare.is_some() { mem::size_of::<Tiles<P>>() } else { 0 }
     }
 
     pub fn map<S, F>(&mut self, src: &TileGroup<S>, f: &F) where F: Mapping<S, Out=P>, S: Copy {
-        self.tiles.map(&src.tiles, f);
+        match (self.tiles.is_some(), &src.tiles) {
+            (false, &None) => {
+                self.clear = f.mapping(src.clear);
+                self.checksum = None;
+            }
+            (_, &Some(ref tiles)) => self.tiles_mut().map(&**tiles, f),
+            (true, &None) => {
+                let tiles = Quad::new(Quad::new(Tile::new(src.clear)));
+                self.tiles_mut().map(&tiles, f);
+            }
+        }
     }
 }
 
+/// the pixels of a single group, row by row from the bottom left corner
+struct Block<T> {
+    x: u32,
+    y: u32,
+    data: Vec<T>
+}
+
+impl<T> Put<T> for Block<T> {
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, p: T) {
+        self.data[((y - self.y) * 32 + x - self.x) as usize] = p;
+    }
+}
+
+impl<T: Copy> Get<T> for Block<T> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<T> {
+        Some(self.data[((y - self.y) * 32 + x - self.x) as usize])
+    }
+}
+
+impl<P: Copy> TileGroup<P> {
+    /// like `map` but `f` is also told the position of every pixel, the group
+    /// starts at `x` and `y` from the bottom left of a `width` by `height` frame
+    pub fn map_at<S, F>(&mut self, src: &TileGroup<S>, x: u32, y: u32, width: u32, height: u32, f: &F)
+        where F: MappingAt<S, Out=P>, S: Copy {
+
+        let mut block = Block { x: x, y: y, data: vec![src.clear; 32 * 32] };
+        src.write(x, y, &mut block);
+
+        let data = block.data.iter().enumerate().map(|(i, p)| {
+            // the pixels past the edge of a partial group are never read
+            // back, they are mapped as if they were on the edge
+            let (px, py) = (min(x + i as u32 % 32, width - 1), min(y + i as u32 / 32, height - 1));
+            f.mapping_at(*p, px, height - 1 - py, width, height)
+        }).collect();
+        self.load(x, y, &Block { x: x, y: y, data: data });
+    }
+}
+
+/// tile storage a triangle can be rastered into, implemented by `Tile` and
+/// by the `Quad`s of tiles a `TileGroup` is made of. `pos` is the position
+/// of the bottom left pixel and `scale` the distance between neighbouring
+/// pixels, in the space of `bary`. A pixel is covered if that point is in
+/// the triangle, `z` are the depths of its vertices. `x` and `y` count
+/// pixels from the bottom left corner of the storage. See `TileRaster` for
+/// driving it outside of a `Frame`.
 pub trait Raster<P> {
     fn mask(&self) -> u32 { 0xFFFF_FFFF - (self.size() - 1) }
+    /// the width and height in pixels
     fn size(&self) -> u32;
-    fn raster<F, T, O>(&mut self,
-                       pos: Vector2<f32>,
-                       scale: Vector2<f32>,
-                       z: &Vector3<f32>,
-                       bary: &Barycentric,
-                       t: &Triangle<T>,
-                       fragment: &F) where
-              T: Interpolate<Out=O>,
-              F: Fragment<O, Color=P>;
+    /// depth and stencil test the covered pixels, then hand the ones that
+    /// pass to `shader`. Returns the number of pixels shaded. The backend
+    /// is a type parameter so a known one is inlined into the tile loop.
+    fn raster<S, L, B: RasterBackend + ?Sized>(&mut self,
+                                               pos: Vector2<f32>,
+                                               scale: Vector2<f32>,
+                                               z: &Vector3<f32>,
+                                               bary: &Barycentric,
+                                               plane: &L,
+                                               shader: &S,
+                                               backend: &B) -> usize where
+              S: Shade<L, P>;
+
+    /// like `raster` for storage the backend found to be inside of the
+    /// triangle, the coverage is not tested again
+    #[inline]
+    fn raster_covered<S, L, B: RasterBackend + ?Sized>(&mut self,
+                                                       pos: Vector2<f32>,
+                                                       scale: Vector2<f32>,
+                                                       z: &Vector3<f32>,
+                                                       bary: &Barycentric,
+                                                       plane: &L,
+                                                       shader: &S,
+                                                       backend: &B) -> usize where
+              S: Shade<L, P> {
+        self.raster(pos, scale, z, bary, plane, shader, backend)
+    }
 
     fn clear(&mut self, p: P);
+    fn clear_where<F: Fn(u32, u32) -> bool>(&mut self, x: u32, y: u32, inside: &F, p: P);
     fn write<W: Put<P>>(&self, x: u32, y: u32, v: &mut W);
+    fn write_depth<W: Put<f32>>(&self, x: u32, y: u32, v: &mut W);
+    fn load<G: Get<P>>(&mut self, x: u32, y: u32, src: &G);
 }
 
 pub trait ApplyMapping<P, T, P2> {
@@ -211,21 +820,51 @@ impl<I, P: Copy> Raster<P> for Quad<I> where I: Raster<P> {
     fn size(&self) -> u32 { 2 * self.0[0].size() }
 
     #[inline]
-    fn raster<F, T, O>(&mut self,
-                       pos: Vector2<f32>,
-                       scale: Vector2<f32>,
-                       z: &Vector3<f32>,
-                       bary: &Barycentric,
-                       t: &Triangle<T>,
-                       fragment: &F) where
-              T: Interpolate<Out=O>,
-              F: Fragment<O, Color=P> {
+    fn raster<S, L, B: RasterBackend + ?Sized>(&mut self,
+                                               pos: Vector2<f32>,
+                                               scale: Vector2<f32>,
+                                               z: &Vector3<f32>,
+                                               bary: &Barycentric,
+                                               plane: &L,
+                                               shader: &S,
+                                               backend: &B) -> usize where
+              S: Shade<L, P> {
 
         let tsize = scale.mul_s(self.0[0].size() as f32);
-        self.0[0].raster(pos,                     scale, z, bary, t, fragment);
-        self.0[1].raster(pos + vec2(tsize.x, 0.), scale, z, bary, t, fragment);
-        self.0[2].raster(pos + vec2(0., tsize.y), scale, z, bary, t, fragment);
-        self.0[3].raster(pos + tsize,             scale, z, bary, t, fragment);
+        let offsets = [vec2(0., 0.), vec2(tsize.x, 0.), vec2(0., tsize.y), tsize];
+        let mut shaded = 0;
+        for (child, offset) in self.0.iter_mut().zip(offsets.iter()) {
+            // children that lie entirely outside of one of the edges
+            // never reach the per pixel work, the ones inside of all of
+            // them are not tested any further
+            let pos = pos + *offset;
+            shaded += match backend.bin_coverage(bary, pos, tsize) {
+                Coverage::Outside => 0,
+                Coverage::Partial => child.raster(pos, scale, z, bary, plane, shader, backend),
+                Coverage::Inside => child.raster_covered(pos, scale, z, bary, plane, shader, backend)
+            };
+        }
+        shaded
+    }
+
+    #[inline]
+    fn raster_covered<S, L, B: RasterBackend + ?Sized>(&mut self,
+                                                       pos: Vector2<f32>,
+                                                       scale: Vector2<f32>,
+                                                       z: &Vector3<f32>,
+                                                       bary: &Barycentric,
+                                                       plane: &L,
+                                                       shader: &S,
+                                                       backend: &B) -> usize where
+              S: Shade<L, P> {
+
+        let tsize = scale.mul_s(self.0[0].size() as f32);
+        let offsets = [vec2(0., 0.), vec2(tsize.x, 0.), vec2(0., tsize.y), tsize];
+        let mut shaded = 0;
+        for (child, offset) in self.0.iter_mut().zip(offsets.iter()) {
+            shaded += child.raster_covered(pos + *offset, scale, z, bary, plane, shader, backend);
+        }
+        shaded
     }
 
     #[inline]
@@ -235,6 +874,15 @@ impl<I, P: Copy> Raster<P> for Quad<I> where I: Raster<P> {
         }
     }
 
+    #[inline]
+    fn clear_where<F: Fn(u32, u32) -> bool>(&mut self, x: u32, y: u32, inside: &F, p: P) {
+        let tsize = self.0[0].size();
+        self.0[0].clear_where(x,       y,       inside, p);
+        self.0[1].clear_where(x+tsize, y,       inside, p);
+        self.0[2].clear_where(x,       y+tsize, inside, p);
+        self.0[3].clear_where(x+tsize, y+tsize, inside, p);
+    }
+
     #[inline]
     fn write<W: Put<P>>(&self, x: u32, y: u32, v: &mut W) {
         let tsize = self.0[0].size();
@@ -243,6 +891,24 @@ impl<I, P: Copy> Raster<P> for Quad<I> where I: Raster<P> {
         self.0[2].write(x,       y+tsize, v);
         self.0[3].write(x+tsize, y+tsize, v);
     }
+
+    #[inline]
+    fn write_depth<W: Put<f32>>(&self, x: u32, y: u32, v: &mut W) {
+        let tsize = self.0[0].size();
+        self.0[0].write_depth(x,       y,       v);
+        self.0[1].write_depth(x+tsize, y,       v);
+        self.0[2].write_depth(x,       y+tsize, v);
+        self.0[3].write_depth(x+tsize, y+tsize, v);
+    }
+
+    #[inline]
+    fn load<G: Get<P>>(&mut self, x: u32, y: u32, src: &G) {
+        let tsize = self.0[0].size();
+        self.0[0].load(x,       y,       src);
+        self.0[1].load(x+tsize, y,       src);
+        self.0[2].load(x,       y+tsize, src);
+        self.0[3].load(x+tsize, y+tsize, src);
+    }
 }
 
 impl<I, I2, P, P2> ApplyMapping<P, Quad<I2>, P2> for Quad<I> where I: ApplyMapping<P, I2, P2> {
@@ -258,28 +924,33 @@ impl<P: Copy> Raster<P> for Tile<P> {
     fn size(&self) -> u32 { 8 }
 
     #[inline]
-    fn raster<F, T, O>(&mut self,
-                       pos: Vector2<f32>,
-                       scale: Vector2<f32>,
-                       z: &Vector3<f32>,
-                       bary: &Barycentric,
-                       t: &Triangle<T>,
-                       fragment: &F) where
-              T: Interpolate<Out=O>,
-              F: Fragment<O, Color=P> {
+    fn raster<S, L, B: RasterBackend + ?Sized>(&mut self,
+                                               pos: Vector2<f32>,
+                                               scale: Vector2<f32>,
+                                               z: &Vector3<f32>,
+                                               bary: &Barycentric,
+                                               plane: &L,
+                                               shader: &S,
+                                               backend: &B) -> usize where
+              S: Shade<L, P> {
 
-        let mut mask = TileMask::new(pos, scale, &bary);
-        if mask.mask == 0 {
-            return;
-        }
+        let mask = backend.coverage(bary, pos, scale);
+        self.shade_mask(mask, pos, z, plane, shader)
+    }
 
-        mask.mask_with_depth(z, &mut self.depth);
-        for (i, w) in mask.iter() {
-            let frag = Interpolate::interpolate(t, w);
-            let new = fragment.fragment(frag);
-            let dst = unsafe { self.color.get_unchecked_mut(i.0 as usize) };
-            *dst = fragment.blend(*dst, new);
-        }
+    #[inline]
+    fn raster_covered<S, L, B: RasterBackend + ?Sized>(&mut self,
+                                                       pos: Vector2<f32>,
+                                                       scale: Vector2<f32>,
+                                                       z: &Vector3<f32>,
+                                                       bary: &Barycentric,
+                                                       plane: &L,
+                                                       shader: &S,
+                                                       backend: &B) -> usize where
+              S: Shade<L, P> {
+
+        let mask = backend.full_coverage(bary, pos, scale);
+        self.shade_mask(mask, pos, z, plane, shader)
     }
 
     #[inline]
@@ -289,10 +960,41 @@ impl<P: Copy> Raster<P> for Tile<P> {
         }
     }
 
+    #[inline]
+    fn write_depth<W: Put<f32>>(&self, x: u32, y: u32, v: &mut W) {
+        let depth = self.depth.to_array();
+        for i in (0..64).map(|x| TileIndex(x)) {
+            v.put(x+i.x(), y+i.y(), depth[i.0 as usize]);
+        }
+    }
+
+    #[inline]
+    fn load<G: Get<P>>(&mut self, x: u32, y: u32, src: &G) {
+        for i in (0..64).map(|x| TileIndex(x)) {
+            if let Some(p) = src.get(x+i.x(), y+i.y()) {
+                self.color[i.0 as usize] = p;
+            }
+        }
+    }
+
     #[inline]
     fn clear(&mut self, p: P) {
         self.depth = f32x8x8::broadcast(1.);
         self.color = [p; 64];
+        self.stencil = [0; 64];
+    }
+
+    #[inline]
+    fn clear_where<F: Fn(u32, u32) -> bool>(&mut self, x: u32, y: u32, inside: &F, p: P) {
+        let mut mask = 0;
+        for i in (0..64).map(|x| TileIndex(x)) {
+            if inside(x+i.x(), y+i.y()) {
+                mask |= 1 << i.0;
+                self.color[i.0 as usize] = p;
+                self.stencil[i.0 as usize] = 0;
+            }
+        }
+        self.depth.replace(f32x8x8::broadcast(1.), mask);
     }
 }
 
@@ -308,6 +1010,55 @@ pub trait Put<P> {
     fn put(&mut self, x: u32, y: u32, v: P);
 }
 
+/// the source of a tile load, returning None leaves the pixel untouched
+pub trait Get<P> {
+    fn get(&self, x: u32, y: u32) -> Option<P>;
+}
+
+impl<'a, P, G: Get<P>> Get<P> for &'a G {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> { (**self).get(x, y) }
+}
+
+/// limits a `Put` or `Get` to the pixels of a `width` by `height` frame,
+/// the tile groups along the right and top edge reach past it
+pub struct Clip<T> {
+    pub inner: T,
+    width: u32,
+    height: u32
+}
+
+impl<T> Clip<T> {
+    pub fn new(inner: T, width: u32, height: u32) -> Clip<T> {
+        Clip {
+            inner: inner,
+            width: width,
+            height: height
+        }
+    }
+}
+
+impl<P, T: Put<P>> Put<P> for Clip<T> {
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, p: P) {
+        if x < self.width && y < self.height {
+            self.inner.put(x, y, p);
+        }
+    }
+}
+
+impl<P, T: Get<P>> Get<P> for Clip<T> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        if x < self.width && y < self.height {
+            self.inner.get(x, y)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(feature = "image")]
 impl Put<Rgba<u8>> for ImageBuffer<Rgba<u8>, Vec<u8>> {
     fn put(&mut self, x: u32, y: u32, p: Rgba<u8>) {
         let h = self.height();
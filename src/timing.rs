@@ -0,0 +1,299 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+use time::precise_time_ns;
+
+use Frame;
+
+/// what a timed pass of a frame did
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pass {
+    /// transforming, clipping and binning the triangles of a raster call,
+    /// on the thread that made it
+    Geometry,
+    /// the tile workers of a raster call
+    Raster,
+    /// `map` and `map_at`
+    Map,
+    /// `load` and the passes built on it
+    Load,
+    Clear,
+    /// reading the frame back, `to_image`, `depth_buffer` and the like
+    Readback
+}
+
+impl Pass {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Pass::Geometry => "geometry",
+            Pass::Raster => "raster",
+            Pass::Map => "map",
+            Pass::Load => "load",
+            Pass::Clear => "clear",
+            Pass::Readback => "readback"
+        }
+    }
+}
+
+/// the time one pass took, in nanoseconds from when timing was turned on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PassTiming {
+    pub pass: Pass,
+    /// when the first task of the pass started
+    pub start: u64,
+    /// when the last task of the pass finished
+    pub end: u64,
+    /// the time of all the tasks added up, more than `end - start` when
+    /// they ran in parallel
+    pub busy: u64,
+    /// the number of tasks that ran
+    pub tasks: usize
+}
+
+impl PassTiming {
+    pub fn duration(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+/// one task of a traced frame, see `Frame::set_tracing`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraceEvent {
+    pub pass: Pass,
+    /// the number of the pass since the last `Frame::trace`
+    pub index: usize,
+    /// the tile group the task worked on, None for the geometry of a draw
+    pub tile: Option<(u32, u32)>,
+    /// the thread the task ran on, numbered in the order the threads
+    /// first ran a traced task
+    pub thread: usize,
+    /// nanoseconds from when tracing was turned on
+    pub start: u64,
+    pub duration: u64
+}
+
+/// the tasks of a traced frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trace {
+    /// in the order the tasks finished
+    pub events: Vec<TraceEvent>
+}
+
+impl Trace {
+    /// the events in the JSON format of chrome://tracing, every thread is
+    /// a row and every task a span on it
+    pub fn to_chrome_json(&self) -> String {
+        let mut out = String::from("{\"traceEvents\":[");
+        for (i, e) in self.events.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let tile = match e.tile {
+                Some((x, y)) => format!("[{},{}]", x, y),
+                None => "null".to_string()
+            };
+            out.push_str(&format!(
+                "{{\"name\":\"{}\",\"cat\":\"rusterize\",\"ph\":\"X\",\"pid\":0,\"tid\":{},\
+                 \"ts\":{:.3},\"dur\":{:.3},\"args\":{{\"pass\":{},\"tile\":{}}}}}",
+                e.pass.name(), e.thread, e.start as f64 / 1000., e.duration as f64 / 1000., e.index, tile));
+        }
+        out.push_str("],\"displayTimeUnit\":\"ns\"}");
+        out
+    }
+
+    /// write `to_chrome_json` into `out`, ready to be loaded in
+    /// chrome://tracing
+    pub fn write_chrome<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(self.to_chrome_json().as_bytes())
+    }
+}
+
+static NEXT_THREAD: AtomicUsize = ATOMIC_USIZE_INIT;
+thread_local!(static THREAD: usize = NEXT_THREAD.fetch_add(1, Ordering::Relaxed));
+
+/// the most passes a timer keeps between two `Frame::timings`, the oldest
+/// are dropped past that
+pub const MAX_PASSES: usize = 4096;
+
+/// the totals of one pass, in nanoseconds
+struct Stamps {
+    start: u64,
+    end: u64,
+    busy: u64,
+    tasks: usize
+}
+
+/// the timestamps the tasks of one pass record
+pub struct PassClock {
+    pass: Pass,
+    index: usize,
+    origin: u64,
+    trace: Option<Arc<Mutex<Vec<TraceEvent>>>>,
+    stamps: Mutex<Stamps>
+}
+
+impl PassClock {
+    fn now(&self) -> u64 {
+        precise_time_ns() - self.origin
+    }
+
+    /// call at the start of a task, the result goes to `end`
+    #[inline]
+    pub fn begin(&self) -> u64 {
+        let t = self.now();
+        let mut stamps = self.stamps.lock().unwrap();
+        if t < stamps.start {
+            stamps.start = t;
+        }
+        t
+    }
+
+    /// call at the end of a task on `tile`, with what `begin` returned
+    #[inline]
+    pub fn end(&self, began: u64, tile: Option<(u32, u32)>) {
+        let t = self.now();
+        {
+            let mut stamps = self.stamps.lock().unwrap();
+            if t > stamps.end {
+                stamps.end = t;
+            }
+            stamps.busy += t - began;
+            stamps.tasks += 1;
+        }
+
+        if let Some(ref trace) = self.trace {
+            let event = TraceEvent {
+                pass: self.pass,
+                index: self.index,
+                tile: tile,
+                thread: THREAD.with(|t| *t),
+                start: began,
+                duration: t - began
+            };
+            trace.lock().unwrap().push(event);
+        }
+    }
+
+    fn timing(&self) -> Option<PassTiming> {
+        let stamps = self.stamps.lock().unwrap();
+        if stamps.tasks == 0 {
+            return None;
+        }
+        Some(PassTiming {
+            pass: self.pass,
+            start: stamps.start,
+            end: stamps.end,
+            busy: stamps.busy,
+            tasks: stamps.tasks
+        })
+    }
+}
+
+/// time `f` with `clock`, if there is one
+#[inline]
+pub fn timed<R, F: FnOnce() -> R>(clock: &Option<Arc<PassClock>>, tile: Option<(u32, u32)>, f: F) -> R {
+    match *clock {
+        Some(ref c) => {
+            let began = c.begin();
+            let r = f();
+            c.end(began, tile);
+            r
+        }
+        None => f()
+    }
+}
+
+/// the passes of a frame with timing on, in the order they were started,
+/// at most `MAX_PASSES` of them
+pub struct Timer {
+    origin: u64,
+    passes: Mutex<VecDeque<Arc<PassClock>>>,
+    /// the number of passes started since the last trace
+    started: AtomicUsize,
+    trace: Option<Arc<Mutex<Vec<TraceEvent>>>>
+}
+
+impl Timer {
+    /// `trace` keeps every task on top of the totals
+    pub fn new(trace: bool) -> Timer {
+        Timer {
+            origin: precise_time_ns(),
+            passes: Mutex::new(VecDeque::new()),
+            started: AtomicUsize::new(0),
+            trace: if trace { Some(Arc::new(Mutex::new(Vec::new()))) } else { None }
+        }
+    }
+
+    /// a clock for a new pass
+    pub fn pass(&self, pass: Pass) -> Arc<PassClock> {
+        let clock = Arc::new(PassClock {
+            pass: pass,
+            index: self.started.fetch_add(1, Ordering::Relaxed),
+            origin: self.origin,
+            trace: self.trace.clone(),
+            stamps: Mutex::new(Stamps {
+                start: !0,
+                end: 0,
+                busy: 0,
+                tasks: 0
+            })
+        });
+        let mut passes = self.passes.lock().unwrap();
+        if passes.len() == MAX_PASSES {
+            passes.pop_front();
+        }
+        passes.push_back(clock.clone());
+        clock
+    }
+
+    /// the passes that ran a task so far, the rest are dropped
+    fn take(&self) -> Vec<PassTiming> {
+        let passes = ::std::mem::replace(&mut *self.passes.lock().unwrap(), VecDeque::new());
+        passes.iter().filter_map(|clock| clock.timing()).collect()
+    }
+
+    fn take_trace(&self) -> Vec<TraceEvent> {
+        self.started.store(0, Ordering::Relaxed);
+        match self.trace {
+            Some(ref t) => ::std::mem::replace(&mut *t.lock().unwrap(), Vec::new()),
+            None => Vec::new()
+        }
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// record how long every pass of the frame takes, see `timings`
+    pub fn set_timing(&mut self, on: bool) {
+        self.timer = if on { Some(Arc::new(Timer::new(false))) } else { None };
+    }
+
+    /// like `set_timing` but every task is kept as well, see `trace`
+    pub fn set_tracing(&mut self, on: bool) {
+        self.timer = if on { Some(Arc::new(Timer::new(true))) } else { None };
+    }
+
+    /// the passes since the last call, this waits for all of them to
+    /// complete. Empty without `set_timing`, only the last `MAX_PASSES`
+    /// are kept.
+    pub fn timings(&mut self) -> Vec<PassTiming> {
+        self.flush();
+        match self.timer {
+            Some(ref t) => t.take(),
+            None => Vec::new()
+        }
+    }
+
+    /// the tasks since the last call, this waits for all of them to
+    /// complete. Empty without `set_tracing`.
+    pub fn trace(&mut self) -> Trace {
+        self.flush();
+        Trace {
+            events: match self.timer {
+                Some(ref t) => t.take_trace(),
+                None => Vec::new()
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::mem;
+
+use cgmath::{Matrix4, Vector4, Matrix, FixedArray};
+
+use f32x8::f32x8;
+use quantize::Quantized;
+use math::{IntoMatrix, Mat4, Mat4d};
+
+#[inline]
+fn lanes(p: &[[f32; 4]], i: usize) -> f32x8 {
+    f32x8(p[0][i], p[1][i], p[2][i], p[3][i], p[4][i], p[5][i], p[6][i], p[7][i])
+}
+
+/// transform eight positions at once, each component is kept in its own
+/// set of lanes
+#[inline]
+fn transform8(m: &Matrix4<f32>, src: &[[f32; 4]], dst: &mut [[f32; 4]]) {
+    let (x, y, z, w) = (lanes(src, 0), lanes(src, 1), lanes(src, 2), lanes(src, 3));
+    let b = f32x8::broadcast;
+
+    let rows: [[f32; 8]; 4] = unsafe { mem::transmute([
+        x * b(m.x.x) + y * b(m.y.x) + z * b(m.z.x) + w * b(m.w.x),
+        x * b(m.x.y) + y * b(m.y.y) + z * b(m.z.y) + w * b(m.w.y),
+        x * b(m.x.z) + y * b(m.y.z) + z * b(m.z.z) + w * b(m.w.z),
+        x * b(m.x.w) + y * b(m.y.w) + z * b(m.z.w) + w * b(m.w.w)
+    ]) };
+
+    for (i, d) in dst.iter_mut().enumerate() {
+        *d = [rows[0][i], rows[1][i], rows[2][i], rows[3][i]];
+    }
+}
+
+/// multiply every position in `src` by `m` and write the results to `dst`,
+/// the bulk of the work is done eight positions at a time
+pub fn transform_into<M: IntoMatrix>(m: M, src: &[[f32; 4]], dst: &mut [[f32; 4]]) {
+    assert!(src.len() == dst.len());
+    let m = &m.into_matrix();
+
+    let batched = src.len() & !7;
+    for i in (0..batched).step_by(8) {
+        transform8(m, &src[i..i+8], &mut dst[i..i+8]);
+    }
+
+    for (s, d) in src[batched..].iter().zip(dst[batched..].iter_mut()) {
+        *d = m.mul_v(&Vector4::new(s[0], s[1], s[2], s[3])).into_fixed();
+    }
+}
+
+/// like `transform_into` but allocates the output
+pub fn transform_positions<M: IntoMatrix>(m: M, src: &[[f32; 4]]) -> Vec<[f32; 4]> {
+    let mut dst = vec![[0.; 4]; src.len()];
+    transform_into(m, src, &mut dst);
+    dst
+}
+
+/// `transform_into` for positions quantized like `Dequantize`, the
+/// decoding is folded into `m` so every position only has its integers
+/// converted on top of the transform
+pub fn transform_quantized_into<T: Quantized, M: IntoMatrix>(m: M, src: &[[T; 3]], scale: [f32; 3],
+                                                             offset: [f32; 3], dst: &mut [[f32; 4]]) {
+    assert!(src.len() == dst.len());
+
+    let decode = Matrix4::new(scale[0], 0., 0., 0.,
+                              0., scale[1], 0., 0.,
+                              0., 0., scale[2], 0.,
+                              offset[0], offset[1], offset[2], 1.);
+    let m = m.into_matrix().mul_m(&decode);
+    let widen = |p: &[T; 3]| [p[0].value(), p[1].value(), p[2].value(), 1.];
+
+    let batched = src.len() & !7;
+    for i in (0..batched).step_by(8) {
+        let mut wide = [[0.; 4]; 8];
+        for (w, p) in wide.iter_mut().zip(src[i..i+8].iter()) {
+            *w = widen(p);
+        }
+        transform8(&m, &wide, &mut dst[i..i+8]);
+    }
+
+    for (s, d) in src[batched..].iter().zip(dst[batched..].iter_mut()) {
+        let s = widen(s);
+        *d = m.mul_v(&Vector4::new(s[0], s[1], s[2], s[3])).into_fixed();
+    }
+}
+
+/// split a rigid `view` matrix, a rotation and a translation only, into
+/// the world position of the eye and the rotation alone. With those a
+/// large world is drawn with `transform_relative_into`, the rotation is
+/// multiplied by the projection in f32 without losing anything.
+pub fn split_view(view: &Mat4d) -> ([f64; 3], Mat4) {
+    let t = view[3];
+    // the inverse of a rotation is its transpose, so the eye is -R^T t
+    let eye = [-(view[0][0] * t[0] + view[0][1] * t[1] + view[0][2] * t[2]),
+               -(view[1][0] * t[0] + view[1][1] * t[1] + view[1][2] * t[2]),
+               -(view[2][0] * t[0] + view[2][1] * t[1] + view[2][2] * t[2])];
+    let mut rotation = [[0f32; 4]; 4];
+    for c in 0..3 {
+        for r in 0..3 {
+            rotation[c][r] = view[c][r] as f32;
+        }
+    }
+    rotation[3][3] = 1.;
+    (eye, rotation)
+}
+
+/// transform positions far from the origin. `model` takes `src` to world
+/// space and `eye` is subtracted from the result, both in f64. Only the
+/// positions relative to the eye, which are small where precision matters,
+/// are turned into f32 and taken to clip space with `view_proj`, which
+/// has to expect the eye at the origin. See `split_view`.
+pub fn transform_relative_into<M: IntoMatrix>(model: &Mat4d, eye: [f64; 3], view_proj: M,
+                                              src: &[[f64; 3]], dst: &mut [[f32; 4]]) {
+    assert!(src.len() == dst.len());
+    let m = view_proj.into_matrix();
+    let relative = |p: &[f64; 3]| {
+        let mut out = [0f32; 4];
+        for r in 0..3 {
+            let world = model[0][r] * p[0] + model[1][r] * p[1] + model[2][r] * p[2] + model[3][r];
+            out[r] = (world - eye[r]) as f32;
+        }
+        out[3] = 1.;
+        out
+    };
+
+    let batched = src.len() & !7;
+    for i in (0..batched).step_by(8) {
+        let mut wide = [[0.; 4]; 8];
+        for (w, p) in wide.iter_mut().zip(src[i..i+8].iter()) {
+            *w = relative(p);
+        }
+        transform8(&m, &wide, &mut dst[i..i+8]);
+    }
+
+    for (s, d) in src[batched..].iter().zip(dst[batched..].iter_mut()) {
+        let s = relative(s);
+        *d = m.mul_v(&Vector4::new(s[0], s[1], s[2], s[3])).into_fixed();
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+
+/// what went wrong with a draw, see `Frame::set_validation`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+    /// a vertex position with a NaN or infinite component, the triangle
+    /// is dropped
+    NonFinite { vertex: usize, position: [f32; 4] },
+    /// a triangle without area on screen
+    Degenerate { area: f32 },
+    /// a captured draw replayed with a different shader than it was
+    /// recorded with
+    ShaderMismatch { expected: String, found: String },
+    /// a draw submitted after `try_flush` reported a failed task, the
+    /// contents of the frame are not to be trusted
+    UseAfterFailedFlush
+}
+
+/// one problem found by the validation layer
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// counts the draws since validation was turned on
+    pub draw: usize,
+    /// the index of the triangle inside of the draw
+    pub triangle: Option<usize>,
+    pub shader: String,
+    pub vertex: String,
+    pub issue: Issue
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "draw {}", self.draw));
+        if let Some(t) = self.triangle {
+            try!(write!(f, " triangle {}", t));
+        }
+        try!(write!(f, " ({} with {}): ", self.shader, self.vertex));
+        match self.issue {
+            Issue::NonFinite { vertex, position } =>
+                write!(f, "vertex {} has a non finite position {:?}", vertex, position),
+            Issue::Degenerate { area } =>
+                write!(f, "degenerate triangle with an area of {} pixels", area),
+            Issue::ShaderMismatch { ref expected, ref found } =>
+                write!(f, "captured with {} but replayed with {}", expected, found),
+            Issue::UseAfterFailedFlush =>
+                write!(f, "the frame is used after a failed flush")
+        }
+    }
+}
+
+/// the state of the validation layer of a frame
+pub struct Validator {
+    draws: usize,
+    shader: String,
+    vertex: String,
+    failed: bool,
+    pub diagnostics: Vec<Diagnostic>
+}
+
+impl Validator {
+    pub fn new() -> Validator {
+        Validator {
+            draws: 0,
+            shader: String::new(),
+            vertex: String::new(),
+            failed: false,
+            diagnostics: Vec::new()
+        }
+    }
+
+    pub fn begin_draw(&mut self, shader: &str, vertex: &str) {
+        self.draws += 1;
+        self.shader = shader.to_string();
+        self.vertex = vertex.to_string();
+        if self.failed {
+            self.report(None, Issue::UseAfterFailedFlush);
+        }
+    }
+
+    pub fn flush_failed(&mut self) {
+        self.failed = true;
+    }
+
+    pub fn report(&mut self, triangle: Option<usize>, issue: Issue) {
+        self.diagnostics.push(Diagnostic {
+            draw: self.draws.saturating_sub(1),
+            triangle: triangle,
+            shader: self.shader.clone(),
+            vertex: self.vertex.clone(),
+            issue: issue
+        });
+    }
+
+    /// check the positions of a triangle, false if it has to be dropped
+    pub fn positions(&mut self, triangle: usize, p: [[f32; 4]; 3]) -> bool {
+        for (i, v) in p.iter().enumerate() {
+            if v.iter().any(|c| !c.is_finite()) {
+                self.report(Some(triangle), Issue::NonFinite { vertex: i, position: *v });
+                return false;
+            }
+        }
+        true
+    }
+
+    /// `a`, `b` and `c` are in pixels
+    pub fn area(&mut self, triangle: usize, a: [f32; 2], b: [f32; 2], c: [f32; 2]) {
+        let area = 0.5 * ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1]));
+        if area == 0. {
+            self.report(Some(triangle), Issue::Degenerate { area: area });
+        }
+    }
+}
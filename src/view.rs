@@ -0,0 +1,122 @@
+use std::mem;
+use std::sync::Arc;
+
+use fibe::{task, IntoTask};
+use future_pulse::Future;
+
+use {Frame, Buffer, Lerp, TileGroup, Resolved};
+use tile::{Get, Clip};
+
+/// a read only copy of a frame taken by `Frame::snapshot`, cloning it only
+/// clones the handles to its tile groups
+pub struct FrameView<P> {
+    pub width: u32,
+    pub height: u32,
+    groups: Vec<Vec<Arc<Box<TileGroup<P>>>>>
+}
+
+impl<P> Clone for FrameView<P> {
+    fn clone(&self) -> FrameView<P> {
+        FrameView {
+            width: self.width,
+            height: self.height,
+            groups: self.groups.clone()
+        }
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> FrameView<P> {
+    /// the color at `x`, `y` from the top left corner like in the images
+    /// read back from a frame
+    #[inline]
+    pub fn get_pixel(&self, x: u32, y: u32) -> P {
+        let y = self.height - 1 - y;
+        self.groups[(x / 32) as usize][(y / 32) as usize].get(x % 32, y % 32)
+    }
+
+    /// the depth at `x`, `y`, see `get_pixel`
+    #[inline]
+    pub fn depth(&self, x: u32, y: u32) -> f32 {
+        let y = self.height - 1 - y;
+        self.groups[(x / 32) as usize][(y / 32) as usize].depth(x % 32, y % 32)
+    }
+
+    /// the pixels, laid out like `Frame::to_buffer`
+    pub fn to_buffer(&self) -> Buffer<P> {
+        let fill = self.groups[0][0].clear_color();
+        let mut out = Clip::new(Buffer::new(self.width, self.height, fill), self.width, self.height);
+
+        for (x, row) in self.groups.iter().enumerate() {
+            for (y, group) in row.iter().enumerate() {
+                group.write((x*32) as u32, (y*32) as u32, &mut out);
+            }
+        }
+        out.inner
+    }
+
+    /// the view as a source for `Frame::map_resolved`
+    pub fn resolved(&self) -> Resolved<P> {
+        Resolved::from_groups(self.width, self.height, &self.groups)
+    }
+}
+
+impl<P: Copy+Lerp+Sync+Send+'static> FrameView<P> {
+    /// bilinear fetch at normalized coordinates like `Buffer::sample`, for
+    /// using the view as a texture
+    pub fn sample(&self, u: f32, v: f32) -> P {
+        let (sx, sy) = (u * self.width as f32 - 0.5, v * self.height as f32 - 0.5);
+        let (x0, y0) = (sx.floor(), sy.floor());
+        let (fx, fy) = (sx - x0, sy - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+        let get = |x: i32, y: i32| {
+            let x = x.max(0).min(self.width as i32 - 1) as u32;
+            let y = y.max(0).min(self.height as i32 - 1) as u32;
+            self.get_pixel(x, y)
+        };
+
+        let top = get(x0, y0).lerp(get(x0 + 1, y0), fx);
+        let bottom = get(x0, y0 + 1).lerp(get(x0 + 1, y0 + 1), fx);
+        top.lerp(bottom, fy)
+    }
+}
+
+/// a view of the same size can be loaded into a frame, see `Frame::load`
+impl<P: Copy+Sync+Send+'static> Get<P> for FrameView<P> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> Option<P> {
+        Some(self.groups[(x / 32) as usize][(y / 32) as usize].get(x % 32, y % 32))
+    }
+}
+
+impl<P: Copy+Sync+Send+'static> Frame<P> {
+    /// wait for the work pending on the frame and take a read only copy of
+    /// it, the frame can be drawn into again right away. The copy shares
+    /// the tiles with the frame, a group is only copied once the frame
+    /// writes to it again.
+    pub fn snapshot(&mut self) -> FrameView<P> {
+        let mut copies = Vec::with_capacity(self.tile.len());
+        for row in self.tile.iter_mut() {
+            let mut column = Vec::with_capacity(row.len());
+            for tile in row.iter_mut() {
+                let (mut new, set) = Future::new();
+                mem::swap(tile, &mut new);
+                let (copy, set_copy) = Future::new();
+                let signal = new.signal();
+                task(move |_| {
+                    let t = new.get();
+                    // only the handle to the tiles is cloned
+                    set_copy.set(Arc::new(t.clone()));
+                    set.set(t);
+                }).after(signal).start(&mut self.pool);
+                column.push(copy);
+            }
+            copies.push(column);
+        }
+
+        FrameView {
+            width: self.width,
+            height: self.height,
+            groups: copies.into_iter().map(|row| row.into_iter().map(|c| c.get()).collect()).collect()
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use {Mapping, Lerp, Rgba};
+
+#[inline]
+fn unit(v: f32, min: f32, max: f32) -> f32 {
+    if max > min { ((v - min) / (max - min)).max(0.).min(1.) } else { 0. }
+}
+
+#[inline]
+fn gray(t: f32) -> Rgba<u8> {
+    let c = (t * 255. + 0.5) as u8;
+    Rgba([c, c, c, 255])
+}
+
+/// shows a depth value as a shade of gray, black at `near` and white
+/// at `far`
+#[derive(Clone, Copy, Debug)]
+pub struct DepthGray {
+    pub near: f32,
+    pub far: f32
+}
+
+impl DepthGray {
+    /// the whole range of the depth buffer
+    pub fn new() -> DepthGray {
+        DepthGray { near: -1., far: 1. }
+    }
+}
+
+impl Mapping<f32> for DepthGray {
+    type Out = Rgba<u8>;
+
+    #[inline]
+    fn mapping(&self, d: f32) -> Rgba<u8> {
+        gray(unit(d, self.near, self.far))
+    }
+}
+
+/// shows a unit normal as a color, each axis goes from -1 to 1 and
+/// ends up in the 0 to 255 range of its channel
+#[derive(Clone, Copy, Debug)]
+pub struct NormalColor;
+
+impl Mapping<[f32; 3]> for NormalColor {
+    type Out = Rgba<u8>;
+
+    #[inline]
+    fn mapping(&self, n: [f32; 3]) -> Rgba<u8> {
+        let c = |v: f32| (unit(v, -1., 1.) * 255. + 0.5) as u8;
+        Rgba([c(n[0]), c(n[1]), c(n[2]), 255])
+    }
+}
+
+const HEAT: [Rgba<u8>; 5] = [Rgba { data: [0, 0, 255, 255] },
+                             Rgba { data: [0, 255, 255, 255] },
+                             Rgba { data: [0, 255, 0, 255] },
+                             Rgba { data: [255, 255, 0, 255] },
+                             Rgba { data: [255, 0, 0, 255] }];
+
+/// a false color ramp going from blue at `min` through cyan, green and
+/// yellow to red at `max`
+#[derive(Clone, Copy, Debug)]
+pub struct Heatmap {
+    pub min: f32,
+    pub max: f32
+}
+
+impl Mapping<f32> for Heatmap {
+    type Out = Rgba<u8>;
+
+    #[inline]
+    fn mapping(&self, v: f32) -> Rgba<u8> {
+        let t = unit(v, self.min, self.max) * (HEAT.len() - 1) as f32;
+        let i = (t as usize).min(HEAT.len() - 2);
+        HEAT[i].lerp(HEAT[i + 1], t - i as f32)
+    }
+}
+
+impl Mapping<usize> for Heatmap {
+    type Out = Rgba<u8>;
+
+    #[inline]
+    fn mapping(&self, v: usize) -> Rgba<u8> {
+        self.mapping(v as f32)
+    }
+}
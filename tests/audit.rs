@@ -0,0 +1,52 @@
+extern crate rusterize;
+extern crate genmesh;
+
+use std::cell::Cell;
+
+use rusterize::{Frame, Fragment, SolidColor, count_allocations};
+
+mod common;
+
+// stands in for the per thread count of a counting allocator
+thread_local!(static ALLOCATIONS: Cell<usize> = Cell::new(0));
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|n| n.get())
+}
+
+/// boxes every color it returns and counts the box
+#[derive(Clone)]
+struct Boxing;
+
+impl Fragment<[f32; 4]> for Boxing {
+    type Color = u32;
+
+    fn fragment(&self, _: [f32; 4]) -> u32 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        *Box::new(1)
+    }
+}
+
+#[test]
+fn shading_allocations() {
+    // closer and closer, so every draw passes the depth test
+    let quad = |z: f32| common::rect(-1., -1., 1., 1., z).into_iter();
+    for &split in [std::usize::MAX, 0].iter() {
+        let mut frame = Frame::new(64, 64, 0u32);
+        frame.set_split_threshold(split);
+        let unaudited = frame.raster(quad(0.5), Boxing);
+        frame.set_alloc_counter(Some(allocations));
+        let quiet = frame.raster(quad(0.25), SolidColor(2u32));
+        let boxing = frame.raster(quad(0.), Boxing);
+        frame.flush();
+
+        assert_eq!(unaudited.allocations(), 0);
+        assert_eq!(quiet.allocations(), 0);
+        assert_eq!(boxing.fragments(), 64 * 64);
+        assert_eq!(boxing.allocations(), boxing.fragments());
+    }
+
+    // the calling thread is measured by hand
+    assert_eq!(count_allocations(allocations, || Boxing.fragment([0.; 4])), (1, 1));
+    assert_eq!(count_allocations(allocations, || 3), (3, 0));
+}
@@ -0,0 +1,150 @@
+#![cfg(feature = "image")]
+
+extern crate rusterize;
+extern crate genmesh;
+extern crate image;
+
+use std::sync::Arc;
+
+use image::Rgba;
+use rusterize::{Frame, Buffer, SolidColor, Outline, FragmentInterlock, PixelStore};
+use rusterize::auxiliary::{Aux, WithAux, ObjectId, FragmentCount, AuxColor, AuxData};
+
+mod common;
+
+use common::rect;
+
+#[test]
+fn object_ids() {
+    let (red, blue) = (Rgba([255u8, 0, 0, 255]), Rgba([0u8, 0, 255, 255]));
+    let mut frame = Frame::new(64, 64, Aux::new(Rgba([0u8, 0, 0, 255]), 0u32));
+    let near = WithAux::new(SolidColor(red), ObjectId(1));
+    let far = WithAux::new(SolidColor(blue), ObjectId(2));
+    frame.raster(rect(-1., -1., 0., 1., 0.).into_iter(), near);
+    frame.raster(rect(-1., -1., 1., 1., 0.5).into_iter(), far);
+
+    let mut ids = Frame::new(64, 64, 0u32);
+    ids.map(&mut frame, AuxData);
+    let mut colors = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    colors.map(&mut frame, AuxColor);
+    let (ids, colors) = (ids.to_buffer(), colors.to_image());
+    assert_eq!((ids.get_pixel(10, 10), ids.get_pixel(50, 10)), (1, 2));
+    assert_eq!((*colors.get_pixel(10, 10), *colors.get_pixel(50, 10)), (red, blue));
+
+    // clearing the frame clears the ids with the color
+    frame.clear(Aux::new(Rgba([0u8, 0, 0, 255]), 7));
+    ids.map(&mut frame, AuxData);
+    assert!(ids.to_buffer().data.iter().all(|&id| id == 7));
+}
+
+#[test]
+fn fragment_count() {
+    let white = Rgba([255u8, 255, 255, 255]);
+    let mut frame = Frame::new(32, 32, Aux::new(white, 0u32));
+    let count = WithAux::new(SolidColor(white), FragmentCount);
+    // closer and closer layers over the left half, one layer on the right
+    for &(x1, z) in [(0., 0.5), (0., 0.25), (1., 0.)].iter() {
+        frame.raster(rect(-1., -1., x1, 1., z).into_iter(), count.clone());
+    }
+
+    let mut counts = Frame::new(32, 32, 0u32);
+    counts.map(&mut frame, AuxData);
+    let counts = counts.to_buffer();
+    assert_eq!((counts.get_pixel(4, 16), counts.get_pixel(28, 16)), (3, 1));
+}
+
+#[test]
+fn selection_outline() {
+    let (red, blue) = (Rgba([255u8, 0, 0, 255]), Rgba([0u8, 0, 255, 255]));
+    let yellow = Rgba([255u8, 255, 0, 255]);
+    let mut frame = Frame::new(64, 64, Aux::new(Rgba([0u8, 0, 0, 255]), 0u32));
+    let near = WithAux::new(SolidColor(red), ObjectId(1));
+    let far = WithAux::new(SolidColor(blue), ObjectId(2));
+    frame.raster(rect(-1., -1., 0., 1., 0.).into_iter(), near);
+    frame.raster(rect(-1., -1., 1., 1., 0.5).into_iter(), far);
+
+    let ids = frame.aux_buffer();
+    let mut colors = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    colors.map(&mut frame, AuxColor);
+    let mut outline = Outline::new(vec![1], yellow);
+    outline.width = 3;
+    colors.outline(&ids, &outline).unwrap();
+    assert!(colors.outline(&Buffer::new(32, 64, 0), &outline).is_err());
+
+    // the outline crosses the tile boundary between the two objects
+    let img = colors.to_image();
+    assert_eq!(*img.get_pixel(10, 10), red);
+    assert_eq!(*img.get_pixel(34, 10), yellow);
+    assert_eq!(*img.get_pixel(40, 10), blue);
+}
+
+#[test]
+fn shared_map_source() {
+    let (red, blue) = (Rgba([255u8, 0, 0, 255]), Rgba([0u8, 0, 255, 255]));
+    let mut frame = Frame::new(64, 64, Aux::new(Rgba([0u8, 0, 0, 255]), 0u32));
+    let near = WithAux::new(SolidColor(red), ObjectId(1));
+    let far = WithAux::new(SolidColor(blue), ObjectId(2));
+    frame.raster(rect(-1., -1., 0., 1., 0.).into_iter(), near);
+    frame.raster(rect(-1., -1., 1., 1., 0.5).into_iter(), far);
+
+    // both passes read the same groups, neither waits for the other
+    let resolved = frame.into_resolved();
+    let mut ids = Frame::new(64, 64, 0u32);
+    let mut colors = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    ids.map_resolved(&resolved, AuxData);
+    colors.map_resolved(&resolved.clone(), AuxColor);
+
+    let (ids, colors) = (ids.to_buffer(), colors.to_image());
+    assert_eq!((ids.get_pixel(10, 10), ids.get_pixel(50, 10)), (1, 2));
+    assert_eq!((*colors.get_pixel(10, 10), *colors.get_pixel(50, 10)), (red, blue));
+    let source = resolved.to_buffer();
+    assert_eq!((source.get_pixel(10, 10).aux, source.get_pixel(50, 10).aux), (1, 2));
+}
+
+/// pushes the id of its draw onto the fragment list of every pixel
+struct Record(u32);
+
+impl FragmentInterlock<[f32; 4], Vec<u32>> for Record {
+    type Color = u32;
+
+    fn fragment(&self, _: [f32; 4], list: &mut Vec<u32>) -> u32 {
+        list.push(self.0);
+        list.len() as u32
+    }
+}
+
+#[test]
+fn interlocked_fragment_lists() {
+    // reaching past the frame
+    let wide = |x0: f32, x1: f32, z: f32| rect(x0, -2., x1, 2., z);
+    let store = Arc::new(PixelStore::new(64, 64, Vec::new()));
+    let mut frame = Frame::with_threads(64, 64, 0u32, 4);
+    frame.raster_interlocked(wide(-2., 2., 0.5).into_iter(), Record(1), store.clone());
+    // the quadrants of split draws keep the same guarantee
+    frame.set_split_threshold(0);
+    frame.raster_interlocked(wide(-2., 0.01, 0.).into_iter(), Record(2), store.clone());
+    frame.raster_interlocked(wide(-0.51, 2., -0.5).into_iter(), Record(3), store.clone());
+
+    let colors = frame.to_buffer();
+    let mut lists = Vec::new();
+    frame.pixel_store(&store, |x, y, list| {
+        // every pixel saw its fragments one at a time in submission order
+        assert!(list.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(colors.get_pixel(x, y), list.len() as u32);
+        if y == 20 && (x == 4 || x == 24 || x == 60) {
+            lists.push(list.clone());
+        }
+        list.clear();
+    });
+    assert_eq!(lists, vec![vec![1, 2], vec![1, 2, 3], vec![1, 3]]);
+}
+
+#[test]
+#[should_panic]
+fn pixel_store_owned_by_one_frame() {
+    let store = Arc::new(PixelStore::new(64, 64, Vec::new()));
+    let mut frame = Frame::new(64, 64, 0u32);
+    frame.raster_interlocked(rect(-1., -1., 1., 1., 0.).into_iter(), Record(1), store.clone());
+    let mut other = Frame::new(64, 64, 0u32);
+    other.raster_interlocked(rect(-1., -1., 1., 1., 0.).into_iter(), Record(2), store);
+}
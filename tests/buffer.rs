@@ -0,0 +1,617 @@
+#![cfg(feature = "image")]
+
+extern crate rusterize;
+extern crate image;
+extern crate genmesh;
+
+use std::sync::Arc;
+
+use rusterize::{Frame, Buffer, Stereo};
+use rusterize::paint::{Canvas, Gradient, LinearGradient};
+use image::Rgba;
+
+mod common;
+
+const SIZE: u32 = 64;
+
+fn white() -> LinearGradient<Rgba<u8>> {
+    LinearGradient {
+        start: [0., 0.],
+        end: [1., 0.],
+        gradient: Gradient::new(Rgba([255u8, 255, 255, 255]), Rgba([255u8, 255, 255, 255]))
+    }
+}
+
+#[test]
+fn load_round_trip() {
+    let mut pattern = Buffer::new(SIZE, SIZE, 0u32);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            pattern.put_pixel(x, y, y * SIZE + x);
+        }
+    }
+
+    let mut frame = Frame::new(SIZE, SIZE, 0u32);
+    frame.load(Arc::new(pattern.clone()));
+    let out = frame.to_buffer();
+    assert_eq!(out.data, pattern.data);
+}
+
+#[test]
+fn buffer_matches_image() {
+    let canvas = Canvas::new(SIZE, SIZE);
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    frame.raster(canvas.rect(8., 8., 16., 40.).into_iter(), white());
+
+    let buffer = frame.to_buffer();
+    let img = frame.to_image();
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            assert_eq!(buffer.get_pixel(x, y), *img.get_pixel(x, y));
+        }
+    }
+}
+
+#[test]
+fn distance_field() {
+    let canvas = Canvas::new(SIZE, SIZE);
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    frame.raster(canvas.rect(16., 16., 32., 32.).into_iter(), white());
+
+    let mut sdf = Frame::new(SIZE, SIZE, 0f32);
+    frame.distance_field(&mut sdf, |p| p.data[3] > 127, 8.);
+    let sdf = sdf.to_buffer();
+
+    assert_eq!(sdf.get_pixel(32, 31), 8.);
+    assert_eq!(sdf.get_pixel(2, 31), -8.);
+    assert!(sdf.get_pixel(20, 31) > 0.);
+    assert!(sdf.get_pixel(12, 31) < 0.);
+}
+
+#[test]
+fn copy_rect() {
+    let mut pattern = Buffer::new(SIZE, SIZE, 0u32);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            pattern.put_pixel(x, y, y * SIZE + x);
+        }
+    }
+
+    let mut src = Frame::new(SIZE, SIZE, 0u32);
+    src.load(Arc::new(pattern.clone()));
+
+    let mut dst = Frame::new(SIZE, SIZE, 7u32);
+    dst.copy_rect(&mut src, rusterize::Rect::new(4, 8, 40, 20), (30, 50));
+    let out = dst.to_buffer();
+
+    assert_eq!(out.get_pixel(30, 50), pattern.get_pixel(4, 8));
+    assert_eq!(out.get_pixel(63, 63), pattern.get_pixel(37, 21));
+    assert_eq!(out.get_pixel(29, 50), 7);
+    assert_eq!(out.get_pixel(30, 49), 7);
+
+    let mut copy = Frame::new(SIZE, SIZE, 0u32);
+    copy.copy_from(&mut dst);
+    assert_eq!(copy.to_buffer().data, out.data);
+}
+
+#[test]
+fn downsample_upsample() {
+    let mut ramp = Buffer::new(SIZE, SIZE, 0f32);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            ramp.put_pixel(x, y, x as f32);
+        }
+    }
+
+    let mut full = Frame::new(SIZE, SIZE, 0f32);
+    full.load(Arc::new(ramp));
+
+    let mut half = Frame::new(SIZE / 2, SIZE / 2, 0f32);
+    full.downsample(&mut half);
+    let out = half.to_buffer();
+    assert_eq!(out.get_pixel(0, 0), 0.5);
+    assert_eq!(out.get_pixel(10, 20), 20.5);
+
+    let mut half_ramp = Buffer::new(SIZE / 2, SIZE / 2, 0f32);
+    for y in 0..SIZE / 2 {
+        for x in 0..SIZE / 2 {
+            half_ramp.put_pixel(x, y, x as f32);
+        }
+    }
+    half.load(Arc::new(half_ramp));
+    half.upsample(&mut full);
+    let out = full.to_buffer();
+    assert_eq!(out.get_pixel(0, 5), 0.);
+    assert_eq!(out.get_pixel(10, 5), 4.75);
+    assert_eq!(out.get_pixel(63, 40), 31.);
+}
+
+#[test]
+fn depth_aware_upsample() {
+    let ones = LinearGradient {
+        start: [0., 0.],
+        end: [1., 0.],
+        gradient: Gradient::new(1f32, 1f32)
+    };
+
+    // the same rectangle rendered at half and at full resolution
+    let mut half = Frame::new(SIZE / 2, SIZE / 2, 0f32);
+    half.raster(Canvas::new(SIZE / 2, SIZE / 2).rect(0., 0., 15.5, 32.).into_iter(), ones.clone());
+    let mut scene = Frame::new(SIZE, SIZE, 0f32);
+    scene.raster(Canvas::new(SIZE, SIZE).rect(0., 0., 31.5, 64.).into_iter(), ones);
+    let depth = scene.depth_buffer();
+    assert_eq!(depth.get_pixel(31, 10), 0.);
+    assert_eq!(depth.get_pixel(32, 10), 1.);
+
+    let mut out = Frame::new(SIZE, SIZE, 0f32);
+    half.upsample_depth_aware(&mut out, depth);
+    let out = out.to_buffer();
+    assert!(out.get_pixel(31, 10) > 0.99);
+    assert!(out.get_pixel(32, 10) < 0.01);
+}
+
+#[test]
+fn resolve() {
+    let mut ramp = Buffer::new(SIZE, SIZE, 0f32);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            ramp.put_pixel(x, y, x as f32);
+        }
+    }
+    let mut samples = Frame::new(SIZE, SIZE, 0f32);
+    samples.load(Arc::new(ramp));
+
+    let mut out = Frame::new(SIZE / 2, SIZE / 2, 0f32);
+    samples.resolve(&mut out, 2, rusterize::BoxResolve);
+    assert!((out.to_buffer().get_pixel(10, 3) - 20.5).abs() < 1e-4);
+
+    samples.resolve(&mut out, 2, rusterize::TentResolve);
+    assert!((out.to_buffer().get_pixel(10, 3) - 20.5).abs() < 1e-4);
+
+    samples.resolve(&mut out, 2, |s: &[f32], _| s.iter().fold(0f32, |a, b| a.max(*b)));
+    assert_eq!(out.to_buffer().get_pixel(10, 3), 21.);
+}
+
+#[test]
+fn lens_distortion() {
+    use rusterize::Lens;
+
+    let mut ramp = Buffer::new(SIZE, SIZE, 0f32);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            ramp.put_pixel(x, y, x as f32);
+        }
+    }
+    let mut src = Frame::new(SIZE, SIZE, 0f32);
+    src.load(Arc::new(ramp));
+
+    // no distortion is a plain copy
+    let mut dst = Frame::new(SIZE, SIZE, 0f32);
+    src.lens_distort(&mut dst, Lens { k1: 0., k2: 0. });
+    let out = dst.to_buffer();
+    assert!((out.get_pixel(10, 40) - 10.).abs() < 1e-3);
+
+    // barrel distortion pulls the edges of the source towards the center
+    src.lens_distort(&mut dst, Lens { k1: 0.2, k2: 0. });
+    let out = dst.to_buffer();
+    let c = SIZE / 2;
+    assert!(out.get_pixel(4, c) < 4.);
+    assert!(out.get_pixel(SIZE - 5, c) > (SIZE - 5) as f32);
+
+    let mut color = Frame::new(SIZE, SIZE, Rgba([255u8, 255, 255, 255]));
+    let mut split = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    color.chromatic_aberration(&mut split, Lens { k1: 0., k2: 0. }, 0.05);
+    assert_eq!(*split.to_image().get_pixel(c, c), Rgba([255, 255, 255, 255]));
+}
+
+#[test]
+fn motion_blur() {
+    // a bright column on black, the left half of the frame moves across
+    let mut column = Buffer::new(SIZE, SIZE, 0f32);
+    let mut motion = Buffer::new(SIZE, SIZE, [0f32, 0.]);
+    for y in 0..SIZE {
+        column.put_pixel(16, y, 1.);
+        column.put_pixel(48, y, 1.);
+        for x in 0..SIZE / 2 {
+            motion.put_pixel(x, y, [8., 0.]);
+        }
+    }
+    let mut src = Frame::new(SIZE, SIZE, 0f32);
+    src.load(Arc::new(column));
+    let mut dst = Frame::new(SIZE, SIZE, 0f32);
+    src.motion_blur(&mut dst, motion, 1., 8);
+    let out = dst.to_buffer();
+
+    // the moving column is smeared over the motion and keeps its energy
+    let row = (10..23).fold(0f32, |a, x| a + out.get_pixel(x, 20));
+    assert!((row - 1.).abs() < 1e-3);
+    assert!(out.get_pixel(16, 20) < 0.5);
+    assert!(out.get_pixel(13, 20) > 0.);
+    assert_eq!(out.get_pixel(24, 20), 0.);
+    // the still one stays sharp
+    assert_eq!(out.get_pixel(48, 20), 1.);
+    assert_eq!(out.get_pixel(47, 20), 0.);
+}
+
+#[test]
+fn edge_preserving_denoise() {
+    use rusterize::Denoise;
+
+    // a checkerboard of noise on the left surface, a flat one on the
+    // right, closer to the eye, with a corner that faces another way
+    let mut noisy = Buffer::new(SIZE, SIZE, 1f32);
+    let mut depth = Buffer::new(SIZE, SIZE, 0.8f32);
+    let mut normals = Buffer::new(SIZE, SIZE, [0f32, 0., 1.]);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            if x < SIZE / 2 {
+                noisy.put_pixel(x, y, ((x + y) % 2) as f32);
+                depth.put_pixel(x, y, 0.2);
+            } else if y < SIZE / 4 {
+                noisy.put_pixel(x, y, 0.);
+                normals.put_pixel(x, y, [1., 0., 0.]);
+            }
+        }
+    }
+    let mut src = Frame::new(SIZE, SIZE, 0f32);
+    src.load(Arc::new(noisy));
+    let mut dst = Frame::new(SIZE, SIZE, 0f32);
+    src.denoise(&mut dst, depth, normals, Denoise::default());
+    let out = dst.to_buffer();
+
+    assert!((out.get_pixel(16, 32) - 0.5).abs() < 1e-3);
+    assert!((out.get_pixel(SIZE / 2, 32) - 1.).abs() < 1e-3);
+    assert!((out.get_pixel(40, SIZE / 4) - 1.).abs() < 1e-3);
+    assert!(out.get_pixel(40, SIZE / 4 - 1) < 1e-3);
+}
+
+#[test]
+fn convert_between_pixel_types() {
+    use rusterize::SolidColor;
+
+    // the left half is drawn at a depth of 0.5, the right half never is
+    let mut src = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 255, 255]));
+    let left = common::rect(-1., -1., 0., 1., 0.5);
+    src.raster(left.into_iter(), SolidColor(Rgba([255u8, 128, 0, 255])));
+
+    let mut dst = Frame::new(SIZE, SIZE, [0f32; 4]);
+    src.convert_into(&mut dst, |p: Rgba<u8>| {
+        let c = |v: u8| v as f32 / 255.;
+        // a swizzle to bgra on the way
+        [c(p.data[2]), c(p.data[1]), c(p.data[0]), c(p.data[3])]
+    });
+    let out = dst.to_buffer();
+    assert_eq!(out.get_pixel(10, 10), [0., 128. / 255., 1., 1.]);
+    assert_eq!(out.get_pixel(50, 10), [1., 0., 0., 1.]);
+    assert_eq!(dst.depth_buffer().data, src.depth_buffer().data);
+    assert_eq!(dst.depth_buffer().get_pixel(10, 10), 0.5);
+
+    // the source keeps its own pixels
+    assert_eq!(src.to_image().get_pixel(10, 10).data, [255, 128, 0, 255]);
+    let mut small = Frame::new(32, 32, 0u32);
+    assert!(src.try_convert_into(&mut small, |p: Rgba<u8>| p.data[0] as u32).is_err());
+
+    // converting a cleared frame keeps the storage of the tiles around
+    let bytes = dst.memory_usage().tile_bytes;
+    src.clear(Rgba([0u8, 0, 0, 255]));
+    src.convert_into(&mut dst, |p: Rgba<u8>| [p.data[0] as f32; 4]);
+    let usage = dst.memory_usage();
+    assert_eq!((usage.tile_bytes, usage.spare_bytes), (0, bytes));
+    assert_eq!(dst.to_buffer().get_pixel(10, 10), [0.; 4]);
+}
+
+#[test]
+fn fallible_apis() {
+    use rusterize::{Error, BoxResolve};
+
+    assert_eq!(Frame::try_new(0, 64, 0f32).err(), Some(Error::InvalidSize { width: 0, height: 64 }));
+    let too_wide = rusterize::capabilities().max_dimension + 1;
+    assert_eq!(Frame::try_new(too_wide, 32, 0f32).err(), Some(Error::InvalidSize { width: too_wide, height: 32 }));
+    assert!(Frame::try_new(64, 40, 0f32).is_ok());
+    let mut small = Frame::try_new(32, 32, Rgba([0u8, 0, 0, 0])).unwrap();
+    let mut large = Frame::try_new(SIZE, SIZE, Rgba([0u8, 0, 0, 0])).unwrap();
+
+    let mismatch = Error::SizeMismatch { expected: (32, 32), found: (SIZE, SIZE) };
+    assert_eq!(small.try_map(&mut large, rusterize::Lut3d::identity(2)), Err(mismatch.clone()));
+    assert_eq!(small.try_copy_from(&mut large), Err(mismatch));
+    assert!(large.try_resolve(&mut small, 2, BoxResolve).is_ok());
+    assert!(large.try_resolve(&mut small, 3, BoxResolve).is_err());
+
+    assert!(small.try_flush().is_ok());
+    assert_eq!(small.try_to_image().unwrap().into_raw(), vec![0; 32 * 32 * 4]);
+}
+
+#[test]
+fn frame_builder() {
+    use rusterize::{Error, FrameBuilder};
+
+    let mut frame = FrameBuilder::new(40, 24, 3u32).samples(2).split_threshold(0).threads(2).build();
+    assert_eq!((frame.width, frame.height), (80, 48));
+    assert!(!frame.painter());
+    assert!(frame.to_buffer().data.iter().all(|&p| p == 3));
+
+    let frame = Frame::builder(32, 32, 0u32).depth(false).deterministic(true).build();
+    assert!(frame.painter());
+
+    // the samples count towards the limits
+    let max = rusterize::capabilities().max_dimension;
+    assert!(Frame::builder(max, 32, 0u32).try_build().is_ok());
+    assert_eq!(Frame::builder(max, 32, 0u32).samples(2).try_build().err(),
+               Some(Error::InvalidSize { width: max * 2, height: 64 }));
+    assert!(FrameBuilder::new(0, 32, 0u32).try_build().is_err());
+}
+
+#[test]
+fn tile_checksums() {
+    use genmesh::Triangle;
+    use rusterize::{Rect, SolidColor};
+
+    let mut frame = Frame::new(SIZE, SIZE, 0u32);
+    let first = frame.checksums();
+    assert_eq!(frame.checksums(), first);
+    assert!(frame.checksums().changed(&first).is_empty());
+
+    // a clear to the same color is no change
+    frame.clear(0);
+    assert!(frame.checksums().changed(&first).is_empty());
+
+    // a triangle in the bottom left group
+    let corner = Triangle::new([-1., -1., 0., 1.], [-0.6, -1., 0., 1.], [-1., -0.6, 0., 1.]);
+    frame.raster(vec![corner.clone()].into_iter(), SolidColor(7u32));
+    let drawn = frame.checksums();
+    assert_eq!(drawn.changed(&first), vec![Rect::new(0, SIZE - 32, 32, 32)]);
+    assert!(drawn.get(0, 0) != first.get(0, 0));
+    assert_eq!(drawn.get(1, 1), first.get(1, 1));
+
+    // the same pixels drawn again give the same checksums
+    frame.clear(0);
+    frame.raster(vec![corner].into_iter(), SolidColor(7u32));
+    assert!(frame.checksums().changed(&drawn).is_empty());
+}
+
+#[test]
+fn tile_streaming() {
+    use std::sync::Mutex;
+    use genmesh::Triangle;
+    use rusterize::{Rect, SolidColor, TilePacket, WriteSink};
+
+    let (w, h) = (70, 40);
+    let mut frame = Frame::new(w, h, Rgba([0u8, 0, 0, 255]));
+    let t = Triangle::new([-1., -1., 0., 1.], [1., -1., 0., 1.], [-1., 1., 0., 1.]);
+    frame.raster(vec![t].into_iter(), SolidColor(Rgba([255u8, 128, 0, 255])));
+
+    let sink = Arc::new(Mutex::new(Vec::new()));
+    frame.stream_tiles(sink.clone(), true);
+    let expected = frame.to_buffer();
+    let packets = sink.lock().unwrap().clone();
+    assert_eq!(packets.len(), 3 * 2);
+
+    // a client rebuilds the frame from the packets alone
+    let mut copy = Buffer::new(w, h, Rgba([9u8, 9, 9, 9]));
+    for p in packets.iter() {
+        assert!(p.rect.width <= 32 && p.rect.height <= 32);
+        p.write_into(&mut copy).unwrap();
+    }
+    assert_eq!(copy.data, expected.data);
+
+    // through a byte stream, compressed and not
+    let out = Arc::new(WriteSink::new(Vec::new()));
+    frame.stream_tiles(out.clone(), false);
+    frame.flush();
+    let bytes = match Arc::try_unwrap(out) {
+        Ok(sink) => sink.into_inner().unwrap(),
+        Err(_) => panic!("the sink is still shared")
+    };
+    let mut src = &bytes[..];
+    let mut raw = Vec::new();
+    while !src.is_empty() {
+        raw.push(TilePacket::read_from(&mut src).unwrap());
+    }
+    assert_eq!(raw.len(), packets.len());
+    let mut total = (0, 0);
+    for r in raw.iter() {
+        // the groups may finish in another order
+        let p = packets.iter().find(|p| p.rect == r.rect).unwrap();
+        assert!(!r.compressed && p.compressed);
+        assert_eq!(r.pixels::<Rgba<u8>>().unwrap(), p.pixels::<Rgba<u8>>().unwrap());
+        total = (total.0 + r.data.len(), total.1 + p.data.len());
+    }
+    assert!(total.1 < total.0 / 4);
+    assert!(raw[0].pixels::<f32>().is_err());
+    assert!(TilePacket::read_from(&mut &bytes[..10]).is_err());
+
+    // rects that wrap around or are not on the group grid
+    let pixels = vec![0f32; 32 * 32];
+    let far = TilePacket::new(Rect::new(::std::u32::MAX - 15, 0, 32, 32), &pixels, false);
+    assert!(far.write_into(&mut Buffer::new(64, 64, 0f32)).is_err());
+    let mut bytes = Vec::new();
+    TilePacket::new(Rect::new(5, 0, 32, 32), &pixels, false).write_to(&mut bytes).unwrap();
+    assert!(TilePacket::read_from(&mut &bytes[..]).is_err());
+}
+
+#[test]
+fn frame_queue() {
+    use genmesh::Triangle;
+    use rusterize::{FrameQueue, SolidColor};
+
+    let mut queue = FrameQueue::new(40, 24, 0u32);
+    for i in 0..7 {
+        queue.push(i);
+    }
+    queue.set_in_flight(Some(3));
+    assert_eq!(queue.in_flight(), 3);
+
+    // every frame draws a triangle that moves with its state
+    let frames = queue.render(|frame, &i| {
+        let x = -1. + i as f32 * 0.25;
+        let t = Triangle::new([x, -1., 0., 1.], [x + 0.25, -1., 0., 1.], [x, 1., 0., 1.]);
+        frame.raster(vec![t].into_iter(), SolidColor(i + 1));
+    });
+    assert_eq!(frames.len(), 7);
+    for (i, f) in frames.iter().enumerate() {
+        let drawn: Vec<u32> = f.data.iter().cloned().filter(|&p| p != 0).collect();
+        assert!(!drawn.is_empty());
+        assert!(drawn.iter().all(|&p| p == i as u32 + 1));
+    }
+
+    // one frame alone is never split further
+    let mut single = FrameQueue::new(SIZE, SIZE, 0u32);
+    single.push(());
+    assert_eq!(single.in_flight(), 1);
+}
+
+#[test]
+fn screen_projection() {
+    use genmesh::Triangle;
+    use rusterize::{SolidColor, pixel_ndc};
+
+    let (w, h) = (70, 40);
+    let mut frame = Frame::new(w, h, 0u32);
+    let t = Triangle::new([-0.5, -0.5, 0.2, 2.], [1.5, -0.5, 0.2, 2.], [-0.5, 1.5, 0.2, 2.]);
+    frame.raster(vec![t].into_iter(), SolidColor(1u32));
+
+    // the pixels on either side of a projected edge
+    let edge = frame.project_to_screen([1.5, -0.5, 0.2, 2.]).unwrap();
+    let corner = frame.project_to_screen([-0.5, -0.5, 0.2, 2.]).unwrap();
+    assert_eq!(edge[2], 0.1);
+    let (x, y) = ((corner[0] + 3.).round() as u32, (corner[1] - 3.).round() as u32);
+    let buffer = frame.to_buffer();
+    assert_eq!(buffer.get_pixel(x, y), 1);
+    assert_eq!(buffer.get_pixel(x, corner[1].round() as u32 + 2), 0);
+    assert_eq!(buffer.get_pixel(edge[0].round() as u32 + 2, y), 0);
+    assert!((frame.depth_buffer().get_pixel(x, y) - 0.1).abs() < 1e-6);
+
+    // the sample points map back to their pixels
+    let ndc = pixel_ndc(12, 7, w, h);
+    let p = frame.project_to_screen([ndc[0], ndc[1], 0., 1.]).unwrap();
+    assert!((p[0] - 12.).abs() < 1e-4 && (p[1] - 7.).abs() < 1e-4);
+    assert_eq!(frame.project_to_screen([0., 0., 0., 0.]), None);
+    assert_eq!(frame.project_to_screen([0., 0., 0., -1.]), None);
+}
+
+#[test]
+fn draw_data() {
+    use genmesh::Triangle;
+    use rusterize::{Flat, FragmentWith};
+
+    // looks the color of a triangle up in the table of the draw
+    struct Palette;
+
+    impl FragmentWith<([f32; 4], usize), Vec<u32>> for Palette {
+        type Color = u32;
+
+        fn fragment(&self, table: &Vec<u32>, (_, i): ([f32; 4], usize)) -> u32 { table[i] }
+    }
+
+    let t = |x: f32, i: usize| Triangle::new(([x, -1., 0., 1.], Flat(i)),
+                                            ([x + 1., -1., 0., 1.], Flat(i)),
+                                            ([x, 1., 0., 1.], Flat(i)));
+    // the same shader draws with two tables
+    let mut frame = Frame::new(SIZE, SIZE, 0u32);
+    frame.raster_with(vec![t(-1., 1)].into_iter(), Palette, Arc::new(vec![10, 20]));
+    frame.raster_with(vec![t(0., 0)].into_iter(), Palette, Arc::new(vec![30, 40]));
+
+    let buffer = frame.to_buffer();
+    assert_eq!(buffer.get_pixel(4, SIZE / 2), 20);
+    assert_eq!(buffer.get_pixel(SIZE / 2 + 4, SIZE / 2), 30);
+}
+
+#[test]
+fn stereo() {
+    let (w, h) = (40, 24);
+    let mut left = Frame::new(w, h, Rgba([200u8, 10, 20, 255]));
+    let mut right = Frame::new(w, h, Rgba([30u8, 100, 150, 255]));
+    let (l, r) = (Rgba([200u8, 10, 20, 255]), Rgba([30u8, 100, 150, 255]));
+
+    let mut out = Frame::new(w, h, Rgba([0u8; 4]));
+    out.stereo(&mut left, &mut right, Stereo::Anaglyph);
+    assert!(out.to_image().pixels().all(|p| *p == Rgba([200, 100, 150, 255])));
+
+    assert_eq!(Stereo::SideBySide.size(w, h), (2 * w, h));
+    let mut out = Frame::new(2 * w, h, Rgba([0u8; 4]));
+    out.stereo(&mut left, &mut right, Stereo::SideBySide);
+    let img = out.to_image();
+    assert_eq!((*img.get_pixel(w - 1, 5), *img.get_pixel(w, 5)), (l, r));
+
+    let mut out = Frame::new(w, h, Rgba([0u8; 4]));
+    out.stereo(&mut left, &mut right, Stereo::RowInterleaved);
+    let img = out.to_image();
+    assert_eq!((*img.get_pixel(3, 0), *img.get_pixel(3, 1), *img.get_pixel(3, 22)), (l, r, l));
+
+    out.stereo(&mut left, &mut right, Stereo::ColumnInterleaved);
+    let img = out.to_image();
+    assert_eq!((*img.get_pixel(0, 7), *img.get_pixel(1, 7), *img.get_pixel(39, 7)), (l, r, r));
+}
+
+#[test]
+fn frame_snapshot() {
+    let mut pattern = Buffer::new(SIZE, SIZE, 0u32);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            pattern.put_pixel(x, y, y * SIZE + x);
+        }
+    }
+    let mut frame = Frame::new(SIZE, SIZE, 0u32);
+    frame.load(Arc::new(pattern.clone()));
+
+    // the view keeps the pixels while the frame moves on
+    let view = frame.snapshot();
+    frame.clear(7);
+    let copy = view.clone();
+    assert_eq!(copy.to_buffer().data, pattern.data);
+    assert_eq!(copy.get_pixel(3, 5), 5 * SIZE + 3);
+    assert_eq!(copy.depth(3, 5), 1.);
+    assert!(frame.to_buffer().data.iter().all(|&p| p == 7));
+
+    // and can be loaded back or read by a map
+    frame.load(Arc::new(view.clone()));
+    assert_eq!(frame.to_buffer().data, pattern.data);
+    let mut colors = Frame::new(SIZE, SIZE, [0f32; 2]);
+    colors.map_resolved(&view.resolved(), Coords);
+    assert_eq!(colors.to_buffer().get_pixel(3, 5), [3., 5.]);
+
+    let mut gradient = Frame::new(SIZE, SIZE, 0f32);
+    gradient.load(Arc::new(Buffer {
+        width: SIZE,
+        height: SIZE,
+        data: (0..SIZE * SIZE).map(|i| (i % SIZE) as f32).collect()
+    }));
+    let view = gradient.snapshot();
+    assert_eq!(view.sample(0.5, 0.5), 31.5);
+}
+
+struct Coords;
+
+impl rusterize::Mapping<u32> for Coords {
+    type Out = [f32; 2];
+    fn mapping(&self, p: u32) -> [f32; 2] {
+        [(p % SIZE) as f32, (p / SIZE) as f32]
+    }
+}
+
+#[test]
+fn compare_images() {
+    use image::ImageBuffer;
+    use rusterize::image_diff;
+
+    let reference = ImageBuffer::from_fn(4, 4, |x, _| {
+        if x < 2 { Rgba([255u8, 255, 255, 255]) } else { Rgba([0u8, 0, 0, 0]) }
+    });
+    let same = image_diff(&reference, &reference, 0);
+    assert_eq!((same.mismatched, same.coverage, same.max), (0, 0, 0));
+    assert!(same.psnr().is_infinite());
+
+    let mut image = reference.clone();
+    // one pixel covered that is not in the reference, one a little off
+    image.put_pixel(2, 0, Rgba([255, 255, 255, 255]));
+    image.put_pixel(0, 3, Rgba([250, 255, 255, 255]));
+    let diff = image_diff(&image, &reference, 2);
+    assert_eq!((diff.pixels, diff.mismatched, diff.coverage, diff.max), (16, 2, 1, 255));
+    assert_eq!(diff.mismatched_ratio(), 2. / 16.);
+    assert_eq!(diff.image.get_pixel(2, 0), &Rgba([255, 0, 0, 255]));
+    assert_eq!(diff.image.get_pixel(0, 3), &Rgba([250, 250, 250, 255]));
+    assert_eq!(diff.image.get_pixel(1, 1), &Rgba([255, 255, 255, 255]));
+
+    // within the tolerance the pixel only counts towards the mean
+    assert_eq!(image_diff(&image, &reference, 5).mismatched, 1);
+}
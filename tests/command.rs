@@ -0,0 +1,214 @@
+#![cfg(feature = "image")]
+
+extern crate rusterize;
+extern crate image;
+extern crate snowstorm;
+extern crate genmesh;
+
+use rusterize::{Frame, CommandList, DrawMerger, SolidColor};
+use rusterize::paint::{Canvas, Gradient, LinearGradient};
+use image::Rgba;
+
+fn solid(c: Rgba<u8>) -> LinearGradient<Rgba<u8>> {
+    LinearGradient {
+        start: [0., 0.],
+        end: [1., 0.],
+        gradient: Gradient::new(c, c)
+    }
+}
+
+#[test]
+fn replay_matches_direct() {
+    let blue = Rgba([0u8, 0, 255, 255]);
+    let white = Rgba([255u8, 255, 255, 255]);
+    let canvas = Canvas::new(64, 64);
+
+    let mut list = CommandList::new();
+    list.clear(blue);
+    list.raster(canvas.rect(8.5, 8.5, 20., 30.).into_iter(), solid(white));
+    assert_eq!(list.len(), 2);
+
+    let mut direct = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    direct.clear(blue);
+    direct.raster(canvas.rect(8.5, 8.5, 20., 30.).into_iter(), solid(white));
+
+    let mut replayed = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    list.replay(&mut replayed);
+    assert!(direct.to_image().into_raw() == replayed.to_image().into_raw());
+
+    // the same list at twice the resolution
+    let mut large = Frame::new(128, 128, Rgba([0u8, 0, 0, 0]));
+    list.replay(&mut large);
+    let img = large.to_image();
+    assert_eq!(*img.get_pixel(2, 2), blue);
+    assert_eq!(*img.get_pixel(40, 60), white);
+}
+
+#[derive(Clone)]
+struct SetValue(Rgba<u8>);
+
+impl rusterize::Fragment<[f32; 4]> for SetValue {
+    type Color = Rgba<u8>;
+
+    fn fragment(&self, _: [f32; 4]) -> Rgba<u8> { self.0 }
+}
+
+#[test]
+fn capture_round_trip() {
+    let white = Rgba([255u8, 255, 255, 255]);
+    let canvas = Canvas::new(64, 64);
+
+    let mut frame = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    frame.begin_capture();
+    frame.raster(canvas.polygon(&[[4.5, 4.5], [60.5, 10.5], [20.5, 50.5]]).into_iter(), solid(white));
+    let capture = frame.end_capture().unwrap();
+    assert_eq!(capture.draws.len(), 1);
+    assert_eq!(capture.draws[0].triangles.len(), 1);
+    assert!(capture.draws[0].shader.contains("LinearGradient"));
+
+    let mut file = Vec::new();
+    capture.save(&mut file).unwrap();
+    let loaded = rusterize::Capture::load(&file[..]).unwrap();
+    assert_eq!(loaded, capture);
+
+    let mut replayed = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    loaded.replay(&mut replayed, |_| SetValue(white));
+    assert!(frame.to_image().into_raw() == replayed.to_image().into_raw());
+}
+
+#[test]
+fn shared_frame_threads() {
+    use std::thread;
+    use rusterize::SharedFrame;
+
+    let white = Rgba([255u8, 255, 255, 255]);
+    let shared = SharedFrame::new(Frame::new(64, 64, Rgba([0u8, 0, 0, 0])));
+
+    let threads: Vec<_> = (0..4).map(|i| {
+        let shared = shared.clone();
+        thread::spawn(move || {
+            let canvas = Canvas::new(64, 64);
+            let (x, y) = ((i % 2) as f32 * 32., (i / 2) as f32 * 32.);
+            shared.raster(canvas.rect(x + 4.5, y + 4.5, 20., 20.).into_iter(), solid(white));
+        })
+    }).collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    let img = shared.lock().to_image();
+    for &(x, y) in [(10, 10), (42, 10), (10, 42), (42, 42)].iter() {
+        assert_eq!(*img.get_pixel(x, y), white);
+    }
+    assert_eq!(*img.get_pixel(30, 30), Rgba([0u8, 0, 0, 0]));
+}
+
+#[test]
+fn raster_from_channel() {
+    use std::thread;
+
+    let white = Rgba([255u8, 255, 255, 255]);
+    let (mut tx, rx) = snowstorm::channel::channel();
+    let producer = thread::spawn(move || {
+        let canvas = Canvas::new(64, 64);
+        for i in 0..4 {
+            let y = i as f32 * 16.;
+            for t in canvas.rect(4.5, y + 4.5, 40., 8.) {
+                tx.send(t);
+            }
+        }
+    });
+
+    let mut frame = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    frame.raster_channel(rx, solid(white));
+    producer.join().unwrap();
+
+    let img = frame.to_image();
+    for i in 0..4 {
+        assert_eq!(*img.get_pixel(20, i * 16 + 8), white);
+        assert_eq!(*img.get_pixel(20, i * 16 + 14), Rgba([0u8, 0, 0, 0]));
+    }
+}
+
+#[test]
+fn merge_draws() {
+    let (red, blue) = (SolidColor(Rgba([255u8, 0, 0, 255])), SolidColor(Rgba([0u8, 0, 255, 255])));
+    let canvas = Canvas::new(64, 64);
+    let cell = |i: u32| canvas.rect((i % 8) as f32 * 8., (i / 8) as f32 * 8., 8., 8.).into_iter();
+
+    let mut ordered = DrawMerger::new();
+    let mut unordered = DrawMerger::unordered();
+    for i in 0..64 {
+        let color = if i < 16 || i % 2 == 0 { red } else { blue };
+        ordered.push(cell(i), color);
+        unordered.push(cell(i), color);
+    }
+    assert_eq!(ordered.draws(), 64);
+    assert_eq!(ordered.batches(), 48);
+    assert_eq!(unordered.batches(), 2);
+
+    let mut a = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    let stats = ordered.submit(&mut a);
+    assert_eq!(stats.len(), 48);
+    let mut b = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    let stats = unordered.submit(&mut b);
+    assert_eq!(stats.iter().map(|s| s.triangles).fold(0, |a, b| a + b), 128);
+
+    // the cells do not overlap, so both orders give the same picture
+    let img = a.to_image();
+    assert!(img.clone().into_raw() == b.to_image().into_raw());
+    assert_eq!(*img.get_pixel(4, 4), Rgba([255, 0, 0, 255]));
+    assert_eq!(*img.get_pixel(12, 60), Rgba([0, 0, 255, 255]));
+}
+
+#[test]
+fn validation_layer() {
+    use rusterize::Issue;
+    use genmesh::Triangle;
+
+    let white = Rgba([255u8, 255, 255, 255]);
+    let nan = ::std::f32::NAN;
+    let tris = vec![
+        Triangle::new([-1., -1., 0., 1.], [1., -1., 0., 1.], [nan, 1., 0., 1.]),
+        Triangle::new([-1., -1., 0., 1.], [0., 0., 0., 1.], [1., 1., 0., 1.]),
+        Triangle::new([-1., -1., 0., 1.], [1., -1., 0., 1.], [-1., 1., 0., 1.])
+    ];
+
+    let mut frame = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    frame.raster(tris.clone().into_iter(), SetValue(white));
+    assert!(frame.diagnostics().is_empty());
+
+    frame.set_validation(true);
+    let stats = frame.raster(tris.into_iter(), SetValue(white));
+    // the triangle with a NaN is dropped as degenerate, not culled
+    assert_eq!((stats.culled, stats.degenerate), (0, 2));
+
+    let found = frame.diagnostics();
+    assert_eq!(found.len(), 2);
+    assert_eq!((found[0].draw, found[0].triangle), (0, Some(0)));
+    match found[0].issue {
+        Issue::NonFinite { vertex, position } => assert!(vertex == 2 && position[0].is_nan()),
+        ref other => panic!("unexpected {:?}", other)
+    }
+    assert_eq!(found[1].triangle, Some(1));
+    assert_eq!(found[1].issue, Issue::Degenerate { area: 0. });
+    assert!(found[1].shader.contains("SetValue"));
+    assert!(format!("{}", found[1]).contains("degenerate"));
+    assert!(frame.diagnostics().is_empty());
+
+    // replaying a capture with another shader
+    let canvas = Canvas::new(64, 64);
+    frame.begin_capture();
+    frame.raster(canvas.rect(0., 0., 8., 8.).into_iter(), solid(white));
+    let capture = frame.end_capture().unwrap();
+    capture.replay(&mut frame, |_| SetValue(white));
+    let found = frame.diagnostics();
+    assert_eq!(found.len(), 1);
+    match found[0].issue {
+        Issue::ShaderMismatch { ref expected, ref found } => {
+            assert!(expected.contains("LinearGradient"));
+            assert!(found.contains("SetValue"));
+        }
+        ref other => panic!("unexpected {:?}", other)
+    }
+}
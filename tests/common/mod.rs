@@ -0,0 +1,19 @@
+//! helpers shared by the tests, not every test uses all of them
+#![allow(dead_code)]
+
+use genmesh::Triangle;
+
+/// the quad through `a`, `b`, `c` and `d` in order, in both windings so
+/// it is drawn whichever way it faces
+pub fn quad<T: Clone>(a: T, b: T, c: T, d: T) -> Vec<Triangle<T>> {
+    vec![Triangle::new(a.clone(), b.clone(), c.clone()),
+         Triangle::new(a.clone(), c.clone(), b),
+         Triangle::new(a.clone(), c.clone(), d.clone()),
+         Triangle::new(a, d, c)]
+}
+
+/// the rectangle from `x0`, `y0` to `x1`, `y1` in clip space at depth `z`
+pub fn rect(x0: f32, y0: f32, x1: f32, y1: f32, z: f32) -> Vec<Triangle<[f32; 4]>> {
+    let v = |x: f32, y: f32| [x, y, z, 1.];
+    quad(v(x0, y0), v(x1, y0), v(x1, y1), v(x0, y1))
+}
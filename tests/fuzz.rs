@@ -0,0 +1,67 @@
+extern crate rusterize;
+
+use std::f32;
+use std::mem;
+
+use rusterize::fuzz::{raster, readback};
+
+fn bytes(floats: &[f32]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for &f in floats.iter() {
+        let bits: u32 = unsafe { mem::transmute(f) };
+        for k in 0..4 {
+            out.push((bits >> (8 * k)) as u8);
+        }
+    }
+    out
+}
+
+fn input(width: u8, height: u8, mode: u8, floats: &[f32]) -> Vec<u8> {
+    let mut data = vec![width - 1, height - 1, mode];
+    data.extend(bytes(floats).into_iter());
+    data
+}
+
+const FULL: [f32; 12] = [-2., -2., 0., 1.,   6., -2., 0., 1.,   -2., 6., 0., 1.];
+
+#[test]
+fn fuzz_raster_covers() {
+    for mode in 0..8 {
+        let buffer = raster(&input(40, 33, mode, &FULL));
+        assert_eq!((buffer.width, buffer.height), (40, 33));
+        assert!(buffer.data.iter().all(|&p| p == 1));
+    }
+}
+
+#[test]
+fn fuzz_raster_odd_inputs() {
+    let odd = [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, f32::MIN_POSITIVE / 4., -0., 1e30, -1e30];
+    for &v in odd.iter() {
+        for i in 0..12 {
+            let mut floats = FULL;
+            floats[i] = v;
+            for mode in 0..16 {
+                let mut data = input(70, 20, mode, &[]);
+                if mode & 8 != 0 {
+                    data.extend(bytes(&[0., 1., 0., v]).into_iter());
+                }
+                data.extend(bytes(&floats).into_iter());
+                let buffer = raster(&data);
+                assert!(buffer.data.iter().all(|&p| p <= 1));
+            }
+        }
+    }
+
+    // short and empty inputs draw nothing
+    assert!(raster(&[]).data.iter().all(|&p| p == 0));
+    assert!(raster(&input(5, 5, 0, &FULL[..11])).data.iter().all(|&p| p == 0));
+}
+
+#[test]
+fn fuzz_readback() {
+    for &(w, h) in [(1, 1), (32, 32), (33, 65), (96, 7)].iter() {
+        let (pattern, all, region) = readback(&[w - 1, h - 1, 3, 5, 9, 200]);
+        assert_eq!(all.data, pattern.data);
+        assert_eq!(region.width, ::std::cmp::min(10, (w as u32).saturating_sub(3)));
+    }
+}
@@ -0,0 +1,76 @@
+#![cfg(all(feature = "gltf", feature = "image"))]
+
+extern crate rusterize;
+extern crate cgmath;
+extern crate image;
+
+use std::path::Path;
+
+use rusterize::Frame;
+use rusterize::gltf;
+use cgmath::Matrix4;
+use image::Rgba;
+
+// one triangle covering the lower left half of clip space, positions are
+// f32 followed by u16 indices padded to four bytes
+const DOCUMENT: &'static str = r#"{
+    "asset": { "version": "2.0" },
+    "buffers": [{
+        "byteLength": 44,
+        "uri": "data:application/octet-stream;base64,AACAvwAAgL8AAAAAAACAPwAAgL8AAAAAAACAvwAAgD8AAAAAAAABAAIAAAA="
+    }],
+    "bufferViews": [
+        { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+    ],
+    "accessors": [
+        { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+        { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+    ],
+    "materials": [{
+        "pbrMetallicRoughness": {
+            "baseColorFactor": [1.0, 0.0, 0.0, 1.0],
+            "metallicFactor": 0.0,
+            "roughnessFactor": 0.8
+        }
+    }],
+    "meshes": [{
+        "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }]
+    }]
+}"#;
+
+#[test]
+fn load_embedded_triangle() {
+    let prims = gltf::parse(DOCUMENT, Path::new(".")).unwrap();
+    assert_eq!(prims.len(), 1);
+    let prim = &prims[0];
+    assert_eq!(prim.mesh.positions, vec![[-1., -1., 0.], [1., -1., 0.], [-1., 1., 0.]]);
+    assert_eq!(prim.mesh.indices, vec![0, 1, 2]);
+    assert_eq!(prim.material.base_color, [1., 0., 0., 1.]);
+    assert_eq!(prim.material.roughness, 0.8);
+
+    let mut material = prim.material.clone();
+    material.eye = [0., 0., 5.];
+    material.light_dir = [0., 0., 1.];
+
+    let id = Matrix4::identity();
+    let mut frame = Frame::new(32, 32, Rgba([0u8, 0, 0, 0]));
+    frame.raster(prim.mesh.triangles(&id, &id).into_iter(), material);
+    let img = frame.to_image();
+    let inside = img.get_pixel(4, 28).data;
+    assert!(inside[0] > 100 && inside[1] < inside[0]);
+    assert_eq!(*img.get_pixel(28, 4), Rgba([0, 0, 0, 0]));
+}
+
+#[test]
+fn reject_bad_references() {
+    let broken = DOCUMENT.replace("\"indices\": 1", "\"indices\": 7");
+    assert!(gltf::parse(&broken, Path::new(".")).is_err());
+    assert!(gltf::parse("{ not json", Path::new(".")).is_err());
+
+    // positions read as two components and sparse accessors are errors
+    let vec2 = DOCUMENT.replace("\"VEC3\"", "\"VEC2\"");
+    assert!(gltf::parse(&vec2, Path::new(".")).is_err());
+    let sparse = DOCUMENT.replace("\"type\": \"VEC3\"", "\"type\": \"VEC3\", \"sparse\": { \"count\": 0 }");
+    assert!(gltf::parse(&sparse, Path::new(".")).is_err());
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "glyph")]
+
+extern crate rusterize;
+
+use rusterize::glyph::{Outline, GlyphAtlas};
+
+#[test]
+fn outline_coverage() {
+    let mut outline = Outline::new();
+    outline.move_to([2.5, 2.]);
+    outline.line_to([6., 2.]);
+    outline.line_to([6., 6.]);
+    outline.line_to([2.5, 6.]);
+    outline.close();
+
+    let coverage = outline.rasterize(8, 8);
+    assert_eq!(coverage[3 * 8 + 4], 255);
+    assert_eq!(coverage[0], 0);
+    assert_eq!(coverage[7 * 8 + 7], 0);
+    assert!((coverage[5 * 8 + 2] as i32 - 128).abs() <= 1);
+}
+
+#[test]
+fn atlas_packing() {
+    let mut atlas = GlyphAtlas::new(16, 16);
+    let glyph = [255u8; 36];
+    assert_eq!(atlas.insert(6, 6, &glyph), Some((0, 0)));
+    assert_eq!(atlas.insert(6, 6, &glyph), Some((7, 0)));
+    assert_eq!(atlas.insert(6, 6, &glyph), Some((0, 7)));
+    assert_eq!(atlas.insert(6, 6, &glyph[..]), Some((7, 7)));
+    assert_eq!(atlas.insert(6, 6, &glyph), None);
+    assert_eq!(atlas.coverage(8, 8), 255);
+    assert_eq!(atlas.coverage(6, 0), 0);
+}
@@ -0,0 +1,172 @@
+//! renders the same scenes with OpenGL and with rusterize and compares the
+//! images, run with `cargo test --features gpu_reference`. A failing scene
+//! leaves both images and their difference in test_data/results.
+#![cfg(all(feature = "gpu_reference", feature = "image"))]
+
+extern crate image;
+extern crate genmesh;
+extern crate cgmath;
+extern crate rusterize;
+extern crate obj;
+extern crate glutin;
+extern crate gl;
+
+use std::ffi::CString;
+use std::fs::File;
+use std::path::{self, Path};
+use std::{mem, ptr};
+
+use rusterize::{Frame, Fragment, image_diff};
+use cgmath::*;
+use genmesh::{Triangulate, MapToVertices, Triangle};
+use image::{ImageBuffer, Rgba};
+use gl::types::*;
+
+const SIZE: u32 = 256;
+
+const VERTEX: &'static str = "
+#version 120
+attribute vec4 position;
+void main() { gl_Position = position; }
+";
+
+const FRAGMENT: &'static str = "
+#version 120
+void main() { gl_FragColor = vec4(1.0); }
+";
+
+#[derive(Clone)]
+struct White;
+
+impl Fragment<[f32; 4]> for White {
+    type Color = Rgba<u8>;
+
+    fn fragment(&self, _: [f32; 4]) -> Rgba<u8> { Rgba([255, 255, 255, 255]) }
+}
+
+fn compile(kind: GLenum, src: &str) -> GLuint {
+    unsafe {
+        let shader = gl::CreateShader(kind);
+        let src = CString::new(src).unwrap();
+        gl::ShaderSource(shader, 1, &src.as_ptr(), ptr::null());
+        gl::CompileShader(shader);
+        let mut ok = gl::FALSE as GLint;
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut ok);
+        assert!(ok == gl::TRUE as GLint);
+        shader
+    }
+}
+
+/// draw the clip space triangles white on transparent black with a depth
+/// test, the rows are flipped to the top left origin of `to_image`
+fn render_gl(triangles: &[Triangle<[f32; 4]>]) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let context = glutin::HeadlessRendererBuilder::new(SIZE, SIZE).build().unwrap();
+    unsafe { context.make_current() };
+    gl::load_with(|s| context.get_proc_address(s));
+
+    // GL samples the pixel centers, rusterize the bottom left corners,
+    // moving the geometry half a pixel up and right lines them up
+    let mut data = Vec::with_capacity(triangles.len() * 12);
+    for t in triangles.iter() {
+        for p in [t.x, t.y, t.z].iter() {
+            data.push(p[0] + p[3] / SIZE as f32);
+            data.push(p[1] + p[3] / SIZE as f32);
+            data.push(p[2]);
+            data.push(p[3]);
+        }
+    }
+
+    let mut pixels = vec![0u8; (SIZE * SIZE * 4) as usize];
+    unsafe {
+        let program = gl::CreateProgram();
+        gl::AttachShader(program, compile(gl::VERTEX_SHADER, VERTEX));
+        gl::AttachShader(program, compile(gl::FRAGMENT_SHADER, FRAGMENT));
+        gl::LinkProgram(program);
+        gl::UseProgram(program);
+
+        let (mut vao, mut vbo) = (0, 0);
+        gl::GenVertexArrays(1, &mut vao);
+        gl::BindVertexArray(vao);
+        gl::GenBuffers(1, &mut vbo);
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl::BufferData(gl::ARRAY_BUFFER,
+                       (data.len() * mem::size_of::<f32>()) as GLsizeiptr,
+                       mem::transmute(data.as_ptr()),
+                       gl::STATIC_DRAW);
+        let position = gl::GetAttribLocation(program, CString::new("position").unwrap().as_ptr());
+        gl::EnableVertexAttribArray(position as GLuint);
+        gl::VertexAttribPointer(position as GLuint, 4, gl::FLOAT, gl::FALSE, 0, ptr::null());
+
+        gl::Viewport(0, 0, SIZE as GLint, SIZE as GLint);
+        gl::Enable(gl::DEPTH_TEST);
+        gl::DepthFunc(gl::LESS);
+        // `Frame::raster` drops the back faces as well
+        gl::Enable(gl::CULL_FACE);
+        gl::FrontFace(gl::CCW);
+        gl::CullFace(gl::BACK);
+        gl::ClearColor(0., 0., 0., 0.);
+        gl::ClearDepth(1.);
+        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        gl::DrawArrays(gl::TRIANGLES, 0, (data.len() / 4) as GLsizei);
+        gl::Finish();
+
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(0, 0, SIZE as GLint, SIZE as GLint, gl::RGBA, gl::UNSIGNED_BYTE,
+                       mem::transmute(pixels.as_mut_ptr()));
+    }
+
+    ImageBuffer::from_fn(SIZE, SIZE, |x, y| {
+        let i = (((SIZE - 1 - y) * SIZE + x) * 4) as usize;
+        Rgba([pixels[i], pixels[i+1], pixels[i+2], pixels[i+3]])
+    })
+}
+
+fn render_rusterize(triangles: &[Triangle<[f32; 4]>]) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    frame.raster(triangles.iter().map(|t| t.clone()), White);
+    frame.to_image()
+}
+
+/// render `triangles` both ways and check that at most `ratio` of the
+/// pixels differ in coverage
+fn compare(name: &str, triangles: Vec<Triangle<[f32; 4]>>, ratio: f32) {
+    let reference = render_gl(&triangles);
+    let image = render_rusterize(&triangles);
+    let diff = image_diff(&image, &reference, 0);
+
+    let coverage = diff.coverage as f32 / diff.pixels as f32;
+    if coverage > ratio {
+        let results = Path::new("test_data/results");
+        for &(suffix, ref img) in [("gl", &reference), ("frame", &image), ("diff", &diff.image)].iter() {
+            let mut fout = File::create(&results.join(format!("{}.{}.png", name, suffix))).unwrap();
+            let _ = image::ImageRgba8((*img).clone()).save(&mut fout, image::PNG);
+        }
+    }
+    assert!(coverage <= ratio, "{}: {} of {} pixels differ in coverage",
+            name, diff.coverage, diff.pixels);
+}
+
+#[test]
+fn gpu_plane() {
+    let proj = ortho(-1., 1., -1., 1., -2., 2.);
+    let triangles = genmesh::generators::Plane::new()
+        .triangulate()
+        .vertex(|v| proj.mul_v(&Vector4::new(v.0 * 0.75, v.1 * 0.5, 0., 1.)).into_fixed())
+        .collect();
+    compare("gpu_plane", triangles, 0.);
+}
+
+#[test]
+fn gpu_monkey() {
+    let obj = obj::load(&path::Path::new("test_assets/monkey.obj")).unwrap();
+    let monkey = obj.object_iter().next().unwrap().group_iter().next().unwrap();
+    let proj = ortho(-1.5, 1.5, -1.5, 1.5, -10., 10.);
+
+    let triangles = monkey.indices().iter().map(|x| *x)
+        .vertex(|(p, _, _)| obj.position()[p])
+        .vertex(|p| proj.mul_v(&Vector4::new(p[0], p[1], p[2], 1.)).into_fixed())
+        .triangulate()
+        .collect();
+    // edges shared by two triangles may land on either side of a sample
+    compare("gpu_monkey", triangles, 0.002);
+}
@@ -96,4 +96,38 @@ fn test_f32_4() {
     assert_eq!(Interpolate::interpolate(&v100, s001), [0., 0., 0., 0.]);
     assert_eq!(Interpolate::interpolate(&v100, s010), [0., 0., 0., 0.]);
     assert_eq!(Interpolate::interpolate(&v100, s100), [1., 2., 3., 4.]);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_plane() {
+    use rusterize::{Plane, Flat};
+
+    let t = Triangle::new(([1., 2.], Flat(7u32)), ([3., 2.], Flat(8u32)), ([1., 6.], Flat(9u32)));
+    let plane = Interpolate::setup(&t);
+
+    for &(u, v) in [(0., 0.), (1., 0.), (0., 1.), (0.25, 0.5)].iter() {
+        assert_eq!(plane.evaluate(u, v), Interpolate::interpolate(&t, [1. - u - v, u, v]));
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Weight(f32);
+
+impl Interpolate for Weight {
+    type Out = f32;
+    fn interpolate(src: &Triangle<Weight>, w: [f32; 3]) -> f32 {
+        src.x.0 * w[0] + src.y.0 * w[1] + src.z.0 * w[2]
+    }
+}
+
+#[test]
+fn default_setup() {
+    use rusterize::Plane;
+
+    let t = Triangle::new(Weight(1.), Weight(3.), Weight(7.));
+    let plane = Interpolate::setup(&t);
+
+    for &(u, v) in [(0., 0.), (1., 0.), (0., 1.), (0.25, 0.5)].iter() {
+        assert_eq!(plane.evaluate(u, v), Interpolate::interpolate(&t, [1. - u - v, u, v]));
+    }
+}
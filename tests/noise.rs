@@ -0,0 +1,50 @@
+extern crate rusterize;
+
+use rusterize::{Noise, BlueNoise};
+
+#[test]
+fn noise_is_deterministic() {
+    let a = Noise::frame(7, 3);
+    let b = Noise::frame(7, 3);
+    let c = Noise::frame(7, 4);
+
+    let mut differs = false;
+    for i in 0..32 {
+        let (x, y) = (i * 7, i * 3);
+        assert_eq!(a.pixel(x, y), b.pixel(x, y));
+        assert!(a.pixel(x, y) >= 0. && a.pixel(x, y) < 1.);
+        differs |= a.pixel(x, y) != c.pixel(x, y);
+
+        let p = (x as f32 * 0.37, y as f32 * 0.21);
+        let v = a.value(p.0, p.1);
+        assert!(v >= 0. && v <= 1.);
+        assert!(a.perlin(p.0, p.1).abs() <= 1.5);
+    }
+    assert!(differs);
+    assert_eq!(a.perlin(3., 5.), 0.);
+}
+
+#[test]
+fn blue_noise_ranks() {
+    let blue = BlueNoise::new(16, 1);
+    let mut sorted = blue.data.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    for (i, v) in sorted.iter().enumerate() {
+        assert_eq!(*v, (i as f32 + 0.5) / 256.);
+    }
+
+    // the first points are spread out, no two of the first 16 touch
+    let first: Vec<(i32, i32)> = (0..256).filter(|&i| blue.data[i] < 16. / 256.)
+                                         .map(|i| ((i % 16) as i32, (i / 16) as i32))
+                                         .collect();
+    for a in first.iter() {
+        for b in first.iter() {
+            if a != b {
+                let dx = (a.0 - b.0).abs().min(16 - (a.0 - b.0).abs());
+                let dy = (a.1 - b.1).abs().min(16 - (a.1 - b.1).abs());
+                assert!(dx > 1 || dy > 1);
+            }
+        }
+    }
+    assert_eq!(blue.get(3, 4), blue.get(19, 36));
+}
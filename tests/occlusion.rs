@@ -0,0 +1,42 @@
+extern crate rusterize;
+extern crate cgmath;
+extern crate genmesh;
+
+use cgmath::{Matrix, Matrix4, perspective, deg};
+use genmesh::Triangle;
+use rusterize::occlusion::OcclusionBuffer;
+
+#[test]
+fn occlusion_queries() {
+    // the eye at the origin looking down -z
+    let view_proj: Matrix4<f32> = perspective(deg(90.), 1., 0.5, 50.);
+    let mut buffer = OcclusionBuffer::new(64, 64);
+    buffer.begin(view_proj);
+
+    // nothing is hidden before the occluders are in
+    assert!(buffer.aabb_visible([-1., -1., -12.], [1., 1., -10.]));
+
+    // a wall covering the left half of the view at z = -5
+    buffer.add_occluders(vec![Triangle::new([-10., -10., -5.], [0., -10., -5.], [0., 10., -5.]),
+                              Triangle::new([-10., -10., -5.], [0., 10., -5.], [-10., 10., -5.])].into_iter());
+    buffer.finish();
+    assert_eq!(buffer.levels().len(), 7);
+    assert_eq!(buffer.levels()[6].width, 1);
+
+    // behind the wall
+    assert!(!buffer.aabb_visible([-4., -1., -12.], [-2., 1., -10.]));
+    // in front of it
+    assert!(buffer.aabb_visible([-3., -1., -4.], [-2., 1., -3.]));
+    // behind it, but reaching past its edge
+    assert!(buffer.aabb_visible([-4., -1., -12.], [1., 1., -10.]));
+    // on the open side
+    assert!(buffer.aabb_visible([2., -1., -12.], [4., 1., -10.]));
+    // outside of the view and around the eye
+    assert!(!buffer.aabb_visible([-1., -1., 5.], [1., 1., 6.]));
+    assert!(buffer.aabb_visible([-1., -1., -1.], [1., 1., 1.]));
+
+    // a new frame forgets the wall
+    buffer.begin(view_proj);
+    buffer.finish();
+    assert!(buffer.aabb_visible([-4., -1., -12.], [-2., 1., -10.]));
+}
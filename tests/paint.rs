@@ -0,0 +1,303 @@
+#![cfg(feature = "image")]
+
+extern crate rusterize;
+extern crate image;
+extern crate genmesh;
+
+use rusterize::Frame;
+use rusterize::paint::{Canvas, Gradient, LinearGradient, RadialGradient};
+use image::Rgba;
+
+const SIZE: u32 = 64;
+
+fn near(a: Rgba<u8>, b: Rgba<u8>) -> bool {
+    a.data.iter().zip(b.data.iter()).all(|(&a, &b)| (a as i32 - b as i32).abs() <= 1)
+}
+
+#[test]
+fn linear_gradient() {
+    let canvas = Canvas::new(SIZE, SIZE);
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    frame.raster(canvas.rect(0., 0., 64., 64.).into_iter(), LinearGradient {
+        start: [0., 0.],
+        end: [64., 0.],
+        gradient: Gradient::new(Rgba([0u8, 0, 0, 255]), Rgba([255u8, 255, 255, 255]))
+    });
+
+    let img = frame.to_image();
+    assert!(near(*img.get_pixel(16, 32), Rgba([64, 64, 64, 255])));
+    assert!(near(*img.get_pixel(48, 32), Rgba([191, 191, 191, 255])));
+    assert!(near(*img.get_pixel(48, 8), Rgba([191, 191, 191, 255])));
+}
+
+#[test]
+fn radial_gradient_stops() {
+    let canvas = Canvas::new(SIZE, SIZE);
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    let gradient = Gradient::new(Rgba([255u8, 0, 0, 255]), Rgba([0u8, 0, 255, 255]))
+        .stop(0.5, Rgba([0u8, 255, 0, 255]));
+
+    frame.raster(canvas.rect(0., 0., 64., 64.).into_iter(), RadialGradient {
+        center: [32., 32.],
+        radius: 32.,
+        gradient: gradient
+    });
+
+    let img = frame.to_image();
+    // image row y samples the canvas at y + 1
+    assert!(near(*img.get_pixel(32, 31), Rgba([255, 0, 0, 255])));
+    assert!(near(*img.get_pixel(48, 31), Rgba([0, 255, 0, 255])));
+    assert!(near(*img.get_pixel(60, 60), Rgba([0, 0, 255, 255])));
+}
+
+#[test]
+fn polygon_winding() {
+    let canvas = Canvas::new(SIZE, SIZE);
+    let cw = canvas.polygon(&[[8., 8.], [56., 8.], [56., 56.], [8., 56.]]);
+    let ccw = canvas.polygon(&[[8., 56.], [56., 56.], [56., 8.], [8., 8.]]);
+    assert_eq!(cw.len(), 2);
+    assert_eq!(ccw.len(), 2);
+
+    for tris in vec![cw, ccw] {
+        let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+        frame.raster(tris.into_iter(), LinearGradient {
+            start: [0., 0.],
+            end: [1., 0.],
+            gradient: Gradient::new(Rgba([255u8, 255, 255, 255]), Rgba([255u8, 255, 255, 255]))
+        });
+        let img = frame.to_image();
+        assert_eq!(*img.get_pixel(32, 32), Rgba([255, 255, 255, 255]));
+        assert_eq!(*img.get_pixel(2, 2), Rgba([0, 0, 0, 0]));
+    }
+}
+
+#[test]
+fn nested_clip() {
+    let white = Gradient::new(Rgba([255u8, 255, 255, 255]), Rgba([255u8, 255, 255, 255]));
+    let brush = LinearGradient { start: [0., 0.], end: [1., 0.], gradient: white };
+
+    let mut canvas = Canvas::new(SIZE, SIZE);
+    canvas.push_clip_rect(8., 8., 48., 48.);
+    canvas.push_clip_path(&[[0., 0.], [40., 0.], [40., 64.], [0., 64.]]);
+    let clipped = canvas.rect(0., 0., 64., 64.);
+    canvas.pop_clip();
+    let panel = canvas.rect(0., 0., 64., 64.);
+    canvas.pop_clip();
+    assert!(canvas.rect(0., 0., 64., 64.).len() == 2);
+
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    frame.raster(clipped.into_iter(), brush.clone());
+    let img = frame.to_image();
+    assert_eq!(*img.get_pixel(20, 20), Rgba([255, 255, 255, 255]));
+    assert_eq!(*img.get_pixel(4, 20), Rgba([0, 0, 0, 0]));
+    assert_eq!(*img.get_pixel(48, 20), Rgba([0, 0, 0, 0]));
+
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    frame.raster(panel.into_iter(), brush);
+    let img = frame.to_image();
+    assert_eq!(*img.get_pixel(48, 20), Rgba([255, 255, 255, 255]));
+    assert_eq!(*img.get_pixel(60, 20), Rgba([0, 0, 0, 0]));
+}
+
+#[test]
+fn reraster_region() {
+    let solid = |c: Rgba<u8>| LinearGradient {
+        start: [0., 0.],
+        end: [1., 0.],
+        gradient: Gradient::new(c, c)
+    };
+    let white = Rgba([255u8, 255, 255, 255]);
+    let red = Rgba([255u8, 0, 0, 255]);
+
+    let canvas = Canvas::new(SIZE, SIZE);
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    frame.raster(canvas.rect(0., 0., 64., 64.).into_iter(), solid(white));
+
+    let damage = rusterize::Rect::new(8, 8, 16, 16);
+    frame.clear_region(damage, Rgba([0u8, 0, 0, 0]));
+    frame.reraster_region(damage, canvas.rect(0., 0., 64., 64.).into_iter(), solid(red));
+
+    let img = frame.to_image();
+    assert_eq!(*img.get_pixel(8, 8), red);
+    assert_eq!(*img.get_pixel(23, 23), red);
+    assert_eq!(*img.get_pixel(7, 8), white);
+    assert_eq!(*img.get_pixel(24, 23), white);
+    assert_eq!(*img.get_pixel(8, 24), white);
+    assert_eq!(*img.get_pixel(8, 7), white);
+}
+
+#[test]
+fn draw_stats() {
+    let canvas = Canvas::new(SIZE, SIZE);
+    let white = Rgba([255u8, 255, 255, 255]);
+    let solid = LinearGradient {
+        start: [0., 0.],
+        end: [1., 0.],
+        gradient: Gradient::new(white, white)
+    };
+
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    let stats = frame.raster(canvas.rect(8.5, 8.5, 20., 30.).into_iter(), solid.clone());
+
+    // the same rectangle wound the other way is culled
+    let back: Vec<_> = canvas.rect(8.5, 8.5, 20., 30.).into_iter().map(|t| {
+        genmesh::Triangle::new(t.x, t.z, t.y)
+    }).collect();
+    let culled = frame.raster(back.into_iter(), solid);
+    frame.flush();
+
+    assert_eq!(stats.triangles, 2);
+    assert_eq!(stats.culled, 0);
+    assert_eq!(stats.fragments(), 20 * 30);
+    assert_eq!(culled.triangles, 2);
+    assert_eq!(culled.culled, 2);
+    assert_eq!(culled.fragments(), 0);
+}
+
+#[test]
+fn split_tiles_match() {
+    let canvas = Canvas::new(SIZE, SIZE);
+    let shapes = || {
+        let mut tris = canvas.polygon(&[[3.5, 60.5], [40.5, 2.5], [62.5, 50.5]]);
+        tris.extend(canvas.rect(10.5, 10.5, 30., 12.).into_iter());
+        tris.into_iter()
+    };
+    let brush = RadialGradient {
+        center: [32., 32.],
+        radius: 30.,
+        gradient: Gradient::new(Rgba([255u8, 0, 0, 255]), Rgba([0u8, 0, 255, 255]))
+    };
+
+    let mut whole = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    whole.raster(shapes(), brush.clone());
+
+    let mut split = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    split.set_split_threshold(0);
+    let stats = split.raster(shapes(), brush);
+    split.flush();
+
+    assert!(stats.fragments() > 0);
+    assert!(whole.to_image().into_raw() == split.to_image().into_raw());
+}
+
+#[test]
+fn priority_region() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+    use rusterize::Fragment;
+    use rusterize::paint::CanvasVertex;
+
+    // notes the fragments outside of the priority groups that are shaded
+    // while the draw is still being binned
+    #[derive(Clone)]
+    struct Watched<F> {
+        brush: F,
+        binning: Arc<AtomicBool>,
+        focus_shaded: Arc<AtomicBool>,
+        early: Arc<AtomicUsize>
+    }
+
+    impl<F: Fragment<CanvasVertex, Color=Rgba<u8>>> Fragment<CanvasVertex> for Watched<F> {
+        type Color = Rgba<u8>;
+
+        fn fragment(&self, v: CanvasVertex) -> Rgba<u8> {
+            // the focus is in the top row of tile groups
+            if v.1[1] < 32. {
+                self.focus_shaded.store(true, Ordering::SeqCst);
+            } else if self.binning.load(Ordering::SeqCst) {
+                self.early.fetch_add(1, Ordering::SeqCst);
+            }
+            self.brush.fragment(v)
+        }
+    }
+
+    // the fence: the last triangle is only followed by the end of the draw
+    // once the focus is being shaded
+    struct Fenced<I> {
+        tris: I,
+        binning: Arc<AtomicBool>,
+        focus_shaded: Arc<AtomicBool>
+    }
+
+    impl<I: Iterator> Iterator for Fenced<I> {
+        type Item = I::Item;
+
+        fn next(&mut self) -> Option<I::Item> {
+            let next = self.tris.next();
+            if next.is_none() {
+                for _ in 0..5000 {
+                    if self.focus_shaded.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(1));
+                }
+                self.binning.store(false, Ordering::SeqCst);
+            }
+            next
+        }
+    }
+
+    let canvas = Canvas::new(SIZE, SIZE);
+    let brush = RadialGradient {
+        center: [20., 20.],
+        radius: 40.,
+        gradient: Gradient::new(Rgba([255u8, 255, 0, 255]), Rgba([0u8, 0, 255, 255]))
+    };
+    let focus = rusterize::Rect::new(12, 20, 24, 8);
+    let (binning, focus_shaded) = (Arc::new(AtomicBool::new(true)), Arc::new(AtomicBool::new(false)));
+    let early = Arc::new(AtomicUsize::new(0));
+
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    frame.set_priority(Some(focus));
+    let tris = Fenced {
+        tris: canvas.polygon(&[[3.5, 60.5], [40.5, 2.5], [62.5, 50.5]]).into_iter(),
+        binning: binning.clone(),
+        focus_shaded: focus_shaded.clone()
+    };
+    frame.raster(tris, Watched {
+        brush: brush.clone(),
+        binning: binning,
+        focus_shaded: focus_shaded.clone(),
+        early: early.clone()
+    });
+
+    let early_region = frame.read_region(focus);
+    assert_eq!((early_region.width, early_region.height), (24, 8));
+    // the focus was drawn before the rest of the frame was handed any work
+    assert!(focus_shaded.load(Ordering::SeqCst));
+    assert_eq!(early.load(Ordering::SeqCst), 0);
+
+    let img = frame.to_image();
+    for y in 0..8 {
+        for x in 0..24 {
+            assert_eq!(early_region.get_pixel(x, y), *img.get_pixel(x + 12, y + 20));
+        }
+    }
+}
+
+#[test]
+fn affine_depth_test() {
+    use genmesh::{Triangle, MapVertex};
+    use rusterize::paint::CanvasVertex;
+
+    let solid = |c: Rgba<u8>| LinearGradient { start: [0., 0.], end: [1., 0.], gradient: Gradient::new(c, c) };
+    let (red, green, blue) = (Rgba([255u8, 0, 0, 255]), Rgba([0u8, 255, 0, 255]), Rgba([0u8, 0, 255, 255]));
+    let canvas = Canvas::new(SIZE, SIZE);
+    let at = |tris: Vec<Triangle<CanvasVertex>>, z: f32| {
+        tris.into_iter().map(move |t| t.map_vertex(|(mut p, c)| { p[2] = z; (p, c) }))
+    };
+
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    frame.raster_affine(at(canvas.rect(0., 0., 48., 48.), 0.), solid(red));
+    frame.raster_affine(at(canvas.rect(16., 16., 48., 48.), -0.5), solid(blue));
+    // behind both
+    frame.raster_affine(at(canvas.rect(0., 0., 64., 64.), 0.5), solid(green));
+
+    let img = frame.to_image();
+    assert_eq!(*img.get_pixel(8, 8), red);
+    assert_eq!(*img.get_pixel(32, 32), blue);
+    assert_eq!(*img.get_pixel(56, 56), blue);
+    assert_eq!(*img.get_pixel(56, 4), green);
+}
@@ -0,0 +1,486 @@
+#![cfg(feature = "image")]
+
+extern crate rusterize;
+extern crate cgmath;
+extern crate image;
+extern crate genmesh;
+
+use rusterize::{Frame, SolidColor, Stencil};
+use rusterize::scene::{Scene, Mesh, Camera, RenderQueue};
+use rusterize::shaders::Pbr;
+use rusterize::animation::{Animation, Track, Interpolation, Property, Trs};
+use rusterize::reflection::Mirror;
+use rusterize::preprocess::{Options, average_cache_miss_ratio};
+use rusterize::meshlet::build_meshlets;
+use cgmath::{Matrix, Matrix4, Vector4, perspective, deg};
+use genmesh::Triangle;
+use image::Rgba;
+
+fn translate(x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    Matrix4::new(1., 0., 0., 0.,
+                 0., 1., 0., 0.,
+                 0., 0., 1., 0.,
+                 x, y, z, 1.)
+}
+
+fn quad(size: f32) -> Mesh {
+    Mesh {
+        positions: vec![[-size, -size, 0.], [size, -size, 0.], [size, size, 0.], [-size, size, 0.]],
+        normals: Vec::new(),
+        uvs: Vec::new(),
+        indices: vec![0, 1, 2, 0, 2, 3]
+    }
+}
+
+#[test]
+fn hierarchy_and_culling() {
+    let mut scene = Scene::new();
+    let mesh = scene.add_mesh(quad(0.25));
+    let red = scene.add_material(Pbr::new([1., 0., 0., 1.], 0., 0.7));
+
+    let root = scene.add_node(None, translate(0.5, 0., 0.), Some((mesh, red)));
+    scene.add_node(Some(root), translate(-1., 0., 0.), Some((mesh, red)));
+    scene.add_node(Some(root), translate(5., 0., 0.), Some((mesh, red)));
+    scene.add_node(None, translate(0., 0.5, 0.), None);
+
+    let world = scene.world_transforms();
+    assert_eq!(world[1], translate(-0.5, 0., 0.));
+
+    let camera = Camera {
+        view: Matrix4::identity(),
+        proj: Matrix4::identity(),
+        position: [0., 0., 5.]
+    };
+    let mut frame = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    let stats = scene.render(&mut frame, &camera);
+    frame.flush();
+
+    assert_eq!(stats.culled_nodes, 1);
+    assert_eq!(stats.draws.len(), 1);
+    assert_eq!(stats.triangles(), 4);
+    // each quad is 16 pixels across, give or take the edge rules
+    assert!(stats.fragments() >= 2 * 15 * 15 && stats.fragments() <= 2 * 17 * 17);
+
+    let img = frame.to_image();
+    assert!(img.get_pixel(48, 32).data[0] > 0);
+    assert!(img.get_pixel(16, 32).data[0] > 0);
+    assert_eq!(*img.get_pixel(32, 32), Rgba([0, 0, 0, 0]));
+}
+
+fn close(a: [f32; 4], b: [f32; 4]) -> bool {
+    a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-4)
+}
+
+#[test]
+fn track_interpolation() {
+    let values = vec![[0., 0., 0., 0.], [2., 4., 0., 0.]];
+    let step = Track::new(vec![0., 1.], values.clone(), Interpolation::Step);
+    let linear = Track::new(vec![0., 1.], values, Interpolation::Linear);
+    assert_eq!(step.sample(0.75, false), [0., 0., 0., 0.]);
+    assert_eq!(step.sample(1.5, false), [2., 4., 0., 0.]);
+    assert!(close(linear.sample(0.25, false), [0.5, 1., 0., 0.]));
+
+    // flat tangents ease in and out, halfway is still halfway
+    let zero = [0.; 4];
+    let cubic = Track::new(vec![0., 1.], vec![zero, [0., 0., 0., 0.], zero,
+                                              zero, [1., 0., 0., 0.], zero], Interpolation::Cubic);
+    assert!(close(cubic.sample(0.5, false), [0.5, 0., 0., 0.]));
+    assert!(cubic.sample(0.25, false)[0] < 0.25);
+
+    // a quarter turn around z slerps through an eighth turn
+    let h = (0.5f32).sqrt();
+    let rot = Track::new(vec![0., 2.], vec![[0., 0., 0., 1.], [0., 0., h, h]], Interpolation::Linear);
+    let eighth = std::f32::consts::PI / 8.;
+    assert!(close(rot.sample(1., true), [0., 0., eighth.sin(), eighth.cos()]));
+}
+
+#[test]
+fn advance_moves_nodes() {
+    let mut scene = Scene::new();
+    let root = scene.add_node_trs(None, Trs::identity(), None);
+    let child = scene.add_node(Some(root), translate(0., 1., 0.), None);
+
+    let track = Track::new(vec![0., 1.], vec![[0., 0., 0., 0.], [4., 0., 0., 0.]], Interpolation::Linear);
+    scene.add_animation(Animation::new(true).channel(root, Property::Translation, track));
+
+    scene.advance(0.5);
+    assert_eq!(scene.nodes[root].pose.unwrap().translation, [2., 0., 0.]);
+    assert_eq!(scene.world_transforms()[child], translate(2., 1., 0.));
+
+    // looping wraps around to the start
+    scene.advance(0.75);
+    assert!((scene.nodes[root].pose.unwrap().translation[0] - 1.).abs() < 1e-5);
+    assert_eq!(scene.nodes[child].transform, translate(0., 1., 0.));
+}
+
+#[test]
+fn queue_order() {
+    let tris = |n: usize| {
+        let v = ([0.; 4], [0.; 3], [0.; 3], [0.; 2]);
+        (0..n).map(|_| Triangle::new(v, v, v)).collect::<Vec<_>>()
+    };
+
+    let mut queue = RenderQueue::new();
+    queue.push(0, false, 5., tris(1));
+    queue.push(1, false, 1., tris(2));
+    queue.push(0, false, 3., tris(3));
+    queue.push(2, true, 2., tris(4));
+    queue.push(3, true, 8., tris(5));
+    // a broken transform counts as the farthest
+    queue.push(4, false, std::f32::NAN, tris(6));
+    queue.push(5, true, std::f32::NAN, tris(7));
+
+    let order: Vec<(usize, usize)> = queue.into_batches().into_iter().map(|(m, t)| (m, t.len())).collect();
+    assert_eq!(order, vec![(1, 2), (0, 4), (4, 6), (5, 7), (3, 5), (2, 4)]);
+}
+
+#[test]
+fn translucent_over_opaque() {
+    let mut scene = Scene::new();
+    let mesh = scene.add_mesh(quad(0.5));
+    assert_eq!(scene.bounds[mesh], scene.meshes[mesh].bounds());
+    let red = scene.add_material(Pbr::new([1., 0., 0., 1.], 0., 0.7));
+    let glass = scene.add_material(Pbr::new([0., 0., 1., 0.5], 0., 0.7));
+
+    // the glass is nearer but submitted first
+    scene.add_node(None, translate(0., 0., -0.5), Some((mesh, glass)));
+    scene.add_node(None, translate(0., 0., 0.5), Some((mesh, red)));
+
+    let camera = Camera {
+        view: Matrix4::identity(),
+        proj: Matrix4::identity(),
+        position: [0., 0., -5.]
+    };
+    let mut frame = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    let stats = scene.render(&mut frame, &camera);
+    assert_eq!(stats.draws.len(), 2);
+    assert!(frame.depth_write());
+
+    let p = frame.to_image().get_pixel(32, 32).data;
+    assert!(p[0] > 0 && p[2] > 0);
+    // only the red quad is in the depth buffer
+    assert!((frame.depth_buffer().get_pixel(32, 32) - 0.5).abs() < 1e-5);
+}
+
+#[test]
+fn planar_reflection() {
+    // a red card stands on a mirror floor at y = 0, a green one sits under
+    // the floor between the card and the reflected eye
+    let mut scene = Scene::new();
+    let red = scene.add_material(Pbr::new([1., 0., 0., 1.], 0., 0.7));
+    let green = scene.add_material(Pbr::new([0., 1., 0., 1.], 0., 0.7));
+    let card = scene.add_mesh(quad(0.8));
+    let under = scene.add_mesh(quad(0.2));
+    scene.add_node(None, translate(0., 1., -5.), Some((card, red)));
+    scene.add_node(None, translate(0., -0.3, 0.), Some((under, green)));
+
+    let camera = Camera {
+        view: translate(0., -1., -3.),
+        proj: perspective(deg(60.), 1., 0.1, 50.),
+        position: [0., 1., 3.]
+    };
+    let (w, h) = (128, 128);
+    let sky = Rgba([0u8, 0, 255, 255]);
+    let mirror = Mirror::new([0., 0., 0.], [0., 1., 0.]);
+    let reflection = mirror.render(&scene, &camera, w, h, sky);
+
+    let view_proj = camera.view_proj();
+    let clip = |p: [f32; 3]| {
+        let c = view_proj.mul_v(&Vector4::new(p[0], p[1], p[2], 1.));
+        [c.x, c.y, c.z, c.w]
+    };
+    let floor = vec![Triangle::new(clip([-5., 0., 2.]), clip([5., 0., 2.]), clip([5., 0., -20.])),
+                     Triangle::new(clip([-5., 0., 2.]), clip([5., 0., -20.]), clip([-5., 0., -20.]))];
+
+    let mut frame = Frame::new(w, h, sky);
+    scene.render(&mut frame, &camera);
+    let stats = frame.raster_reflective(floor.into_iter(), SolidColor(Rgba([0u8, 0, 0, 255])), &reflection);
+    assert_eq!(stats.culled, 0);
+
+    let img = frame.to_image();
+    let pixel = |p: [f32; 3]| {
+        let c = clip(p);
+        let (x, y) = (c[0] / c[3], c[1] / c[3]);
+        let px = ((x + 1.) * w as f32 / 2.) as u32;
+        let py = ((y + 1.) * h as f32 / 2.) as u32;
+        img.get_pixel(px, h - 1 - py).data
+    };
+
+    // the eye sees the card in the floor right where the green card would
+    // hide it, were the reflection not clipped by the mirror
+    let p = pixel([0., 0., -1.]);
+    assert!(p[0] > p[1] && p[0] > p[2]);
+    // further to the side the floor shows the sky
+    assert_eq!(pixel([1.5, 0., -1.]), sky.data);
+}
+
+#[test]
+fn portal_stencil() {
+    // the world is red, the one behind the portal is green
+    let mut world = Scene::new();
+    let mesh = world.add_mesh(quad(1.));
+    let red = world.add_material(Pbr::new([1., 0., 0., 1.], 0., 0.7));
+    world.add_node(None, translate(0., 0., 0.5), Some((mesh, red)));
+    let mut beyond = Scene::new();
+    let mesh = beyond.add_mesh(quad(1.));
+    let green = beyond.add_material(Pbr::new([0., 1., 0., 1.], 0., 0.7));
+    beyond.add_node(None, translate(0., 0., 0.5), Some((mesh, green)));
+
+    let camera = Camera {
+        view: Matrix4::identity(),
+        proj: Matrix4::identity(),
+        position: [0., 0., 5.]
+    };
+    let portal = vec![Triangle::new([-0.5, -0.5, 0., 1.], [0.5, -0.5, 0., 1.], [0.5, 0.5, 0., 1.]),
+                      Triangle::new([-0.5, -0.5, 0., 1.], [0.5, 0.5, 0., 1.], [-0.5, 0.5, 0., 1.])];
+
+    let mut frame = Frame::new(64, 64, Rgba([0u8, 0, 0, 255]));
+    // mark the opening without hiding what is behind it
+    frame.set_painter(true);
+    frame.set_stencil(Stencil::write(1));
+    frame.raster(portal.into_iter(), SolidColor(Rgba([0u8, 0, 0, 255])));
+    frame.set_painter(false);
+    frame.set_stencil(Stencil::test(1));
+    beyond.render(&mut frame, &camera);
+    frame.set_stencil(Stencil::test(0));
+    world.render(&mut frame, &camera);
+
+    let stencil = frame.stencil_buffer();
+    assert_eq!((stencil.get_pixel(32, 32), stencil.get_pixel(4, 4)), (1, 0));
+    let img = frame.to_image();
+    let (inside, outside) = (img.get_pixel(32, 32).data, img.get_pixel(4, 4).data);
+    assert!(inside[1] > inside[0] && outside[0] > outside[1]);
+}
+
+fn rotate_lowest(t: &[u32]) -> [u32; 3] {
+    if t[0] <= t[1] && t[0] <= t[2] {
+        [t[0], t[1], t[2]]
+    } else if t[1] <= t[2] {
+        [t[1], t[2], t[0]]
+    } else {
+        [t[2], t[0], t[1]]
+    }
+}
+
+#[test]
+fn preprocess_grid() {
+    // a 16x16 grid of quads with the triangles in a scattered order
+    let n = 16;
+    let mut positions = Vec::new();
+    for y in 0..n + 1 {
+        for x in 0..n + 1 {
+            positions.push([x as f32 * 0.5, y as f32 * 0.25 - 1., (x + y) as f32 * 0.1]);
+        }
+    }
+    let mut triangles = Vec::new();
+    for y in 0..n {
+        for x in 0..n {
+            let i = (y * (n + 1) + x) as u32;
+            let n = n as u32;
+            triangles.push([i, i + 1, i + n + 2]);
+            triangles.push([i, i + n + 2, i + n + 1]);
+        }
+    }
+    let mut indices = Vec::new();
+    for k in 0..triangles.len() {
+        indices.extend(triangles[(k * 97) % triangles.len()].iter().cloned());
+    }
+    let mesh = Mesh {
+        positions: positions,
+        normals: vec![[0., 0., 1.]; (n + 1) * (n + 1)],
+        uvs: Vec::new(),
+        indices: indices
+    };
+
+    let mut options = Options::new();
+    options.meshlets = Some((16, 12));
+    let mut frame = Frame::new(8, 8, Rgba([0u8, 0, 0, 255]));
+    let prepared = frame.preprocess(vec![mesh.clone(), quad(1.)], options);
+    assert_eq!(prepared.len(), 2);
+    let p = &prepared[0];
+
+    assert!(average_cache_miss_ratio(&p.mesh.indices, 16) < average_cache_miss_ratio(&mesh.indices, 16));
+    // the same triangles with the same winding
+    let mut sorted: Vec<[u32; 3]> = p.mesh.indices.chunks(3).map(rotate_lowest).collect();
+    let mut expected: Vec<[u32; 3]> = mesh.indices.chunks(3).map(rotate_lowest).collect();
+    sorted.sort();
+    expected.sort();
+    assert_eq!(sorted, expected);
+
+    assert_eq!(p.min, [0., -1., 0.]);
+    assert_eq!(p.max, [8., 3., 3.2]);
+
+    let q = p.quantized.as_ref().unwrap();
+    for (a, b) in mesh.positions.iter().zip(q.positions.iter()) {
+        for k in 0..3 {
+            let d = q.offset[k] + q.scale[k] * b[k] as f32;
+            assert!((d - a[k]).abs() <= q.scale[k]);
+        }
+    }
+    assert_eq!(q.normals[0], [0, 0, 127]);
+
+    let mut covered = 0;
+    for m in p.meshlets.iter() {
+        let mut vertices = m.indices.clone();
+        vertices.sort();
+        vertices.dedup();
+        assert!(vertices.len() <= 16 && m.triangles() <= 12);
+        for &i in m.indices.iter() {
+            let v = mesh.positions[i as usize];
+            let d = [v[0] - m.center[0], v[1] - m.center[1], v[2] - m.center[2]];
+            assert!((d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt() <= m.radius + 1e-4);
+        }
+        covered += m.triangles();
+    }
+    assert_eq!(covered, 2 * n * n);
+
+    assert_eq!(prepared[1].mesh.indices.len(), 6);
+    assert_eq!(prepared[1].max, [1., 1., 0.]);
+}
+
+fn cube(s: f32) -> Mesh {
+    // the normal of every face and two axes across it with u x v = n
+    let faces = [([1., 0., 0.], [0., 1., 0.], [0., 0., 1.]),
+                 ([-1., 0., 0.], [0., 0., 1.], [0., 1., 0.]),
+                 ([0., 1., 0.], [0., 0., 1.], [1., 0., 0.]),
+                 ([0., -1., 0.], [1., 0., 0.], [0., 0., 1.]),
+                 ([0., 0., 1.], [1., 0., 0.], [0., 1., 0.]),
+                 ([0., 0., -1.], [0., 1., 0.], [1., 0., 0.])];
+    let mut mesh = Mesh {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        uvs: Vec::new(),
+        indices: Vec::new()
+    };
+    for &(n, u, v) in faces.iter() {
+        let base = mesh.positions.len() as u32;
+        for &(a, b) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)].iter() {
+            let mut p = [0.; 3];
+            for k in 0..3 {
+                p[k] = (n[k] + a * u[k] + b * v[k]) * s;
+            }
+            mesh.positions.push(p);
+            mesh.normals.push(n);
+        }
+        mesh.indices.extend([0, 1, 2, 0, 2, 3].iter().map(|&i| base + i));
+    }
+    mesh
+}
+
+#[test]
+fn meshlet_culling() {
+    let mut scene = Scene::new();
+    let mesh = scene.add_mesh(cube(0.5));
+    let red = scene.add_material(Pbr::new([1., 0., 0., 1.], 0., 0.7));
+    scene.add_node(None, Matrix4::identity(), Some((mesh, red)));
+    let camera = Camera {
+        view: Matrix4::identity(),
+        proj: Matrix4::identity(),
+        position: [0., 0., 5.]
+    };
+
+    let mut whole = Frame::new(32, 32, Rgba([0u8, 0, 0, 0]));
+    let stats = scene.render(&mut whole, &camera);
+    whole.flush();
+    assert_eq!(stats.triangles(), 12);
+    assert_eq!(stats.culled_meshlets, 0);
+
+    // one meshlet per face, the one facing -z is behind the cube
+    let meshlets = build_meshlets(&scene.meshes[mesh].positions, &scene.meshes[mesh].indices, 4, 2);
+    assert_eq!(meshlets.len(), 6);
+    assert!(meshlets[5].backfacing([0., 0., 5.]));
+    assert!(!meshlets[4].backfacing([0., 0., 5.]));
+    scene.set_meshlets(mesh, meshlets);
+
+    let mut culled = Frame::new(32, 32, Rgba([0u8, 0, 0, 0]));
+    let stats = scene.render(&mut culled, &camera);
+    culled.flush();
+    assert_eq!(stats.culled_meshlets, 1);
+    assert_eq!(stats.triangles(), 10);
+    assert!(culled.to_image().pixels().zip(whole.to_image().pixels()).all(|(a, b)| a == b));
+
+    // a node moved to the side keeps only the meshlets in the camera
+    let mut scene2 = scene.clone();
+    scene2.nodes[0].transform = Matrix4::new(1., 0., 0., 0.,
+                                             0., 1., 0., 0.,
+                                             0., 0., 1., 0.,
+                                             1.4, 0., 0., 1.);
+    let stats = scene2.render(&mut culled, &camera);
+    assert_eq!(stats.culled_nodes, 0);
+    assert_eq!(stats.culled_meshlets, 2);
+}
+
+#[test]
+fn batch_thumbnails() {
+    use std::sync::Arc;
+    use rusterize::thumbnail::render_batch;
+
+    let mut far = quad(3.);
+    for p in far.positions.iter_mut() {
+        p[0] += 100.;
+        p[2] -= 40.;
+    }
+    let meshes = vec![Arc::new(quad(0.25)), Arc::new(far), Arc::new(quad(1.))];
+    let images = render_batch(meshes, 64, Pbr::new([1., 0., 0., 1.], 0., 0.7));
+
+    // every mesh is framed the same no matter its size and place
+    assert_eq!(images.len(), 3);
+    for img in images.iter() {
+        assert_eq!(img.dimensions(), (64, 64));
+        assert_eq!(img.get_pixel(32, 32).data[3], 255);
+        assert!(img.get_pixel(32, 32).data[0] > 0);
+        assert_eq!(*img.get_pixel(2, 2), Rgba([0, 0, 0, 0]));
+        assert_eq!(*img.get_pixel(61, 61), Rgba([0, 0, 0, 0]));
+    }
+
+    // a scene is framed by the meshes of all its nodes
+    let mut scene = Scene::new();
+    let card = scene.add_mesh(quad(1.));
+    let red = scene.add_material(Pbr::new([1., 0., 0., 1.], 0., 0.7));
+    scene.add_node(None, translate(-10., 0., 0.), Some((card, red)));
+    scene.add_node(None, translate(10., 0., 0.), Some((card, red)));
+    let (center, radius) = scene.world_bounds();
+    assert_eq!(center, [0., 0., 0.]);
+    assert!((radius - (10. + 2f32.sqrt())).abs() < 1e-4);
+
+    let images = render_batch(vec![scene], 64, Pbr::new([1., 0., 0., 1.], 0., 0.7));
+    assert_eq!(*images[0].get_pixel(32, 32), Rgba([0, 0, 0, 0]));
+    assert!(images[0].get_pixel(6, 32).data[0] > 0);
+    assert!(images[0].get_pixel(58, 32).data[0] > 0);
+}
+
+#[test]
+fn equirect_panorama() {
+    use std::sync::Arc;
+    use rusterize::Buffer;
+    use rusterize::panorama::{CubeMap, render_panorama};
+
+    // every face a color of its own
+    let faces = (0..6).map(|i| Buffer::new(8, 8, Rgba([i as u8 * 40, 0, 0, 255]))).collect();
+    let mut frame = Frame::new(64, 32, Rgba([0u8, 0, 0, 0]));
+    frame.equirect(Arc::new(CubeMap { faces: faces }));
+    let img = frame.to_image();
+    let face = |x: u32, y: u32| img.get_pixel(x, y).data[0] / 40;
+    // -z in the middle, +x a quarter turn right, +z at the seam
+    assert_eq!(face(32, 16), 5);
+    assert_eq!(face(48, 16), 0);
+    assert_eq!(face(16, 16), 1);
+    assert_eq!(face(0, 16), 4);
+    assert_eq!(face(63, 16), 4);
+    assert_eq!(face(32, 0), 2);
+    assert_eq!(face(32, 31), 3);
+
+    // a red card ahead of the eye shows up in the middle only
+    let mut scene = Scene::new();
+    let red = scene.add_material(Pbr::new([1., 0., 0., 1.], 0., 0.7));
+    let card = scene.add_mesh(quad(1.));
+    scene.add_node(None, translate(0., 0., -3.), Some((card, red)));
+    let sky = Rgba([0u8, 0, 255, 255]);
+    let pano = render_panorama(&scene, [0., 0., 0.], 128, 64, 0.1, 50., sky);
+    assert_eq!((pano.width, pano.height), (128, 64));
+    let p = pano.get_pixel(64, 32).data;
+    assert!(p[0] > p[2]);
+    assert_eq!(pano.get_pixel(0, 32), sky);
+    assert_eq!(pano.get_pixel(96, 32), sky);
+    assert_eq!(pano.get_pixel(64, 0), sky);
+}
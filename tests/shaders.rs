@@ -0,0 +1,218 @@
+#![cfg(feature = "image")]
+
+extern crate rusterize;
+extern crate image;
+extern crate cgmath;
+extern crate genmesh;
+
+use std::sync::Arc;
+
+use rusterize::{Frame, Fragment, Buffer, SolidColor};
+use rusterize::shaders::{ScreenVertex, Sky, Matcap, Pbr};
+use cgmath::{Matrix, perspective, deg};
+use image::Rgba;
+
+mod common;
+
+const SIZE: u32 = 64;
+
+#[derive(Clone, Copy)]
+struct White;
+
+impl Fragment<ScreenVertex> for White {
+    type Color = Rgba<u8>;
+
+    fn fragment(&self, _: ScreenVertex) -> Rgba<u8> {
+        Rgba([255, 255, 255, 255])
+    }
+}
+
+#[test]
+fn fullscreen_covers_frame() {
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    frame.fullscreen(White);
+    let img = frame.to_image();
+    for p in img.pixels() {
+        assert_eq!(*p, Rgba([255, 255, 255, 255]));
+    }
+}
+
+#[test]
+fn sky_gradient() {
+    let proj = perspective(deg(90.), 1., 0.1, 10.);
+    let sky = Sky::new([0., 0.3, -1.], proj.invert().unwrap());
+
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    frame.fullscreen(sky);
+    let img = frame.to_image();
+
+    // the camera looks down -z, so the top half is sky and the bottom ground
+    let top = img.get_pixel(4, 2).data;
+    let bottom = img.get_pixel(4, SIZE - 3).data;
+    assert!(top[2] > top[0]);
+    assert!(top[2] > bottom[2]);
+    assert_eq!(top[3], 255);
+
+    // the sun sits slightly above the center and is the brightest spot
+    let sun = img.get_pixel(SIZE / 2, SIZE / 2 - 10).data;
+    assert!(sun[0] > top[0] && sun[1] > top[1]);
+}
+
+#[test]
+fn matcap_lookup() {
+    // left half red, right half blue, the top row is white
+    let mut texture = Buffer::new(16, 16, Rgba([255u8, 0, 0, 255]));
+    for y in 0..16 {
+        for x in 8..16 {
+            texture.put_pixel(x, y, Rgba([0, 0, 255, 255]));
+        }
+    }
+    for x in 0..16 {
+        texture.put_pixel(x, 0, Rgba([255, 255, 255, 255]));
+    }
+
+    let matcap = Matcap::new(Arc::new(texture));
+    assert_eq!(matcap.color([-1., 0., 0.2]), Rgba([255, 0, 0, 255]));
+    assert_eq!(matcap.color([2., 0., 0.]), Rgba([0, 0, 255, 255]));
+    assert_eq!(matcap.color([0., 1., 0.]), Rgba([255, 255, 255, 255]));
+    assert_eq!(matcap.fragment(([0.; 4], [-0.5, -0.5, 1.])), Rgba([255, 0, 0, 255]));
+}
+
+#[test]
+fn pbr_lighting() {
+    let mut pbr = Pbr::new([0.8, 0.2, 0.2, 1.], 0., 0.5);
+    pbr.light_dir = [0., 0., 1.];
+    pbr.eye = [0., 0., 5.];
+
+    let lit = pbr.shade([0.; 3], [0., 0., 1.], [0.; 2]);
+    let unlit = pbr.shade([0.; 3], [0., 0., -1.], [0.; 2]);
+    assert!(lit[0] > lit[1] && lit[0] > 0.2);
+    assert!((unlit[0] - 0.03 * 0.8).abs() < 1e-5);
+    assert_eq!(lit[3], 1.);
+
+    // a smooth surface concentrates the highlight along the reflection
+    let rough = pbr.shade([0.; 3], [0., 0., 1.], [0.; 2]);
+    pbr.roughness = 0.1;
+    let smooth = pbr.shade([0.; 3], [0., 0., 1.], [0.; 2]);
+    assert!(smooth[1] > rough[1]);
+
+    // metals have no diffuse and tint their reflections
+    pbr.metallic = 1.;
+    pbr.roughness = 0.5;
+    let metal = pbr.shade([0.; 3], [0., 0., 1.], [0.; 2]);
+    assert!(metal[0] > metal[1] * 2.);
+
+    let texture = Buffer::new(4, 4, Rgba([255u8, 255, 0, 128]));
+    pbr.base_color_texture = Some(Arc::new(texture));
+    let tinted = pbr.shade([0.; 3], [0., 0., 1.], [0.5; 2]);
+    assert!(tinted[2] < 1e-3);
+    assert!((tinted[3] - 128. / 255.).abs() < 1e-5);
+}
+
+#[derive(Clone, Copy)]
+struct Gray;
+
+impl Fragment<[f32; 4]> for Gray {
+    type Color = Rgba<u8>;
+
+    fn fragment(&self, _: [f32; 4]) -> Rgba<u8> { Rgba([128, 128, 128, 255]) }
+}
+
+#[test]
+fn decal_projection() {
+    use cgmath::Matrix4;
+    use rusterize::Decal;
+
+    // a plane at depth 0 over the left part of the frame, the clip space
+    // of the camera is world space
+    let plane = common::rect(-1., -1., 0.25, 1., 0.);
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 255]));
+    frame.raster(plane.into_iter(), Gray);
+
+    // a box reaching from -0.5 to 0.5 on every axis
+    let red = Rgba([255u8, 0, 0, 255]);
+    let to_box = Matrix4::new(2., 0., 0., 0., 0., 2., 0., 0., 0., 0., 2., 0., 0., 0., 0., 1.);
+    frame.decal(&Decal::new(Matrix4::identity(), to_box, Arc::new(Buffer::new(2, 2, red))));
+
+    let img = frame.to_image();
+    assert_eq!(*img.get_pixel(32, 31), red);
+    assert_eq!(*img.get_pixel(4, 31), Rgba([128, 128, 128, 255]));
+    // inside of the box on screen but there is no surface behind it
+    assert_eq!(*img.get_pixel(44, 31), Rgba([0, 0, 0, 255]));
+}
+
+#[test]
+fn depth_reconstruction() {
+    use cgmath::Vector4;
+    use rusterize::Projection;
+
+    let (near, far) = (1., 10.);
+    let persp = Projection::Perspective { near: near, far: far };
+    let ortho = Projection::Orthographic { near: near, far: far };
+    for &p in [persp, ortho].iter() {
+        assert!((p.linear_depth(-1.) - near).abs() < 1e-5);
+        assert!((p.linear_depth(1.) - far).abs() < 1e-4);
+    }
+
+    // a wall 4 units in front of the eye filling the view
+    let proj = perspective(deg(90.), 1., near, far);
+    let v = |x: f32, y: f32| {
+        let p = proj.mul_v(&Vector4::new(x, y, -4., 1.));
+        [p.x, p.y, p.z, p.w]
+    };
+    let wall = common::quad(v(-8., -8.), v(8., -8.), v(8., 8.), v(-8., 8.));
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 255]));
+    frame.raster(wall.into_iter(), Gray);
+
+    let linear = frame.linear_depth(persp);
+    assert!(linear.data.iter().all(|&d| (d - 4.).abs() < 1e-3));
+    let positions = frame.view_positions(&proj.invert().unwrap());
+    let center = positions.get_pixel(SIZE / 2, SIZE / 2 - 1);
+    assert!(center[0].abs() < 1e-3 && center[1].abs() < 1e-3 && (center[2] + 4.).abs() < 1e-3);
+    // the left edge of the view is 4 units off at that distance
+    assert!((positions.get_pixel(0, 10)[0] + 4.).abs() < 1e-3);
+
+    // a click finds the same points one pixel at a time
+    let hit = frame.unproject(0, 10, proj.invert().unwrap()).unwrap();
+    assert!((hit[0] + 4.).abs() < 1e-3 && (hit[2] + 4.).abs() < 1e-3);
+    assert_eq!(frame.unproject(SIZE, 10, proj.invert().unwrap()), None);
+    let identity = [[1., 0., 0., 0.], [0., 1., 0., 0.], [0., 0., 1., 0.], [0., 0., 0., 1.]];
+    assert_eq!(Frame::new(8, 8, 0u8).unproject(3, 3, identity), None);
+}
+
+#[test]
+fn orthographic_linear_depth() {
+    use rusterize::{DepthMode, Projection};
+
+    let (near, far) = (1., 10000.);
+    // walls filling the view, the z of an orthographic draw in linear mode
+    // is the distance from the eye
+    let wall = |d: f32| common::rect(-1., -1., 1., 1., d);
+    let red = Rgba([255u8, 0, 0, 255]);
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 255]));
+    frame.set_depth_mode(DepthMode::Linear { near: near, far: far });
+    assert_eq!(frame.depth_mode(), DepthMode::Linear { near: near, far: far });
+
+    // beyond the far plane nothing is drawn
+    frame.raster(wall(far + 1.).into_iter(), Gray);
+    assert!(frame.depth_buffer().data.iter().all(|&z| z == 1.));
+
+    // right by the far plane, a wall a few ulps behind another loses
+    frame.raster(wall(9999.).into_iter(), SolidColor(red));
+    frame.raster(wall(9999.002).into_iter(), Gray);
+    let img = frame.to_image();
+    assert!(img.pixels().all(|p| *p == red));
+
+    let ortho = Projection::Orthographic { near: near, far: far };
+    let linear = frame.linear_depth(ortho);
+    assert!(linear.data.iter().all(|&d| (d - 9999.).abs() < 1e-3));
+    let stored = frame.project_to_screen([0., 0., 9999., 1.]).unwrap();
+    assert!((stored[2] - frame.depth_buffer().get_pixel(SIZE / 2, SIZE / 2 - 1)).abs() < 1e-9);
+
+    // the inverse of an orthographic projection between the planes, with
+    // the eye looking down -z
+    let (a, b) = (-2. / (far - near), -(far + near) / (far - near));
+    let inverse = [[1., 0., 0., 0.], [0., 1., 0., 0.], [0., 0., 1. / a, 0.], [0., 0., -b / a, 1.]];
+    let hit = frame.unproject(SIZE / 2, SIZE / 2 - 1, inverse).unwrap();
+    assert!((hit[2] + 9999.).abs() < 1e-2);
+}
@@ -0,0 +1,37 @@
+extern crate rusterize;
+extern crate cgmath;
+extern crate genmesh;
+
+use cgmath::{Matrix, perspective, deg};
+use rusterize::Projection;
+use rusterize::shadow::{CascadedShadows, cascade_splits};
+
+mod common;
+
+#[test]
+fn cascaded_shadows() {
+    let splits = cascade_splits(0.5, 50., 3, 0.75);
+    assert_eq!(splits.len(), 3);
+    assert!(splits[0] < splits[1] && splits[1] < splits[2]);
+    assert!((splits[2] - 50.).abs() < 1e-3);
+
+    // the camera sits at the origin looking down -z over a ground plane,
+    // with one occluder close by and one far away. The sun is overhead.
+    let quad = |x0: f32, x1: f32, y: f32, z0: f32, z1: f32| {
+        common::quad([x0, y, z0], [x1, y, z0], [x1, y, z1], [x0, y, z1])
+    };
+    let mut casters = quad(-40., 40., -1., 0., -60.);
+    casters.extend(quad(-1., 1., 0., -3., -5.));
+    casters.extend(quad(-2., 2., 0., -29., -33.));
+    let proj = perspective(deg(60.), 1., 0.5, 50.);
+    let shadows = CascadedShadows::render(256, &proj.invert().unwrap(),
+                                          Projection::Perspective { near: 0.5, far: 50. },
+                                          &splits, [0., -1., 0.], &casters);
+
+    assert_eq!((shadows.select(1.), shadows.select(31.), shadows.select(60.)), (Some(0), Some(2), None));
+    let bias = 0.01;
+    assert_eq!(shadows.visibility([0., -1., -4.], 4., bias), 0.);
+    assert_eq!(shadows.visibility([1.5, -1., -4.], 4., bias), 1.);
+    assert_eq!(shadows.visibility([0., -1., -31.], 31., bias), 0.);
+    assert_eq!(shadows.visibility([6., -1., -31.], 31., bias), 1.);
+}
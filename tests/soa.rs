@@ -0,0 +1,174 @@
+extern crate rusterize;
+extern crate genmesh;
+extern crate cgmath;
+
+use genmesh::Triangle;
+use rusterize::{IndexedTriangles, IndexedStrip, IndexedFan, PRIMITIVE_RESTART, Normalized, Dequantize};
+
+#[test]
+fn gather_separate_arrays() {
+    let positions = [[-1., -1., 0., 1.], [1., -1., 0., 1.], [1., 1., 0., 1.], [-1., 1., 0., 1.]];
+    let uvs = [[0., 0.], [1., 0.], [1., 1.], [0., 1.]];
+    let shade = [0.1, 0.2, 0.3, 0.4];
+    let indices = [0, 1, 2, 0, 2, 3, 1];
+
+    let tris = IndexedTriangles::new(&indices, (&positions[..], &uvs[..], &shade[..]));
+    assert_eq!(tris.size_hint(), (2, Some(2)));
+
+    let tris: Vec<Triangle<([f32; 4], [f32; 2], f32)>> = tris.collect();
+    assert_eq!(tris.len(), 2);
+    assert_eq!(tris[1].x, (positions[0], uvs[0], shade[0]));
+    assert_eq!(tris[1].y, (positions[2], uvs[2], shade[2]));
+    assert_eq!(tris[1].z, (positions[3], uvs[3], shade[3]));
+}
+
+#[test]
+fn batch_transform() {
+    use cgmath::*;
+
+    let m = perspective(deg(60.), 1.5, 0.1, 10.).mul_m(&Matrix4::from_translation(&Vector3::new(0.5, -1., -3.)));
+    let src: Vec<[f32; 4]> = (0..11).map(|i| {
+        let f = i as f32;
+        [f * 0.5 - 2., f * 0.25, -f, 1.]
+    }).collect();
+
+    let out = rusterize::transform_positions(&m, &src);
+    for (s, o) in src.iter().zip(out.iter()) {
+        let expected = m.mul_v(&Vector4::new(s[0], s[1], s[2], s[3]));
+        for (a, b) in o.iter().zip(expected.into_fixed().iter()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn matrix_without_cgmath() {
+    use cgmath::*;
+    use rusterize::{IntoMatrix, to_columns};
+
+    let m = perspective(deg(60.), 1.5, 0.1, 10.).mul_m(&Matrix4::from_translation(&Vector3::new(0.5, -1., -3.)));
+    // columns as another math library would hand them out
+    let columns = to_columns(&m);
+    let mut flat = [0f32; 16];
+    for i in 0..16 {
+        flat[i] = columns[i / 4][i % 4];
+    }
+    assert_eq!(columns.into_matrix(), m);
+    assert_eq!(flat.into_matrix(), m);
+
+    let src = [[1., 2., -3., 1.], [-0.5, 0.25, -1., 1.]];
+    let expected = rusterize::transform_positions(&m, &src);
+    assert_eq!(rusterize::transform_positions(columns, &src), expected);
+    assert_eq!(rusterize::transform_positions(&flat, &src), expected);
+}
+
+#[test]
+fn large_world_positions() {
+    use cgmath::*;
+    use rusterize::{Mat4d, split_view, transform_relative_into, to_columns};
+
+    // an object ten thousand kilometers out, seen from a few meters away
+    let model: Mat4d = [[1., 0., 0., 0.], [0., 1., 0., 0.], [0., 0., 1., 0.],
+                        [1.0e7, 2.5e6, -3.0e6, 1.]];
+    let eye = [1.0e7 + 0.25, 2.5e6 - 0.5, -3.0e6 + 4.];
+    // looking down -z from the eye
+    let view: Mat4d = [[1., 0., 0., 0.], [0., 1., 0., 0.], [0., 0., 1., 0.],
+                       [-eye[0], -eye[1], -eye[2], 1.]];
+    let (split_eye, rotation) = split_view(&view);
+    for i in 0..3 {
+        assert!((split_eye[i] - eye[i]).abs() < 1e-6);
+    }
+
+    let proj = perspective(deg(60.), 1., 0.1, 100.);
+    let view_proj = proj.mul_m(&Matrix4::new(
+        rotation[0][0], rotation[0][1], rotation[0][2], rotation[0][3],
+        rotation[1][0], rotation[1][1], rotation[1][2], rotation[1][3],
+        rotation[2][0], rotation[2][1], rotation[2][2], rotation[2][3],
+        rotation[3][0], rotation[3][1], rotation[3][2], rotation[3][3]));
+    let p = to_columns(&proj);
+
+    // more than a batch of eight, so the remainder is covered as well
+    let src: Vec<[f64; 3]> = (0..11).map(|i| {
+        let i = i as f64;
+        [i * 0.125 - 0.5, 0.0625 * i, -1. - 0.01 * i]
+    }).collect();
+    let mut dst = vec![[0f32; 4]; src.len()];
+    transform_relative_into(&model, split_eye, view_proj, &src, &mut dst);
+
+    for (s, d) in src.iter().zip(dst.iter()) {
+        // the reference in f64 all the way
+        let mut rel = [0f64; 4];
+        for r in 0..3 {
+            rel[r] = model[3][r] + s[r] - eye[r];
+        }
+        rel[3] = 1.;
+        for r in 0..4 {
+            let expected = (0..4).fold(0f64, |sum, c| sum + p[c][r] as f64 * rel[c]);
+            assert!((d[r] as f64 - expected).abs() < 1e-4, "{:?} {:?}", d, expected);
+        }
+    }
+
+    // a naive transform in f32 loses the detail: the world positions are
+    // already rounded to a meter
+    let world = (1.0e7f64 + 0.125) as f32;
+    assert!((world as f64 - 1.0e7 - 0.125).abs() > 0.1);
+}
+
+#[test]
+fn quantized_attributes() {
+    use cgmath::*;
+
+    // positions on a grid of 1/1000 over a box from -2, -2, -2, normals
+    // as signed bytes and uvs as unsigned shorts
+    let (scale, offset) = ([0.001; 3], [-2.; 3]);
+    let positions: [[u16; 3]; 3] = [[1000, 2000, 3000], [4000, 0, 2000], [2000, 4000, 0]];
+    let normals: [[i8; 3]; 3] = [[0, 0, 127], [-128, 0, 0], [0, 127, 0]];
+    let uvs: [[u16; 2]; 3] = [[0, 0], [65535, 0], [0, 65535]];
+    let indices = [0, 1, 2];
+
+    let attributes = (Dequantize::new(&positions[..], scale, offset), Normalized(&normals[..]), Normalized(&uvs[..]));
+    let tri = IndexedTriangles::new(&indices, attributes).next().unwrap();
+    let close = |a: &[f32], b: &[f32]| a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-5);
+    assert!(close(&tri.x.0, &[-1., 0., 1.]) && close(&tri.y.0, &[2., -2., 0.]));
+    assert!(close(&tri.x.1, &[0., 0., 1.]) && close(&tri.y.1, &[-1., 0., 0.]));
+    assert!(close(&tri.y.2, &[1., 0.]) && close(&tri.z.2, &[0., 1.]));
+
+    // the decoding folded into the matrix matches decoding first
+    let m = perspective(deg(60.), 1.5, 0.1, 10.).mul_m(&Matrix4::from_translation(&Vector3::new(0.5, -1., -3.)));
+    let src: Vec<[i16; 3]> = (0..11).map(|i| [i * 300 - 1500, i * 150, -i * 600]).collect();
+    let decoded: Vec<[f32; 4]> = (0..src.len()).map(|i| {
+        let p = src[i];
+        [offset[0] + scale[0] * p[0] as f32, offset[1] + scale[1] * p[1] as f32, offset[2] + scale[2] * p[2] as f32, 1.]
+    }).collect();
+    let mut out = vec![[0.; 4]; src.len()];
+    rusterize::transform_quantized_into(&m, &src, scale, offset, &mut out);
+    for (o, e) in out.iter().zip(rusterize::transform_positions(&m, &decoded).iter()) {
+        assert!(close(o, e));
+    }
+}
+
+#[test]
+fn strips_and_fans() {
+    let ids: Vec<u32> = (0..8).collect();
+    let ids = &ids[..];
+    let list = |t: Vec<Triangle<u32>>| -> Vec<(u32, u32, u32)> { t.into_iter().map(|t| (t.x, t.y, t.z)).collect() };
+    const R: u32 = PRIMITIVE_RESTART;
+
+    let strip = IndexedStrip::new(&[0, 1, 2, 3, 4], ids).collect();
+    assert_eq!(list(strip), vec![(0, 1, 2), (2, 1, 3), (2, 3, 4)]);
+    // two strips, once with a restart and once joined by degenerate triangles
+    let restart = IndexedStrip::new(&[0, 1, 2, 3, R, 4, 5, 6, 7], ids).collect();
+    let joined = IndexedStrip::new(&[0, 1, 2, 3, 3, 4, 4, 5, 6, 7], ids).collect();
+    assert_eq!(list(restart), vec![(0, 1, 2), (2, 1, 3), (4, 5, 6), (6, 5, 7)]);
+    assert_eq!(list(joined), vec![(0, 1, 2), (2, 1, 3), (4, 5, 6), (6, 5, 7)]);
+
+    let fan = IndexedFan::new(&[0, 1, 2, 3, R, 4, 5, 6, R], ids).collect();
+    assert_eq!(list(fan), vec![(0, 1, 2), (0, 2, 3), (4, 5, 6)]);
+
+    // every triangle of a strip over a grid row winds the same way
+    let positions = [[0., 0.], [0., 1.], [1., 0.], [1., 1.], [2., 0.], [2., 1.]];
+    for t in IndexedStrip::new(&[1, 0, 3, 2, 5, 4], &positions[..]) {
+        let area = (t.y[0] - t.x[0]) * (t.z[1] - t.x[1]) - (t.z[0] - t.x[0]) * (t.y[1] - t.x[1]);
+        assert!(area > 0.);
+    }
+}
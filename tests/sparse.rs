@@ -0,0 +1,109 @@
+extern crate rusterize;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rusterize::{Frame, Fragment, Buffer};
+use rusterize::shaders::ScreenVertex;
+use rusterize::sparse::{SparseTexture, PageTable, Page};
+
+/// a texture whose texels tell the level they came from
+fn levels(loads: Arc<AtomicUsize>) -> SparseTexture<f32> {
+    SparseTexture::new(256, 256, 32, 8, Arc::new(move |page: Page, w: u32, h: u32| {
+        loads.fetch_add(1, Ordering::SeqCst);
+        Buffer::new(w, h, page.level as f32)
+    }))
+}
+
+#[test]
+fn pages_on_demand() {
+    let loads = Arc::new(AtomicUsize::new(0));
+    let mut texture = levels(loads.clone());
+    let table = texture.table();
+    // 256, 128, 64 and the 32 of a single page
+    assert_eq!(table.levels(), 4);
+    assert_eq!(texture.resident(), 1);
+    assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+    // only the coarsest level is there to stand in
+    assert_eq!(table.sample(0.5, 0.5, 0.), 3.);
+    // the center of the base level touches four pages
+    let mut wanted = table.feedback();
+    wanted.sort_by(|a, b| (a.y, a.x).cmp(&(b.y, b.x)));
+    assert_eq!(wanted, vec![Page { level: 0, x: 3, y: 3 }, Page { level: 0, x: 4, y: 3 },
+                            Page { level: 0, x: 3, y: 4 }, Page { level: 0, x: 4, y: 4 }]);
+
+    assert_eq!(texture.update(2), 2);
+    assert_eq!(texture.update(8), 0);
+    let table = texture.table();
+    assert!(table.feedback().is_empty());
+    assert_eq!(table.sample(0.5, 0.5, 0.), 3.);
+    assert_eq!(texture.update(8), 2);
+    assert_eq!(texture.table().sample(0.5, 0.5, 0.), 0.);
+    assert_eq!(texture.resident(), 5);
+    assert_eq!(texture.table().lod(4.), 2.);
+}
+
+#[test]
+fn pages_evicted_at_capacity() {
+    let loads = Arc::new(AtomicUsize::new(0));
+    let mut texture = levels(loads.clone());
+    // the corners and the middle of the base level in turn
+    for &(u, v) in [(0., 0.), (1., 0.), (0., 1.), (1., 1.), (0.5, 0.5), (0., 0.5)].iter() {
+        let table = texture.table();
+        table.sample(u, v, 0.);
+        table.sample(0.1, 0.1, 1.);
+        texture.update(8);
+        assert!(texture.resident() <= 8);
+        let table = texture.table();
+        assert_eq!(table.sample(u, v, 0.), 0.);
+        assert_eq!(table.sample(0.1, 0.1, 1.), 1.);
+    }
+    // the coarsest page stayed through all of it
+    assert!(texture.table().is_resident(Page { level: 3, x: 0, y: 0 }));
+    assert!(!texture.table().is_resident(Page { level: 0, x: 0, y: 0 }));
+}
+
+#[test]
+fn sparse_edge_pages() {
+    let texture = SparseTexture::new(100, 60, 32, 4, Arc::new(|page: Page, w: u32, h: u32| {
+        if page.level == 2 {
+            assert_eq!((w, h), (25, 15));
+        }
+        Buffer::new(w, h, 1f32)
+    }));
+    // 100x60, 50x30 and the single page of 25x15
+    assert_eq!(texture.table().levels(), 3);
+    assert_eq!(texture.table().page_of(0, 99, 59), Page { level: 0, x: 3, y: 1 });
+}
+
+struct Sampled(Arc<PageTable<f32>>);
+
+impl Fragment<ScreenVertex> for Sampled {
+    type Color = f32;
+
+    fn fragment(&self, (_, ndc): ScreenVertex) -> f32 {
+        self.0.sample((ndc[0] + 1.) / 2., (1. - ndc[1]) / 2., 0.)
+    }
+}
+
+#[test]
+fn sparse_feedback_from_frame() {
+    let loads = Arc::new(AtomicUsize::new(0));
+    let mut texture = levels(loads.clone());
+    let mut frame = Frame::new(64, 64, -1f32);
+
+    // the tile workers fill the feedback of the table together
+    frame.fullscreen(Sampled(texture.table()));
+    assert!(frame.to_buffer().data.iter().all(|&v| v == 3.));
+    assert_eq!(texture.table().feedback().len(), 64);
+
+    // seven pages of the base level fit next to the coarsest one, the
+    // rest of the frame keeps the stand in
+    texture.update(64);
+    assert_eq!(texture.resident(), 8);
+    frame.fullscreen(Sampled(texture.table()));
+    let pixels = frame.to_buffer();
+    assert!(pixels.data.iter().any(|&v| v == 0.));
+    assert!(pixels.data.iter().all(|&v| v == 0. || v == 3.));
+}
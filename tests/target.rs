@@ -0,0 +1,48 @@
+extern crate rusterize;
+extern crate genmesh;
+extern crate cgmath;
+
+use rusterize::{Frame, TiledTarget};
+use rusterize::paint::{Canvas, CanvasVertex, Gradient, LinearGradient};
+use genmesh::{Triangle, MapVertex};
+use cgmath::*;
+
+fn scene() -> Vec<(Vec<Triangle<CanvasVertex>>, f32)> {
+    let canvas = Canvas::new(64, 128);
+    vec![(canvas.rect(10.5, 20.5, 30., 70.), 1.),
+         (canvas.polygon(&[[2.5, 120.5], [60.5, 100.5], [30.5, 40.5]]), 2.)]
+}
+
+fn solid(v: f32) -> LinearGradient<f32> {
+    LinearGradient {
+        start: [0., 0.],
+        end: [1., 0.],
+        gradient: Gradient::new(v, v)
+    }
+}
+
+#[test]
+fn bands_match_full_frame() {
+    let mut full = Frame::new(64, 128, 0f32);
+    for (tris, v) in scene() {
+        full.raster(tris.into_iter(), solid(v));
+    }
+    let expected = full.to_buffer();
+
+    let mut rows = Vec::new();
+    let target = TiledTarget::new(64, 128, 32, 0f32);
+    target.render(|frame, band| {
+        let m = band.transform;
+        for (tris, v) in scene() {
+            let tris = tris.into_iter().map(|t| t.map_vertex(|(p, c)| {
+                (m.mul_v(&Vector4::new(p[0], p[1], p[2], p[3])).into_fixed(), c)
+            }));
+            frame.raster(tris, solid(v));
+        }
+    }, |band, buffer| {
+        assert_eq!(buffer.height, band.height);
+        rows.extend(buffer.data.into_iter());
+    });
+
+    assert_eq!(rows, expected.data);
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "image")]
+
 extern crate image;
 extern crate genmesh;
 extern crate cgmath;
@@ -13,6 +15,8 @@ use genmesh::generators;
 use genmesh::{Triangulate, MapToVertices, Quad};
 use image::Rgba;
 
+mod common;
+
 const SIZE: u32 = 512;
 
 fn check(name: &str, mut frame: Frame<Rgba<u8>>) {
@@ -327,3 +331,742 @@ fn plane_checker() {
     check("plane_checker", frame);
 }
 
+
+#[test]
+fn plane_two_sided() {
+    let white = Rgba([255, 255, 255, 255]);
+    let red = Rgba([255, 0, 0, 255]);
+    let center = |mut frame: Frame<Rgba<u8>>| *frame.to_image().get_pixel(SIZE / 2, SIZE / 2);
+
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    let front = generators::Plane::new()
+        .triangulate()
+        .vertex(|v| proj().mul_v(&Vector4::new(v.0, v.1, 0., 2.).mul_s(0.5)).into_fixed());
+    frame.raster_two_sided(front, SetValue(white), SetValue(red));
+    assert_eq!(center(frame), white);
+
+    let mut frame = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    let back = generators::Plane::new()
+        .triangulate()
+        .vertex(|v| proj().mul_v(&Vector4::new(-v.0, v.1, 0., 2.).mul_s(0.5)).into_fixed());
+    frame.raster_two_sided(back, SetValue(white), SetValue(red));
+    assert_eq!(center(frame), red);
+}
+
+#[test]
+fn plane_clip_user() {
+    let white = Rgba([255, 255, 255, 255]);
+    let black = Rgba([0, 0, 0, 0]);
+
+    let mut frame = Frame::new(SIZE, SIZE, black);
+    let plane = generators::Plane::new()
+        .triangulate()
+        .vertex(|v| proj().mul_v(&Vector4::new(v.0, v.1, 0., 2.).mul_s(0.5)).into_fixed());
+
+    // keep only the right half, x >= 0
+    frame.raster_clipped(plane, &[[1., 0., 0., 0.]], SetValue(white));
+    let img = frame.to_image();
+    assert_eq!(*img.get_pixel(200, SIZE / 2), black);
+    assert_eq!(*img.get_pixel(300, SIZE / 2), white);
+}
+
+#[test]
+fn triangle_simd() {
+    use std::mem;
+    use genmesh::Triangle;
+    use rusterize::FragmentSimd;
+    use rusterize::f32x8::f32x8;
+
+    let triangle = [Triangle::new(
+        ([ -0.5, -0.5, 0., 1., ], [1.0, 0.0, 0.0]),
+        ([  0.5, -0.5, 0., 1., ], [0.0, 1.0, 0.0]),
+        ([  0.0,  0.5, 0., 1., ], [0.0, 0.0, 1.0]),
+    )];
+
+    #[derive(Clone)]
+    struct V;
+
+    impl Fragment<([f32; 4], [f32; 3])> for V {
+        type Color = Rgba<u8>;
+
+        fn fragment(&self, (_, color) : ([f32; 4], [f32; 3])) -> Rgba<u8> {
+            Rgba([(color[0] * 255.) as u8, (color[1] * 255.) as u8, (color[2] * 255.) as u8, 255])
+        }
+    }
+
+    impl FragmentSimd<([f32x8; 4], [f32x8; 3])> for V {
+        type Color = Rgba<u8>;
+
+        fn fragment8(&self, (_, color) : ([f32x8; 4], [f32x8; 3])) -> [Rgba<u8>; 8] {
+            let scale = f32x8::broadcast(255.);
+            let c: [[f32; 8]; 3] = unsafe { mem::transmute([color[0] * scale, color[1] * scale, color[2] * scale]) };
+            let mut out = [Rgba([0, 0, 0, 255]); 8];
+            for (i, p) in out.iter_mut().enumerate() {
+                *p = Rgba([c[0][i] as u8, c[1][i] as u8, c[2][i] as u8, 255]);
+            }
+            out
+        }
+    }
+
+    let mut scalar = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    scalar.raster(triangle.iter().map(|x| *x), V);
+
+    let mut simd = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    let stats = simd.raster_simd(triangle.iter().map(|x| *x), V);
+    assert!(scalar.to_image().into_raw() == simd.to_image().into_raw());
+    assert!(stats.fragments() > 0);
+}
+
+#[test]
+fn triangle_flat_fast_path() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use genmesh::Triangle;
+
+    // the extra float is interpolated, so these go through every pixel
+    let interpolated = [Triangle::new(
+        ([ -0.5, -0.5, 0., 1., ], Flat([1.0, 0.0, 0.0]), 0.),
+        ([  0.5, -0.5, 0., 1., ], Flat([0.0, 1.0, 0.0]), 1.),
+        ([  0.0,  0.5, 0., 1., ], Flat([0.0, 0.0, 1.0]), 0.),
+    )];
+    // every varying is flat and the shader leaves the position unread, the
+    // tiles shade once instead of per pixel
+    let flat = [Triangle::new(
+        ([ -0.5, -0.5, 0., 1., ], Flat([1.0, 0.0, 0.0])),
+        ([  0.5, -0.5, 0., 1., ], Flat([0.0, 1.0, 0.0])),
+        ([  0.0,  0.5, 0., 1., ], Flat([0.0, 0.0, 1.0])),
+    )];
+
+    #[derive(Clone)]
+    struct V(Arc<AtomicUsize>);
+
+    impl Fragment<([f32; 4], [f32; 3])> for V {
+        type Color = Rgba<u8>;
+
+        fn fragment(&self, (_, color) : ([f32; 4], [f32; 3])) -> Rgba<u8> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Rgba([(color[0] * 255.) as u8, (color[1] * 255.) as u8, (color[2] * 255.) as u8, 255])
+        }
+
+        fn position_unused(&self) -> bool { true }
+    }
+
+    impl Fragment<([f32; 4], [f32; 3], f32)> for V {
+        type Color = Rgba<u8>;
+
+        fn fragment(&self, (p, color, _) : ([f32; 4], [f32; 3], f32)) -> Rgba<u8> {
+            self.fragment((p, color))
+        }
+    }
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut a = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    let stats = a.raster(interpolated.iter().map(|x| *x), V(calls.clone()));
+    a.flush();
+    assert_eq!(calls.swap(0, Ordering::Relaxed), stats.fragments());
+
+    let mut b = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    b.raster(flat.iter().map(|x| *x), V(calls.clone()));
+    assert!(a.to_image().into_raw() == b.to_image().into_raw());
+    assert!(calls.load(Ordering::Relaxed) < stats.fragments());
+
+    // a shader that reads the position still sees it change
+    #[derive(Clone)]
+    struct Position;
+
+    impl Fragment<([f32; 4], [f32; 3])> for Position {
+        type Color = Rgba<u8>;
+
+        fn fragment(&self, (p, color) : ([f32; 4], [f32; 3])) -> Rgba<u8> {
+            Rgba([((p[0] + 1.) * 127.) as u8, (color[1] * 255.) as u8, (color[2] * 255.) as u8, 255])
+        }
+    }
+
+    let mut c = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    c.raster(flat.iter().map(|x| *x), Position);
+    let img = c.to_image();
+    let (left, right) = (img.get_pixel(160, 370), img.get_pixel(350, 370));
+    assert!(left.data[3] == 255 && right.data[3] == 255);
+    assert!(left.data[0] < right.data[0]);
+}
+
+#[test]
+fn plane_solid_color() {
+    use rusterize::SolidColor;
+
+    let white = Rgba([255, 255, 255, 255]);
+    let plane = || generators::Plane::new()
+        .triangulate()
+        .vertex(|v| proj().mul_v(&Vector4::new(v.0, v.1, 0., 2.).mul_s(0.5)).into_fixed());
+
+    let mut shaded = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    let a = shaded.raster(plane(), SetValue(white));
+    let mut solid = Frame::new(SIZE, SIZE, Rgba([0u8, 0, 0, 0]));
+    let b = solid.raster(plane(), SolidColor(white));
+
+    assert!(shaded.to_image().into_raw() == solid.to_image().into_raw());
+    assert_eq!(a.fragments(), b.fragments());
+}
+
+#[test]
+fn capabilities() {
+    let caps = rusterize::capabilities();
+    assert_eq!(caps.group_size % caps.tile_size, 0);
+    assert_eq!(caps.max_dimension % caps.size_granularity, 0);
+    assert!(caps.max_dimension as u64 * caps.max_dimension as u64 <= caps.max_pixels);
+    assert!(caps.formats.contains(&rusterize::Format::Rgba8));
+    assert!(caps.threads >= 1);
+
+    // a frame at the granularity keeps its size
+    let mut frame = Frame::new(caps.size_granularity, caps.size_granularity, Rgba([0u8, 0, 0, 0]));
+    let img = frame.to_image();
+    assert_eq!((img.width(), img.height()), (caps.size_granularity, caps.size_granularity));
+}
+
+#[test]
+fn degenerate_triangles() {
+    use genmesh::Triangle;
+    use rusterize::Barycentric;
+
+    let collinear = Triangle::new(Vector2::new(0., 0.), Vector2::new(1., 1.), Vector2::new(2., 2.));
+    let coincident = Triangle::new(Vector2::new(0.5, 0.5), Vector2::new(0.5, 0.5), Vector2::new(1., 0.));
+    let nan = Triangle::new(Vector2::new(0., 0.), Vector2::new(std::f32::NAN, 1.), Vector2::new(1., 0.));
+    let good = Triangle::new(Vector2::new(0., 0.), Vector2::new(1., 0.), Vector2::new(0., 1.));
+    assert!(Barycentric::new(collinear).is_degenerate());
+    assert!(Barycentric::new(coincident).is_degenerate());
+    assert!(Barycentric::new(nan).is_degenerate());
+    assert!(!Barycentric::new(good).is_degenerate());
+
+    let white = Rgba([255u8, 255, 255, 255]);
+    let tris = vec![
+        Triangle::new([-1., -1., 0., 1.], [0., 0., 0., 1.], [1., 1., 0., 1.]),
+        Triangle::new([-1., -1., 0., 1.], [-1., -1., 0., 1.], [1., -1., 0., 1.]),
+        Triangle::new([-1., -1., 0., 1.], [1., -1., 0., 1.], [0., std::f32::INFINITY, 0., 1.]),
+        Triangle::new([-1., -1., 0., 1.], [1., -1., 0., 1.], [0., 1., 0., 0.])
+    ];
+
+    let mut frame = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    let stats = frame.raster(tris.clone().into_iter(), SetValue(white));
+    let two_sided = frame.raster_two_sided(tris.into_iter(), SetValue(white), SetValue(white));
+    frame.flush();
+    assert_eq!((stats.degenerate, stats.fragments()), (4, 0));
+    assert_eq!((two_sided.degenerate, two_sided.fragments()), (4, 0));
+    assert!(frame.to_image().pixels().all(|p| *p == Rgba([0, 0, 0, 0])));
+}
+
+// a fan of thin triangles around a point close to the corner of four tile
+// groups, on a frame whose size is not a power of two
+fn seam_fan() -> Vec<genmesh::Triangle<[f32; 4]>> {
+    let center = [-0.3107, 0.3269];
+    (0..13).map(|i| {
+        let a = i as f32 * 0.4833 + 0.07;
+        let b = (i + 1) as f32 * 0.4833 + 0.07;
+        genmesh::Triangle::new([center[0], center[1], 0., 1.],
+                               [center[0] + 0.9 * a.cos(), center[1] + 0.9 * a.sin(), 0., 1.],
+                               [center[0] + 0.9 * b.cos(), center[1] + 0.9 * b.sin(), 0., 1.])
+    }).collect()
+}
+
+#[test]
+fn seams_split_and_unsplit() {
+    let white = Rgba([255u8, 255, 255, 255]);
+    let draw = |threshold| {
+        let mut frame = Frame::new(96, 96, Rgba([0u8, 0, 0, 0]));
+        frame.set_split_threshold(threshold);
+        frame.raster_two_sided(seam_fan().into_iter(), SetValue(white), SetValue(white));
+        frame.to_image()
+    };
+    let (whole, split) = (draw(std::usize::MAX), draw(0));
+    assert!(whole.pixels().any(|p| *p == white));
+    // the groups around the center of the fan are split half way through
+    let handed_over = draw(5);
+    assert!(whole.clone().into_raw() == split.into_raw());
+    assert!(whole.into_raw() == handed_over.into_raw());
+}
+
+#[test]
+fn seams_match_reference() {
+    use rusterize::Barycentric;
+
+    let (w, h) = (96u32, 96u32);
+    let (wh, hh) = (w as f32 / 2., h as f32 / 2.);
+    let white = Rgba([255u8, 255, 255, 255]);
+    let fan = seam_fan();
+
+    let mut frame = Frame::new(w, h, Rgba([0u8, 0, 0, 0]));
+    frame.raster_two_sided(fan.clone().into_iter(), SetValue(white), SetValue(white));
+    let img = frame.to_image();
+
+    // pixels are sampled at integer positions in pixels from the center
+    let reference: Vec<Barycentric> = fan.iter().map(|t| {
+        let s = |v: [f32; 4]| Vector2::new(v[0] * wh, v[1] * hh);
+        Barycentric::new(genmesh::Triangle::new(s(t.x), s(t.y), s(t.z)))
+    }).collect();
+    for iy in 0..h {
+        for ix in 0..w {
+            let p = Vector2::new(ix as f32 - wh, (h - 1 - iy) as f32 - hh);
+            let covered = reference.iter().any(|b| b.coordinate(p).inside());
+            assert_eq!(*img.get_pixel(ix, iy) == white, covered, "pixel {} {}", ix, iy);
+        }
+    }
+}
+
+#[test]
+fn partial_edge_groups() {
+    use genmesh::Triangle;
+
+    let white = Rgba([255u8, 255, 255, 255]);
+    let quad = |x0: f32, y0: f32, x1: f32, y1: f32| vec![
+        Triangle::new([x0, y0, 0., 1.], [x1, y0, 0., 1.], [x1, y1, 0., 1.]),
+        Triangle::new([x0, y0, 0., 1.], [x1, y1, 0., 1.], [x0, y1, 0., 1.])
+    ];
+
+    // 100 by 70 pixels leaves partial groups on the right and top edge,
+    // every quad covers the single row or column of pixels at one edge
+    let (w, h) = (100, 70);
+    let mut tris = quad(0.97, -1., 1., 1.);
+    tris.extend(quad(-1., -1., -0.985, 1.));
+    tris.extend(quad(-1., 0.96, 1., 1.));
+    tris.extend(quad(-1., -1., 1., -0.98));
+
+    let mut frame = Frame::new(w, h, Rgba([0u8, 0, 0, 0]));
+    frame.raster_two_sided(tris.into_iter(), SetValue(white), SetValue(white));
+    let img = frame.to_image();
+    assert_eq!((img.width(), img.height()), (w, h));
+    for (x, y, p) in img.enumerate_pixels() {
+        let edge = x == 0 || x == w - 1 || y == 0 || y == h - 1;
+        assert_eq!(*p == white, edge, "pixel {} {}", x, y);
+    }
+
+    let mut full = Frame::new(w, h, Rgba([0u8, 0, 0, 0]));
+    full.raster_two_sided(quad(-1., -1., 1., 1.).into_iter(), SetValue(white), SetValue(white));
+    assert!(full.to_image().pixels().all(|p| *p == white));
+}
+
+#[derive(Clone)]
+struct Over(Rgba<u8>);
+
+impl Fragment<[f32; 4]> for Over {
+    type Color = Rgba<u8>;
+
+    fn fragment(&self, _: [f32; 4]) -> Rgba<u8> { self.0 }
+    fn blend(&self, old: Rgba<u8>, new: Rgba<u8>) -> Rgba<u8> { rusterize::alpha_over(old, new) }
+}
+
+#[test]
+fn deterministic_across_threads() {
+    use genmesh::Triangle;
+
+    // a fixed pseudo random stream of overlapping translucent triangles,
+    // alpha blending does not commute so any reordering shows up
+    let mut seed = 12345u32;
+    let mut next = || {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        (seed >> 8) as f32 / (1 << 24) as f32
+    };
+    let mut draws = Vec::new();
+    for i in 0..64 {
+        // every draw in front of the ones before it, so they blend in order
+        let z = -(i as f32) / 64.;
+        let v = |x: f32, y: f32| [x * 2. - 1., y * 2. - 1., z, 1.];
+        let (a, b, c) = (v(next(), next()), v(next(), next()), v(next(), next()));
+        let color = Rgba([(next() * 255.) as u8, (next() * 255.) as u8, (next() * 255.) as u8, 128]);
+        // one of the two windings is a front face
+        draws.push((vec![Triangle::new(a, b, c), Triangle::new(a, c, b)], color));
+    }
+
+    let draw = |threads, split, priority| {
+        let mut frame = Frame::with_threads(96, 64, Rgba([0u8, 0, 0, 255]), threads);
+        frame.set_split_threshold(split);
+        frame.set_priority(priority);
+        for &(ref tris, color) in draws.iter() {
+            frame.raster_affine(tris.clone().into_iter(), Over(color));
+        }
+        frame.to_image().into_raw()
+    };
+
+    let reference = draw(1, std::usize::MAX, None);
+    assert!(draw(2, std::usize::MAX, None) == reference);
+    assert!(draw(8, 0, None) == reference);
+    assert!(draw(4, 0, Some(rusterize::Rect::new(32, 0, 32, 32))) == reference);
+}
+
+#[test]
+fn load_ops() {
+    use genmesh::Triangle;
+    use rusterize::LoadOp;
+
+    let (red, black) = (Rgba([255u8, 0, 0, 255]), Rgba([0u8, 0, 0, 255]));
+    let white = Rgba([255u8, 255, 255, 255]);
+    // covers the bottom left pixels of the frame, either winding
+    let tri = || vec![
+        Triangle::new([-1., -1., 0., 1.], [-0.5, -1., 0., 1.], [-1., -0.5, 0., 1.]),
+        Triangle::new([-1., -1., 0., 1.], [-1., -0.5, 0., 1.], [-0.5, -1., 0., 1.])
+    ].into_iter();
+
+    let mut fused = Frame::new(64, 64, red);
+    fused.raster(tri(), SetValue(white));
+    // the clear keeps the storage of the tiles for the draw
+    let bytes = fused.memory_usage().tile_bytes;
+    fused.load_op(LoadOp::Clear(black));
+    assert_eq!(fused.memory_usage().spare_bytes, bytes);
+    fused.raster_with(LoadOp::Clear(black), tri(), SetValue(white));
+    assert_eq!(fused.memory_usage().tile_bytes, bytes);
+    let mut separate = Frame::new(64, 64, red);
+    separate.clear(black);
+    separate.raster(tri(), SetValue(white));
+    let fused = fused.to_image();
+    assert!(fused.clone().into_raw() == separate.to_image().into_raw());
+    assert_eq!(*fused.get_pixel(0, 63), white);
+    assert_eq!(*fused.get_pixel(63, 0), black);
+
+    // loading keeps what is there and the depth with it
+    let mut loaded = Frame::new(64, 64, red);
+    loaded.raster_with(LoadOp::Load, tri(), SetValue(white));
+    loaded.raster_with(LoadOp::Load, tri(), SetValue(black));
+    let loaded = loaded.to_image();
+    assert_eq!((*loaded.get_pixel(0, 63), *loaded.get_pixel(63, 0)), (white, red));
+}
+
+#[test]
+fn scalar_backend_matches() {
+    use std::sync::Arc;
+    use rusterize::ScalarBackend;
+
+    let white = Rgba([255u8, 255, 255, 255]);
+    let draw = |scalar: bool| {
+        let mut frame = Frame::new(96, 96, Rgba([0u8, 0, 0, 0]));
+        if scalar {
+            frame.set_backend(Arc::new(ScalarBackend));
+        }
+        let stats = frame.raster_two_sided(seam_fan().into_iter(), SetValue(white), SetValue(white));
+        (stats.fragments(), frame.to_image().into_raw())
+    };
+    let (simd, scalar) = (draw(false), draw(true));
+    assert!(simd.0 > 0);
+    assert!(simd == scalar);
+}
+
+#[test]
+fn tile_check() {
+    use genmesh::Triangle;
+    use rusterize::{Barycentric, Coverage};
+
+    let bary = Barycentric::new(Triangle::new(Vector2::new(0., 0.), Vector2::new(64., 0.), Vector2::new(0., 64.)));
+    let size = Vector2::new(8., 8.);
+    assert_eq!(bary.tile_check(Vector2::new(8., 8.), size), Coverage::Inside);
+    assert_eq!(bary.tile_check(Vector2::new(28., 28.), size), Coverage::Partial);
+    assert_eq!(bary.tile_check(Vector2::new(40., 40.), size), Coverage::Outside);
+    assert_eq!(bary.tile_check(Vector2::new(-16., 8.), size), Coverage::Outside);
+
+    let collinear = Barycentric::new(Triangle::new(Vector2::new(0., 0.), Vector2::new(1., 1.), Vector2::new(2., 2.)));
+    assert_eq!(collinear.tile_check(Vector2::new(0., 0.), size), Coverage::Outside);
+}
+
+#[test]
+fn backend_resolve_weights() {
+    use std::sync::Arc;
+    use rusterize::{Buffer, Barycentric, RasterBackend, ScalarBackend};
+    use rusterize::tile::TileMask;
+
+    // the scalar coverage, resolved to the top left sample of a pixel
+    struct TopLeft;
+
+    impl RasterBackend for TopLeft {
+        fn bin(&self, bary: &Barycentric, pos: Vector2<f32>, size: Vector2<f32>) -> bool {
+            ScalarBackend.bin(bary, pos, size)
+        }
+
+        fn coverage(&self, bary: &Barycentric, pos: Vector2<f32>, scale: Vector2<f32>) -> TileMask {
+            ScalarBackend.coverage(bary, pos, scale)
+        }
+
+        fn resolve_weights(&self, factor: u32) -> Vec<f32> {
+            (0..factor * factor).map(|i| if i == 0 { 1. } else { 0. }).collect()
+        }
+    }
+
+    let samples = Buffer {
+        width: 64,
+        height: 64,
+        data: (0..64 * 64).map(|i| i as f32).collect()
+    };
+    let mut big = Frame::new(64, 64, 0f32);
+    big.load(Arc::new(samples));
+    let mut small = Frame::new(32, 32, 0f32);
+    big.resolve_backend(&mut small, 2);
+    assert_eq!(small.to_buffer().get_pixel(1, 0), 2.5 + 32.);
+
+    big.set_backend(Arc::new(TopLeft));
+    big.resolve_backend(&mut small, 2);
+    assert_eq!(small.to_buffer().get_pixel(1, 0), 2.);
+}
+
+#[test]
+fn fog() {
+    use rusterize::{Fog, FogMode, FogCoord};
+
+    let linear = Fog::new(FogMode::Linear { start: -1., end: 1. }, FogCoord::Depth, 0u8);
+    assert_eq!((linear.visibility(-2.), linear.visibility(0.), linear.visibility(3.)), (1., 0.5, 0.));
+    let exp = Fog::new(FogMode::Exp { density: 1. }, FogCoord::ViewDepth, 0u8);
+    let exp2 = Fog::new(FogMode::Exp2 { density: 1. }, FogCoord::ViewDepth, 0u8);
+    assert!((exp.visibility(1.) - (-1f32).exp()).abs() < 1e-6);
+    assert!(exp2.visibility(2.) < exp.visibility(2.));
+
+    // a near quad on the left and a far one on the right, fading to black
+    let mut tris = common::rect(-1., -1., 0., 1., -0.5);
+    tris.extend(common::rect(0., -1., 1., 1., 0.5));
+
+    let black = Rgba([0u8, 0, 0, 255]);
+    let mut frame = Frame::new(64, 64, black);
+    let fog = Fog::new(FogMode::Linear { start: -1., end: 1. }, FogCoord::Depth, black);
+    frame.raster_fog(tris.into_iter(), SetValue(Rgba([255, 255, 255, 255])), fog);
+    let img = frame.to_image();
+    assert_eq!(*img.get_pixel(16, 32), Rgba([191, 191, 191, 255]));
+    assert_eq!(*img.get_pixel(48, 32), Rgba([64, 64, 64, 255]));
+}
+
+#[test]
+fn painter_mode() {
+    use rusterize::SolidColor;
+
+    let (red, blue) = (Rgba([255u8, 0, 0, 255]), Rgba([0u8, 0, 255, 255]));
+    // the red quad is in front of the blue one but submitted first
+    let quad = |z: f32| common::rect(-1., -1., 1., 1., z).into_iter();
+    let draw = |painter| {
+        let mut frame = Frame::with_threads(64, 64, Rgba([0u8, 0, 0, 255]), 4);
+        frame.set_split_threshold(0);
+        frame.set_painter(painter);
+        assert_eq!(frame.painter(), painter);
+        frame.raster(quad(-0.5), SolidColor(red));
+        frame.raster(quad(0.5), SolidColor(blue));
+        (frame.to_image(), frame.depth_buffer())
+    };
+
+    let (img, depth) = draw(false);
+    assert!(img.pixels().all(|p| *p == red));
+    assert!(depth.data.iter().all(|&z| z == -0.5));
+
+    // the last draw wins everywhere and the depth buffer stays cleared
+    let (img, depth) = draw(true);
+    assert!(img.pixels().all(|p| *p == blue));
+    assert!(depth.data.iter().all(|&z| z == 1.));
+}
+
+#[test]
+fn depth_write_off() {
+    use rusterize::SolidColor;
+
+    let (red, green, blue) = (Rgba([255u8, 0, 0, 255]), Rgba([0u8, 255, 0, 255]), Rgba([0u8, 0, 255, 255]));
+    let quad = |z: f32| common::rect(-1., -1., 1., 1., z).into_iter();
+
+    let mut frame = Frame::new(64, 64, Rgba([0u8, 0, 0, 255]));
+    frame.raster(quad(0.), SolidColor(red));
+    frame.set_depth_write(false);
+    // still tested against the red quad
+    frame.raster(quad(0.5), SolidColor(green));
+    assert!(frame.to_image().pixels().all(|p| *p == red));
+    // but the green quad in front does not hide the blue one behind it
+    frame.raster(quad(-0.5), SolidColor(green));
+    frame.set_depth_write(true);
+    frame.raster(quad(-0.25), SolidColor(blue));
+
+    assert!(frame.to_image().pixels().all(|p| *p == blue));
+    assert!(frame.depth_buffer().data.iter().all(|&z| z == -0.25));
+}
+
+#[test]
+fn pass_timings() {
+    use rusterize::{SolidColor, Pass};
+
+    let quad = common::rect(-1., -1., 1., 1., 0.);
+    let mut frame = Frame::new(64, 64, Rgba([0u8, 0, 0, 255]));
+    frame.raster(quad.clone().into_iter(), SolidColor(Rgba([255u8, 0, 0, 255])));
+    assert!(frame.timings().is_empty());
+
+    frame.set_timing(true);
+    frame.clear(Rgba([0u8, 0, 0, 255]));
+    frame.raster(quad.into_iter(), SolidColor(Rgba([255u8, 0, 0, 255])));
+    frame.to_image();
+    let timings = frame.timings();
+
+    let passes: Vec<Pass> = timings.iter().map(|t| t.pass).collect();
+    assert_eq!(passes, vec![Pass::Clear, Pass::Geometry, Pass::Raster, Pass::Readback]);
+    // a task per tile group, the geometry runs on the calling thread, the
+    // workers of a draw may be resumed more than once and the readback
+    // also writes every group on the calling thread
+    assert_eq!(timings[0].tasks, 4);
+    assert_eq!(timings[1].tasks, 1);
+    assert!(timings[2].tasks >= 4);
+    assert_eq!(timings[3].tasks, 8);
+    for t in timings.iter() {
+        assert!(t.start <= t.end);
+    }
+    assert!(timings[0].start <= timings[3].end);
+
+    // they are only reported once
+    assert!(frame.timings().is_empty());
+}
+
+#[test]
+fn frame_trace() {
+    use rusterize::{SolidColor, Pass};
+
+    let quad = common::rect(-1., -1., 1., 1., 0.);
+    let mut frame = Frame::with_threads(64, 32, Rgba([0u8, 0, 0, 255]), 2);
+    frame.set_tracing(true);
+    frame.clear(Rgba([0u8, 0, 0, 255]));
+    frame.raster(quad.into_iter(), SolidColor(Rgba([255u8, 0, 0, 255])));
+    let trace = frame.trace();
+
+    let clears: Vec<_> = trace.events.iter().filter(|e| e.pass == Pass::Clear).collect();
+    assert_eq!(clears.len(), 2);
+    assert!(clears.iter().all(|e| e.index == 0));
+    assert!(clears.iter().any(|e| e.tile == Some((0, 0))) && clears.iter().any(|e| e.tile == Some((1, 0))));
+    let geometry: Vec<_> = trace.events.iter().filter(|e| e.pass == Pass::Geometry).collect();
+    assert_eq!(geometry.len(), 1);
+    assert_eq!((geometry[0].index, geometry[0].tile), (1, None));
+    assert!(trace.events.iter().any(|e| e.pass == Pass::Raster && e.index == 2 && e.tile == Some((1, 0))));
+
+    let json = trace.to_chrome_json();
+    assert!(json.starts_with("{\"traceEvents\":[{\"name\":"));
+    assert!(json.contains("\"name\":\"geometry\""));
+    assert!(json.contains("\"tile\":[1,0]"));
+    assert_eq!(json.matches("\"ph\":\"X\"").count(), trace.events.len());
+
+    // the next frame starts counting passes again
+    frame.clear(Rgba([0u8, 0, 0, 255]));
+    assert!(frame.trace().events.iter().all(|e| e.index == 0));
+}
+
+#[test]
+fn deadline() {
+    use rusterize::SolidColor;
+
+    let (black, red, green) = (Rgba([0u8, 0, 0, 255]), Rgba([255u8, 0, 0, 255]), Rgba([0u8, 255, 0, 255]));
+    // covers the left quarter, all in the first tile group
+    let quad = || common::rect(-1., -1., -0.5, 1., 0.).into_iter();
+
+    let mut history = Frame::new(64, 32, green);
+    let mut frame = Frame::new(64, 32, black);
+
+    // plenty of time
+    frame.set_deadline(Some(60_000_000_000));
+    frame.raster(quad(), SolidColor(red));
+    assert!(frame.finish_late(Some(&mut history)).is_empty());
+    assert_eq!(*frame.to_image().get_pixel(4, 4), red);
+
+    // already late, the draw is dropped and its front faces reported, the
+    // touched group is taken from the history
+    frame.clear(black);
+    frame.set_deadline(Some(0));
+    let stats = frame.raster(quad(), SolidColor(red));
+    assert_eq!(stats.late, 2);
+    assert_eq!(frame.finish_late(Some(&mut history)), vec![(0, 0)]);
+    let img = frame.to_image();
+    assert_eq!(*img.get_pixel(4, 4), green);
+    assert_eq!(*img.get_pixel(20, 4), green);
+    assert_eq!(*img.get_pixel(40, 4), black);
+
+    // without one the group is left at the clear color, even the parts
+    // drawn in time
+    frame.clear(black);
+    frame.raster(quad(), SolidColor(red));
+    frame.set_deadline(Some(0));
+    frame.raster(quad(), SolidColor(green));
+    assert_eq!(frame.finish_late(None), vec![(0, 0)]);
+    assert_eq!(*frame.to_image().get_pixel(4, 4), black);
+
+    // a clear ends the deadline
+    frame.set_deadline(Some(0));
+    frame.clear(black);
+    assert_eq!(frame.raster(quad(), SolidColor(red)).late, 0);
+    assert!(frame.finish_late(None).is_empty());
+    assert_eq!(*frame.to_image().get_pixel(4, 4), red);
+}
+
+#[test]
+fn adaptive_resolution() {
+    use rusterize::{SolidColor, AdaptiveFrame};
+
+    let (black, red) = (Rgba([0u8, 0, 0, 255]), Rgba([255u8, 0, 0, 255]));
+    let mut frame = AdaptiveFrame::new(64, 64, black, Some(1_000_000));
+    assert_eq!(frame.scale(), 1.);
+
+    // four times too slow, the scale goes half way towards 0.5 each time
+    frame.update(4_000_000);
+    assert_eq!(frame.scale(), 0.75);
+    frame.update(4_000_000);
+    assert_eq!(frame.scale(), 0.5625);
+    frame.update(4_000_000);
+    assert_eq!(frame.scale(), 0.5);
+    assert_eq!(frame.size(), (32, 32));
+
+    {
+        let f = frame.begin();
+        assert_eq!((f.width, f.height), (32, 32));
+        f.raster(common::rect(-1., -1., 1., 1., 0.).into_iter(), SolidColor(red));
+    }
+    let img = frame.finish().to_image();
+    assert_eq!((img.width(), img.height()), (64, 64));
+    assert!(img.pixels().all(|p| *p == red));
+    assert!(frame.frame_time().is_some());
+
+    // the same frame is resized back down and cleared for the next one
+    {
+        let f = frame.begin();
+        assert_eq!((f.width, f.height), (32, 32));
+    }
+    assert!(frame.finish().to_image().pixels().all(|p| *p == black));
+
+    // fast frames bring it back up
+    frame.set_scale(0.5);
+    frame.update(250_000);
+    assert_eq!(frame.scale(), 0.75);
+
+    frame.set_target(None);
+    frame.update(250_000);
+    assert_eq!(frame.scale(), 0.75);
+}
+
+#[test]
+fn fences() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use rusterize::SolidColor;
+
+    /// holds its draw until the gate opens
+    struct Gated(Arc<AtomicBool>);
+
+    impl Fragment<[f32; 4]> for Gated {
+        type Color = Rgba<u8>;
+
+        fn fragment(&self, _: [f32; 4]) -> Rgba<u8> {
+            while !self.0.load(Ordering::SeqCst) {
+                thread::yield_now();
+            }
+            Rgba([255, 0, 0, 255])
+        }
+    }
+
+    let quad = |x1: f32| common::rect(-1., -1., x1, 1., 0.).into_iter();
+    let mut frame = Frame::with_threads(64, 64, Rgba([0u8, 0, 0, 255]), 4);
+    assert!(frame.fence().is_done());
+
+    frame.raster(quad(1.), SolidColor(Rgba([0u8, 0, 255, 255])));
+    let blue = frame.fence();
+    let gate = Arc::new(AtomicBool::new(false));
+    // only the left half waits for the gate
+    frame.raster(quad(0.), Gated(gate.clone()));
+    let gated = frame.fence();
+
+    blue.wait().unwrap();
+    assert!(!gated.is_done());
+    gate.store(true, Ordering::SeqCst);
+    gated.wait().unwrap();
+
+    let img = frame.to_image();
+    assert_eq!(*img.get_pixel(8, 32), Rgba([255, 0, 0, 255]));
+    assert_eq!(*img.get_pixel(56, 32), Rgba([0, 0, 255, 255]));
+}
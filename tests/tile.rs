@@ -0,0 +1,168 @@
+extern crate rusterize;
+extern crate genmesh;
+
+use std::sync::Arc;
+
+use genmesh::Triangle;
+use rusterize::{Frame, Buffer, TileGroup, TilePool, FramePool, SolidColor};
+
+mod common;
+
+#[test]
+fn lazy_tile_group() {
+    let mut group = TileGroup::new(3u32);
+    assert!(!group.is_allocated());
+
+    let mut out = Buffer::new(32, 32, 0u32);
+    group.write(0, 0, &mut out);
+    assert!(out.data.iter().all(|&p| p == 3));
+    let mut row = [0u32; 32];
+    group.row(5, &mut row);
+    assert!(row.iter().all(|&p| p == 3));
+
+    group.load(0, 0, &Buffer::new(32, 32, 5u32));
+    assert!(group.is_allocated());
+    group.write(0, 0, &mut out);
+    assert!(out.data.iter().all(|&p| p == 5));
+
+    group.clear(7);
+    assert!(!group.is_allocated());
+    group.write(0, 0, &mut out);
+    assert!(out.data.iter().all(|&p| p == 7));
+}
+
+#[test]
+fn lazy_frame_readback() {
+    let mut frame = Frame::new(64, 64, 9u32);
+    frame.load_region(Arc::new(Buffer::new(64, 64, 1u32)), rusterize::Rect::new(0, 0, 16, 16));
+
+    let out = frame.to_buffer();
+    assert_eq!(out.get_pixel(0, 0), 1);
+    assert_eq!(out.get_pixel(40, 40), 9);
+    assert_eq!(frame.depth_buffer().get_pixel(40, 40), 1.);
+}
+
+#[test]
+fn tile_group_pixels() {
+    let mut group = TileGroup::new(1u32);
+    assert_eq!((group.get(31, 31), group.depth(0, 0)), (1, 1.));
+    assert!(group.tile(0, 0).is_none());
+
+    group.set(9, 17, 4);
+    assert!(group.is_allocated());
+    assert_eq!((group.get(9, 17), group.get(17, 9)), (4, 1));
+    assert_eq!(group.tile(1, 2).unwrap().get(1, 1), 4);
+    assert_eq!(group.tile(1, 2).unwrap().row(1), &[1, 4, 1, 1, 1, 1, 1, 1][..]);
+    group.tile_mut(3, 0).row_mut(7)[2] = 6;
+
+    // the accessors agree with a readback through `write`
+    let mut out = Buffer::new(32, 32, 0u32);
+    group.write(0, 0, &mut out);
+    for (x, y, p) in group.pixels() {
+        assert_eq!(out.get_pixel(x, 31 - y), p);
+    }
+    let mut row = [0u32; 32];
+    group.row(7, &mut row);
+    assert_eq!(row[26], 6);
+    assert_eq!(group.pixels().filter(|&(_, _, p)| p != 1).count(), 2);
+}
+
+#[test]
+fn tile_reuse() {
+    // the left quarter of the frame
+    let quarter = || common::rect(-1., -1., -0.5, 1., 0.).into_iter();
+
+    let mut frame = Frame::new(64, 64, 0u32);
+    frame.raster(quarter(), SolidColor(1u32));
+    let usage = frame.memory_usage();
+    assert_eq!((usage.groups, usage.draws, usage.futures, usage.bins), (4, 1, 2, 2));
+    assert!(usage.tile_bytes > 0 && usage.spare_bytes == 0);
+    let group = usage.tile_bytes / 2;
+
+    // a clear keeps the storage and the next draw takes it back
+    frame.clear(2);
+    let usage = frame.memory_usage();
+    assert_eq!((usage.tile_bytes, usage.spare_bytes), (0, 2 * group));
+    frame.raster(quarter(), SolidColor(3u32));
+    let usage = frame.memory_usage();
+    assert_eq!((usage.tile_bytes, usage.spare_bytes), (2 * group, 0));
+    // the bins of the first draw are taken again
+    assert_eq!((usage.draws, usage.futures, usage.bins), (2, 4, 2));
+    let out = frame.to_buffer();
+    assert_eq!((out.get_pixel(10, 10), out.get_pixel(50, 10)), (3, 2));
+
+    frame.clear(2);
+    frame.trim();
+    assert_eq!(frame.memory_usage().spare_bytes, 0);
+
+    // frames hand their groups over to the next ones through a pool
+    let pool = TilePool::new(std::usize::MAX);
+    let mut frame = Frame::new(64, 64, 0u32);
+    frame.raster(quarter(), SolidColor(1u32));
+    frame.recycle(&pool);
+    assert_eq!(pool.len(), 4);
+    let mut frame = Frame::from_pool(64, 64, 5u32, &pool);
+    assert_eq!((pool.len(), pool.memory()), (0, 0));
+    assert_eq!(frame.memory_usage().spare_bytes, 2 * group);
+    assert!(frame.to_buffer().data.iter().all(|&p| p == 5));
+
+    // nothing is kept past the budget
+    let small = TilePool::new(0);
+    frame.recycle(&small);
+    assert_eq!((small.len(), small.memory()), (0, 0));
+}
+
+#[test]
+fn frame_pool() {
+    let quarter = || common::rect(-1., -1., -0.5, 1., 0.).into_iter();
+
+    let mut pool = FramePool::new(64, 64, 0u32, 1);
+    let mut frame = pool.get();
+    frame.set_painter(true);
+    frame.raster(quarter(), SolidColor(1u32));
+    frame.raster(quarter(), SolidColor(1u32));
+    let tiles = frame.memory_usage().tile_bytes;
+    assert!(tiles > 0);
+    pool.put(frame);
+    // past the limit of the pool
+    pool.put(Frame::new(64, 64, 0u32));
+    assert_eq!(pool.len(), 1);
+
+    // the recycled frame is cleared, keeps its tiles and has the settings
+    // of a new one
+    let mut frame = pool.get();
+    assert_eq!(pool.len(), 0);
+    assert!(!frame.painter());
+    assert!(frame.to_buffer().data.iter().all(|&p| p == 0));
+    let usage = frame.memory_usage();
+    assert_eq!((usage.spare_bytes, usage.draws), (tiles, 0));
+}
+
+#[test]
+fn tile_raster_by_hand() {
+    use rusterize::{TileRaster, PerFragment};
+
+    // a right triangle off the pixel grid, pixels 1..39 along both legs
+    // are under it up to x + y = 40
+    let mut raster = TileRaster::new(70, 50, 0u32);
+    let tri = |z: f32| Triangle::new([0.25, 0.25, z], [40.25, 0.25, z], [0.25, 40.25, z]);
+    let attributes = Triangle::new(0f32, 0., 0.);
+    assert_eq!(raster.triangle(tri(0.5), &attributes, &PerFragment(SolidColor(1u32))), 780);
+    // behind what is there already, then in front of it
+    assert_eq!(raster.triangle(tri(0.8), &attributes, &PerFragment(SolidColor(2u32))), 0);
+    assert_eq!(raster.triangle(tri(0.2), &attributes, &PerFragment(SolidColor(3u32))), 780);
+
+    let colors = raster.to_buffer();
+    assert_eq!((colors.width, colors.height), (70, 50));
+    assert_eq!(colors.data.iter().filter(|&&c| c == 3).count(), 780);
+    assert_eq!(colors.data.iter().filter(|&&c| c != 3 && c != 0).count(), 0);
+    // the buffer has the top row first
+    assert_eq!(colors.get_pixel(1, 48), 3);
+    assert_eq!(colors.get_pixel(0, 49), 0);
+    assert_eq!(raster.depth_buffer().get_pixel(1, 48), 0.2);
+
+    // groups the triangle can't reach stay unallocated
+    assert!(raster.group(0, 1).is_allocated());
+    assert!(!raster.group(1, 1).is_allocated());
+    assert!(!raster.group(2, 0).is_allocated());
+}
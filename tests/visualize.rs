@@ -0,0 +1,87 @@
+#![cfg(feature = "image")]
+
+extern crate rusterize;
+extern crate image;
+
+use rusterize::{Frame, Mapping, DepthGray, NormalColor, Heatmap};
+use image::Rgba;
+
+#[test]
+fn debug_mappings() {
+    let depth = DepthGray { near: 0., far: 2. };
+    assert_eq!(depth.mapping(0.), Rgba([0, 0, 0, 255]));
+    assert_eq!(depth.mapping(1.), Rgba([128, 128, 128, 255]));
+    assert_eq!(depth.mapping(5.), Rgba([255, 255, 255, 255]));
+
+    assert_eq!(NormalColor.mapping([0., 0., 1.]), Rgba([128, 128, 255, 255]));
+    assert_eq!(NormalColor.mapping([-1., 1., 0.]), Rgba([0, 255, 128, 255]));
+
+    let heat = Heatmap { min: 10., max: 20. };
+    assert_eq!(heat.mapping(0.), Rgba([0, 0, 255, 255]));
+    assert_eq!(heat.mapping(15.), Rgba([0, 255, 0, 255]));
+    assert_eq!(heat.mapping(20.), Rgba([255, 0, 0, 255]));
+}
+
+#[test]
+fn map_depth_frame() {
+    let mut depth = Frame::new(64, 64, 0.5f32);
+    let mut frame = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    frame.map(&mut depth, DepthGray::new());
+    assert_eq!(*frame.to_image().get_pixel(10, 10), Rgba([191, 191, 191, 255]));
+}
+
+#[test]
+fn map_with_position() {
+    use rusterize::MappingAt;
+
+    struct Coords;
+
+    impl MappingAt<f32> for Coords {
+        type Out = Rgba<u8>;
+
+        fn mapping_at(&self, _: f32, x: u32, y: u32, width: u32, height: u32) -> Rgba<u8> {
+            Rgba([x as u8, y as u8, (width / 4) as u8, (height / 4) as u8])
+        }
+    }
+
+    let mut src = Frame::new(64, 64, 0f32);
+    let mut frame = Frame::new(64, 64, Rgba([0u8, 0, 0, 0]));
+    frame.map_at(&mut src, Coords);
+
+    let img = frame.to_image();
+    assert_eq!(*img.get_pixel(3, 5), Rgba([3, 5, 16, 16]));
+    assert_eq!(*img.get_pixel(40, 60), Rgba([40, 60, 16, 16]));
+}
+
+#[test]
+fn color_grading_lut() {
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use rusterize::Lut3d;
+
+    let identity = Lut3d::identity(4);
+    let c = identity.sample([0.2, 0.5, 0.9]);
+    assert!((c[0] - 0.2).abs() < 1e-5 && (c[1] - 0.5).abs() < 1e-5 && (c[2] - 0.9).abs() < 1e-5);
+
+    // a 2x2x2 table that inverts every channel
+    let cube = "TITLE \"invert\"\n\
+                # red changes the fastest\n\
+                LUT_3D_SIZE 2\n\
+                1 1 1\n0 1 1\n1 0 1\n0 0 1\n\
+                1 1 0\n0 1 0\n1 0 0\n0 0 0\n";
+    let invert = Lut3d::load_cube(Cursor::new(cube.as_bytes())).unwrap();
+    assert_eq!(invert.mapping(Rgba([255u8, 0, 64, 200])), Rgba([0, 255, 191, 200]));
+    assert!(Lut3d::load_cube(Cursor::new("LUT_3D_SIZE 3\n0 0 0\n".as_bytes())).is_err());
+
+    // the domain applies to the colors looked up, not to the entries
+    let wide = "LUT_3D_INPUT_RANGE 0 2\nLUT_3D_SIZE 2\n\
+                0 0 0\n1 0 0\n0 1 0\n1 1 0\n\
+                0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+    let wide = Lut3d::load_cube(Cursor::new(wide.as_bytes())).unwrap();
+    let c = wide.sample([1., 0.5, 2.]);
+    assert!((c[0] - 0.5).abs() < 1e-5 && (c[1] - 0.25).abs() < 1e-5 && (c[2] - 1.).abs() < 1e-5);
+
+    let mut frame = Frame::new(64, 64, Rgba([255u8, 255, 255, 255]));
+    let img = frame.to_image_graded(Arc::new(invert));
+    assert_eq!(*img.get_pixel(5, 5), Rgba([0, 0, 0, 255]));
+}